@@ -0,0 +1,44 @@
+/* C API of src/ffi.rs, built with `--features ffi`. Keep both in sync. */
+
+#ifndef RUST_BLOOMFILTERS_H
+#define RUST_BLOOMFILTERS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* HashStrategy wire ids accepted by rbf_bloom_new */
+#define RBF_STRATEGY_XXH3_REJECTION 1
+#define RBF_STRATEGY_SIP_DOUBLE 6
+
+typedef struct RbfBloom RbfBloom;
+
+/* NULL for zero sizes, k > m_bits, other strategies or a wrong seed length
+ * (0 bytes for xxh3 rejection, 32 for SipHash double hashing) */
+RbfBloom *rbf_bloom_new(uint64_t m_bits, uint32_t k, uint8_t strategy,
+                        const uint8_t *seed, size_t seed_len);
+
+void rbf_bloom_free(RbfBloom *bloom);
+
+/* true if the element definitely wasn't in the filter before */
+bool rbf_bloom_add(RbfBloom *bloom, const uint8_t *element, size_t len);
+
+bool rbf_bloom_has(const RbfBloom *bloom, const uint8_t *element, size_t len);
+
+/* writes the serialized filter if it fits into capacity bytes and returns its
+ * length either way */
+size_t rbf_bloom_serialize(const RbfBloom *bloom, uint8_t *out,
+                           size_t capacity);
+
+/* NULL if data isn't a serialized DynBloom */
+RbfBloom *rbf_bloom_deserialize(const uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,163 @@
+use crate::{
+    envelope::{Envelope, EnvelopeError, FilterKind},
+    filter::Filter,
+    Bloom,
+};
+
+// One type for the `Bloom<M, K>` shapes the crate keeps using, so filters of
+// different sizes can share a Vec or a map. Each variant boxes its filter,
+// keeping the enum small next to Bloom<32768, 18>. Shapes not listed here
+// still need their own `Bloom<M, K>`, or a `DynBloom`.
+macro_rules! any_bloom {
+    ($($variant:ident = ($m:literal, $k:literal)),* $(,)?) => {
+        #[derive(Clone)]
+        pub enum AnyBloom {
+            $($variant(Box<Bloom<$m, $k>>),)*
+        }
+
+        // (M, K) of every variant
+        pub const SHAPES: &[(usize, usize)] = &[$(($m, $k)),*];
+
+        impl AnyBloom {
+            // an empty filter of M bytes and K hash functions, if that's a
+            // listed shape
+            pub fn new(m_bytes: usize, k: usize) -> Option<Self> {
+                match (m_bytes, k) {
+                    $(($m, $k) => Some(Self::$variant(Box::default())),)*
+                    _ => None,
+                }
+            }
+
+            // (M, K)
+            pub fn shape(&self) -> (usize, usize) {
+                match self {
+                    $(Self::$variant(_) => ($m, $k),)*
+                }
+            }
+
+            pub fn add(&mut self, element: &[u8]) -> bool {
+                match self {
+                    $(Self::$variant(bloom) => bloom.add(element),)*
+                }
+            }
+
+            pub fn has(&self, element: &[u8]) -> bool {
+                match self {
+                    $(Self::$variant(bloom) => bloom.has(element),)*
+                }
+            }
+
+            pub fn indices_for(&self, element: &[u8]) -> Vec<usize> {
+                match self {
+                    $(Self::$variant(bloom) => bloom.indices_for(element).to_vec(),)*
+                }
+            }
+
+            pub fn count_ones(&self) -> u32 {
+                match self {
+                    $(Self::$variant(bloom) => bloom.count_ones(),)*
+                }
+            }
+
+            pub fn bytes(&self) -> &[u8] {
+                match self {
+                    $(Self::$variant(bloom) => &bloom.bytes,)*
+                }
+            }
+
+            // the envelope of the underlying `Bloom`
+            pub fn to_bytes(&self) -> Vec<u8> {
+                match self {
+                    $(Self::$variant(bloom) => bloom.to_bytes(),)*
+                }
+            }
+
+            // a `Bloom` envelope of any listed shape
+            pub fn try_from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+                let envelope = Envelope::decode(data)?;
+                if envelope.kind != FilterKind::Bloom {
+                    return Err(EnvelopeError::Mismatch("kind"));
+                }
+                match (envelope.m_bits, envelope.k) {
+                    $(
+                        (m_bits, $k) if m_bits == $m * 8 => {
+                            Ok(Self::$variant(Box::new(Bloom::try_from_bytes(data)?)))
+                        }
+                    )*
+                    _ => Err(EnvelopeError::Mismatch("shape")),
+                }
+            }
+        }
+
+        $(
+            impl From<Bloom<$m, $k>> for AnyBloom {
+                fn from(bloom: Bloom<$m, $k>) -> Self {
+                    Self::$variant(Box::new(bloom))
+                }
+            }
+        )*
+    };
+}
+
+any_bloom! {
+    // the WNFS namefilter shape, sized for 47 elements at 1e-9
+    M256K30 = (256, 30),
+    M256K16 = (256, 16),
+    // the size of the WNFS test vectors
+    M125K4 = (125, 4),
+    M128K7 = (128, 7),
+    M1024K7 = (1024, 7),
+    M4096K7 = (4096, 7),
+    M16384K7 = (16384, 7),
+    // the folding experiments' 2^18 bits
+    M32768K18 = (32768, 18),
+}
+
+impl Filter for AnyBloom {
+    fn add(&mut self, element: &[u8]) {
+        AnyBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        AnyBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        AnyBloom::count_ones(self)
+    }
+}
+
+#[test]
+fn test_any_bloom() {
+    let mut filters: Vec<AnyBloom> = SHAPES
+        .iter()
+        .map(|&(m, k)| AnyBloom::new(m, k).unwrap())
+        .collect();
+    for filter in filters.iter_mut() {
+        assert!(filter.add(b"one"));
+        assert!(filter.has(b"one") && !filter.has(b"two"));
+    }
+    assert_eq!(filters[2].shape(), (125, 4));
+    assert_eq!(AnyBloom::new(256, 29).map(|f| f.shape()), None);
+
+    let mut bloom = Bloom::<256, 30>::new();
+    bloom.add(b"one");
+    assert_eq!(filters[0].to_bytes(), bloom.to_bytes());
+    assert_eq!(AnyBloom::from(bloom.clone()).bytes(), bloom.bytes);
+    for filter in &filters {
+        let decoded = AnyBloom::try_from_bytes(&filter.to_bytes()).unwrap();
+        assert_eq!(decoded.shape(), filter.shape());
+        assert_eq!(decoded.bytes(), filter.bytes());
+    }
+
+    let unlisted = Bloom::<8, 1>::new().to_bytes();
+    assert_eq!(
+        AnyBloom::try_from_bytes(&unlisted).err(),
+        Some(EnvelopeError::Mismatch("shape"))
+    );
+    let folded = crate::folded::Folded::<1, 128, 30>::new().to_bytes();
+    assert_eq!(
+        AnyBloom::try_from_bytes(&folded).err(),
+        Some(EnvelopeError::Mismatch("kind"))
+    );
+}
@@ -0,0 +1,101 @@
+use arrow_array::{Array, BinaryArray, StringArray, UInt64Array};
+
+use crate::{dyn_bloom::DynBloom, element::encode_u64, filter::Filter, Bloom};
+
+// Bulk inserts of Arrow columns. Null slots are skipped, u64 values are
+// inserted as `encode_u64` bytes like the experiments' probes. Every
+// variant answers the number of elements inserted.
+
+fn insert_binary<F: Filter>(filter: &mut F, array: &BinaryArray) -> usize {
+    if array.null_count() == 0 {
+        // skips the validity checks of `iter`
+        for i in 0..array.len() {
+            filter.add(array.value(i));
+        }
+        return array.len();
+    }
+    array
+        .iter()
+        .flatten()
+        .map(|value| filter.add(value))
+        .count()
+}
+
+fn insert_utf8<F: Filter>(filter: &mut F, array: &StringArray) -> usize {
+    if array.null_count() == 0 {
+        for i in 0..array.len() {
+            filter.add(array.value(i).as_bytes());
+        }
+        return array.len();
+    }
+    array
+        .iter()
+        .flatten()
+        .map(|value| filter.add(value.as_bytes()))
+        .count()
+}
+
+fn insert_u64<F: Filter>(filter: &mut F, array: &UInt64Array) -> usize {
+    if array.null_count() == 0 {
+        for value in array.values() {
+            filter.add(&encode_u64(*value));
+        }
+        return array.len();
+    }
+    array
+        .iter()
+        .flatten()
+        .map(|value| filter.add(&encode_u64(value)))
+        .count()
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn insert_arrow(&mut self, array: &BinaryArray) -> usize {
+        insert_binary(self, array)
+    }
+
+    pub fn insert_arrow_utf8(&mut self, array: &StringArray) -> usize {
+        insert_utf8(self, array)
+    }
+
+    pub fn insert_arrow_u64(&mut self, array: &UInt64Array) -> usize {
+        insert_u64(self, array)
+    }
+}
+
+impl DynBloom {
+    pub fn insert_arrow(&mut self, array: &BinaryArray) -> usize {
+        insert_binary(self, array)
+    }
+
+    pub fn insert_arrow_utf8(&mut self, array: &StringArray) -> usize {
+        insert_utf8(self, array)
+    }
+
+    pub fn insert_arrow_u64(&mut self, array: &UInt64Array) -> usize {
+        insert_u64(self, array)
+    }
+}
+
+#[test]
+fn test_insert_arrow() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    let binary = BinaryArray::from(vec![Some(&b"one"[..]), None, Some(&b"two"[..])]);
+    assert_eq!(bloom.insert_arrow(&binary), 2);
+    let utf8 = StringArray::from(vec!["three", "four"]);
+    assert_eq!(bloom.insert_arrow_utf8(&utf8), 2);
+    let numbers = UInt64Array::from(vec![Some(5), None]);
+    assert_eq!(bloom.insert_arrow_u64(&numbers), 1);
+
+    let mut expected: Bloom<256, 30> = Bloom::new();
+    for element in [&b"one"[..], b"two", b"three", b"four", &5u64.to_le_bytes()] {
+        expected.add(element);
+    }
+    assert_eq!(bloom.bytes, expected.bytes);
+
+    let mut dyn_bloom = DynBloom::new(256 * 8, 30);
+    dyn_bloom.insert_arrow(&binary);
+    dyn_bloom.insert_arrow_utf8(&utf8);
+    dyn_bloom.insert_arrow_u64(&UInt64Array::from(vec![5]));
+    assert_eq!(dyn_bloom.bytes(), &expected.bytes[..]);
+}
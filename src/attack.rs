@@ -0,0 +1,129 @@
+use crate::{bits_per_index, strategy::HashStrategy, Bloom};
+
+// Cost estimates for an attacker searching for an element whose bit indices
+// land on a chosen pattern, in hash evaluations. The attacker derives indices
+// lazily and abandons a candidate at the first index outside the pattern.
+
+// expected accepted indices produced per hash evaluation
+pub fn indices_per_evaluation(strategy: HashStrategy, m_bits: usize) -> f64 {
+    match strategy {
+        HashStrategy::Xxh3Rejection | HashStrategy::Blake3Keyed => {
+            let bits = bits_per_index(m_bits as u64);
+            let chunks = (64 / bits) as f64;
+            let acceptance = m_bits as f64 / (1u64 << bits) as f64;
+            chunks * acceptance
+        }
+        HashStrategy::Xxh3Seeded | HashStrategy::Murmur3Bip37 => 1.0,
+        // two evaluations give all k indices, but rejecting a candidate at its
+        // first or second index costs as much as deriving it
+        HashStrategy::SipDouble | HashStrategy::Xxh3Double => 1.0,
+        HashStrategy::Xxh3Wnfs => m_bits as f64 / m_bits.next_power_of_two() as f64,
+    }
+}
+
+// Expected evaluations to find an element hitting only set bits of a filter
+// with `m_bits` bits of which `ones` are set, i.e. a forged positive.
+// None when the attacker can't evaluate the index derivation (keyed filters).
+pub fn all_set_search_cost(
+    strategy: HashStrategy,
+    m_bits: usize,
+    k: usize,
+    ones: u32,
+) -> Option<f64> {
+    if strategy == HashStrategy::Blake3Keyed {
+        return None;
+    }
+    let density = ones as f64 / m_bits as f64;
+    let candidates = density.powi(-(k as i32));
+    // indices derived per candidate: 1 + d + d^2 + ... + d^(k-1)
+    let indices = (0..k).map(|i| density.powi(i as i32)).sum::<f64>();
+    Some(candidates * indices / indices_per_evaluation(strategy, m_bits))
+}
+
+// Expected evaluations to find an element hitting exactly a given set of k
+// distinct positions (in any order).
+pub fn pattern_search_cost(strategy: HashStrategy, m_bits: usize, k: usize) -> Option<f64> {
+    if strategy == HashStrategy::Blake3Keyed {
+        return None;
+    }
+    // the i-th index has to hit one of the k - i positions not matched yet
+    let mut candidates = 1.0;
+    for i in 0..k {
+        candidates *= m_bits as f64 / (k - i) as f64;
+    }
+    let density = k as f64 / m_bits as f64;
+    let indices = (0..k).map(|i| density.powi(i as i32)).sum::<f64>();
+    Some(candidates * indices / indices_per_evaluation(strategy, m_bits))
+}
+
+// Empirical search against a real filter: the 8-byte candidates u64::MAX,
+// u64::MAX - 1, ... tried until one tests positive in `bloom`, at most
+// `max_candidates` of them. The callers fill `bloom` with shorter elements,
+// so every hit is a false positive. Counts candidates, not evaluations.
+pub fn empirical_search<const M: usize, const K: usize>(
+    bloom: &Bloom<M, K>,
+    max_candidates: u64,
+) -> Option<u64> {
+    (0..max_candidates).find(|candidate| bloom.has(&(u64::MAX - candidate).to_le_bytes()))
+}
+
+// estimated vs empirical candidates for forged positives on small filters
+pub fn test_attack_costs() {
+    println!("prefill;estimated_candidates;empirical_candidates");
+    for prefill in [2, 4, 8, 16] {
+        let mut estimated = 0.0;
+        let mut empirical = 0;
+        let runs = 20;
+        for run in 0..runs {
+            let mut bloom: Bloom<16, 4> = Bloom::new();
+            for i in 0..prefill {
+                bloom.add(&[run as u8, i as u8]);
+            }
+            let density = bloom.count_ones() as f64 / 128.0;
+            estimated += density.powi(-4) / runs as f64;
+            empirical += empirical_search(&bloom, 100_000_000).unwrap() + 1;
+        }
+        println!("{prefill};{estimated};{}", empirical as f64 / runs as f64);
+    }
+
+    println!("strategy;m;k;density;all_set_evaluations;pattern_evaluations");
+    for strategy in crate::strategy::ALL_STRATEGIES {
+        for (m, k) in [(2048, 30), (262_144, 18)] {
+            let ones = m as u32 / 2;
+            println!(
+                "{strategy:?};{m};{k};0.5;{:?};{:?}",
+                all_set_search_cost(strategy, m, k, ones),
+                pattern_search_cost(strategy, m, k)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_search_costs() {
+    // 2048 bits: 13 bit chunks, 4 per hash, a quarter of them in range
+    assert_eq!(
+        indices_per_evaluation(HashStrategy::Xxh3Rejection, 2048),
+        1.0
+    );
+    let cost = all_set_search_cost(HashStrategy::Xxh3Seeded, 2048, 30, 1024).unwrap();
+    assert!((cost - 2f64.powi(31) * (1.0 - 0.5f64.powi(30))).abs() < 1.0);
+    assert_eq!(
+        all_set_search_cost(HashStrategy::Blake3Keyed, 2048, 30, 1024),
+        None
+    );
+    assert!(pattern_search_cost(HashStrategy::Xxh3Seeded, 2048, 2).unwrap() > 2048.0 * 1024.0);
+}
+
+#[test]
+fn test_empirical_search() {
+    let mut bloom: Bloom<16, 4> = Bloom::new();
+    for i in 0..8u8 {
+        bloom.add(&[i]);
+    }
+    let density = bloom.count_ones() as f64 / 128.0;
+    let expected = density.powi(-4);
+    // well within an order of magnitude of the estimate
+    let found = empirical_search(&bloom, (expected * 100.0) as u64);
+    assert!(found.is_some());
+}
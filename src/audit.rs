@@ -0,0 +1,195 @@
+use crate::Bloom;
+
+// Audit mode: every `add` appends a record (element hash, ones-count after
+// the insertion) to a MAC chain keyed with blake3. An auditor holding the key
+// can check that the log wasn't modified, and by replaying the elements that
+// the filter was built from exactly the logged sequence.
+pub struct AuditedBloom<const M: usize, const K: usize> {
+    bloom: Bloom<M, K>,
+    key: [u8; 32],
+    log: Vec<AuditRecord>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub element_hash: [u8; 32],
+    pub ones: u32,
+    // mac over the previous record's mac, the element hash and the ones-count
+    pub mac: [u8; 32],
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuditError {
+    BrokenChain {
+        record: usize,
+    },
+    ElementMismatch {
+        record: usize,
+    },
+    OnesMismatch {
+        record: usize,
+        logged: u32,
+        actual: u32,
+    },
+    LengthMismatch {
+        logged: usize,
+        replayed: usize,
+    },
+    FilterMismatch,
+}
+
+fn element_hash(element: &[u8]) -> [u8; 32] {
+    *blake3::Hasher::new_derive_key("rust-bloomfilters audit element v1")
+        .update(element)
+        .finalize()
+        .as_bytes()
+}
+
+fn record_mac(key: &[u8; 32], previous: &[u8; 32], element_hash: &[u8; 32], ones: u32) -> [u8; 32] {
+    *blake3::Hasher::new_keyed(key)
+        .update(previous)
+        .update(element_hash)
+        .update(&ones.to_le_bytes())
+        .finalize()
+        .as_bytes()
+}
+
+impl<const M: usize, const K: usize> AuditedBloom<M, K> {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            bloom: Bloom::new(),
+            key,
+            log: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, element: &[u8]) {
+        self.bloom.add(element);
+
+        let element_hash = element_hash(element);
+        let ones = self.bloom.count_ones();
+        let previous = self.log.last().map(|r| r.mac).unwrap_or([0u8; 32]);
+        self.log.push(AuditRecord {
+            element_hash,
+            ones,
+            mac: record_mac(&self.key, &previous, &element_hash, ones),
+        });
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.bloom.has(element)
+    }
+
+    pub fn bloom(&self) -> &Bloom<M, K> {
+        &self.bloom
+    }
+
+    pub fn log(&self) -> &[AuditRecord] {
+        &self.log
+    }
+}
+
+// checks the MAC chain of a log
+pub fn verify_chain(key: &[u8; 32], log: &[AuditRecord]) -> Result<(), AuditError> {
+    let mut previous = [0u8; 32];
+    for (record, entry) in log.iter().enumerate() {
+        let expected = record_mac(key, &previous, &entry.element_hash, entry.ones);
+        if blake3::Hash::from(expected) != blake3::Hash::from(entry.mac) {
+            return Err(AuditError::BrokenChain { record });
+        }
+        previous = entry.mac;
+    }
+    Ok(())
+}
+
+// checks the chain, then replays `elements` and compares against the log and the final filter
+pub fn verify_build<const M: usize, const K: usize, E: AsRef<[u8]>>(
+    key: &[u8; 32],
+    log: &[AuditRecord],
+    elements: impl Iterator<Item = E>,
+    filter: &Bloom<M, K>,
+) -> Result<(), AuditError> {
+    verify_chain(key, log)?;
+
+    let mut replayed: Bloom<M, K> = Bloom::new();
+    let mut count = 0;
+    for (record, element) in elements.enumerate() {
+        count += 1;
+        let Some(entry) = log.get(record) else {
+            continue;
+        };
+        if element_hash(element.as_ref()) != entry.element_hash {
+            return Err(AuditError::ElementMismatch { record });
+        }
+        replayed.add(element.as_ref());
+        let actual = replayed.count_ones();
+        if actual != entry.ones {
+            return Err(AuditError::OnesMismatch {
+                record,
+                logged: entry.ones,
+                actual,
+            });
+        }
+    }
+    if count != log.len() {
+        return Err(AuditError::LengthMismatch {
+            logged: log.len(),
+            replayed: count,
+        });
+    }
+    if replayed.bytes != filter.bytes {
+        return Err(AuditError::FilterMismatch);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_audit_log() {
+    let key = [3u8; 32];
+    let elements: Vec<[u8; 1]> = (0..20u8).map(|i| [i]).collect();
+    let mut audited: AuditedBloom<256, 30> = AuditedBloom::new(key);
+    for element in elements.iter() {
+        audited.add(element);
+    }
+
+    let log = audited.log().to_vec();
+    assert_eq!(
+        verify_build(&key, &log, elements.iter(), audited.bloom()),
+        Ok(())
+    );
+
+    // wrong key or tampered record
+    assert_eq!(
+        verify_chain(&[4u8; 32], &log),
+        Err(AuditError::BrokenChain { record: 0 })
+    );
+    let mut tampered = log.clone();
+    tampered[5].ones += 1;
+    assert_eq!(
+        verify_chain(&key, &tampered),
+        Err(AuditError::BrokenChain { record: 5 })
+    );
+
+    // replaying a different sequence
+    let mut reordered = elements.clone();
+    reordered.swap(2, 3);
+    assert_eq!(
+        verify_build(&key, &log, reordered.iter(), audited.bloom()),
+        Err(AuditError::ElementMismatch { record: 2 })
+    );
+    assert_eq!(
+        verify_build(&key, &log, elements.iter().take(19), audited.bloom()),
+        Err(AuditError::LengthMismatch {
+            logged: 20,
+            replayed: 19
+        })
+    );
+
+    // filter with extra bits the log doesn't account for
+    let mut extended = audited.bloom().clone();
+    extended.add(b"unlogged");
+    assert_eq!(
+        verify_build(&key, &log, elements.iter(), &extended),
+        Err(AuditError::FilterMismatch)
+    );
+}
@@ -0,0 +1,280 @@
+use crate::filter::Filter;
+
+// BIP-37 bloom filters as carried by Bitcoin's `filterload` message:
+// varint length, filter bytes, nHashFuncs (u32 le), nTweak (u32 le), nFlags.
+// Index i of an element is MurmurHash3_x86_32(element, i * 0xFBA4C795 + nTweak)
+// modulo the filter's bit count, bits numbered least significant first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bip37Filter {
+    data: Vec<u8>,
+    hash_funcs: u32,
+    tweak: u32,
+    flags: u8,
+}
+
+pub const MAX_FILTER_BYTES: usize = 36_000;
+pub const MAX_HASH_FUNCS: u32 = 50;
+
+pub const BLOOM_UPDATE_NONE: u8 = 0;
+pub const BLOOM_UPDATE_ALL: u8 = 1;
+pub const BLOOM_UPDATE_P2PUBKEY_ONLY: u8 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Bip37Error {
+    Truncated,
+    FilterTooLarge(usize),
+    TooManyHashFuncs(u32),
+    TrailingBytes(usize),
+}
+
+pub fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+
+    let mut h1 = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k1 = u32::from_le_bytes(block.try_into().unwrap());
+        k1 = k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h1 ^= k1;
+        h1 = h1.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+
+    let tail = blocks.remainder();
+    let mut k1 = 0u32;
+    for (i, byte) in tail.iter().enumerate() {
+        k1 ^= (*byte as u32) << (8 * i);
+    }
+    if !tail.is_empty() {
+        k1 = k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h1 ^= k1;
+    }
+
+    h1 ^= data.len() as u32;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85ebca6b);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xc2b2ae35);
+    h1 ^= h1 >> 16;
+    h1
+}
+
+impl Bip37Filter {
+    // sized like Bitcoin Core's CBloomFilter for `elements` at `fpr`, with
+    // no elements sized for one as Core's callers clamp nElements
+    pub fn new(elements: usize, fpr: f64, tweak: u32, flags: u8) -> Self {
+        let elements = elements.max(1);
+        let ln2 = std::f64::consts::LN_2;
+        let bytes = (-1.0 / (ln2 * ln2) * elements as f64 * fpr.ln() / 8.0) as usize;
+        let bytes = bytes.min(MAX_FILTER_BYTES);
+        // the bits per element rounded down before scaling, as Core's
+        // `vData.size() * 8 / nElements * LN2` divides integers first
+        let hash_funcs = ((bytes * 8 / elements) as f64 * ln2) as u32;
+        Self {
+            data: vec![0u8; bytes],
+            hash_funcs: hash_funcs.min(MAX_HASH_FUNCS),
+            tweak,
+            flags,
+        }
+    }
+
+    fn index(&self, hash_num: u32, element: &[u8]) -> usize {
+        let seed = hash_num.wrapping_mul(0xFBA4C795).wrapping_add(self.tweak);
+        murmur3_32(element, seed) as usize % (self.data.len() * 8)
+    }
+
+    pub fn insert(&mut self, element: &[u8]) {
+        if self.data.is_empty() {
+            return;
+        }
+        for hash_num in 0..self.hash_funcs {
+            let index = self.index(hash_num, element);
+            self.data[index >> 3] |= 1 << (7 & index);
+        }
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        if self.data.is_empty() {
+            return true;
+        }
+        (0..self.hash_funcs).all(|hash_num| {
+            let index = self.index(hash_num, element);
+            self.data[index >> 3] & (1 << (7 & index)) != 0
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn hash_funcs(&self) -> u32 {
+        self.hash_funcs
+    }
+
+    pub fn tweak(&self) -> u32 {
+        self.tweak
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    // payload of a `filterload` message
+    pub fn to_filterload(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.data.len() + 12);
+        write_varint(&mut out, self.data.len() as u64);
+        out.extend_from_slice(&self.data);
+        out.extend_from_slice(&self.hash_funcs.to_le_bytes());
+        out.extend_from_slice(&self.tweak.to_le_bytes());
+        out.push(self.flags);
+        out
+    }
+
+    pub fn from_filterload(payload: &[u8]) -> Result<Self, Bip37Error> {
+        let (length, rest) = read_varint(payload)?;
+        let length = length as usize;
+        if length > MAX_FILTER_BYTES {
+            return Err(Bip37Error::FilterTooLarge(length));
+        }
+        if rest.len() < length + 9 {
+            return Err(Bip37Error::Truncated);
+        }
+        let (data, rest) = rest.split_at(length);
+        let hash_funcs = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+        let tweak = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+        let flags = rest[8];
+        if hash_funcs > MAX_HASH_FUNCS {
+            return Err(Bip37Error::TooManyHashFuncs(hash_funcs));
+        }
+        if rest.len() > 9 {
+            return Err(Bip37Error::TrailingBytes(rest.len() - 9));
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            hash_funcs,
+            tweak,
+            flags,
+        })
+    }
+}
+
+impl Filter for Bip37Filter {
+    fn add(&mut self, element: &[u8]) {
+        self.insert(element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.data.iter().map(|b| b.count_ones()).sum()
+    }
+}
+
+// bitcoin's CompactSize
+pub fn write_varint(out: &mut Vec<u8>, value: u64) {
+    match value {
+        0..=0xfc => out.push(value as u8),
+        0xfd..=0xffff => {
+            out.push(0xfd);
+            out.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        0x10000..=0xffff_ffff => {
+            out.push(0xfe);
+            out.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            out.push(0xff);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+pub fn read_varint(input: &[u8]) -> Result<(u64, &[u8]), Bip37Error> {
+    let (&first, rest) = input.split_first().ok_or(Bip37Error::Truncated)?;
+    let width = match first {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        _ => return Ok((first as u64, rest)),
+    };
+    if rest.len() < width {
+        return Err(Bip37Error::Truncated);
+    }
+    let mut buffer = [0u8; 8];
+    buffer[..width].copy_from_slice(&rest[..width]);
+    Ok((u64::from_le_bytes(buffer), &rest[width..]))
+}
+
+#[test]
+fn test_murmur3() {
+    // vectors from bitcoin core's hash_tests
+    assert_eq!(murmur3_32(b"", 0x00000000), 0x00000000);
+    assert_eq!(murmur3_32(b"", 0xFBA4C795), 0x6a396f08);
+    assert_eq!(murmur3_32(b"", 0xffffffff), 0x81f16f39);
+    assert_eq!(murmur3_32(&[0x00], 0x00000000), 0x514e28b7);
+    assert_eq!(murmur3_32(&[0x00], 0xFBA4C795), 0xea3f0b17);
+    assert_eq!(murmur3_32(&[0xff], 0x00000000), 0xfd6cf10d);
+    assert_eq!(murmur3_32(&[0x00, 0x11], 0x00000000), 0x16c6b7ab);
+    assert_eq!(murmur3_32(&[0x00, 0x11, 0x22], 0x00000000), 0x8eb51c3d);
+    assert_eq!(
+        murmur3_32(&[0x00, 0x11, 0x22, 0x33], 0x00000000),
+        0xb4471bf8
+    );
+    assert_eq!(
+        murmur3_32(&[0x00, 0x11, 0x22, 0x33, 0x44], 0x00000000),
+        0xe2301fa8
+    );
+}
+
+#[test]
+fn test_filterload_vectors() {
+    // bitcoin core's bloom_create_insert_serialize(_with_tweak)
+    let elements = [
+        "99108ad8ed9bb6274d3980bab5a85c048f0950c8",
+        "b5a2c786d9ef4658287ced5914b37a1b4aa32eee",
+        "b9300670b4c5366e95b2699e8b18bc75e5f729c5",
+    ];
+    for (tweak, expected) in [
+        (0, "03614e9b050000000000000001"),
+        (2147483649, "03ce4299050000000100008001"),
+    ] {
+        let mut filter = Bip37Filter::new(3, 0.01, tweak, BLOOM_UPDATE_ALL);
+        for element in elements {
+            filter.insert(&hex::decode(element).unwrap());
+        }
+        assert!(filter.contains(&hex::decode(elements[0]).unwrap()));
+        assert!(!filter.contains(&hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()));
+
+        let payload = filter.to_filterload();
+        assert_eq!(hex::encode(&payload), expected);
+        assert_eq!(Bip37Filter::from_filterload(&payload), Ok(filter));
+    }
+
+    assert_eq!(
+        Bip37Filter::from_filterload(&hex::decode("03614e9b0500000000000000").unwrap()),
+        Err(Bip37Error::Truncated)
+    );
+}
+
+#[test]
+fn test_bip37_sizing() {
+    // (elements, fpr, bytes, hash_funcs) as CBloomFilter's constructor
+    // picks them; 88 bits for 10 elements is 8 per element, so 5 hash
+    // functions where 8.8 per element would give 6
+    for (elements, fpr, bytes, hash_funcs) in [
+        (10, 0.01, 11, 5),
+        (7, 0.05, 5, 3),
+        (3, 0.01, 3, 5),
+        (100_000, 0.0001, MAX_FILTER_BYTES, 1),
+        (0, 0.01, 1, 5),
+    ] {
+        let filter = Bip37Filter::new(elements, fpr, 0, BLOOM_UPDATE_NONE);
+        assert_eq!(filter.bytes().len(), bytes);
+        assert_eq!(filter.hash_funcs(), hash_funcs);
+    }
+    let mut empty = Bip37Filter::new(0, 0.01, 0, BLOOM_UPDATE_NONE);
+    empty.insert(b"one");
+    assert!(empty.contains(b"one"));
+}
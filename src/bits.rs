@@ -0,0 +1,76 @@
+// Bit streams for the Golomb-Rice codings, most significant bit of each byte
+// first.
+
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self::after(Vec::new())
+    }
+
+    // continues after the bytes already written, e.g. a header
+    pub fn after(bytes: Vec<u8>) -> Self {
+        Self {
+            bits: bytes.len() * 8,
+            bytes,
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.bits.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+        }
+        self.bits += 1;
+    }
+
+    // value >> rice in unary (ones ended by a zero), then the low rice bits
+    pub fn push_rice(&mut self, value: u64, rice: u8) {
+        for _ in 0..value >> rice {
+            self.push(true);
+        }
+        self.push(false);
+        for bit in (0..rice).rev() {
+            self.push(value >> bit & 1 == 1);
+        }
+    }
+
+    // zero padded to a whole byte
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, bits: 0 }
+    }
+}
+
+impl Iterator for BitReader<'_> {
+    type Item = bool;
+
+    // None past the end
+    fn next(&mut self) -> Option<bool> {
+        let byte = self.bytes.get(self.bits / 8)?;
+        let bit = byte & (0x80 >> (self.bits % 8)) != 0;
+        self.bits += 1;
+        Some(bit)
+    }
+}
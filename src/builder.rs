@@ -0,0 +1,110 @@
+use crate::{
+    dyn_bloom::{DynBloom, DynBloomError, DynIndexing},
+    params::FilterSpec,
+};
+
+// Runtime-sized filters from what a caller knows about their data instead
+// of m and k:
+//
+//   BloomBuilder::with_capacity(10_000)
+//       .false_positive_rate(1e-4)
+//       .hasher(DynIndexing::Xxh3Rejection)
+//       .build()
+//
+// m and k are the `FilterSpec` for the capacity and rate, so with the
+// default hasher the filter sets the same bits as the `bloom_params!` type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomBuilder {
+    capacity: u64,
+    fpr: f64,
+    indexing: DynIndexing,
+}
+
+pub const DEFAULT_FPR: f64 = 0.01;
+
+#[derive(Debug, PartialEq)]
+pub enum BuildError {
+    NoCapacity,
+    // outside 0 < fpr < 1
+    InvalidFalsePositiveRate(f64),
+    Filter(DynBloomError),
+}
+
+impl BloomBuilder {
+    pub fn with_capacity(capacity: u64) -> Self {
+        Self {
+            capacity,
+            fpr: DEFAULT_FPR,
+            indexing: DynIndexing::Xxh3Rejection,
+        }
+    }
+
+    pub fn false_positive_rate(mut self, fpr: f64) -> Self {
+        self.fpr = fpr;
+        self
+    }
+
+    pub fn hasher(mut self, indexing: DynIndexing) -> Self {
+        self.indexing = indexing;
+        self
+    }
+
+    pub fn spec(&self) -> Result<FilterSpec, BuildError> {
+        if self.capacity == 0 {
+            return Err(BuildError::NoCapacity);
+        }
+        if !(self.fpr > 0.0 && self.fpr < 1.0) {
+            return Err(BuildError::InvalidFalsePositiveRate(self.fpr));
+        }
+        Ok(FilterSpec::new(self.capacity, self.fpr))
+    }
+
+    pub fn build(&self) -> Result<DynBloom, BuildError> {
+        let spec = self.spec()?;
+        DynBloom::try_new(spec.m_bits, spec.k, self.indexing).map_err(BuildError::Filter)
+    }
+}
+
+#[test]
+fn test_bloom_builder() {
+    let mut filter = BloomBuilder::with_capacity(10_000)
+        .false_positive_rate(1e-4)
+        .hasher(DynIndexing::Xxh3Rejection)
+        .build()
+        .unwrap();
+    assert_eq!(filter.m_bits(), 23_963 * 8);
+    assert_eq!(filter.k(), 13);
+    for i in 0..10_000u64 {
+        filter.add(&i.to_le_bytes());
+    }
+    assert!((0..10_000u64).all(|i| filter.has(&i.to_le_bytes())));
+    let false_positives = (1 << 32..(1 << 32) + 100_000u64)
+        .filter(|i| filter.has(&i.to_le_bytes()))
+        .count();
+    assert!(false_positives < 20, "{false_positives}");
+
+    let default = BloomBuilder::with_capacity(1000).build().unwrap();
+    assert_eq!(
+        (default.m_bits(), default.k()),
+        (FilterSpec::new(1000, DEFAULT_FPR).m_bits, 7)
+    );
+    let sip = DynIndexing::SipDouble {
+        keys: [[1; 16], [2; 16]],
+    };
+    let keyed = BloomBuilder::with_capacity(1000)
+        .hasher(sip)
+        .build()
+        .unwrap();
+    assert_eq!(keyed.indexing(), sip);
+
+    assert_eq!(
+        BloomBuilder::with_capacity(0).build(),
+        Err(BuildError::NoCapacity)
+    );
+    assert_eq!(
+        BloomBuilder::with_capacity(10)
+            .false_positive_rate(1.0)
+            .build(),
+        Err(BuildError::InvalidFalsePositiveRate(1.0))
+    );
+}
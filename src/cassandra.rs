@@ -0,0 +1,224 @@
+use crate::filter::Filter;
+
+// Bloom filters from Cassandra SSTable -Filter.db components, as written by
+// BloomFilterSerializer since 3.0: i32 hash count, i32 number of 64-bit words
+// (both big endian), then the bit set's bytes. Bit i lives in byte i / 8 at
+// position i % 8.
+//
+// Index i is |(h1 + i * h0) % bits| over Cassandra's MurmurHash3 x64_128
+// of the partition key with seed 0, using Java's signed arithmetic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CassandraBloom {
+    hash_count: u32,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CassandraError {
+    Truncated,
+    TrailingBytes(usize),
+    // an empty bit set, which indices_for would reduce modulo zero
+    NoWords,
+    NoHashes,
+}
+
+// Cassandra's MurmurHash.hash3_x64_128. Tail bytes are sign extended as in
+// the Java implementation, so hashes differ from the reference MurmurHash3
+// for keys whose tail contains bytes >= 0x80.
+pub fn cassandra_murmur3_x64_128(key: &[u8], seed: u64) -> [u64; 2] {
+    const C1: u64 = 0x87c37b91114253d5;
+    const C2: u64 = 0x4cf5ad432745937f;
+
+    fn fmix(mut k: u64) -> u64 {
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xff51afd7ed558ccd);
+        k ^= k >> 33;
+        k = k.wrapping_mul(0xc4ceb9fe1a85ec53);
+        k ^= k >> 33;
+        k
+    }
+
+    let mut h1 = seed;
+    let mut h2 = seed;
+    let mut blocks = key.chunks_exact(16);
+    for block in &mut blocks {
+        let mut k1 = u64::from_le_bytes(block[..8].try_into().unwrap());
+        let mut k2 = u64::from_le_bytes(block[8..].try_into().unwrap());
+
+        k1 = k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 ^= k1;
+        h1 = h1.rotate_left(27).wrapping_add(h2);
+        h1 = h1.wrapping_mul(5).wrapping_add(0x52dce729);
+
+        k2 = k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 ^= k2;
+        h2 = h2.rotate_left(31).wrapping_add(h1);
+        h2 = h2.wrapping_mul(5).wrapping_add(0x38495ab5);
+    }
+
+    let tail = blocks.remainder();
+    let signed = |i: usize| tail[i] as i8 as i64 as u64;
+    let mut k1 = 0u64;
+    let mut k2 = 0u64;
+    for i in (8..tail.len()).rev() {
+        k2 ^= signed(i) << (8 * (i - 8));
+    }
+    if tail.len() > 8 {
+        k2 = k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1);
+        h2 ^= k2;
+    }
+    for i in (0..tail.len().min(8)).rev() {
+        k1 ^= signed(i) << (8 * i);
+    }
+    if !tail.is_empty() {
+        k1 = k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2);
+        h1 ^= k1;
+    }
+
+    h1 ^= key.len() as u64;
+    h2 ^= key.len() as u64;
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+    [h1, h2]
+}
+
+impl CassandraBloom {
+    pub fn new(words: usize, hash_count: u32) -> Self {
+        Self {
+            hash_count,
+            bytes: vec![0u8; words * 8],
+        }
+    }
+
+    pub fn from_filter_db(data: &[u8]) -> Result<Self, CassandraError> {
+        if data.len() < 8 {
+            return Err(CassandraError::Truncated);
+        }
+        let hash_count = u32::from_be_bytes(data[0..4].try_into().unwrap());
+        let words = u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize;
+        if words == 0 {
+            return Err(CassandraError::NoWords);
+        }
+        if hash_count == 0 {
+            return Err(CassandraError::NoHashes);
+        }
+        let bytes = &data[8..];
+        if bytes.len() < words * 8 {
+            return Err(CassandraError::Truncated);
+        }
+        if bytes.len() > words * 8 {
+            return Err(CassandraError::TrailingBytes(bytes.len() - words * 8));
+        }
+        Ok(Self {
+            hash_count,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    pub fn to_filter_db(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(8 + self.bytes.len());
+        out.extend_from_slice(&self.hash_count.to_be_bytes());
+        out.extend_from_slice(&((self.bytes.len() / 8) as u32).to_be_bytes());
+        out.extend_from_slice(&self.bytes);
+        out
+    }
+
+    pub fn indices_for(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+        let [h0, h1] = cassandra_murmur3_x64_128(key, 0);
+        let bits = (self.bytes.len() * 8) as i64;
+        (0..self.hash_count as i64).map(move |i| {
+            let base = (h1 as i64).wrapping_add(i.wrapping_mul(h0 as i64));
+            (base % bits).wrapping_abs() as usize
+        })
+    }
+
+    pub fn insert(&mut self, key: &[u8]) {
+        for index in self.indices_for(key).collect::<Vec<_>>() {
+            self.bytes[index >> 3] |= 1 << (index & 7);
+        }
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.indices_for(key)
+            .all(|index| self.bytes[index >> 3] & (1 << (index & 7)) != 0)
+    }
+
+    pub fn hash_count(&self) -> u32 {
+        self.hash_count
+    }
+
+    pub fn density(&self) -> f64 {
+        self.count_ones() as f64 / (self.bytes.len() * 8) as f64
+    }
+}
+
+impl Filter for CassandraBloom {
+    fn add(&mut self, element: &[u8]) {
+        self.insert(element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.bytes.iter().map(|b| b.count_ones()).sum()
+    }
+}
+
+// fraction of `keys`, assumed absent from the filter, that test positive
+pub fn sampled_fpr<E: AsRef<[u8]>>(filter: &impl Filter, keys: impl Iterator<Item = E>) -> f64 {
+    let (mut positives, mut total) = (0, 0);
+    for key in keys {
+        total += 1;
+        if filter.has(key.as_ref()) {
+            positives += 1;
+        }
+    }
+    positives as f64 / total as f64
+}
+
+#[test]
+fn test_cassandra_murmur3() {
+    // reference MurmurHash3_x64_128 outputs, all bytes below 0x80
+    assert_eq!(
+        cassandra_murmur3_x64_128(b"hello", 0),
+        [0xcbd8a7b341bd9b02, 0x5b1e906a48ae1d19]
+    );
+    assert_eq!(
+        cassandra_murmur3_x64_128(b"The quick brown fox jumps over the lazy dog", 0),
+        [0xe34bbc7bbc071b6c, 0x7a433ca9c49a9347]
+    );
+}
+
+#[test]
+fn test_cassandra_filter_db() {
+    let mut filter = CassandraBloom::new(16, 5);
+    for i in 0..50u32 {
+        filter.insert(&i.to_be_bytes());
+    }
+    assert!((0..50u32).all(|i| filter.contains(&i.to_be_bytes())));
+
+    let data = filter.to_filter_db();
+    assert_eq!(&data[..8], &[0, 0, 0, 5, 0, 0, 0, 16]);
+    assert_eq!(CassandraBloom::from_filter_db(&data), Ok(filter.clone()));
+    assert_eq!(
+        CassandraBloom::from_filter_db(&data[..20]),
+        Err(CassandraError::Truncated)
+    );
+    assert_eq!(
+        CassandraBloom::from_filter_db(&[0, 0, 0, 3, 0, 0, 0, 0]),
+        Err(CassandraError::NoWords)
+    );
+    assert_eq!(
+        CassandraBloom::from_filter_db(&[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]),
+        Err(CassandraError::NoHashes)
+    );
+
+    let fpr = sampled_fpr(&filter, (1000..11_000u32).map(|i| i.to_be_bytes()));
+    assert!(fpr < 0.1, "fpr {fpr} at density {}", filter.density());
+}
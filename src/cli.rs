@@ -0,0 +1,59 @@
+// minimal `--flag value` handling for the subcommands
+
+pub fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+// every value of a flag that may be repeated
+pub fn flags<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    args.iter()
+        .enumerate()
+        .filter(|(_, arg)| *arg == name)
+        .filter_map(|(i, _)| args.get(i + 1))
+        .map(String::as_str)
+        .collect()
+}
+
+// a flag without a value
+pub fn switch(args: &[String], name: &str) -> bool {
+    args.iter().any(|arg| arg == name)
+}
+
+pub fn required<'a>(args: &'a [String], name: &str) -> Result<&'a str, String> {
+    flag(args, name).ok_or_else(|| format!("missing {name}"))
+}
+
+pub fn parsed<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> Result<T, String> {
+    match flag(args, name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("invalid value for {name}: {value}")),
+        None => Ok(default),
+    }
+}
+
+#[test]
+fn test_flags() {
+    let args: Vec<String> = [
+        "--filter",
+        "f.hex",
+        "--generate",
+        "10",
+        "--hex",
+        "--filter",
+        "g.hex",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    assert_eq!(flag(&args, "--filter"), Some("f.hex"));
+    assert_eq!(parsed(&args, "--generate", 0u64), Ok(10));
+    assert_eq!(parsed(&args, "--missing", 5u64), Ok(5));
+    assert!(required(&args, "--universe").is_err());
+    assert_eq!(flags(&args, "--filter"), vec!["f.hex", "g.hex"]);
+    assert!(switch(&args, "--hex"));
+    assert!(!switch(&args, "--raw"));
+}
@@ -0,0 +1,56 @@
+use crate::{folded::Folded, Bloom};
+
+// Commitments to filters: a domain separated blake3 hash over the filter kind,
+// its parameters (u64 little endian) and its bytes. Lets protocols commit to a
+// filter before revealing it.
+
+fn commit(kind: &[u8], parameters: &[usize], bytes: &[u8]) -> blake3::Hash {
+    let mut hasher = blake3::Hasher::new_derive_key("rust-bloomfilters filter commitment v1");
+    hasher.update(&(kind.len() as u64).to_le_bytes());
+    hasher.update(kind);
+    for parameter in parameters {
+        hasher.update(&(*parameter as u64).to_le_bytes());
+    }
+    hasher.update(bytes);
+    hasher.finalize()
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn commitment(&self) -> [u8; 32] {
+        *commit(b"bloom", &[M, K], &self.bytes).as_bytes()
+    }
+
+    // constant time comparison against a previously published commitment
+    pub fn matches_commitment(&self, commitment: &[u8; 32]) -> bool {
+        commit(b"bloom", &[M, K], &self.bytes) == blake3::Hash::from(*commitment)
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    pub fn commitment(&self) -> [u8; 32] {
+        *commit(b"folded", &[F, S, K], &self.bytes).as_bytes()
+    }
+
+    pub fn matches_commitment(&self, commitment: &[u8; 32]) -> bool {
+        commit(b"folded", &[F, S, K], &self.bytes) == blake3::Hash::from(*commitment)
+    }
+}
+
+#[test]
+fn test_commitment() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    bloom.add(b"Hello, World");
+    let commitment = bloom.commitment();
+    assert!(bloom.matches_commitment(&commitment));
+
+    // same bytes, different parameters
+    let other_k: Bloom<256, 29> = Bloom::from_bytes(bloom.bytes);
+    assert_ne!(other_k.commitment(), commitment);
+
+    bloom.add(b"Test");
+    assert!(!bloom.matches_commitment(&commitment));
+
+    let folded = Folded::<1, 128, 30>::new();
+    assert!(folded.matches_commitment(&folded.commitment()));
+    assert_ne!(folded.commitment(), Bloom::<128, 30>::new().commitment());
+}
@@ -0,0 +1,267 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+use crate::{dyn_bloom::DynBloom, Bloom};
+
+// Bit-level comparisons between filters of identical parameters, e.g. to
+// watch replicas of one logical filter drift apart. `Bloom`'s parameters are
+// part of its type; `DynBloom`s answer None unless m, k and indexing match.
+
+fn hamming_distance(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
+
+fn bits_only_in(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a & !b).count_ones()).sum()
+}
+
+fn overlap_bits(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (a & b).count_ones()).sum()
+}
+
+// Elements in a filter of m bits with k indices given its set bits,
+// -m / k ln(1 - ones / m) (Swamidass and Baldi). Infinite once saturated.
+pub fn estimate_elements(m_bits: usize, k: usize, ones: u32) -> f64 {
+    let m = m_bits as f64;
+    -m / k as f64 * (1.0 - ones as f64 / m).ln()
+}
+
+// |A \ B| as |A ∪ B| - |B|, where the union's ones are B's plus A AND NOT B's.
+fn estimate_difference(m_bits: usize, k: usize, a: &[u8], b: &[u8]) -> f64 {
+    let ones_b: u32 = b.iter().map(|byte| byte.count_ones()).sum();
+    let ones_union = ones_b + bits_only_in(a, b);
+    (estimate_elements(m_bits, k, ones_union) - estimate_elements(m_bits, k, ones_b)).max(0.0)
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // bits set in exactly one of the filters
+    pub fn hamming_distance(&self, other: &Self) -> u32 {
+        hamming_distance(&self.bytes, &other.bytes)
+    }
+
+    // bits set here but not in other
+    pub fn bits_only_in_self(&self, other: &Self) -> u32 {
+        bits_only_in(&self.bytes, &other.bytes)
+    }
+
+    // about how many elements were added here but not to other
+    pub fn estimate_difference_size(&self, other: &Self) -> f64 {
+        estimate_difference(M * 8, K, &self.bytes, &other.bytes)
+    }
+
+    // bits set in both filters
+    pub fn overlap_bits(&self, other: &Self) -> u32 {
+        overlap_bits(&self.bytes, &other.bytes)
+    }
+
+    // no bit set in both, so no element was added to both
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.overlap_bits(other) == 0
+    }
+
+    pub fn canonical(&self) -> Canonical {
+        Canonical::new(self.to_bytes(), self.count_ones())
+    }
+}
+
+impl DynBloom {
+    pub fn same_parameters(&self, other: &Self) -> bool {
+        (self.m_bits(), self.k(), self.indexing()) == (other.m_bits(), other.k(), other.indexing())
+    }
+
+    pub fn hamming_distance(&self, other: &Self) -> Option<u32> {
+        self.same_parameters(other)
+            .then(|| hamming_distance(self.bytes(), other.bytes()))
+    }
+
+    pub fn bits_only_in_self(&self, other: &Self) -> Option<u32> {
+        self.same_parameters(other)
+            .then(|| bits_only_in(self.bytes(), other.bytes()))
+    }
+
+    pub fn estimate_difference_size(&self, other: &Self) -> Option<f64> {
+        self.same_parameters(other)
+            .then(|| estimate_difference(self.m_bits(), self.k(), self.bytes(), other.bytes()))
+    }
+
+    pub fn overlap_bits(&self, other: &Self) -> Option<u32> {
+        self.same_parameters(other)
+            .then(|| overlap_bits(self.bytes(), other.bytes()))
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> Option<bool> {
+        self.overlap_bits(other).map(|overlap| overlap == 0)
+    }
+
+    pub fn canonical(&self) -> Canonical {
+        Canonical::new(self.to_bytes(), self.count_ones())
+    }
+}
+
+// A filter's envelope (`to_bytes`) along with its set bits, as a key for
+// deduplicating, sorting or hashing filters. Equal keys are equal filters of
+// the same type and parameters. Ordering is lexicographic over the
+// envelope, so filters group by kind and m before their bits compare.
+#[derive(Clone, Debug)]
+pub struct Canonical {
+    bytes: Vec<u8>,
+    ones: u32,
+}
+
+impl Canonical {
+    fn new(bytes: Vec<u8>, ones: u32) -> Self {
+        Self { bytes, ones }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn ones(&self) -> u32 {
+        self.ones
+    }
+
+    // Differing set bit counts settle most comparisons of same sized filters
+    // without touching their bytes.
+    pub fn eq_fast(&self, other: &Self) -> bool {
+        self.ones == other.ones && self.bytes == other.bytes
+    }
+}
+
+impl PartialEq for Canonical {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_fast(other)
+    }
+}
+
+impl Eq for Canonical {}
+
+// the set bits follow from the bytes
+impl Hash for Canonical {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state);
+    }
+}
+
+impl PartialOrd for Canonical {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Canonical {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bytes.cmp(&other.bytes)
+    }
+}
+
+#[test]
+fn test_bit_differences() {
+    let mut a = Bloom::<4, 1>::from_bytes([0b0000_0111, 0, 0xff, 0]);
+    let b = Bloom::<4, 1>::from_bytes([0b0000_1100, 0, 0x0f, 0]);
+    assert_eq!(a.hamming_distance(&b), 3 + 4);
+    assert_eq!(a.bits_only_in_self(&b), 2 + 4);
+    assert_eq!(b.bits_only_in_self(&a), 1);
+    a.bytes = b.bytes;
+    assert_eq!(a.hamming_distance(&b), 0);
+
+    let mut replica = DynBloom::new(1001, 7);
+    let mut lagging = DynBloom::new(1001, 7);
+    for i in 0..20u32 {
+        replica.add(&i.to_le_bytes());
+        if i < 15 {
+            lagging.add(&i.to_le_bytes());
+        }
+    }
+    let drift = replica.hamming_distance(&lagging).unwrap();
+    assert!(drift > 0);
+    assert_eq!(replica.bits_only_in_self(&lagging), Some(drift));
+    assert_eq!(lagging.bits_only_in_self(&replica), Some(0));
+    assert_eq!(replica.hamming_distance(&DynBloom::new(1001, 6)), None);
+    assert_eq!(replica.bits_only_in_self(&DynBloom::new(1000, 7)), None);
+}
+
+#[test]
+fn test_estimate_difference_size() {
+    // 300 shared elements, 100 only in a, 50 only in b
+    let mut a = Bloom::<1024, 7>::new();
+    let mut b = Bloom::<1024, 7>::new();
+    let mut dyn_a = DynBloom::new(8192, 7);
+    let mut dyn_b = DynBloom::new(8192, 7);
+    for i in 0..400u32 {
+        a.add(&i.to_le_bytes());
+        dyn_a.add(&i.to_le_bytes());
+    }
+    for i in 100..450u32 {
+        b.add(&i.to_le_bytes());
+        dyn_b.add(&i.to_le_bytes());
+    }
+    let estimate = a.estimate_difference_size(&b);
+    assert!((90.0..110.0).contains(&estimate), "{estimate}");
+    let estimate = b.estimate_difference_size(&a);
+    assert!((40.0..60.0).contains(&estimate), "{estimate}");
+    assert_eq!(a.estimate_difference_size(&a), 0.0);
+    assert_eq!(
+        dyn_a.estimate_difference_size(&dyn_b),
+        Some(a.estimate_difference_size(&b))
+    );
+    assert_eq!(
+        dyn_a.estimate_difference_size(&DynBloom::new(8192, 6)),
+        None
+    );
+}
+
+#[test]
+fn test_overlap_and_disjoint() {
+    let a = Bloom::<4, 1>::from_bytes([0b0000_0111, 0, 0xff, 0]);
+    let b = Bloom::<4, 1>::from_bytes([0b0000_1100, 0, 0x0f, 0]);
+    let c = Bloom::<4, 1>::from_bytes([0b1111_0000, 0xff, 0, 0]);
+    assert_eq!(a.overlap_bits(&b), 1 + 4);
+    assert!(!a.is_disjoint(&b) && a.is_disjoint(&c));
+
+    let mut x = DynBloom::new(1001, 7);
+    let mut y = DynBloom::new(1001, 7);
+    x.add(b"one");
+    y.add(b"two");
+    assert_eq!(x.overlap_bits(&x), Some(x.count_ones()));
+    assert_eq!(x.is_disjoint(&y), Some(x.overlap_bits(&y) == Some(0)));
+    assert_eq!(x.is_disjoint(&DynBloom::new(1000, 7)), None);
+}
+
+#[test]
+fn test_canonical() {
+    use std::collections::BTreeSet;
+
+    let mut one = Bloom::<256, 30>::new();
+    one.add(b"one");
+    let mut two = Bloom::<256, 30>::new();
+    two.add(b"two");
+    let mut dyn_one = DynBloom::new(2048, 30);
+    dyn_one.add(b"one");
+
+    assert!(one.canonical().eq_fast(&one.clone().canonical()));
+    assert_eq!(one.canonical().ones(), one.count_ones());
+    assert_ne!(one.canonical(), two.canonical());
+    // same bits, different envelope kinds
+    assert_eq!(dyn_one.bytes(), one.bytes);
+    assert_ne!(dyn_one.canonical(), one.canonical());
+
+    let filters = [
+        one.canonical(),
+        two.canonical(),
+        one.canonical(),
+        dyn_one.canonical(),
+    ];
+    let unique: BTreeSet<Canonical> = filters.iter().cloned().collect();
+    assert_eq!(unique.len(), 3);
+    let sorted: Vec<&Canonical> = unique.iter().collect();
+    assert!(sorted
+        .windows(2)
+        .all(|pair| pair[0].bytes() < pair[1].bytes()));
+    assert_eq!(
+        one.canonical().cmp(&two.canonical()),
+        one.to_bytes().cmp(&two.to_bytes())
+    );
+}
@@ -0,0 +1,419 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use serde_json::{json, Value};
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+use crate::{
+    cli,
+    dyn_bloom::{DynBloom, DynIndexing},
+    indexer::{Blake3Keyed, BloomIndexer, Murmur3Bip37, Xxh3Double, Xxh3Rejection, Xxh3Wnfs},
+    strategy::{HashStrategy, ALL_STRATEGIES},
+};
+
+// Conformance fixtures shared with other implementations, one JSON file per
+// parameter set at vectors/<strategy>/m<m>-k<k>.json, <strategy> being a
+// `HashStrategy::name`:
+//
+//   {
+//     "m_bits": 1000, "k": 4,
+//     "seed": "..",            hex; the key, tweak (u32 le) or SipHash keys
+//     "elements": [".."],      hex, inserted in order
+//     "indices": [[..]],       per element, in derivation order
+//     "bits": "..",            hex of the filter holding all elements, bit i
+//                              at byte i / 8, position i % 8
+//     "envelope": "..",        optional, hex of `DynBloom::to_bytes`
+//     "absent": [".."]         hex, elements the filter must reject
+//   }
+//
+// `conformance [--dir <dir>]` checks every fixture and prints a matrix of
+// the features each one exercises; `conformance --generate [--dir <dir>]`
+// rewrites the fixtures from this implementation.
+
+const FEATURES: [&str; 4] = ["indices", "bits", "envelope", "membership"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+    // the fixture has no data for the feature
+    Skipped,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FixtureReport {
+    pub name: String,
+    // one per `FEATURES`, or why the fixture couldn't be checked
+    pub outcomes: Result<[Outcome; 4], String>,
+}
+
+// the indices of `element` as `strategy` derives them
+pub fn reference_indices(
+    strategy: HashStrategy,
+    m_bits: usize,
+    k: usize,
+    seed: &[u8],
+    element: &[u8],
+) -> Result<Vec<usize>, String> {
+    let seed_of = |len: usize| {
+        (seed.len() == len)
+            .then_some(seed)
+            .ok_or_else(|| format!("{} needs a {len} byte seed", strategy.name()))
+    };
+    Ok(match strategy {
+        HashStrategy::Xxh3Rejection => {
+            seed_of(0)?;
+            Xxh3Rejection.indices(element, m_bits, k).collect()
+        }
+        HashStrategy::Xxh3Seeded => {
+            seed_of(0)?;
+            let mut indices = Vec::with_capacity(k);
+            for seed in 0..k as u64 {
+                let index = (xxh3_64_with_seed(element, seed) % m_bits as u64) as usize;
+                if !indices.contains(&index) {
+                    indices.push(index);
+                }
+            }
+            indices
+        }
+        HashStrategy::Blake3Keyed => {
+            let key = seed_of(32)?.try_into().unwrap();
+            Blake3Keyed { key }.indices(element, m_bits, k).collect()
+        }
+        HashStrategy::Xxh3Wnfs => {
+            seed_of(0)?;
+            Xxh3Wnfs.indices(element, m_bits, k).collect()
+        }
+        HashStrategy::Murmur3Bip37 => {
+            let tweak = u32::from_le_bytes(seed_of(4)?.try_into().unwrap());
+            Murmur3Bip37 { tweak }.indices(element, m_bits, k).collect()
+        }
+        HashStrategy::SipDouble => sip_double(m_bits, k, seed_of(32)?).indices_for(element),
+        HashStrategy::Xxh3Double => {
+            seed_of(0)?;
+            Xxh3Double.indices(element, m_bits, k).collect()
+        }
+    })
+}
+
+fn sip_double(m_bits: usize, k: usize, seed: &[u8]) -> DynBloom {
+    let keys = [
+        seed[..16].try_into().unwrap(),
+        seed[16..].try_into().unwrap(),
+    ];
+    DynBloom::with_indexing(m_bits, k, DynIndexing::SipDouble { keys })
+}
+
+// strategies with a runtime sized filter, whose envelope fixtures can check
+fn dyn_bloom(strategy: HashStrategy, m_bits: usize, k: usize, seed: &[u8]) -> Option<DynBloom> {
+    match strategy {
+        HashStrategy::Xxh3Rejection => Some(DynBloom::new(m_bits, k)),
+        HashStrategy::SipDouble => Some(sip_double(m_bits, k, seed)),
+        _ => None,
+    }
+}
+
+fn hex_list(fixture: &Value, key: &str) -> Result<Option<Vec<Vec<u8>>>, String> {
+    let Some(list) = fixture.get(key) else {
+        return Ok(None);
+    };
+    list.as_array()
+        .ok_or(format!("{key}: expected an array"))?
+        .iter()
+        .map(|value| {
+            let text = value
+                .as_str()
+                .ok_or(format!("{key}: expected hex strings"))?;
+            hex::decode(text).map_err(|e| format!("{key}: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn positive(fixture: &Value, key: &str) -> Result<usize, String> {
+    fixture
+        .get(key)
+        .and_then(Value::as_u64)
+        .filter(|value| *value > 0)
+        .map(|value| value as usize)
+        .ok_or(format!("{key}: expected a positive integer"))
+}
+
+fn outcome(passed: bool) -> Outcome {
+    if passed {
+        Outcome::Pass
+    } else {
+        Outcome::Fail
+    }
+}
+
+pub fn check_fixture(strategy: HashStrategy, fixture: &Value) -> Result<[Outcome; 4], String> {
+    let m_bits = positive(fixture, "m_bits")?;
+    let k = positive(fixture, "k")?;
+    let seed = match fixture.get("seed").and_then(Value::as_str) {
+        Some(seed) => hex::decode(seed).map_err(|e| format!("seed: {e}"))?,
+        None => Vec::new(),
+    };
+    let elements = hex_list(fixture, "elements")?.ok_or("elements: missing")?;
+    let derived = elements
+        .iter()
+        .map(|element| reference_indices(strategy, m_bits, k, &seed, element))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut bits = vec![0u8; m_bits.div_ceil(8)];
+    for index in derived.iter().flatten() {
+        bits[index / 8] |= 1 << (index % 8);
+    }
+
+    let indices = match fixture.get("indices") {
+        None => Outcome::Skipped,
+        Some(expected) => outcome(*expected == json!(derived)),
+    };
+    let bits_outcome = match fixture.get("bits").and_then(Value::as_str) {
+        None => Outcome::Skipped,
+        Some(expected) => outcome(expected == hex::encode(&bits)),
+    };
+    let envelope = match (
+        fixture.get("envelope").and_then(Value::as_str),
+        dyn_bloom(strategy, m_bits, k, &seed),
+    ) {
+        (Some(expected), Some(mut filter)) => {
+            for element in &elements {
+                filter.add(element);
+            }
+            outcome(expected == hex::encode(filter.to_bytes()))
+        }
+        (Some(_), None) => return Err(format!("{} has no envelope", strategy.name())),
+        (None, _) => Outcome::Skipped,
+    };
+    let has = |element: &[u8]| -> Result<bool, String> {
+        Ok(reference_indices(strategy, m_bits, k, &seed, element)?
+            .iter()
+            .all(|index| bits[index / 8] & (1 << (index % 8)) != 0))
+    };
+    let membership = match hex_list(fixture, "absent")? {
+        None => Outcome::Skipped,
+        Some(absent) => {
+            let mut passed = true;
+            for element in &elements {
+                passed &= has(element)?;
+            }
+            for element in &absent {
+                passed &= !has(element)?;
+            }
+            outcome(passed)
+        }
+    };
+    Ok([indices, bits_outcome, envelope, membership])
+}
+
+pub fn check_dir(dir: &Path) -> Result<Vec<FixtureReport>, String> {
+    let mut reports = Vec::new();
+    let read_dir = |dir: &Path| -> Result<Vec<_>, String> {
+        let mut entries = fs::read_dir(dir)
+            .map_err(|e| format!("can't read {}: {e}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("can't read {}: {e}", dir.display()))?;
+        entries.sort();
+        Ok(entries)
+    };
+    for strategy_dir in read_dir(dir)?.into_iter().filter(|path| path.is_dir()) {
+        let strategy_name = strategy_dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        for path in read_dir(&strategy_dir)? {
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let name = format!(
+                "{strategy_name}/{}",
+                path.file_name().unwrap().to_string_lossy()
+            );
+            let outcomes = HashStrategy::from_name(&strategy_name)
+                .ok_or(format!("unknown strategy {strategy_name}"))
+                .and_then(|strategy| {
+                    let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                    let fixture: Value =
+                        serde_json::from_str(&text).map_err(|e| format!("invalid JSON: {e}"))?;
+                    check_fixture(strategy, &fixture)
+                });
+            reports.push(FixtureReport { name, outcomes });
+        }
+    }
+    Ok(reports)
+}
+
+pub fn render_matrix(reports: &[FixtureReport]) -> String {
+    let width = reports
+        .iter()
+        .map(|report| report.name.len())
+        .chain([7])
+        .max()
+        .unwrap();
+    let mut out = format!("{:width$}", "fixture");
+    for feature in FEATURES {
+        write!(out, "  {feature:10}").unwrap();
+    }
+    out = out.trim_end().to_string() + "\n";
+    for report in reports {
+        let mut line = format!("{:width$}", report.name);
+        match &report.outcomes {
+            Ok(outcomes) => {
+                for outcome in outcomes {
+                    let cell = match outcome {
+                        Outcome::Pass => "pass",
+                        Outcome::Fail => "FAIL",
+                        Outcome::Skipped => "-",
+                    };
+                    write!(line, "  {cell:10}").unwrap();
+                }
+            }
+            Err(message) => write!(line, "  error: {message}").unwrap(),
+        }
+        out += line.trim_end();
+        out.push('\n');
+    }
+    out
+}
+
+// the fixture this implementation answers for one parameter set
+pub fn generate_fixture(strategy: HashStrategy, m_bits: usize, k: usize, seed: &[u8]) -> Value {
+    let elements: Vec<Vec<u8>> = ["", "one", "two", "three", "ducks", "chickens", "goats"]
+        .iter()
+        .map(|element| element.as_bytes().to_vec())
+        .chain((0..20u32).map(|i| i.to_le_bytes().to_vec()))
+        .collect();
+    let indices: Vec<Vec<usize>> = elements
+        .iter()
+        .map(|element| reference_indices(strategy, m_bits, k, seed, element).unwrap())
+        .collect();
+    let mut bits = vec![0u8; m_bits.div_ceil(8)];
+    for index in indices.iter().flatten() {
+        bits[index / 8] |= 1 << (index % 8);
+    }
+    let rejected = |element: &Vec<u8>| {
+        !reference_indices(strategy, m_bits, k, seed, element)
+            .unwrap()
+            .iter()
+            .all(|index| bits[index / 8] & (1 << (index % 8)) != 0)
+    };
+    let absent: Vec<String> = (1000..1100u32)
+        .map(|i| i.to_le_bytes().to_vec())
+        .filter(rejected)
+        .take(10)
+        .map(hex::encode)
+        .collect();
+    let mut fixture = json!({
+        "m_bits": m_bits,
+        "k": k,
+        "seed": hex::encode(seed),
+        "elements": elements.iter().map(hex::encode).collect::<Vec<_>>(),
+        "indices": indices,
+        "bits": hex::encode(&bits),
+        "absent": absent,
+    });
+    if let Some(mut filter) = dyn_bloom(strategy, m_bits, k, seed) {
+        for element in &elements {
+            filter.add(element);
+        }
+        fixture["envelope"] = json!(hex::encode(filter.to_bytes()));
+    }
+    fixture
+}
+
+fn generated_seed(strategy: HashStrategy) -> Vec<u8> {
+    let len = match strategy {
+        HashStrategy::Blake3Keyed | HashStrategy::SipDouble => 32,
+        HashStrategy::Murmur3Bip37 => 4,
+        _ => 0,
+    };
+    blake3::derive_key("conformance fixture seed", strategy.name().as_bytes())[..len].to_vec()
+}
+
+pub fn generate_dir(dir: &Path) -> Result<(), String> {
+    for strategy in ALL_STRATEGIES {
+        let strategy_dir = dir.join(strategy.name());
+        fs::create_dir_all(&strategy_dir)
+            .map_err(|e| format!("can't create {}: {e}", strategy_dir.display()))?;
+        for (m_bits, k) in [(1000, 4), (2048, 30)] {
+            let fixture = generate_fixture(strategy, m_bits, k, &generated_seed(strategy));
+            let path = strategy_dir.join(format!("m{m_bits}-k{k}.json"));
+            let text = serde_json::to_string_pretty(&fixture).unwrap() + "\n";
+            fs::write(&path, text).map_err(|e| format!("can't write {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+// `conformance [--generate] [--dir <dir>]`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let dir = Path::new(cli::flag(args, "--dir").unwrap_or("vectors"));
+    if cli::switch(args, "--generate") {
+        return generate_dir(dir);
+    }
+    let reports = check_dir(dir)?;
+    print!("{}", render_matrix(&reports));
+    let failed = reports
+        .iter()
+        .filter(|report| {
+            report
+                .outcomes
+                .as_ref()
+                .map_or(true, |outcomes| outcomes.contains(&Outcome::Fail))
+        })
+        .count();
+    if failed > 0 {
+        return Err(format!("{failed} of {} fixtures failed", reports.len()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_conformance_fixtures() {
+    let reports = check_dir(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/vectors"))).unwrap();
+    assert_eq!(reports.len(), 2 * ALL_STRATEGIES.len());
+    for report in &reports {
+        let outcomes = report.outcomes.as_ref().unwrap();
+        assert!(!outcomes.contains(&Outcome::Fail), "{}", report.name);
+        assert_eq!(outcomes[0], Outcome::Pass, "{}", report.name);
+    }
+}
+
+#[test]
+fn test_check_fixture() {
+    let mut fixture = generate_fixture(HashStrategy::Xxh3Rejection, 1000, 4, &[]);
+    assert_eq!(
+        check_fixture(HashStrategy::Xxh3Rejection, &fixture),
+        Ok([Outcome::Pass; 4])
+    );
+    fixture["indices"][0][0] = json!(1001);
+    fixture.as_object_mut().unwrap().remove("envelope");
+    assert_eq!(
+        check_fixture(HashStrategy::Xxh3Rejection, &fixture),
+        Ok([
+            Outcome::Fail,
+            Outcome::Pass,
+            Outcome::Skipped,
+            Outcome::Pass
+        ])
+    );
+    assert_eq!(
+        check_fixture(HashStrategy::Blake3Keyed, &fixture),
+        Err("blake3-keyed needs a 32 byte seed".to_string())
+    );
+    let report = render_matrix(&[FixtureReport {
+        name: "xxh3-rejection/m1000-k4.json".to_string(),
+        outcomes: Ok([
+            Outcome::Fail,
+            Outcome::Pass,
+            Outcome::Skipped,
+            Outcome::Pass,
+        ]),
+    }]);
+    assert_eq!(
+        report,
+        "fixture                       indices     bits        envelope    membership\n\
+         xxh3-rejection/m1000-k4.json  FAIL        pass        -           pass\n"
+    );
+}
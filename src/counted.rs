@@ -0,0 +1,165 @@
+use crate::filter::Filter;
+
+// Wraps a filter to count `add` calls and report, once, when the filter
+// grows past what it was designed for. Counts calls, not distinct elements,
+// so re-adding an element counts again.
+pub struct Counted<F> {
+    filter: F,
+    m_bits: usize,
+    inserted: u64,
+    capacity: Option<Capacity>,
+    on_overfill: Box<dyn FnMut(&Overfill)>,
+    warned: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Capacity {
+    pub max_elements: u64,
+    // fraction of bits set
+    pub max_fill: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overfill {
+    Elements { inserted: u64, max: u64 },
+    Fill { fill: f64, max: f64 },
+}
+
+impl Capacity {
+    // Largest n whose expected false positive rate (1 - e^(-kn/m))^k stays
+    // within fpr, and the fill fpr^(1/k) at which a query's k probes hit set
+    // bits with probability fpr.
+    pub fn for_fpr(m_bits: usize, k: usize, fpr: f64) -> Self {
+        let max_fill = fpr.powf(1.0 / k as f64);
+        let max_elements = -(m_bits as f64) / k as f64 * (1.0 - max_fill).ln();
+        Self {
+            max_elements: max_elements.floor() as u64,
+            max_fill,
+        }
+    }
+}
+
+impl<F: Filter> Counted<F> {
+    // m_bits of the wrapped filter, needed for its fill ratio
+    pub fn new(filter: F, m_bits: usize) -> Self {
+        Self {
+            filter,
+            m_bits,
+            inserted: 0,
+            capacity: None,
+            on_overfill: Box::new(|overfill| eprintln!("warning: filter overfilled: {overfill:?}")),
+            warned: false,
+        }
+    }
+
+    // warns on stderr unless `on_overfill` replaces that
+    pub fn with_capacity(mut self, capacity: Capacity) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    pub fn on_overfill(mut self, f: impl FnMut(&Overfill) + 'static) -> Self {
+        self.on_overfill = Box::new(f);
+        self
+    }
+
+    pub fn inserted(&self) -> u64 {
+        self.inserted
+    }
+
+    pub fn fill(&self) -> f64 {
+        self.filter.count_ones() as f64 / self.m_bits as f64
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    pub fn into_inner(self) -> F {
+        self.filter
+    }
+
+    fn overfill(&self) -> Option<Overfill> {
+        let capacity = self.capacity?;
+        if self.inserted > capacity.max_elements {
+            return Some(Overfill::Elements {
+                inserted: self.inserted,
+                max: capacity.max_elements,
+            });
+        }
+        let fill = self.fill();
+        (fill > capacity.max_fill).then_some(Overfill::Fill {
+            fill,
+            max: capacity.max_fill,
+        })
+    }
+}
+
+impl<F: Filter> Filter for Counted<F> {
+    fn add(&mut self, element: &[u8]) {
+        self.filter.add(element);
+        self.inserted += 1;
+        if !self.warned {
+            if let Some(overfill) = self.overfill() {
+                self.warned = true;
+                (self.on_overfill)(&overfill);
+            }
+        }
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.filter.has(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.filter.count_ones()
+    }
+}
+
+#[test]
+fn test_capacity_for_fpr() {
+    // the textbook optimum: n = 1000 at k = 7 needs about 9585 bits for 1%
+    let capacity = Capacity::for_fpr(9585, 7, 0.01);
+    assert!((990..=1010).contains(&capacity.max_elements));
+    assert!((capacity.max_fill - 0.518).abs() < 0.001);
+}
+
+#[test]
+fn test_counted_warns_once() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let warnings = Rc::new(RefCell::new(Vec::new()));
+    let sink = warnings.clone();
+    let mut counted = Counted::new(crate::Bloom::<128, 7>::new(), 1024)
+        .with_capacity(Capacity::for_fpr(1024, 7, 0.01))
+        .on_overfill(move |overfill| sink.borrow_mut().push(*overfill));
+    for i in 0..100u32 {
+        counted.add(&i.to_le_bytes());
+    }
+    assert_eq!(counted.inserted(), 100);
+    assert!(warnings.borrow().is_empty());
+    for i in 100..500u32 {
+        counted.add(&i.to_le_bytes());
+    }
+    assert_eq!(warnings.borrow().len(), 1);
+    // these elements happen to reach the fill limit before the 107th add
+    assert!(matches!(warnings.borrow()[0], Overfill::Fill { .. }));
+
+    let mut counted = Counted::new(crate::Bloom::<128, 7>::new(), 1024)
+        .with_capacity(Capacity {
+            max_elements: 10,
+            max_fill: 1.0,
+        })
+        .on_overfill(move |overfill| {
+            assert_eq!(
+                *overfill,
+                Overfill::Elements {
+                    inserted: 11,
+                    max: 10
+                }
+            )
+        });
+    for i in 0..20u32 {
+        counted.add(&i.to_le_bytes());
+    }
+}
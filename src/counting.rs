@@ -0,0 +1,228 @@
+use crate::{bloom_indices_for_element, element::encode_u64, filter::Filter, Bloom};
+
+// A `Bloom<M, K>` with a counter in place of each of its M * 8 bits, so
+// elements can be removed again. Indices are `Bloom`'s; a counter is nonzero
+// exactly where the plain filter of the same elements has its bit set, as
+// long as no counter saturated. Saturated counters stick at their maximum,
+// since decrementing one could drop an element that's still in the filter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CountingBloom<const M: usize, const K: usize> {
+    counters: Vec<u8>,
+    width: CounterWidth,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterWidth {
+    // two counters per byte, saturating at 15
+    Four,
+    Eight,
+}
+
+impl CounterWidth {
+    pub fn bits(self) -> u32 {
+        match self {
+            CounterWidth::Four => 4,
+            CounterWidth::Eight => 8,
+        }
+    }
+
+    pub fn max(self) -> u8 {
+        match self {
+            CounterWidth::Four => 15,
+            CounterWidth::Eight => u8::MAX,
+        }
+    }
+}
+
+impl<const M: usize, const K: usize> CountingBloom<M, K> {
+    pub fn new(width: CounterWidth) -> Self {
+        let () = Bloom::<M, K>::CHECK;
+        Self {
+            counters: vec![0; M * width.bits() as usize],
+            width,
+        }
+    }
+
+    pub fn width(&self) -> CounterWidth {
+        self.width
+    }
+
+    pub fn counter(&self, index: usize) -> u8 {
+        match self.width {
+            CounterWidth::Four => (self.counters[index / 2] >> (index % 2 * 4)) & 0xf,
+            CounterWidth::Eight => self.counters[index],
+        }
+    }
+
+    fn set_counter(&mut self, index: usize, value: u8) {
+        match self.width {
+            CounterWidth::Four => {
+                let shift = index % 2 * 4;
+                let byte = &mut self.counters[index / 2];
+                *byte = (*byte & !(0xf << shift)) | value << shift;
+            }
+            CounterWidth::Eight => self.counters[index] = value,
+        }
+    }
+
+    // true if a counter left zero, like `Bloom::add` setting a bit
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let mut new = false;
+        for index in self.indices_for(element) {
+            let counter = self.counter(index);
+            new |= counter == 0;
+            if counter < self.width.max() {
+                self.set_counter(index, counter + 1);
+            }
+        }
+        new
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.indices_for(element)
+            .into_iter()
+            .all(|index| self.counter(index) != 0)
+    }
+
+    // Takes element out again, false (changing nothing) if it wasn't in the
+    // filter. Removing an element that was never added, but is a false
+    // positive, corrupts the counts of the elements it collides with.
+    pub fn remove(&mut self, element: &[u8]) -> bool {
+        if !self.has(element) {
+            return false;
+        }
+        for index in self.indices_for(element) {
+            let counter = self.counter(index);
+            if counter < self.width.max() {
+                self.set_counter(index, counter.saturating_sub(1));
+            }
+        }
+        true
+    }
+
+    // see `Bloom::indices_for`
+    pub fn indices_for(&self, element: &[u8]) -> [usize; K] {
+        let mut indices = bloom_indices_for_element(element, M * 8, K);
+        std::array::from_fn(|_| indices.next().unwrap())
+    }
+
+    // nonzero counters, the plain filter's set bits
+    pub fn count_ones(&self) -> u32 {
+        (0..M * 8).filter(|&index| self.counter(index) != 0).count() as u32
+    }
+
+    // counters stuck at their maximum
+    pub fn saturated(&self) -> u32 {
+        (0..M * 8)
+            .filter(|&index| self.counter(index) == self.width.max())
+            .count() as u32
+    }
+
+    // the plain filter answering the same queries
+    pub fn to_bloom(&self) -> Bloom<M, K> {
+        let mut bloom = Bloom::new();
+        for index in (0..M * 8).filter(|&index| self.counter(index) != 0) {
+            bloom.set_bit(index);
+        }
+        bloom
+    }
+}
+
+impl<const M: usize, const K: usize> Filter for CountingBloom<M, K> {
+    fn add(&mut self, element: &[u8]) {
+        CountingBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        CountingBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        CountingBloom::count_ones(self)
+    }
+}
+
+// Churn: `live` elements in the filter after adding and removing as many
+// again, against a plain filter of the live ones. Saturated counters never
+// come down, so they're bits the plain filter wouldn't have set.
+pub fn test_counting_rates() {
+    const PROBES: u64 = 1_000_000;
+
+    println!("width;live;churned;saturated;ones;plain_ones;false_positives;plain_false_positives");
+    for width in [CounterWidth::Four, CounterWidth::Eight] {
+        for live in [10, 20, 47, 100] {
+            for churned in [0, live, 10 * live] {
+                let mut counting = CountingBloom::<256, 30>::new(width);
+                let mut plain = Bloom::<256, 30>::new();
+                for element in 0..churned {
+                    counting.add(&encode_u64(element));
+                }
+                for element in churned..churned + live {
+                    counting.add(&encode_u64(element));
+                    plain.add(&encode_u64(element));
+                }
+                for element in 0..churned {
+                    counting.remove(&encode_u64(element));
+                }
+                let probes = u64::MAX - PROBES..u64::MAX;
+                let false_positives = probes
+                    .clone()
+                    .filter(|&element| counting.has(&encode_u64(element)))
+                    .count();
+                let plain_false_positives = probes
+                    .filter(|&element| plain.has(&encode_u64(element)))
+                    .count();
+                println!(
+                    "{};{live};{churned};{};{};{};{false_positives};{plain_false_positives}",
+                    width.bits(),
+                    counting.saturated(),
+                    counting.count_ones(),
+                    plain.count_ones(),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_counting_bloom() {
+    for width in [CounterWidth::Four, CounterWidth::Eight] {
+        let mut counting = CountingBloom::<256, 30>::new(width);
+        let mut kept = Bloom::<256, 30>::new();
+        for i in 0..10u64 {
+            assert!(counting.add(&encode_u64(i)));
+            if i % 2 == 0 {
+                kept.add(&encode_u64(i));
+            }
+        }
+        assert!(!counting.add(&encode_u64(4)));
+        assert!(counting.remove(&encode_u64(4)));
+        for i in (1..10u64).step_by(2) {
+            assert!(counting.remove(&encode_u64(i)));
+        }
+        assert!(!counting.remove(&encode_u64(100)));
+
+        // no counter saturated, so what's left is exactly the plain filter
+        assert_eq!(counting.saturated(), 0);
+        assert!(counting.to_bloom().bytes == kept.bytes);
+        assert_eq!(counting.count_ones(), kept.count_ones());
+        assert!((0..10u64).step_by(2).all(|i| counting.has(&encode_u64(i))));
+        assert_eq!(
+            counting.indices_for(b"one"),
+            Bloom::<256, 30>::new().indices_for(b"one")
+        );
+    }
+
+    // 200 elements in 8 counters saturate all of them at 4 bits, and
+    // removing every element leaves them at 15
+    let mut tiny = CountingBloom::<1, 1>::new(CounterWidth::Four);
+    for i in 0..200u64 {
+        tiny.add(&encode_u64(i));
+    }
+    assert_eq!(tiny.saturated(), 8);
+    for i in 0..200u64 {
+        assert!(tiny.remove(&encode_u64(i)));
+    }
+    assert_eq!(tiny.saturated(), 8);
+    assert_eq!(tiny.counter(3), 15);
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use crate::filter::Filter;
+
+// Copy-on-write handle to a filter. Clones share the filter until one of
+// them is mutated, so branching off a "what if I add this element" copy is
+// free, and adding an element that every index already covers never copies.
+pub struct CowFilter<F> {
+    inner: Arc<F>,
+}
+
+impl<F> Clone for CowFilter<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<F: Clone> CowFilter<F> {
+    pub fn new(filter: F) -> Self {
+        Self {
+            inner: Arc::new(filter),
+        }
+    }
+
+    pub fn get(&self) -> &F {
+        &self.inner
+    }
+
+    // copies the filter first if another handle shares it
+    pub fn make_mut(&mut self) -> &mut F {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.inner) > 1
+    }
+
+    pub fn into_inner(self) -> F {
+        Arc::unwrap_or_clone(self.inner)
+    }
+}
+
+impl<F: Filter + Clone> Filter for CowFilter<F> {
+    // an element that `has` answers true for sets no new bits
+    fn add(&mut self, element: &[u8]) {
+        if !self.inner.has(element) {
+            self.make_mut().add(element);
+        }
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.inner.has(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.inner.count_ones()
+    }
+}
+
+#[test]
+fn test_cow_filter() {
+    let mut base = CowFilter::new(crate::Bloom::<256, 30>::new());
+    base.add(b"one");
+    assert!(!base.is_shared());
+
+    let mut branch = base.clone();
+    assert!(branch.is_shared());
+    branch.add(b"one");
+    assert!(branch.is_shared(), "re-adding doesn't copy");
+    branch.add(b"two");
+    assert!(!branch.is_shared() && !base.is_shared());
+    assert!(branch.has(b"two") && !base.has(b"two"));
+    assert!(base.has(b"one"));
+    let mut plain = crate::Bloom::<256, 30>::new();
+    plain.add(b"one");
+    assert_eq!(base.into_inner().bytes, plain.bytes);
+}
@@ -0,0 +1,282 @@
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    entropy::EntropySource,
+    filter::Filter,
+    xof::{Xof, XXH3XOF},
+    Bloom,
+};
+
+// Cuckoo filter (Fan et al., 2014): buckets of 4 fingerprints of 1 to 16
+// bits, each element in one of two buckets, i1 and i2 = i1 ^ hash(fp), so
+// either is found from the other and the fingerprint alone. Elements are
+// hashed through `XXH3XOF` like `Bloom`'s: the first u64 picks i1, the
+// second the fingerprint, the rest which slot to kick when both buckets are
+// full. A fingerprint kicked out after `MAX_KICKS` waits in a one-slot stash;
+// while it's occupied the filter is full.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CuckooFilter {
+    // 0 is an empty slot
+    buckets: Vec<[u16; BUCKET_SIZE]>,
+    fingerprint_bits: u32,
+    stash: Option<(usize, u16)>,
+    len: usize,
+}
+
+pub const BUCKET_SIZE: usize = 4;
+
+const MAX_KICKS: usize = 500;
+
+// the load factor 4-slot buckets reach before inserts start failing
+const MAX_LOAD: f64 = 0.95;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CuckooError {
+    // the stash is taken, the element wasn't added
+    Full,
+}
+
+impl CuckooFilter {
+    // buckets is rounded up to a power of two, fingerprints are 1 to 16 bits
+    pub fn new(buckets: usize, fingerprint_bits: u32) -> Self {
+        assert!(
+            (1..=16).contains(&fingerprint_bits),
+            "fingerprints are 1 to 16 bits"
+        );
+        Self {
+            buckets: vec![[0; BUCKET_SIZE]; buckets.max(1).next_power_of_two()],
+            fingerprint_bits,
+            stash: None,
+            len: 0,
+        }
+    }
+
+    // enough buckets for `elements` below the maximum load factor
+    pub fn with_capacity(elements: usize, fingerprint_bits: u32) -> Self {
+        let slots = (elements as f64 / MAX_LOAD).ceil() as usize;
+        Self::new(slots.div_ceil(BUCKET_SIZE), fingerprint_bits)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.capacity() as f64
+    }
+
+    // the fingerprint table's size, to compare with a Bloom filter's m
+    pub fn size_bits(&self) -> usize {
+        self.capacity() * self.fingerprint_bits as usize
+    }
+
+    pub fn fingerprint_bits(&self) -> u32 {
+        self.fingerprint_bits
+    }
+
+    // (i1, fingerprint) and the hash stream continuing after them
+    fn locate<'a>(&self, element: &'a [u8]) -> (usize, u16, XXH3XOF<'a>) {
+        let mut hashes = XXH3XOF::from(element);
+        let index = hashes.next_u64() as usize & (self.buckets.len() - 1);
+        // nonzero, so it never reads as an empty slot
+        let fingerprint = hashes.next_u64() % ((1 << self.fingerprint_bits) - 1) + 1;
+        (index, fingerprint as u16, hashes)
+    }
+
+    fn alternate(&self, index: usize, fingerprint: u16) -> usize {
+        (index ^ xxh3_64(&fingerprint.to_le_bytes()) as usize) & (self.buckets.len() - 1)
+    }
+
+    // puts fingerprint in a free slot of the bucket at index
+    fn place(&mut self, index: usize, fingerprint: u16) -> bool {
+        match self.buckets[index].iter_mut().find(|slot| **slot == 0) {
+            Some(slot) => {
+                *slot = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, element: &[u8]) -> Result<(), CuckooError> {
+        if self.stash.is_some() {
+            return Err(CuckooError::Full);
+        }
+        let (i1, mut fingerprint, mut hashes) = self.locate(element);
+        self.len += 1;
+        let i2 = self.alternate(i1, fingerprint);
+        if self.place(i1, fingerprint) || self.place(i2, fingerprint) {
+            return Ok(());
+        }
+        let mut index = if hashes.next_u64() & 1 == 0 { i1 } else { i2 };
+        for _ in 0..MAX_KICKS {
+            let slot = hashes.next_u64() as usize % BUCKET_SIZE;
+            std::mem::swap(&mut fingerprint, &mut self.buckets[index][slot]);
+            index = self.alternate(index, fingerprint);
+            if self.place(index, fingerprint) {
+                return Ok(());
+            }
+        }
+        self.stash = Some((index, fingerprint));
+        Ok(())
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        let (i1, fingerprint, _) = self.locate(element);
+        let i2 = self.alternate(i1, fingerprint);
+        self.buckets[i1].contains(&fingerprint)
+            || self.buckets[i2].contains(&fingerprint)
+            || self.stash.is_some_and(|(index, stashed)| {
+                stashed == fingerprint && (index == i1 || index == i2)
+            })
+    }
+
+    // Removes one copy of element's fingerprint, false if there's none.
+    // Deleting an element that was never inserted may remove another
+    // element's matching fingerprint.
+    pub fn delete(&mut self, element: &[u8]) -> bool {
+        let (i1, fingerprint, _) = self.locate(element);
+        let i2 = self.alternate(i1, fingerprint);
+        if self
+            .stash
+            .is_some_and(|(index, stashed)| stashed == fingerprint && (index == i1 || index == i2))
+        {
+            self.stash = None;
+            self.len -= 1;
+            return true;
+        }
+        for index in [i1, i2] {
+            if let Some(slot) = self.buckets[index]
+                .iter_mut()
+                .find(|slot| **slot == fingerprint)
+            {
+                *slot = 0;
+                self.len -= 1;
+                // the freed slot may fit the stashed fingerprint
+                if let Some((stashed_index, stashed)) = self.stash {
+                    let alternate = self.alternate(stashed_index, stashed);
+                    if self.place(stashed_index, stashed) || self.place(alternate, stashed) {
+                        self.stash = None;
+                    }
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+// `Filter::add` can't report a full filter, so it panics instead of
+// silently leaving the element out; size with `with_capacity`.
+impl Filter for CuckooFilter {
+    fn add(&mut self, element: &[u8]) {
+        self.insert(element).expect("cuckoo filter is full");
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains(element)
+    }
+
+    // occupied slots
+    fn count_ones(&self) -> u32 {
+        self.len as u32
+    }
+}
+
+// False positives of cuckoo filters with 8 and 16 bit fingerprints next to a
+// `Bloom` of the same 2^18 bits as the folding experiments, for n elements
+// from entropy, each probed with the same `probes` other ones. The cuckoo
+// filters stop at their maximum load.
+pub fn test_cuckoo_rates(mut entropy: impl EntropySource) {
+    const M_BITS: usize = 1 << 18;
+    const PROBES: usize = 1_000_000;
+
+    println!("n;bloom_false_positives;cuckoo8_false_positives;cuckoo16_false_positives");
+    for n in (2000..=30000).step_by(2000) {
+        let mut bloom = Bloom::<{ M_BITS / 8 }, 7>::new();
+        let mut cuckoo8 = CuckooFilter::new(M_BITS / 8 / BUCKET_SIZE, 8);
+        let mut cuckoo16 = CuckooFilter::new(M_BITS / 16 / BUCKET_SIZE, 16);
+        let mut full = [false; 2];
+        for _ in 0..n {
+            let element = entropy.block::<32>();
+            bloom.add(&element);
+            for (cuckoo, full) in [&mut cuckoo8, &mut cuckoo16].into_iter().zip(&mut full) {
+                *full |= cuckoo.insert(&element).is_err();
+            }
+        }
+        let probes: Vec<[u8; 32]> = (0..PROBES).map(|_| entropy.block()).collect();
+        let bloom_false_positives = probes.iter().filter(|probe| bloom.has(&probe[..])).count();
+        let false_positives = |cuckoo: &CuckooFilter, full: bool| match full {
+            true => "full".to_string(),
+            false => probes
+                .iter()
+                .filter(|probe| cuckoo.contains(&probe[..]))
+                .count()
+                .to_string(),
+        };
+        println!(
+            "{n};{bloom_false_positives};{};{}",
+            false_positives(&cuckoo8, full[0]),
+            false_positives(&cuckoo16, full[1])
+        );
+    }
+}
+
+#[test]
+fn test_cuckoo_filter() {
+    use crate::element::encode_u64;
+
+    // 1900 / 0.95 slots round up to 512 buckets; filled to 93%, kicking
+    let mut filter = CuckooFilter::with_capacity(1900, 12);
+    assert_eq!(filter.capacity(), 2048);
+    assert!(filter.is_empty());
+    for i in 0..1900u64 {
+        filter.insert(&encode_u64(i)).unwrap();
+    }
+    assert_eq!(filter.len(), 1900);
+    assert!((0..1900u64).all(|i| filter.contains(&encode_u64(i))));
+    // about 2 * 4 / 2^12 per probe
+    let false_positives = (1900..101_900u64)
+        .filter(|&i| filter.contains(&encode_u64(i)))
+        .count();
+    assert!(false_positives < 400, "{false_positives}");
+
+    for i in (0..1900u64).step_by(2) {
+        assert!(filter.delete(&encode_u64(i)));
+    }
+    assert_eq!(filter.len(), 950);
+    assert!((1..1900u64)
+        .step_by(2)
+        .all(|i| filter.contains(&encode_u64(i))));
+    let still_there = (0..1900u64)
+        .step_by(2)
+        .filter(|&i| filter.contains(&encode_u64(i)))
+        .count();
+    assert!(still_there < 10, "{still_there}");
+
+    // every fingerprint sits in one of its element's two buckets
+    let (i1, fingerprint, _) = filter.locate(b"one");
+    let i2 = filter.alternate(i1, fingerprint);
+    assert_eq!(filter.alternate(i2, fingerprint), i1);
+
+    // overfilling stashes one fingerprint, then refuses
+    let mut small = CuckooFilter::new(4, 16);
+    let mut inserted = 0;
+    while small.insert(&encode_u64(inserted)).is_ok() {
+        inserted += 1;
+    }
+    assert!(small.stash.is_some() && inserted as usize == small.len());
+    assert!((0..inserted).all(|i| small.contains(&encode_u64(i))));
+    for i in 0..inserted {
+        assert!(small.delete(&encode_u64(i)));
+    }
+    assert!(small.is_empty() && small.stash.is_none());
+}
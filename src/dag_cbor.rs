@@ -0,0 +1,290 @@
+use sha2::{Digest, Sha256};
+
+use crate::{folded::Folded, multibase::base32_encode, Bloom};
+
+// DAG-CBOR encodings of filters: a map of the filter's parameters and its
+// bytes, with keys in DAG-CBOR's canonical order (shorter first, then
+// bytewise) and minimal integer encodings, so equal filters always encode to
+// the same block. CIDs are v1 with the dag-cbor codec and a sha2-256
+// multihash.
+//
+//   bloom:  {"k": K, "m": M * 8, "kind": "bloom", "bytes": h'..'}
+//   folded: {"f": F, "k": K, "m": S * 8, "kind": "folded-xxh3-rejection", "bytes": h'..'}
+
+const CID_VERSION: u8 = 0x01;
+const DAG_CBOR_CODEC: u8 = 0x71;
+const SHA2_256_CODE: u8 = 0x12;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DagCborError {
+    Truncated,
+    // well formed CBOR, but not the block this filter type encodes to
+    NotCanonical,
+    KindMismatch,
+    ParameterMismatch {
+        key: &'static str,
+        expected: u64,
+        found: u64,
+    },
+}
+
+fn write_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.extend_from_slice(&[major | 24, value as u8]);
+    } else if value <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_text(out: &mut Vec<u8>, text: &str) {
+    write_head(out, 3, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn encode(kind: &str, parameters: &[(&str, u64)], bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + 32);
+    write_head(&mut out, 5, parameters.len() as u64 + 2);
+    for (key, value) in parameters {
+        write_text(&mut out, key);
+        write_head(&mut out, 0, *value);
+    }
+    write_text(&mut out, "kind");
+    write_text(&mut out, kind);
+    write_text(&mut out, "bytes");
+    write_head(&mut out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+    out
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn head(&mut self) -> Result<(u8, u64), DagCborError> {
+        let (&first, rest) = self.data.split_first().ok_or(DagCborError::Truncated)?;
+        let width = match first & 0x1f {
+            value @ 0..=23 => {
+                self.data = rest;
+                return Ok((first >> 5, value as u64));
+            }
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(DagCborError::NotCanonical),
+        };
+        if rest.len() < width {
+            return Err(DagCborError::Truncated);
+        }
+        let value = rest[..width]
+            .iter()
+            .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+        self.data = &rest[width..];
+        Ok((first >> 5, value))
+    }
+
+    fn take(&mut self, len: u64) -> Result<&'a [u8], DagCborError> {
+        if (self.data.len() as u64) < len {
+            return Err(DagCborError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(len as usize);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn expect_text(&mut self, text: &str) -> Result<(), DagCborError> {
+        match self.head()? {
+            (3, len) if self.take(len)? == text.as_bytes() => Ok(()),
+            _ => Err(DagCborError::NotCanonical),
+        }
+    }
+}
+
+// checks data is exactly the encoding of a filter of this kind and these
+// parameters and returns its bytes
+fn decode<'a>(
+    data: &'a [u8],
+    kind: &str,
+    parameters: &[(&'static str, u64)],
+) -> Result<&'a [u8], DagCborError> {
+    let mut reader = Reader { data };
+    if reader.head()? != (5, parameters.len() as u64 + 2) {
+        return Err(DagCborError::NotCanonical);
+    }
+    for &(key, expected) in parameters {
+        reader.expect_text(key)?;
+        match reader.head()? {
+            (0, found) if found == expected => {}
+            (0, found) => {
+                return Err(DagCborError::ParameterMismatch {
+                    key,
+                    expected,
+                    found,
+                })
+            }
+            _ => return Err(DagCborError::NotCanonical),
+        }
+    }
+    reader.expect_text("kind")?;
+    let (major, len) = reader.head()?;
+    if major != 3 {
+        return Err(DagCborError::NotCanonical);
+    }
+    if reader.take(len)? != kind.as_bytes() {
+        return Err(DagCborError::KindMismatch);
+    }
+    reader.expect_text("bytes")?;
+    let (major, len) = reader.head()?;
+    if major != 2 {
+        return Err(DagCborError::NotCanonical);
+    }
+    let bytes = reader.take(len)?;
+    // rejects trailing data and non-minimal heads
+    if encode(kind, parameters, bytes) != data {
+        return Err(DagCborError::NotCanonical);
+    }
+    Ok(bytes)
+}
+
+// binary CIDv1 of a dag-cbor block
+pub fn cid(block: &[u8]) -> Vec<u8> {
+    let digest = Sha256::digest(block);
+    let mut cid = vec![
+        CID_VERSION,
+        DAG_CBOR_CODEC,
+        SHA2_256_CODE,
+        digest.len() as u8,
+    ];
+    cid.extend_from_slice(&digest);
+    cid
+}
+
+// the CID's usual string form: multibase 'b', lowercase RFC 4648 base32
+// without padding
+pub fn cid_string(cid: &[u8]) -> String {
+    format!("b{}", base32_encode(cid))
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    fn dag_cbor_parameters() -> [(&'static str, u64); 2] {
+        [("k", K as u64), ("m", (M * 8) as u64)]
+    }
+
+    pub fn to_dag_cbor(&self) -> Vec<u8> {
+        encode("bloom", &Self::dag_cbor_parameters(), &self.bytes)
+    }
+
+    pub fn from_dag_cbor(data: &[u8]) -> Result<Self, DagCborError> {
+        let bytes = decode(data, "bloom", &Self::dag_cbor_parameters())?;
+        Ok(Self::from_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn cid(&self) -> Vec<u8> {
+        cid(&self.to_dag_cbor())
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    fn dag_cbor_parameters() -> [(&'static str, u64); 3] {
+        [("f", F as u64), ("k", K as u64), ("m", (S * 8) as u64)]
+    }
+
+    pub fn to_dag_cbor(&self) -> Vec<u8> {
+        encode(
+            "folded-xxh3-rejection",
+            &Self::dag_cbor_parameters(),
+            &self.bytes,
+        )
+    }
+
+    pub fn from_dag_cbor(data: &[u8]) -> Result<Self, DagCborError> {
+        let bytes = decode(data, "folded-xxh3-rejection", &Self::dag_cbor_parameters())?;
+        Ok(Self::from_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn cid(&self) -> Vec<u8> {
+        cid(&self.to_dag_cbor())
+    }
+}
+
+#[test]
+fn test_dag_cbor_encoding() {
+    let bloom = Bloom::<2, 1>::from_bytes([0x12, 0x34]);
+    let expected = [
+        &[0xa4, 0x61, b'k', 0x01, 0x61, b'm', 0x10, 0x64][..],
+        b"kind",
+        &[0x65],
+        b"bloom",
+        &[0x65],
+        b"bytes",
+        &[0x42, 0x12, 0x34],
+    ]
+    .concat();
+    assert_eq!(bloom.to_dag_cbor(), expected);
+    assert_eq!(
+        Bloom::<2, 1>::from_dag_cbor(&expected).unwrap().bytes,
+        bloom.bytes
+    );
+    // sha2-256 of the block, checked with python's hashlib
+    assert_eq!(
+        cid_string(&bloom.cid()),
+        "bafyreiglyhcm7fl5bdc3dljwjsgvu7cnoubuluzx7xek67wjaiagmeegmm"
+    );
+}
+
+#[test]
+fn test_dag_cbor_round_trip() {
+    let mut bloom = Bloom::<256, 30>::new();
+    let mut folded = Folded::<2, 64, 30>::new();
+    for i in 0..10u32 {
+        bloom.add(&i.to_le_bytes());
+        folded.insert(&i.to_le_bytes());
+    }
+    let encoded = bloom.to_dag_cbor();
+    assert_eq!(
+        Bloom::<256, 30>::from_dag_cbor(&encoded).unwrap().bytes,
+        bloom.bytes
+    );
+    let encoded_folded = folded.to_dag_cbor();
+    assert_eq!(
+        Folded::<2, 64, 30>::from_dag_cbor(&encoded_folded)
+            .unwrap()
+            .bytes,
+        folded.bytes
+    );
+    assert_ne!(bloom.cid(), Bloom::<256, 30>::new().cid());
+
+    assert_eq!(
+        Bloom::<256, 16>::from_dag_cbor(&encoded).err(),
+        Some(DagCborError::ParameterMismatch {
+            key: "k",
+            expected: 16,
+            found: 30
+        })
+    );
+    assert_eq!(
+        Folded::<2, 64, 30>::from_dag_cbor(&encoded).err(),
+        Some(DagCborError::NotCanonical)
+    );
+    assert_eq!(
+        Bloom::<256, 30>::from_dag_cbor(&encoded[..encoded.len() - 1]).err(),
+        Some(DagCborError::Truncated)
+    );
+    let mut trailing = encoded.clone();
+    trailing.push(0);
+    assert_eq!(
+        Bloom::<256, 30>::from_dag_cbor(&trailing).err(),
+        Some(DagCborError::NotCanonical)
+    );
+}
@@ -0,0 +1,160 @@
+use crate::{bloom_indices_for_element, element::encode_u64};
+
+// A filter whose entries expire: the bits are grouped into 64-bit blocks,
+// each stamped with the last tick a bit in it was set, and a block whose
+// stamp is `ttl` or more ticks old reads as empty (and is cleared on its
+// next write). Ticks are whatever the caller counts in, e.g. `epoch_at`
+// seconds, and must not go backwards.
+//
+// An element stays present for at least `ttl` ticks after its last add.
+// After that it's gone unless every one of its blocks was refreshed by
+// later elements, so stale entries linger as extra false positives rather
+// than dropping out exactly on time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecayingBloom {
+    blocks: Vec<u64>,
+    stamps: Vec<u64>,
+    m_bits: usize,
+    k: usize,
+    ttl: u64,
+}
+
+const BLOCK_BITS: usize = 64;
+
+impl DecayingBloom {
+    pub fn new(m_bits: usize, k: usize, ttl: u64) -> Self {
+        assert!(m_bits > 0 && k > 0 && ttl > 0);
+        let blocks = m_bits.div_ceil(BLOCK_BITS);
+        Self {
+            blocks: vec![0; blocks],
+            stamps: vec![0; blocks],
+            m_bits,
+            k,
+            ttl,
+        }
+    }
+
+    pub fn m_bits(&self) -> usize {
+        self.m_bits
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn ttl(&self) -> u64 {
+        self.ttl
+    }
+
+    // indices regardless of expiry
+    pub fn indices_for(&self, element: &[u8]) -> Vec<usize> {
+        bloom_indices_for_element(element, self.m_bits, self.k).collect()
+    }
+
+    fn live(&self, block: usize, now: u64) -> bool {
+        now < self.stamps[block].saturating_add(self.ttl)
+    }
+
+    // true if this set a bit that was unset or expired
+    pub fn add(&mut self, element: &[u8], now: u64) -> bool {
+        let mut new = false;
+        for index in bloom_indices_for_element(element, self.m_bits, self.k) {
+            let (block, bit) = (index / BLOCK_BITS, 1u64 << (index % BLOCK_BITS));
+            if !self.live(block, now) {
+                self.blocks[block] = 0;
+            }
+            new |= self.blocks[block] & bit == 0;
+            self.blocks[block] |= bit;
+            self.stamps[block] = now;
+        }
+        new
+    }
+
+    pub fn has(&self, element: &[u8], now: u64) -> bool {
+        bloom_indices_for_element(element, self.m_bits, self.k).all(|index| {
+            let block = index / BLOCK_BITS;
+            self.live(block, now) && self.blocks[block] & (1 << (index % BLOCK_BITS)) != 0
+        })
+    }
+
+    // clears every expired block now instead of on its next write
+    pub fn expire(&mut self, now: u64) {
+        for block in 0..self.blocks.len() {
+            if !self.live(block, now) {
+                self.blocks[block] = 0;
+            }
+        }
+    }
+
+    // set bits in blocks that haven't expired by now
+    pub fn count_ones(&self, now: u64) -> u32 {
+        (0..self.blocks.len())
+            .filter(|&block| self.live(block, now))
+            .map(|block| self.blocks[block].count_ones())
+            .sum()
+    }
+}
+
+// A stream of RATE new elements per tick for TICKS ticks, then at the last
+// tick: elements added within the ttl that are missing (false negatives,
+// zero by construction), older ones still present (stale), and never added
+// ones present (false positives), for a few filter sizes. Small filters
+// have every block refreshed within the ttl, so nothing expires at all.
+pub fn test_decay_rates() {
+    const TICKS: u64 = 200;
+    const RATE: u64 = 50;
+    const TTL: u64 = 20;
+    const PROBES: u64 = 100_000;
+
+    println!("m_bits;k;ttl;recent_missing;stale_present;false_positives");
+    for m_bits in [16384, 65536, 262_144, 1_048_576] {
+        let k = 7;
+        let mut filter = DecayingBloom::new(m_bits, k, TTL);
+        for tick in 0..TICKS {
+            for i in 0..RATE {
+                filter.add(&encode_u64(tick * RATE + i), tick);
+            }
+        }
+        let now = TICKS - 1;
+        let recent = (TICKS - TTL) * RATE..TICKS * RATE;
+        let recent_total = recent.end - recent.start;
+        let recent_missing = recent
+            .filter(|&element| !filter.has(&encode_u64(element), now))
+            .count();
+        let stale = 0..(TICKS - TTL) * RATE;
+        let stale_total = stale.end;
+        let stale_present = stale
+            .filter(|&element| filter.has(&encode_u64(element), now))
+            .count();
+        let false_positives = (TICKS * RATE..TICKS * RATE + PROBES)
+            .filter(|&element| filter.has(&encode_u64(element), now))
+            .count();
+        println!(
+            "{m_bits};{k};{TTL};{recent_missing}/{recent_total};{stale_present}/{stale_total};{false_positives}/{PROBES}"
+        );
+    }
+}
+
+#[test]
+fn test_decaying_bloom() {
+    let mut filter = DecayingBloom::new(4096, 7, 10);
+    assert!(filter.add(b"one", 100));
+    assert!(!filter.add(b"one", 100));
+    assert!(filter.has(b"one", 100) && filter.has(b"one", 109));
+    assert!(!filter.has(b"one", 110));
+    assert!(!filter.has(b"two", 100));
+
+    // re-adding refreshes, and re-sets bits of expired blocks
+    assert!(filter.add(b"one", 115));
+    assert!(filter.has(b"one", 124) && !filter.has(b"one", 125));
+    assert_eq!(filter.count_ones(115), 7);
+
+    filter.add(b"two", 120);
+    let stored = |filter: &DecayingBloom| filter.blocks.iter().map(|b| b.count_ones()).sum::<u32>();
+    assert_eq!(stored(&filter), 14);
+    filter.expire(126);
+    // what survives is the blocks "two" refreshed, bits of "one" included
+    assert_eq!(stored(&filter), filter.count_ones(126));
+    assert!((7..14).contains(&stored(&filter)));
+    assert!(filter.has(b"two", 126) && !filter.has(b"one", 126));
+}
@@ -0,0 +1,93 @@
+use crate::{entropy::EntropySource, filter::Filter, Bloom};
+
+// Every real element is inserted together with `decoys` elements derived from
+// it under a secret key, so the bits changed by one insertion don't single out
+// the element. Decoys are deterministic, inserting an element twice changes
+// nothing. Costs FPR like inserting (1 + decoys) times as many elements.
+#[derive(Clone)]
+pub struct DeniableBloom<const M: usize, const K: usize> {
+    bloom: Bloom<M, K>,
+    key: [u8; 32],
+    decoys: u32,
+}
+
+impl<const M: usize, const K: usize> DeniableBloom<M, K> {
+    pub fn new(key: [u8; 32], decoys: u32) -> Self {
+        Self {
+            bloom: Bloom::new(),
+            key,
+            decoys,
+        }
+    }
+
+    pub fn add(&mut self, element: &[u8]) {
+        self.bloom.add(element);
+        for i in 0..self.decoys {
+            let decoy = blake3::Hasher::new_keyed(&self.key)
+                .update(element)
+                .update(&i.to_le_bytes())
+                .finalize();
+            self.bloom.add(decoy.as_bytes());
+        }
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.bloom.has(element)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bloom.count_ones()
+    }
+
+    pub fn bloom(&self) -> &Bloom<M, K> {
+        &self.bloom
+    }
+}
+
+impl<const M: usize, const K: usize> Filter for DeniableBloom<M, K> {
+    fn add(&mut self, element: &[u8]) {
+        DeniableBloom::add(self, element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        DeniableBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        DeniableBloom::count_ones(self)
+    }
+}
+
+// FPR of a Bloom<256, 30> with 10 real elements for increasing decoy counts
+pub fn test_deniable_rates(mut entropy: impl EntropySource) {
+    const QUERIES: usize = 1_000_000;
+    println!("decoys;ones;false_positives");
+    for decoys in 0..6 {
+        let mut filter: DeniableBloom<256, 30> = DeniableBloom::new([0u8; 32], decoys);
+        for _ in 0..10 {
+            filter.add(&entropy.block::<32>());
+        }
+        let false_positives = (0..QUERIES)
+            .filter(|_| filter.has(&entropy.block::<32>()))
+            .count();
+        println!(
+            "{decoys};{};{false_positives}/{QUERIES}",
+            filter.count_ones()
+        );
+    }
+}
+
+#[test]
+fn test_deniable_insertion() {
+    let mut plain: Bloom<256, 30> = Bloom::new();
+    let mut deniable: DeniableBloom<256, 30> = DeniableBloom::new([9u8; 32], 3);
+    plain.add(b"Hello, World");
+    deniable.add(b"Hello, World");
+
+    assert!(deniable.has(b"Hello, World"));
+    assert!(deniable.count_ones() > plain.count_ones());
+
+    let ones = deniable.count_ones();
+    deniable.add(b"Hello, World");
+    assert_eq!(deniable.count_ones(), ones);
+}
@@ -0,0 +1,362 @@
+use crate::{
+    bloom_indices_for_element,
+    compare::estimate_elements,
+    filter::Filter,
+    indexer::{self, BloomIndexer},
+    params::FilterSpec,
+};
+
+// how a `DynBloom` turns an element into bit indices
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DynIndexing {
+    // same derivation as `Bloom`
+    Xxh3Rejection,
+    // the `bloomfilter` crate's: two SipHash-1-3 hashes h0, h1 of the
+    // element, index i is (h0 + i * h1) % (2^64 - 59) % m
+    SipDouble { keys: [[u8; 16]; 2] },
+}
+
+// Runtime-sized bloom filter: m bits (not necessarily a multiple of 8) and
+// k indices, numbered least significant bit first as in `Bloom`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynBloom {
+    bits: Vec<u8>,
+    m_bits: usize,
+    k: usize,
+    indexing: DynIndexing,
+}
+
+// `Bloom<M, K>` with the size picked at runtime: a `DynBloom` built by `new`
+// derives the same indices
+pub type BloomVec = DynBloom;
+
+// Rejection sampling takes `bits_per_index(m_bits)` bits of a 64-bit hash per
+// candidate, which stays below 64 up to here.
+const XXH3_REJECTION_MAX_BITS: u64 = 1 << 61;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DynBloomError {
+    NoBits,
+    NoHashFunctions,
+    // more indices per element than bits to put them in
+    TooManyHashFunctions { k: usize, m_bits: usize },
+    // the indexing can't address this many bits
+    TooManyBits { m_bits: usize, max: u64 },
+    // `from_bytes` and `BloomRef::new` got a different number of bytes than
+    // m_bits needs
+    BytesLength { expected: usize, found: usize },
+    // bits past m_bits are set in the last byte
+    Padding,
+}
+
+// `try_new`'s checks, shared with the constructors over received bytes
+fn check_parameters(m_bits: usize, k: usize, indexing: DynIndexing) -> Result<(), DynBloomError> {
+    if m_bits == 0 {
+        return Err(DynBloomError::NoBits);
+    }
+    if k == 0 {
+        return Err(DynBloomError::NoHashFunctions);
+    }
+    if k > m_bits {
+        return Err(DynBloomError::TooManyHashFunctions { k, m_bits });
+    }
+    // `SipDouble` reduces 64-bit hashes, so any usize works for it
+    if indexing == DynIndexing::Xxh3Rejection && m_bits as u64 > XXH3_REJECTION_MAX_BITS {
+        return Err(DynBloomError::TooManyBits {
+            m_bits,
+            max: XXH3_REJECTION_MAX_BITS,
+        });
+    }
+    Ok(())
+}
+
+fn check_length(bytes: &[u8], m_bits: usize) -> Result<(), DynBloomError> {
+    if bytes.len() != m_bits.div_ceil(8) {
+        return Err(DynBloomError::BytesLength {
+            expected: m_bits.div_ceil(8),
+            found: bytes.len(),
+        });
+    }
+    Ok(())
+}
+
+impl DynBloom {
+    pub fn new(m_bits: usize, k: usize) -> Self {
+        Self::with_indexing(m_bits, k, DynIndexing::Xxh3Rejection)
+    }
+
+    // Like `with_indexing`, but refuses parameters that would give a filter
+    // which is always full, never set, or indexes past its bits.
+    pub fn try_new(m_bits: usize, k: usize, indexing: DynIndexing) -> Result<Self, DynBloomError> {
+        check_parameters(m_bits, k, indexing)?;
+        Ok(Self::with_indexing(m_bits, k, indexing))
+    }
+
+    // sized by `params` for a capacity and false positive rate
+    pub fn from_spec(spec: FilterSpec) -> Self {
+        Self::new(spec.m_bits, spec.k)
+    }
+
+    pub fn with_indexing(m_bits: usize, k: usize, indexing: DynIndexing) -> Self {
+        Self {
+            bits: vec![0; m_bits.div_ceil(8)],
+            m_bits,
+            k,
+            indexing,
+        }
+    }
+
+    // checked like `try_new`; bits beyond m_bits in the last byte must be zero
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        m_bits: usize,
+        k: usize,
+        indexing: DynIndexing,
+    ) -> Result<Self, DynBloomError> {
+        check_parameters(m_bits, k, indexing)?;
+        check_length(&bytes, m_bits)?;
+        if !m_bits.is_multiple_of(8) && bytes[m_bits / 8] >> (m_bits % 8) != 0 {
+            return Err(DynBloomError::Padding);
+        }
+        Ok(Self {
+            bits: bytes,
+            m_bits,
+            k,
+            indexing,
+        })
+    }
+
+    // true if this set a bit, like `Bloom::add`
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let mut new = false;
+        for index in self.indices_for(element) {
+            new |= self.bits[index / 8] & (1u8 << (index % 8)) == 0;
+            self.bits[index / 8] |= 1u8 << (index % 8);
+        }
+        new
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.indices_for(element)
+            .into_iter()
+            .all(|index| self.bits[index / 8] & (1u8 << (index % 8)) != 0)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bits.iter().map(|byte| byte.count_ones()).sum()
+    }
+
+    // see `Bloom::estimate_len`
+    pub fn estimate_len(&self) -> f64 {
+        estimate_elements(self.m_bits, self.k, self.count_ones())
+    }
+
+    // see `Bloom::estimated_fpr`
+    pub fn estimated_fpr(&self) -> f64 {
+        (self.count_ones() as f64 / self.m_bits as f64).powi(self.k as i32)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
+    pub(crate) fn bits_mut(&mut self) -> &mut [u8] {
+        &mut self.bits
+    }
+
+    pub fn m_bits(&self) -> usize {
+        self.m_bits
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn indexing(&self) -> DynIndexing {
+        self.indexing
+    }
+
+    // the k indices `add` sets, in derivation order
+    pub fn indices_for(&self, element: &[u8]) -> Vec<usize> {
+        match self.indexing {
+            DynIndexing::Xxh3Rejection => indexer::Xxh3Rejection
+                .indices(element, self.m_bits, self.k)
+                .collect(),
+            DynIndexing::SipDouble { keys } => indexer::SipDouble { keys }
+                .indices(element, self.m_bits, self.k)
+                .collect(),
+        }
+    }
+}
+
+// Borrowed, read-only view of xxh3 rejection indexed filter bits, e.g. a
+// slice of a received buffer. Queries like `DynBloom::new(m_bits, k)` would.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BloomRef<'a> {
+    bits: &'a [u8],
+    m_bits: usize,
+    k: usize,
+}
+
+impl<'a> BloomRef<'a> {
+    // checked like `try_new`
+    pub fn new(bits: &'a [u8], m_bits: usize, k: usize) -> Result<Self, DynBloomError> {
+        check_parameters(m_bits, k, DynIndexing::Xxh3Rejection)?;
+        check_length(bits, m_bits)?;
+        Ok(Self { bits, m_bits, k })
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        bloom_indices_for_element(element, self.m_bits, self.k)
+            .all(|index| self.bits[index / 8] & (1u8 << (index % 8)) != 0)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bits.iter().map(|byte| byte.count_ones()).sum()
+    }
+
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bits
+    }
+
+    pub fn m_bits(&self) -> usize {
+        self.m_bits
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+}
+
+impl DynBloom {
+    pub fn as_bloom_ref(&self) -> Option<BloomRef<'_>> {
+        match self.indexing {
+            DynIndexing::Xxh3Rejection => BloomRef::new(&self.bits, self.m_bits, self.k).ok(),
+            DynIndexing::SipDouble { .. } => None,
+        }
+    }
+}
+
+impl<const M: usize, const K: usize> crate::Bloom<M, K> {
+    pub fn as_bloom_ref(&self) -> BloomRef<'_> {
+        BloomRef {
+            bits: &self.bytes,
+            m_bits: M * 8,
+            k: K,
+        }
+    }
+}
+
+impl Filter for DynBloom {
+    fn add(&mut self, element: &[u8]) {
+        DynBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        DynBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        DynBloom::count_ones(self)
+    }
+
+    fn estimate_len(&self) -> Option<f64> {
+        Some(DynBloom::estimate_len(self))
+    }
+
+    fn estimated_fpr(&self) -> Option<f64> {
+        Some(DynBloom::estimated_fpr(self))
+    }
+}
+
+#[test]
+fn test_dyn_bloom_matches_bloom() {
+    let mut bloom = crate::Bloom::<256, 30>::new();
+    let mut dyn_bloom = DynBloom::new(256 * 8, 30);
+    for i in 0..20u32 {
+        bloom.add(&i.to_le_bytes());
+        dyn_bloom.add(&i.to_le_bytes());
+    }
+    assert_eq!(dyn_bloom.bytes(), &bloom.bytes[..]);
+    assert!(dyn_bloom.has(&7u32.to_le_bytes()));
+    let view = dyn_bloom.as_bloom_ref().unwrap();
+    assert_eq!(view, bloom.as_bloom_ref());
+    assert!((0..1000u32).all(|i| view.has(&i.to_le_bytes()) == bloom.has(&i.to_le_bytes())));
+}
+
+#[test]
+fn test_dyn_bloom_odd_size() {
+    let mut bloom = DynBloom::new(1001, 7);
+    for i in 0..50u32 {
+        bloom.add(&i.to_le_bytes());
+    }
+    assert_eq!(bloom.bytes().len(), 126);
+    assert!((0..50u32).all(|i| bloom.has(&i.to_le_bytes())));
+    assert!(
+        DynBloom::from_bytes(bloom.bytes().to_vec(), 1001, 7, DynIndexing::Xxh3Rejection).is_ok()
+    );
+    assert!(DynBloom::from_bytes(vec![0xff; 126], 1001, 7, DynIndexing::Xxh3Rejection).is_err());
+}
+
+#[test]
+fn test_try_new() {
+    use DynIndexing::Xxh3Rejection;
+    let sip = DynIndexing::SipDouble {
+        keys: [[1; 16], [2; 16]],
+    };
+    assert_eq!(
+        DynBloom::try_new(0, 7, Xxh3Rejection),
+        Err(DynBloomError::NoBits)
+    );
+    assert_eq!(
+        DynBloom::try_new(1001, 0, sip),
+        Err(DynBloomError::NoHashFunctions)
+    );
+    assert_eq!(
+        DynBloom::try_new(6, 7, Xxh3Rejection),
+        Err(DynBloomError::TooManyHashFunctions { k: 7, m_bits: 6 })
+    );
+    if let Ok(m_bits) = usize::try_from(XXH3_REJECTION_MAX_BITS + 1) {
+        assert_eq!(
+            DynBloom::try_new(m_bits, 7, Xxh3Rejection),
+            Err(DynBloomError::TooManyBits {
+                m_bits,
+                max: XXH3_REJECTION_MAX_BITS
+            })
+        );
+    }
+    assert_eq!(
+        DynBloom::try_new(1001, 7, sip),
+        Ok(DynBloom::with_indexing(1001, 7, sip))
+    );
+    assert_eq!(
+        DynBloom::try_new(7, 7, Xxh3Rejection),
+        Ok(DynBloom::new(7, 7))
+    );
+
+    // from_bytes and BloomRef::new check the same
+    assert_eq!(
+        DynBloom::from_bytes(vec![], 0, 3, Xxh3Rejection),
+        Err(DynBloomError::NoBits)
+    );
+    assert_eq!(
+        DynBloom::from_bytes(vec![0], 8, 0, sip),
+        Err(DynBloomError::NoHashFunctions)
+    );
+    assert_eq!(BloomRef::new(&[], 0, 3), Err(DynBloomError::NoBits));
+    assert_eq!(
+        BloomRef::new(&[0], 8, 9),
+        Err(DynBloomError::TooManyHashFunctions { k: 9, m_bits: 8 })
+    );
+    assert_eq!(
+        DynBloom::from_bytes(vec![0; 2], 8, 3, Xxh3Rejection),
+        Err(DynBloomError::BytesLength {
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(
+        DynBloom::from_bytes(vec![0x80], 7, 3, Xxh3Rejection),
+        Err(DynBloomError::Padding)
+    );
+}
@@ -0,0 +1,120 @@
+// Canonical byte representations of common element types, so programs
+// adding "the same" value agree on the bytes that get hashed regardless of
+// platform or formatting:
+//
+//   integers   fixed width little endian, two's complement when signed
+//              (what the arrow and sqlite importers already write)
+//   strings    their UTF-8 bytes, unchanged, as `build` adds input lines
+//   bytes      unchanged
+//   pairs      the first encoding's length as u64 little endian, then both
+//              encodings, so ("ab", "c") and ("a", "bc") differ
+//
+// Values of `std::hash::Hash` types don't qualify as is: `Hash` writes
+// integers in native byte order and usize at native width.
+
+pub fn encode_u64(value: u64) -> [u8; 8] {
+    value.to_le_bytes()
+}
+
+pub fn encode_i64(value: i64) -> [u8; 8] {
+    value.to_le_bytes()
+}
+
+pub fn encode_str(value: &str) -> &[u8] {
+    value.as_bytes()
+}
+
+pub fn encode_pair(first: &[u8], second: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(8 + first.len() + second.len());
+    encoded.extend_from_slice(&(first.len() as u64).to_le_bytes());
+    encoded.extend_from_slice(first);
+    encoded.extend_from_slice(second);
+    encoded
+}
+
+// types with a canonical encoding, for `Filter::add_element`
+pub trait Element {
+    fn encode(&self) -> Vec<u8>;
+}
+
+impl Element for u64 {
+    fn encode(&self) -> Vec<u8> {
+        encode_u64(*self).to_vec()
+    }
+}
+
+impl Element for u32 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_le_bytes().to_vec()
+    }
+}
+
+impl Element for i64 {
+    fn encode(&self) -> Vec<u8> {
+        encode_i64(*self).to_vec()
+    }
+}
+
+impl Element for str {
+    fn encode(&self) -> Vec<u8> {
+        encode_str(self).to_vec()
+    }
+}
+
+impl Element for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_str().encode()
+    }
+}
+
+impl Element for [u8] {
+    fn encode(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl Element for Vec<u8> {
+    fn encode(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl<A: Element, B: Element> Element for (A, B) {
+    fn encode(&self) -> Vec<u8> {
+        encode_pair(&self.0.encode(), &self.1.encode())
+    }
+}
+
+impl<T: Element + ?Sized> Element for &T {
+    fn encode(&self) -> Vec<u8> {
+        (**self).encode()
+    }
+}
+
+#[test]
+fn test_canonical_encodings() {
+    assert_eq!(encode_u64(0x0102), [2, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        encode_i64(-2),
+        [0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+    );
+    assert_eq!(encode_str("abc"), b"abc");
+    assert_eq!(encode_pair(b"ab", b"c"), b"\x02\0\0\0\0\0\0\0abc");
+    assert_ne!(("ab", "c").encode(), ("a", "bc").encode());
+    assert_eq!(7u64.encode(), 7u64.to_le_bytes());
+    assert_eq!("one".to_string().encode(), b"one");
+}
+
+#[test]
+fn test_add_element() {
+    use crate::filter::Filter;
+
+    let mut bloom = crate::Bloom::<256, 30>::new();
+    bloom.add_element(&7u64);
+    bloom.add_element("seven");
+    bloom.add_element(&("user", 7u64));
+    assert!(bloom.has(&7u64.to_le_bytes()));
+    assert!(bloom.has(b"seven"));
+    assert!(bloom.has_element(&("user".to_string(), 7u64)));
+    assert!(!bloom.has_element(&("user", 8u64)));
+}
@@ -0,0 +1,136 @@
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+#[cfg(feature = "blake3")]
+use crate::xof::{Blake3XOF, Xof};
+
+// Where experiments get their random elements and coin flips. Passing one
+// in instead of calling `thread_rng` lets the same experiment run with true
+// randomness or reproducibly from a seed:
+//
+//   ThreadEntropy      rand's thread RNG, different every run
+//   ChaChaEntropy      seeded ChaCha (rand's StdRng); reproducible for one
+//                      version of rand, which may change the algorithm
+//   Blake3XOF          a seeded blake3 output stream, blake3's counter mode;
+//                      reproducible everywhere
+//
+// `from_env` is the switch: BLOOM_SEED=<anything> picks a seeded source.
+pub trait EntropySource {
+    fn fill(&mut self, buffer: &mut [u8]);
+
+    fn block<const N: usize>(&mut self) -> [u8; N]
+    where
+        Self: Sized,
+    {
+        let mut block = [0u8; N];
+        self.fill(&mut block);
+        block
+    }
+}
+
+impl<E: EntropySource + ?Sized> EntropySource for &mut E {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        (**self).fill(buffer)
+    }
+}
+
+impl<E: EntropySource + ?Sized> EntropySource for Box<E> {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        (**self).fill(buffer)
+    }
+}
+
+pub struct ThreadEntropy;
+
+impl EntropySource for ThreadEntropy {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        rand::thread_rng().fill_bytes(buffer)
+    }
+}
+
+pub struct ChaChaEntropy(StdRng);
+
+impl ChaChaEntropy {
+    pub fn from_seed(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl EntropySource for ChaChaEntropy {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        self.0.fill_bytes(buffer)
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl EntropySource for Blake3XOF {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        Xof::fill(self, buffer)
+    }
+}
+
+// A seeded source for Some, thread randomness for None. Seeds go through
+// blake3 when it's built in, ChaCha otherwise.
+pub fn from_seed(seed: Option<&str>) -> Box<dyn EntropySource> {
+    match seed {
+        None => Box::new(ThreadEntropy),
+        #[cfg(feature = "blake3")]
+        Some(seed) => Box::new(Blake3XOF::derived(
+            "rust-bloomfilters experiment entropy",
+            seed.as_bytes(),
+        )),
+        #[cfg(not(feature = "blake3"))]
+        Some(seed) => Box::new(ChaChaEntropy::from_seed(xxhash_rust::xxh3::xxh3_64(
+            seed.as_bytes(),
+        ))),
+    }
+}
+
+pub fn from_env() -> Box<dyn EntropySource> {
+    from_seed(std::env::var("BLOOM_SEED").ok().as_deref())
+}
+
+// An `EntropySource` as a `rand::Rng`, for `gen_bool` and friends.
+pub struct AsRng<E>(pub E);
+
+impl<E: EntropySource> RngCore for AsRng<E> {
+    fn next_u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.0.block())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.0.block())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.fill(dest);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_entropy_sources() {
+    use rand::Rng;
+
+    let mut a = ChaChaEntropy::from_seed(7);
+    let mut b = ChaChaEntropy::from_seed(7);
+    assert_eq!(a.block::<32>(), b.block::<32>());
+    assert_ne!(a.block::<32>(), ChaChaEntropy::from_seed(8).block::<32>());
+    assert_ne!(ThreadEntropy.block::<32>(), ThreadEntropy.block::<32>());
+
+    let mut seeded = from_seed(Some("seed"));
+    let mut again = from_seed(Some("seed"));
+    assert_eq!(seeded.block::<100>(), again.block::<100>());
+    let mut flips = AsRng(&mut seeded);
+    assert!(!flips.gen_bool(0.0) && flips.gen_bool(1.0));
+
+    #[cfg(feature = "blake3")]
+    {
+        let expected: Vec<[u8; 32]> = Blake3XOF::new(b"data").blocks().take(2).collect();
+        let mut source = Blake3XOF::new(b"data");
+        assert_eq!([source.block::<32>(), source.block::<32>()], expected[..]);
+    }
+}
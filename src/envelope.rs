@@ -0,0 +1,643 @@
+use crate::{
+    dyn_bloom::{DynBloom, DynBloomError, DynIndexing},
+    folded::{or_fold, Folded},
+    strategy::HashStrategy,
+    wnfs::WnfsBloom,
+    Bloom,
+};
+#[cfg(feature = "blake3")]
+use crate::{keyed::KeyedBloom, oprf::BlindBloom};
+
+// The self-describing binary form every filter type's `to_bytes` produces,
+// all integers little endian:
+//
+//   0   magic "RBLM"
+//   4   version (1)
+//   5   filter kind
+//   6   header length (u16, 24 in version 1)
+//   8   m (u64, bits before folding)
+//   16  k (u32)
+//   20  fold level
+//   21  hash strategy id (`HashStrategy::id`)
+//   22  seed length
+//   23  reserved, zero
+//   ..  seed, then the payload: (m >> fold level) bits, rounded up to bytes
+//
+// Compatibility rules: readers reject versions they don't know, so a new
+// version may change anything after the version byte. Within a version,
+// writers may grow the header by appending fields whose all-zero value keeps
+// today's meaning, and bump the header length; readers skip header bytes
+// past the ones they know. Everything else is strict: reserved bits must be
+// zero and nothing may follow the payload.
+//
+// Seeds are public parameters. `KeyedBloom` keys are secret and never part
+// of the envelope; they have to be passed to `try_from_bytes` separately.
+
+pub const MAGIC: &[u8; 4] = b"RBLM";
+pub const VERSION: u8 = 1;
+const HEADER_LEN: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterKind {
+    Bloom = 1,
+    Folded = 2,
+    Keyed = 3,
+    Wnfs = 4,
+    Dyn = 5,
+    Blind = 6,
+}
+
+const ALL_KINDS: [FilterKind; 6] = [
+    FilterKind::Bloom,
+    FilterKind::Folded,
+    FilterKind::Keyed,
+    FilterKind::Wnfs,
+    FilterKind::Dyn,
+    FilterKind::Blind,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EnvelopeError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    HeaderTooShort(u16),
+    UnknownKind(u8),
+    UnknownStrategy(u8),
+    ReservedNotZero,
+    PayloadLength {
+        expected: usize,
+        found: usize,
+    },
+    // a well formed envelope for a different filter than the one requested
+    Mismatch(&'static str),
+    // ... differing in a numeric parameter the requested type fixes
+    ParameterMismatch {
+        parameter: &'static str,
+        expected: u64,
+        found: u64,
+    },
+    // a `DynBloom` whose m and k `DynBloom::try_new` would refuse
+    InvalidParameters(DynBloomError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    pub kind: FilterKind,
+    pub m_bits: u64,
+    pub k: u32,
+    pub fold_level: u8,
+    pub strategy: HashStrategy,
+    pub seed: &'a [u8],
+    pub payload: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + self.seed.len() + self.payload.len());
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(self.kind as u8);
+        out.extend_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+        out.extend_from_slice(&self.m_bits.to_le_bytes());
+        out.extend_from_slice(&self.k.to_le_bytes());
+        out.push(self.fold_level);
+        out.push(self.strategy.id());
+        out.push(u8::try_from(self.seed.len()).expect("seeds are at most 255 bytes"));
+        out.push(0);
+        out.extend_from_slice(self.seed);
+        out.extend_from_slice(self.payload);
+        out
+    }
+
+    pub fn decode(data: &'a [u8]) -> Result<Self, EnvelopeError> {
+        if data.len() < 8 {
+            return Err(EnvelopeError::Truncated);
+        }
+        if &data[0..4] != MAGIC {
+            return Err(EnvelopeError::BadMagic);
+        }
+        if data[4] != VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(data[4]));
+        }
+        let kind = ALL_KINDS
+            .into_iter()
+            .find(|kind| *kind as u8 == data[5])
+            .ok_or(EnvelopeError::UnknownKind(data[5]))?;
+        let header_len = u16::from_le_bytes([data[6], data[7]]);
+        if (header_len as usize) < HEADER_LEN {
+            return Err(EnvelopeError::HeaderTooShort(header_len));
+        }
+        if data.len() < header_len as usize {
+            return Err(EnvelopeError::Truncated);
+        }
+        let m_bits = u64::from_le_bytes(data[8..16].try_into().unwrap());
+        let k = u32::from_le_bytes(data[16..20].try_into().unwrap());
+        let fold_level = data[20];
+        let strategy =
+            HashStrategy::from_id(data[21]).ok_or(EnvelopeError::UnknownStrategy(data[21]))?;
+        let seed_len = data[22] as usize;
+        if data[23] != 0 {
+            return Err(EnvelopeError::ReservedNotZero);
+        }
+        let rest = &data[header_len as usize..];
+        if rest.len() < seed_len {
+            return Err(EnvelopeError::Truncated);
+        }
+        let (seed, payload) = rest.split_at(seed_len);
+        if fold_level >= 64 {
+            return Err(EnvelopeError::Mismatch("fold level"));
+        }
+        let expected = usize::try_from((m_bits >> fold_level).div_ceil(8))
+            .map_err(|_| EnvelopeError::Mismatch("m"))?;
+        if payload.len() != expected {
+            return Err(EnvelopeError::PayloadLength {
+                expected,
+                found: payload.len(),
+            });
+        }
+        Ok(Self {
+            kind,
+            m_bits,
+            k,
+            fold_level,
+            strategy,
+            seed,
+            payload,
+        })
+    }
+
+    // decodes and checks the parameters a const-generic filter type fixes
+    fn decode_expecting(
+        data: &'a [u8],
+        kind: FilterKind,
+        m_bits: usize,
+        k: usize,
+        fold_level: usize,
+        strategy: HashStrategy,
+    ) -> Result<Self, EnvelopeError> {
+        let envelope = Self::decode(data)?;
+        if envelope.kind != kind {
+            return Err(EnvelopeError::Mismatch("kind"));
+        }
+        if envelope.strategy != strategy {
+            return Err(EnvelopeError::Mismatch("strategy"));
+        }
+        let parameters = [
+            ("m", m_bits as u64, envelope.m_bits),
+            ("k", k as u64, envelope.k as u64),
+            ("fold level", fold_level as u64, envelope.fold_level as u64),
+        ];
+        for (parameter, expected, found) in parameters {
+            if expected != found {
+                return Err(EnvelopeError::ParameterMismatch {
+                    parameter,
+                    expected,
+                    found,
+                });
+            }
+        }
+        if !envelope.seed.is_empty() {
+            return Err(EnvelopeError::Mismatch("seed"));
+        }
+        Ok(envelope)
+    }
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Envelope {
+            kind: FilterKind::Bloom,
+            m_bits: (M * 8) as u64,
+            k: K as u32,
+            fold_level: 0,
+            strategy: HashStrategy::Xxh3Rejection,
+            seed: &[],
+            payload: &self.bytes,
+        }
+        .encode()
+    }
+
+    // the inverse of `to_bytes`; `from_bytes` takes the raw bits
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode_expecting(
+            data,
+            FilterKind::Bloom,
+            M * 8,
+            K,
+            0,
+            HashStrategy::Xxh3Rejection,
+        )?;
+        Ok(Self::from_bytes(envelope.payload.try_into().unwrap()))
+    }
+
+    // The deepest fold level f whose estimated false positive rate,
+    // (ones / bits after folding)^K, stays within target_fpr. Stops while M
+    // is divisible by 2^f, so folded filters keep whole bytes.
+    pub fn max_fold_for_fpr(&self, target_fpr: f64) -> usize {
+        let mut level = 0;
+        while M.is_multiple_of(1 << (level + 1)) {
+            let folded = or_fold(&self.bytes, level + 1);
+            let ones: u32 = folded.iter().map(|byte| byte.count_ones()).sum();
+            let density = ones as f64 / (folded.len() * 8) as f64;
+            if density.powi(K as i32) > target_fpr {
+                break;
+            }
+            level += 1;
+        }
+        level
+    }
+
+    // A `Folded` envelope of the bits OR-folded `max_fold_for_fpr` times;
+    // it decodes as `Folded<f, { M >> f }, K>` for the f in its header and
+    // answers every element added here.
+    pub fn to_bytes_with_max_fpr(&self, target_fpr: f64) -> Vec<u8> {
+        let level = self.max_fold_for_fpr(target_fpr);
+        Envelope {
+            kind: FilterKind::Folded,
+            m_bits: (M * 8) as u64,
+            k: K as u32,
+            fold_level: level as u8,
+            strategy: HashStrategy::Xxh3Rejection,
+            seed: &[],
+            payload: &or_fold(&self.bytes, level),
+        }
+        .encode()
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Envelope {
+            kind: FilterKind::Folded,
+            m_bits: ((S * 8) << F) as u64,
+            k: K as u32,
+            fold_level: F as u8,
+            strategy: HashStrategy::Xxh3Rejection,
+            seed: &[],
+            payload: &self.bytes,
+        }
+        .encode()
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode_expecting(
+            data,
+            FilterKind::Folded,
+            (S * 8) << F,
+            K,
+            F,
+            HashStrategy::Xxh3Rejection,
+        )?;
+        Ok(Self::from_bytes(envelope.payload.try_into().unwrap()))
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl<const M: usize, const K: usize> KeyedBloom<M, K> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Envelope {
+            kind: FilterKind::Keyed,
+            m_bits: (M * 8) as u64,
+            k: K as u32,
+            fold_level: 0,
+            strategy: HashStrategy::Blake3Keyed,
+            seed: &[],
+            payload: self.bytes(),
+        }
+        .encode()
+    }
+
+    pub fn try_from_bytes(key: [u8; 32], data: &[u8]) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode_expecting(
+            data,
+            FilterKind::Keyed,
+            M * 8,
+            K,
+            0,
+            HashStrategy::Blake3Keyed,
+        )?;
+        Ok(Self::from_bytes(key, envelope.payload.try_into().unwrap()))
+    }
+}
+
+impl<const M: usize, const K: usize> WnfsBloom<M, K> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Envelope {
+            kind: FilterKind::Wnfs,
+            m_bits: (M * 8) as u64,
+            k: K as u32,
+            fold_level: 0,
+            strategy: HashStrategy::Xxh3Wnfs,
+            seed: &[],
+            payload: self.bytes(),
+        }
+        .encode()
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode_expecting(
+            data,
+            FilterKind::Wnfs,
+            M * 8,
+            K,
+            0,
+            HashStrategy::Xxh3Wnfs,
+        )?;
+        Ok(Self::from_bytes(envelope.payload.try_into().unwrap()))
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl<const M: usize, const K: usize> BlindBloom<M, K> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Envelope {
+            kind: FilterKind::Blind,
+            m_bits: (M * 8) as u64,
+            k: K as u32,
+            fold_level: 0,
+            strategy: HashStrategy::Xxh3Rejection,
+            seed: &[],
+            payload: self.bytes(),
+        }
+        .encode()
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode_expecting(
+            data,
+            FilterKind::Blind,
+            M * 8,
+            K,
+            0,
+            HashStrategy::Xxh3Rejection,
+        )?;
+        Ok(Self::from_bytes(envelope.payload.try_into().unwrap()))
+    }
+}
+
+impl DynBloom {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (strategy, seed) = match self.indexing() {
+            DynIndexing::Xxh3Rejection => (HashStrategy::Xxh3Rejection, Vec::new()),
+            DynIndexing::SipDouble { keys } => (HashStrategy::SipDouble, keys.concat()),
+        };
+        Envelope {
+            kind: FilterKind::Dyn,
+            m_bits: self.m_bits() as u64,
+            k: self.k() as u32,
+            fold_level: 0,
+            strategy,
+            seed: &seed,
+            payload: self.bytes(),
+        }
+        .encode()
+    }
+
+    pub fn try_from_bytes(data: &[u8]) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode(data)?;
+        if envelope.kind != FilterKind::Dyn {
+            return Err(EnvelopeError::Mismatch("kind"));
+        }
+        if envelope.fold_level != 0 {
+            return Err(EnvelopeError::ParameterMismatch {
+                parameter: "fold level",
+                expected: 0,
+                found: envelope.fold_level as u64,
+            });
+        }
+        let indexing = match (envelope.strategy, envelope.seed.len()) {
+            (HashStrategy::Xxh3Rejection, 0) => DynIndexing::Xxh3Rejection,
+            (HashStrategy::SipDouble, 32) => DynIndexing::SipDouble {
+                keys: [
+                    envelope.seed[..16].try_into().unwrap(),
+                    envelope.seed[16..].try_into().unwrap(),
+                ],
+            },
+            (HashStrategy::Xxh3Rejection | HashStrategy::SipDouble, _) => {
+                return Err(EnvelopeError::Mismatch("seed"))
+            }
+            _ => return Err(EnvelopeError::Mismatch("strategy")),
+        };
+        let m_bits = usize::try_from(envelope.m_bits).map_err(|_| EnvelopeError::Mismatch("m"))?;
+        // `decode` checked the payload's length
+        DynBloom::from_bytes(
+            envelope.payload.to_vec(),
+            m_bits,
+            envelope.k as usize,
+            indexing,
+        )
+        .map_err(|e| match e {
+            DynBloomError::Padding => EnvelopeError::Mismatch("padding"),
+            e => EnvelopeError::InvalidParameters(e),
+        })
+    }
+}
+
+#[test]
+fn test_envelope_layout() {
+    let bloom = Bloom::<2, 1>::from_bytes([0x12, 0x34]);
+    let expected = [
+        &b"RBLM"[..],
+        &[1, 1, 24, 0],
+        &16u64.to_le_bytes(),
+        &1u32.to_le_bytes(),
+        &[0, 1, 0, 0],
+        &[0x12, 0x34],
+    ]
+    .concat();
+    assert_eq!(bloom.to_bytes(), expected);
+    assert_eq!(
+        Bloom::<2, 1>::try_from_bytes(&expected).unwrap().bytes,
+        bloom.bytes
+    );
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_envelope_round_trips() {
+    let mut bloom = Bloom::<256, 30>::new();
+    let mut folded = Folded::<2, 64, 30>::new();
+    let mut keyed = KeyedBloom::<256, 30>::new([3; 32]);
+    let mut wnfs = WnfsBloom::<256, 30>::new();
+    let mut dyn_bloom = DynBloom::new(1001, 7);
+    let mut sip = DynBloom::with_indexing(
+        1024,
+        6,
+        DynIndexing::SipDouble {
+            keys: [[1; 16], [2; 16]],
+        },
+    );
+    for i in 0..10u32 {
+        let element = i.to_le_bytes();
+        bloom.add(&element);
+        folded.insert(&element);
+        keyed.add(&element);
+        wnfs.add(&element);
+        dyn_bloom.add(&element);
+        sip.add(&element);
+    }
+    assert_eq!(
+        Bloom::<256, 30>::try_from_bytes(&bloom.to_bytes())
+            .unwrap()
+            .bytes,
+        bloom.bytes
+    );
+    assert_eq!(
+        Folded::<2, 64, 30>::try_from_bytes(&folded.to_bytes())
+            .unwrap()
+            .bytes,
+        folded.bytes
+    );
+    let decoded = KeyedBloom::<256, 30>::try_from_bytes([3; 32], &keyed.to_bytes()).unwrap();
+    assert_eq!(decoded.bytes(), keyed.bytes());
+    assert!(decoded.has(&7u32.to_le_bytes()));
+    assert_eq!(
+        WnfsBloom::<256, 30>::try_from_bytes(&wnfs.to_bytes())
+            .unwrap()
+            .bytes(),
+        wnfs.bytes()
+    );
+    assert_eq!(
+        DynBloom::try_from_bytes(&dyn_bloom.to_bytes()).unwrap(),
+        dyn_bloom
+    );
+    assert_eq!(DynBloom::try_from_bytes(&sip.to_bytes()).unwrap(), sip);
+}
+
+#[test]
+fn test_envelope_validation() {
+    let bytes = Bloom::<256, 30>::new().to_bytes();
+    assert_eq!(
+        Bloom::<256, 16>::try_from_bytes(&bytes).err(),
+        Some(EnvelopeError::ParameterMismatch {
+            parameter: "k",
+            expected: 16,
+            found: 30
+        })
+    );
+    assert_eq!(
+        Bloom::<128, 30>::try_from_bytes(&bytes).err(),
+        Some(EnvelopeError::ParameterMismatch {
+            parameter: "m",
+            expected: 1024,
+            found: 2048
+        })
+    );
+    assert_eq!(
+        Folded::<1, 128, 30>::try_from_bytes(&Folded::<2, 64, 30>::new().to_bytes()).err(),
+        Some(EnvelopeError::ParameterMismatch {
+            parameter: "fold level",
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(
+        WnfsBloom::<256, 30>::try_from_bytes(&bytes).err(),
+        Some(EnvelopeError::Mismatch("kind"))
+    );
+    assert_eq!(
+        DynBloom::try_from_bytes(&bytes).err(),
+        Some(EnvelopeError::Mismatch("kind"))
+    );
+    let corrupt = |offset: usize, value: u8| {
+        let mut bytes = bytes.clone();
+        bytes[offset] = value;
+        Envelope::decode(&bytes).err()
+    };
+    assert_eq!(corrupt(0, b'X'), Some(EnvelopeError::BadMagic));
+    assert_eq!(corrupt(4, 2), Some(EnvelopeError::UnsupportedVersion(2)));
+    assert_eq!(corrupt(5, 99), Some(EnvelopeError::UnknownKind(99)));
+    assert_eq!(corrupt(6, 23), Some(EnvelopeError::HeaderTooShort(23)));
+    assert_eq!(corrupt(21, 0), Some(EnvelopeError::UnknownStrategy(0)));
+    assert_eq!(corrupt(23, 1), Some(EnvelopeError::ReservedNotZero));
+    assert_eq!(
+        Envelope::decode(&bytes[..bytes.len() - 1]).err(),
+        Some(EnvelopeError::PayloadLength {
+            expected: 256,
+            found: 255
+        })
+    );
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+        Envelope::decode(&trailing).err(),
+        Some(EnvelopeError::PayloadLength {
+            expected: 256,
+            found: 257
+        })
+    );
+}
+
+#[test]
+fn test_envelope_longer_header() {
+    // a later writer appending header fields: older readers skip them
+    let bloom = Bloom::<2, 1>::from_bytes([0x12, 0x34]);
+    let mut bytes = bloom.to_bytes();
+    bytes[6] = 28;
+    bytes.splice(24..24, [0xaa; 4]);
+    assert_eq!(
+        Bloom::<2, 1>::try_from_bytes(&bytes).unwrap().bytes,
+        bloom.bytes
+    );
+}
+
+#[test]
+fn test_to_bytes_with_max_fpr() {
+    let mut bloom = Bloom::<256, 30>::new();
+    for i in 0..10u32 {
+        bloom.add(&i.to_le_bytes());
+    }
+    assert_eq!(bloom.max_fold_for_fpr(1e-9), 2);
+    let encoded = bloom.to_bytes_with_max_fpr(1e-9);
+    assert_eq!(encoded.len(), HEADER_LEN + 64);
+    let folded = Folded::<2, 64, 30>::try_from_bytes(&encoded).unwrap();
+    assert!((0..10u32).all(|i| folded.has(&i.to_le_bytes())));
+    assert_eq!(folded.bytes.to_vec(), or_fold(&bloom.bytes, 2));
+
+    // stricter targets fold less, anything goes down to a single byte
+    assert_eq!(bloom.max_fold_for_fpr(1e-30), 0);
+    assert_eq!(bloom.max_fold_for_fpr(1.0), 8);
+    assert!(Folded::<0, 256, 30>::try_from_bytes(&bloom.to_bytes_with_max_fpr(1e-30)).is_ok());
+}
+
+#[test]
+fn test_dyn_envelope_parameters() {
+    let envelope = |m_bits: u64, k: u32, strategy, seed: &[u8]| {
+        Envelope {
+            kind: FilterKind::Dyn,
+            m_bits,
+            k,
+            fold_level: 0,
+            strategy,
+            seed,
+            payload: &vec![0; m_bits.div_ceil(8) as usize],
+        }
+        .encode()
+    };
+    let decode = |m_bits, k| {
+        DynBloom::try_from_bytes(&envelope(m_bits, k, HashStrategy::Xxh3Rejection, &[])).err()
+    };
+    assert_eq!(
+        decode(0, 1),
+        Some(EnvelopeError::InvalidParameters(DynBloomError::NoBits))
+    );
+    assert_eq!(
+        decode(64, 0),
+        Some(EnvelopeError::InvalidParameters(
+            DynBloomError::NoHashFunctions
+        ))
+    );
+    assert_eq!(
+        decode(8, 9),
+        Some(EnvelopeError::InvalidParameters(
+            DynBloomError::TooManyHashFunctions { k: 9, m_bits: 8 }
+        ))
+    );
+    assert_eq!(decode(8, 8), None);
+    let sip = envelope(0, 1, HashStrategy::SipDouble, &[0; 32]);
+    assert_eq!(
+        DynBloom::try_from_bytes(&sip).err(),
+        Some(EnvelopeError::InvalidParameters(DynBloomError::NoBits))
+    );
+}
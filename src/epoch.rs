@@ -0,0 +1,88 @@
+use crate::keyed::KeyedBloom;
+
+// Keyed filter whose key rotates every epoch, so a given bit pattern only
+// says something about membership for one epoch's lifetime. The epoch key
+// is derived from a long-lived master key and the epoch number.
+pub struct EpochFilter<const M: usize, const K: usize> {
+    master_key: [u8; 32],
+    epoch: u64,
+    filter: KeyedBloom<M, K>,
+}
+
+pub fn epoch_key(master_key: &[u8; 32], epoch: u64) -> [u8; 32] {
+    let mut material = [0u8; 40];
+    material[..32].copy_from_slice(master_key);
+    material[32..].copy_from_slice(&epoch.to_le_bytes());
+    blake3::derive_key("rust-bloomfilters epoch key v1", &material)
+}
+
+// epoch number for a unix timestamp, with epochs of `epoch_length` seconds
+pub fn epoch_at(unix_seconds: u64, epoch_length: u64) -> u64 {
+    unix_seconds / epoch_length
+}
+
+impl<const M: usize, const K: usize> EpochFilter<M, K> {
+    pub fn new(master_key: [u8; 32], epoch: u64) -> Self {
+        Self {
+            master_key,
+            epoch,
+            filter: KeyedBloom::new(epoch_key(&master_key, epoch)),
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        self.filter.add(element)
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.filter.has(element)
+    }
+
+    // indices under this epoch's key
+    pub fn indices_for(&self, element: &[u8]) -> [usize; K] {
+        self.filter.indices_for(element)
+    }
+
+    pub fn filter(&self) -> &KeyedBloom<M, K> {
+        &self.filter
+    }
+
+    // Moves to the next epoch, rebuilding the filter from `elements`.
+    // Elements not passed in are dropped from the filter.
+    pub fn migrate<E: AsRef<[u8]>>(&mut self, elements: impl Iterator<Item = E>) {
+        let mut next = Self::new(self.master_key, self.epoch + 1);
+        for element in elements {
+            next.add(element.as_ref());
+        }
+        *self = next;
+    }
+}
+
+#[test]
+fn test_epoch_migration() {
+    let mut filter: EpochFilter<256, 30> = EpochFilter::new([5u8; 32], 7);
+    let elements: Vec<[u8; 1]> = (0..10u8).map(|i| [i]).collect();
+    for element in elements.iter() {
+        filter.add(element);
+    }
+    let old_bytes = *filter.filter().bytes();
+
+    filter.migrate(elements.iter().skip(5));
+
+    assert_eq!(filter.epoch(), 8);
+    assert!(elements.iter().skip(5).all(|e| filter.has(e)));
+    assert!(!filter.has(&elements[0]));
+    // same elements, unrelated bit pattern
+    let mut same_epoch: EpochFilter<256, 30> = EpochFilter::new([5u8; 32], 7);
+    for e in elements.iter().skip(5) {
+        same_epoch.add(e);
+    }
+    assert_ne!(filter.filter().bytes(), same_epoch.filter().bytes());
+    assert_ne!(filter.filter().bytes(), &old_bytes);
+
+    assert_eq!(epoch_at(86_400 * 3 + 5, 86_400), 3);
+}
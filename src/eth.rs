@@ -0,0 +1,167 @@
+use sha3::{Digest, Keccak256};
+
+use crate::filter::Filter;
+
+// Ethereum's 2048-bit logs bloom from block headers and receipts. Each input
+// sets 3 bits, taken from the first three 16-bit big endian words of its
+// keccak256 hash modulo 2048. The bloom is big endian: bit b lives in byte
+// 255 - b / 8.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthLogsBloom {
+    pub bytes: [u8; 256],
+}
+
+pub struct Log<'a> {
+    pub address: &'a [u8; 20],
+    pub topics: &'a [[u8; 32]],
+}
+
+fn eth_indices(input: &[u8]) -> [usize; 3] {
+    let hash = Keccak256::digest(input);
+    [0, 2, 4].map(|i| (((hash[i] as usize) << 8) | hash[i + 1] as usize) & 2047)
+}
+
+impl EthLogsBloom {
+    pub fn new() -> Self {
+        Self { bytes: [0u8; 256] }
+    }
+
+    pub fn from_bytes(bytes: [u8; 256]) -> Self {
+        Self { bytes }
+    }
+
+    // the bloom of a receipt: every log's address and topics
+    pub fn from_logs(logs: &[Log]) -> Self {
+        let mut bloom = Self::new();
+        for log in logs {
+            bloom.accrue_log(log);
+        }
+        bloom
+    }
+
+    pub fn accrue_log(&mut self, log: &Log) {
+        self.accrue(log.address);
+        for topic in log.topics {
+            self.accrue(topic);
+        }
+    }
+
+    pub fn accrue(&mut self, input: &[u8]) {
+        for bit in eth_indices(input) {
+            self.bytes[255 - bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        eth_indices(input)
+            .iter()
+            .all(|bit| self.bytes[255 - bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    // block bloom: the union of all receipt blooms
+    pub fn accrue_bloom(&mut self, other: &EthLogsBloom) {
+        for (byte, other) in self.bytes.iter_mut().zip(other.bytes.iter()) {
+            *byte |= other;
+        }
+    }
+}
+
+impl Default for EthLogsBloom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filter for EthLogsBloom {
+    fn add(&mut self, element: &[u8]) {
+        self.accrue(element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains_input(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.bytes.iter().map(|b| b.count_ones()).sum()
+    }
+}
+
+// Mainnet block 14764013 (0x720704f3...f09c), header and receipts as in
+// trin's test assets: `block <number> <logsBloom hex>`, then for each receipt
+// `receipt <logsBloom hex>` followed by one `log <address> <topics...>` line
+// per log.
+#[cfg(test)]
+const MAINNET_BLOCK: &str = include_str!("../vectors/eth_block_14764013.txt");
+
+#[cfg(test)]
+fn bloom_from_hex(text: &str) -> EthLogsBloom {
+    EthLogsBloom::from_bytes(hex::decode(text).unwrap().try_into().unwrap())
+}
+
+#[test]
+fn test_eth_bloom_extensively() {
+    // go-ethereum's core/types TestBloomExtensively
+    let mut bloom = EthLogsBloom::new();
+    for i in 0..100 {
+        bloom.accrue(format!("xxxxxxxxxx data {i} yyyyyyyyyyyyyy").as_bytes());
+    }
+    assert_eq!(
+        hex::encode(Keccak256::digest(bloom.bytes)),
+        "c8d3ca65cdb4874300a9e39475508f23ed6da09fdbc487f89a2dcf50b09eb263"
+    );
+    assert!(bloom.contains_input(b"xxxxxxxxxx data 42 yyyyyyyyyyyyyy"));
+}
+
+#[test]
+fn test_eth_from_logs() {
+    let address = [0x11u8; 20];
+    let topics = [[0x22u8; 32], [0x33u8; 32]];
+    let bloom = EthLogsBloom::from_logs(&[Log {
+        address: &address,
+        topics: &topics,
+    }]);
+    assert!(bloom.contains_input(&address));
+    assert!(bloom.contains_input(&topics[1]));
+    assert!(bloom.count_ones() <= 9);
+}
+
+#[test]
+fn test_eth_mainnet_block() {
+    let mut lines = MAINNET_BLOCK.lines();
+    let header = bloom_from_hex(lines.next().unwrap().split(' ').nth(2).unwrap());
+    // each receipt's bloom and its logs' addresses and topics
+    type Receipt = (EthLogsBloom, Vec<([u8; 20], Vec<[u8; 32]>)>);
+    let mut receipts: Vec<Receipt> = Vec::new();
+    for line in lines {
+        let mut fields = line.split(' ');
+        match fields.next() {
+            Some("receipt") => receipts.push((bloom_from_hex(fields.next().unwrap()), Vec::new())),
+            Some("log") => {
+                let mut fields = fields.map(|field| hex::decode(field).unwrap());
+                let address = fields.next().unwrap().try_into().unwrap();
+                let topics = fields.map(|topic| topic.try_into().unwrap()).collect();
+                receipts.last_mut().unwrap().1.push((address, topics));
+            }
+            other => panic!("unexpected line {other:?}"),
+        }
+    }
+    assert_eq!(receipts.len(), 19);
+
+    // every receipt's bloom from its logs, and the header's from all of them
+    let mut block = EthLogsBloom::new();
+    for (expected, logs) in &receipts {
+        let logs: Vec<Log> = logs
+            .iter()
+            .map(|(address, topics)| Log { address, topics })
+            .collect();
+        let bloom = EthLogsBloom::from_logs(&logs);
+        assert_eq!(&bloom, expected);
+        block.accrue_bloom(&bloom);
+    }
+    assert_eq!(block, header);
+    assert!(receipts
+        .iter()
+        .flat_map(|(_, logs)| logs)
+        .all(|(address, topics)| header.contains_input(address)
+            && topics.iter().all(|topic| header.contains_input(topic))));
+}
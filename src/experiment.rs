@@ -0,0 +1,447 @@
+use std::{fmt::Write as _, fs, io::Write as _};
+
+use toml::{Table, Value};
+
+use crate::{
+    cli,
+    dyn_bloom::{DynBloom, DynIndexing},
+    harness::{count_false_positives, fill_deterministic},
+};
+
+// Experiments described by a TOML file, run with `run --config <file>`:
+//
+//   name = "fpr sweep"
+//   seed = "bloom experiments"          # optional, derives all elements
+//
+//   [[filter]]
+//   m_bits = 2048
+//   k = 30
+//   strategy = "xxh3-rejection"         # optional, or "sip-double"
+//
+//   [workload]
+//   kind = "false-positives"            # or "average-ones"
+//   prefill = [10, 47]                  # or { from = 10, to = 50, step = 10 }
+//   probes = 1_000_000                  # false-positives only
+//   trials = 100                        # average-ones only
+//
+//   [output]
+//   path = "results.csv"                # optional, stdout otherwise
+//
+// Every run is deterministic given the file. Results are CSV, one row per
+// filter and prefill. Unknown keys are errors, so typos don't silently fall
+// back to defaults.
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Experiment {
+    pub name: String,
+    pub seed: String,
+    pub filters: Vec<FilterConfig>,
+    pub workload: Workload,
+    pub output: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilterConfig {
+    pub m_bits: usize,
+    pub k: usize,
+    pub sip_double: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Workload {
+    FalsePositives { prefill: Vec<u32>, probes: u64 },
+    AverageOnes { prefill: Vec<u32>, trials: u32 },
+}
+
+// collects every problem in the file instead of stopping at the first
+struct Validator {
+    errors: Vec<String>,
+}
+
+impl Validator {
+    fn unknown_keys(&mut self, table: &Table, path: &str, known: &[&str]) {
+        for key in table.keys().filter(|key| !known.contains(&key.as_str())) {
+            self.errors.push(format!("{path}{key}: unknown key"));
+        }
+    }
+
+    fn string(&mut self, table: &Table, path: &str, key: &str) -> Option<String> {
+        match table.get(key) {
+            None => None,
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(_) => {
+                self.errors.push(format!("{path}{key}: expected a string"));
+                None
+            }
+        }
+    }
+
+    fn positive(&mut self, value: Option<&Value>, path: &str, key: &str) -> Option<u64> {
+        match value {
+            None => {
+                self.errors.push(format!("{path}{key}: missing"));
+                None
+            }
+            Some(Value::Integer(value)) if *value > 0 => Some(*value as u64),
+            Some(_) => {
+                self.errors
+                    .push(format!("{path}{key}: expected a positive integer"));
+                None
+            }
+        }
+    }
+
+    fn count(&mut self, value: &Value, path: &str) -> Option<u32> {
+        match value {
+            Value::Integer(value) if (0..=u32::MAX as i64).contains(value) => Some(*value as u32),
+            _ => {
+                self.errors
+                    .push(format!("{path}: expected an element count"));
+                None
+            }
+        }
+    }
+
+    fn prefill(&mut self, table: &Table) -> Vec<u32> {
+        let path = "workload.prefill";
+        match table.get("prefill") {
+            None => {
+                self.errors.push(format!("{path}: missing"));
+                Vec::new()
+            }
+            Some(Value::Array(values)) if !values.is_empty() => values
+                .iter()
+                .enumerate()
+                .filter_map(|(i, value)| self.count(value, &format!("{path}[{i}]")))
+                .collect(),
+            Some(Value::Table(range)) => {
+                self.unknown_keys(range, "workload.prefill.", &["from", "to", "step"]);
+                let bound = |validator: &mut Self, key: &str| {
+                    range
+                        .get(key)
+                        .and_then(|value| validator.count(value, &format!("{path}.{key}")))
+                };
+                let from = bound(self, "from");
+                let to = bound(self, "to");
+                let step = self.positive(range.get("step"), "workload.prefill.", "step");
+                match (from, to, step) {
+                    (Some(from), Some(to), Some(step)) if from <= to => {
+                        (from..=to).step_by(step as usize).collect()
+                    }
+                    (Some(_), Some(_), Some(_)) => {
+                        self.errors.push(format!("{path}: from is larger than to"));
+                        Vec::new()
+                    }
+                    _ => {
+                        if from.is_none() || to.is_none() {
+                            self.errors
+                                .push(format!("{path}: a range needs from and to"));
+                        }
+                        Vec::new()
+                    }
+                }
+            }
+            Some(_) => {
+                self.errors.push(format!(
+                    "{path}: expected a non-empty array or a {{ from, to, step }} range"
+                ));
+                Vec::new()
+            }
+        }
+    }
+
+    fn filter(&mut self, value: &Value, i: usize) -> Option<FilterConfig> {
+        let path = format!("filter[{i}].");
+        let Value::Table(table) = value else {
+            self.errors.push(format!("filter[{i}]: expected a table"));
+            return None;
+        };
+        self.unknown_keys(table, &path, &["m_bits", "k", "strategy"]);
+        let m_bits = self.positive(table.get("m_bits"), &path, "m_bits");
+        let k = self.positive(table.get("k"), &path, "k");
+        let sip_double = match self.string(table, &path, "strategy").as_deref() {
+            None | Some("xxh3-rejection") => false,
+            Some("sip-double") => true,
+            Some(other) => {
+                self.errors.push(format!(
+                    "{path}strategy: unknown strategy {other:?}, expected \"xxh3-rejection\" or \"sip-double\""
+                ));
+                false
+            }
+        };
+        if let (Some(m_bits), Some(k)) = (m_bits, k) {
+            if k > m_bits {
+                self.errors
+                    .push(format!("{path}k: {k} indices don't fit into {m_bits} bits"));
+            }
+        }
+        Some(FilterConfig {
+            m_bits: m_bits? as usize,
+            k: k? as usize,
+            sip_double,
+        })
+    }
+
+    fn workload(&mut self, table: Option<&Value>) -> Option<Workload> {
+        let Some(Value::Table(table)) = table else {
+            self.errors.push("workload: expected a table".to_string());
+            return None;
+        };
+        let kind = self.string(table, "workload.", "kind");
+        match kind.as_deref() {
+            Some("false-positives") => {
+                self.unknown_keys(table, "workload.", &["kind", "prefill", "probes"]);
+                let prefill = self.prefill(table);
+                let probes = self.positive(table.get("probes"), "workload.", "probes")?;
+                Some(Workload::FalsePositives { prefill, probes })
+            }
+            Some("average-ones") => {
+                self.unknown_keys(table, "workload.", &["kind", "prefill", "trials"]);
+                let prefill = self.prefill(table);
+                let trials = self.positive(table.get("trials"), "workload.", "trials")?;
+                let trials = u32::try_from(trials).ok().or_else(|| {
+                    self.errors
+                        .push("workload.trials: too many trials".to_string());
+                    None
+                })?;
+                Some(Workload::AverageOnes { prefill, trials })
+            }
+            Some(other) => {
+                self.errors.push(format!(
+                    "workload.kind: unknown workload {other:?}, expected \"false-positives\" or \"average-ones\""
+                ));
+                None
+            }
+            None => {
+                self.errors.push("workload.kind: missing".to_string());
+                None
+            }
+        }
+    }
+}
+
+impl Experiment {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let table: Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| format!("invalid TOML: {}", e.message()))?;
+        let mut validator = Validator { errors: Vec::new() };
+        validator.unknown_keys(
+            &table,
+            "",
+            &["name", "seed", "filter", "workload", "output"],
+        );
+        let name = validator.string(&table, "", "name");
+        if name.is_none() && !table.contains_key("name") {
+            validator.errors.push("name: missing".to_string());
+        }
+        let seed = validator.string(&table, "", "seed");
+        let filters: Vec<FilterConfig> = match table.get("filter") {
+            Some(Value::Array(filters)) if !filters.is_empty() => filters
+                .iter()
+                .enumerate()
+                .filter_map(|(i, filter)| validator.filter(filter, i))
+                .collect(),
+            _ => {
+                validator
+                    .errors
+                    .push("filter: expected at least one [[filter]] table".to_string());
+                Vec::new()
+            }
+        };
+        let workload = validator.workload(table.get("workload"));
+        let output = match table.get("output") {
+            None => None,
+            Some(Value::Table(output)) => {
+                validator.unknown_keys(output, "output.", &["path"]);
+                validator.string(output, "output.", "path")
+            }
+            Some(_) => {
+                validator
+                    .errors
+                    .push("output: expected a table".to_string());
+                None
+            }
+        };
+        match (validator.errors.is_empty(), name, workload) {
+            (true, Some(name), Some(workload)) => Ok(Self {
+                name,
+                seed: seed.unwrap_or_else(|| "bloom experiments".to_string()),
+                filters,
+                workload,
+                output,
+            }),
+            _ => Err(validator.errors.join("\n")),
+        }
+    }
+
+    fn filter(&self, config: &FilterConfig) -> DynBloom {
+        let indexing = if config.sip_double {
+            let keys = blake3::derive_key("experiment sip-double keys", self.seed.as_bytes());
+            DynIndexing::SipDouble {
+                keys: [
+                    keys[..16].try_into().unwrap(),
+                    keys[16..].try_into().unwrap(),
+                ],
+            }
+        } else {
+            DynIndexing::Xxh3Rejection
+        };
+        DynBloom::with_indexing(config.m_bits, config.k, indexing)
+    }
+
+    // the results as CSV
+    pub fn run(&self) -> String {
+        let mut csv = String::new();
+        match &self.workload {
+            Workload::FalsePositives { prefill, probes } => {
+                csv.push_str("experiment,m_bits,k,strategy,prefill,ones,false_positives,probes\n");
+                for config in &self.filters {
+                    for n in prefill {
+                        let mut filter = self.filter(config);
+                        let seed = format!("{} prefill", self.seed);
+                        fill_deterministic(&seed, *n, &mut filter);
+                        let false_positives = count_false_positives(&mut filter, 0, *probes);
+                        writeln!(
+                            csv,
+                            "{},{},{},{},{n},{},{false_positives},{probes}",
+                            self.name,
+                            config.m_bits,
+                            config.k,
+                            config.strategy(),
+                            filter.count_ones()
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            Workload::AverageOnes { prefill, trials } => {
+                csv.push_str("experiment,m_bits,k,strategy,prefill,trials,average_ones\n");
+                for config in &self.filters {
+                    for n in prefill {
+                        let mut sum = 0u64;
+                        for trial in 0..*trials {
+                            let mut filter = self.filter(config);
+                            fill_deterministic(&format!("{} {trial}", self.seed), *n, &mut filter);
+                            sum += filter.count_ones() as u64;
+                        }
+                        writeln!(
+                            csv,
+                            "{},{},{},{},{n},{trials},{}",
+                            self.name,
+                            config.m_bits,
+                            config.k,
+                            config.strategy(),
+                            sum as f64 / *trials as f64
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+        csv
+    }
+}
+
+impl FilterConfig {
+    fn strategy(&self) -> &'static str {
+        if self.sip_double {
+            "sip-double"
+        } else {
+            "xxh3-rejection"
+        }
+    }
+}
+
+// `run --config <experiment.toml>`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let path = cli::required(args, "--config")?;
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let experiment = Experiment::parse(&text).map_err(|e| format!("{path}:\n{e}"))?;
+    let csv = experiment.run();
+    match &experiment.output {
+        Some(output) => fs::write(output, csv).map_err(|e| format!("can't write {output}: {e}")),
+        None => std::io::stdout()
+            .lock()
+            .write_all(csv.as_bytes())
+            .map_err(|e| format!("can't write results: {e}")),
+    }
+}
+
+#[test]
+fn test_parse_experiment() {
+    let experiment = Experiment::parse(
+        r#"
+        name = "sweep"
+        [[filter]]
+        m_bits = 2048
+        k = 30
+        [[filter]]
+        m_bits = 1001
+        k = 7
+        strategy = "sip-double"
+        [workload]
+        kind = "false-positives"
+        prefill = { from = 10, to = 30, step = 10 }
+        probes = 1000
+        "#,
+    )
+    .unwrap();
+    assert_eq!(experiment.seed, "bloom experiments");
+    assert_eq!(experiment.filters.len(), 2);
+    assert!(experiment.filters[1].sip_double);
+    assert_eq!(
+        experiment.workload,
+        Workload::FalsePositives {
+            prefill: vec![10, 20, 30],
+            probes: 1000
+        }
+    );
+    assert_eq!(experiment.output, None);
+
+    let csv = experiment.run();
+    assert_eq!(csv.lines().count(), 1 + 2 * 3);
+    assert!(csv.starts_with("experiment,m_bits,k,strategy,prefill,ones,false_positives,probes\nsweep,2048,30,xxh3-rejection,10,"));
+    assert_eq!(csv, experiment.run());
+}
+
+#[test]
+fn test_experiment_errors() {
+    assert_eq!(
+        Experiment::parse(
+            r#"
+            name = "broken"
+            colour = "blue"
+            [[filter]]
+            m_bits = 0
+            k = 3
+            [[filter]]
+            m_bits = 8
+            k = 9
+            strategy = "md5"
+            [workload]
+            kind = "average-ones"
+            prefill = [1, -2]
+            "#
+        ),
+        Err([
+            "colour: unknown key",
+            "filter[0].m_bits: expected a positive integer",
+            "filter[1].strategy: unknown strategy \"md5\", expected \"xxh3-rejection\" or \"sip-double\"",
+            "filter[1].k: 9 indices don't fit into 8 bits",
+            "workload.prefill[1]: expected an element count",
+            "workload.trials: missing",
+        ]
+        .join("\n"))
+    );
+    assert!(Experiment::parse("name = ")
+        .unwrap_err()
+        .starts_with("invalid TOML"));
+    assert_eq!(
+        Experiment::parse("name = \"empty\""),
+        Err(
+            "filter: expected at least one [[filter]] table\nworkload: expected a table"
+                .to_string()
+        )
+    );
+}
@@ -0,0 +1,102 @@
+use crate::{dyn_bloom::DynBloom, folded::Folded, Bloom};
+
+// Why a query answered what it did: each probed index in the order `has`
+// checks them, whether its bit was set, and how full the filter was. `has`
+// stops at the first unset bit; this probes all of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryExplanation {
+    // (index, set)
+    pub probes: Vec<(usize, bool)>,
+    // set bits over all bits
+    pub density: f64,
+}
+
+impl QueryExplanation {
+    fn new(indices: impl IntoIterator<Item = usize>, bytes: &[u8], m_bits: usize) -> Self {
+        let ones: u32 = bytes.iter().map(|byte| byte.count_ones()).sum();
+        Self {
+            probes: indices
+                .into_iter()
+                .map(|index| (index, bytes[index / 8] & (1 << (index % 8)) != 0))
+                .collect(),
+            density: ones as f64 / m_bits as f64,
+        }
+    }
+
+    // what `has` answers
+    pub fn present(&self) -> bool {
+        self.probes.iter().all(|&(_, set)| set)
+    }
+
+    // the index that made `has` answer false
+    pub fn first_missing(&self) -> Option<usize> {
+        self.probes
+            .iter()
+            .find(|(_, set)| !set)
+            .map(|&(index, _)| index)
+    }
+
+    pub fn matched(&self) -> usize {
+        self.probes.iter().filter(|(_, set)| *set).count()
+    }
+
+    // The chance that a filter this full matches as many probes by
+    // accident, density^matched; for an element that was added and now
+    // misses bits, near 1 means the probes look like noise.
+    pub fn chance_of_matches(&self) -> f64 {
+        self.density.powi(self.matched() as i32)
+    }
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn explain(&self, element: &[u8]) -> QueryExplanation {
+        QueryExplanation::new(self.indices_for(element), &self.bytes, M * 8)
+    }
+}
+
+impl DynBloom {
+    pub fn explain(&self, element: &[u8]) -> QueryExplanation {
+        QueryExplanation::new(self.indices_for(element), self.bytes(), self.m_bits())
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    // Probes the folded indices, so an unfolded index pair that cancelled
+    // out doesn't show up; density is over the S * 8 stored bits.
+    pub fn explain<H: AsRef<[u8]>>(&self, hash: &H) -> QueryExplanation {
+        QueryExplanation::new(self.indices_for(hash), &self.bytes, S * 8)
+    }
+}
+
+#[test]
+fn test_explain() {
+    let mut bloom = Bloom::<256, 30>::new();
+    bloom.add(b"one");
+    let explanation = bloom.explain(b"one");
+    assert!(explanation.present());
+    assert_eq!(explanation.first_missing(), None);
+    assert_eq!(explanation.matched(), 30);
+    assert_eq!(explanation.density, bloom.count_ones() as f64 / 2048.0);
+    let probed: Vec<usize> = explanation.probes.iter().map(|&(index, _)| index).collect();
+    assert_eq!(probed, bloom.indices_for(b"one"));
+
+    // clearing one of its bits makes that the reported rejection
+    let cleared = bloom.indices_for(b"one")[7];
+    bloom.bytes[cleared / 8] &= !(1 << (cleared % 8));
+    let explanation = bloom.explain(b"one");
+    assert!(!explanation.present() && !bloom.has(b"one"));
+    assert_eq!(explanation.first_missing(), Some(cleared));
+    assert_eq!(explanation.matched(), 29);
+
+    let mut dyn_bloom = DynBloom::new(1001, 7);
+    dyn_bloom.add(b"one");
+    assert_eq!(dyn_bloom.explain(b"one").matched(), 7);
+    assert_eq!(dyn_bloom.explain(b"two").present(), dyn_bloom.has(b"two"));
+
+    let mut folded = Folded::<2, 64, 30>::new();
+    folded.insert(b"one");
+    let explanation = folded.explain(b"one");
+    assert!(explanation.present());
+    assert_eq!(explanation.probes.len(), folded.indices_for(b"one").len());
+    assert_eq!(explanation.density, folded.explain(b"two").density);
+}
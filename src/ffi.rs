@@ -0,0 +1,177 @@
+use std::{ptr, slice};
+
+use crate::{
+    dyn_bloom::{DynBloom, DynIndexing},
+    strategy::HashStrategy,
+};
+
+// C API over `DynBloom`, declared in include/rust_bloomfilters.h. Written by
+// hand like the proto bindings; keep both in sync.
+//
+// Handles are owned by the caller and released with `rbf_bloom_free`.
+// Pointer arguments may be null only when their length is zero.
+
+pub struct RbfBloom(DynBloom);
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// `strategy` is a `HashStrategy` wire id: 1 (xxh3 rejection, no seed) or 6
+/// (SipHash double hashing, a 32 byte seed holding both keys). Answers null
+/// for parameters `DynBloom::try_new` refuses, other strategies or a wrong
+/// seed length.
+///
+/// # Safety
+///
+/// `seed` must point to `seed_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rbf_bloom_new(
+    m_bits: u64,
+    k: u32,
+    strategy: u8,
+    seed: *const u8,
+    seed_len: usize,
+) -> *mut RbfBloom {
+    let seed = bytes(seed, seed_len);
+    let indexing = match (HashStrategy::from_id(strategy), seed.len()) {
+        (Some(HashStrategy::Xxh3Rejection), 0) => DynIndexing::Xxh3Rejection,
+        (Some(HashStrategy::SipDouble), 32) => DynIndexing::SipDouble {
+            keys: [
+                seed[..16].try_into().unwrap(),
+                seed[16..].try_into().unwrap(),
+            ],
+        },
+        _ => return ptr::null_mut(),
+    };
+    let Ok(m_bits) = usize::try_from(m_bits) else {
+        return ptr::null_mut();
+    };
+    match DynBloom::try_new(m_bits, k as usize, indexing) {
+        Ok(bloom) => Box::into_raw(Box::new(RbfBloom(bloom))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `bloom` must be null or a handle from this API that wasn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rbf_bloom_free(bloom: *mut RbfBloom) {
+    if !bloom.is_null() {
+        drop(Box::from_raw(bloom));
+    }
+}
+
+/// true if the element definitely wasn't in the filter before
+///
+/// # Safety
+///
+/// `bloom` must be a live handle, `element` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rbf_bloom_add(
+    bloom: *mut RbfBloom,
+    element: *const u8,
+    len: usize,
+) -> bool {
+    (*bloom).0.add(bytes(element, len))
+}
+
+/// # Safety
+///
+/// `bloom` must be a live handle, `element` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rbf_bloom_has(
+    bloom: *const RbfBloom,
+    element: *const u8,
+    len: usize,
+) -> bool {
+    (*bloom).0.has(bytes(element, len))
+}
+
+/// Writes the `DynBloom::to_bytes` envelope to `out` if it fits in
+/// `capacity` bytes, and answers its length either way, so callers can ask
+/// with a capacity of zero first.
+///
+/// # Safety
+///
+/// `bloom` must be a live handle, `out` must point to `capacity` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rbf_bloom_serialize(
+    bloom: *const RbfBloom,
+    out: *mut u8,
+    capacity: usize,
+) -> usize {
+    let encoded = (*bloom).0.to_bytes();
+    if encoded.len() <= capacity {
+        ptr::copy_nonoverlapping(encoded.as_ptr(), out, encoded.len());
+    }
+    encoded.len()
+}
+
+/// the inverse of `rbf_bloom_serialize`, null if `data` isn't a valid
+/// `DynBloom` envelope
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rbf_bloom_deserialize(data: *const u8, len: usize) -> *mut RbfBloom {
+    match DynBloom::try_from_bytes(bytes(data, len)) {
+        Ok(filter) => Box::into_raw(Box::new(RbfBloom(filter))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[test]
+fn test_ffi_round_trip() {
+    unsafe {
+        let bloom = rbf_bloom_new(1001, 7, HashStrategy::Xxh3Rejection.id(), ptr::null(), 0);
+        assert!(!bloom.is_null());
+        assert!(rbf_bloom_add(bloom, b"one".as_ptr(), 3));
+        assert!(!rbf_bloom_add(bloom, b"one".as_ptr(), 3));
+        assert!(rbf_bloom_has(bloom, b"one".as_ptr(), 3));
+        assert!(!rbf_bloom_has(bloom, b"two".as_ptr(), 3));
+
+        let len = rbf_bloom_serialize(bloom, ptr::null_mut(), 0);
+        let mut out = vec![0u8; len];
+        assert_eq!(rbf_bloom_serialize(bloom, out.as_mut_ptr(), len), len);
+        assert_eq!(out, (*bloom).0.to_bytes());
+
+        let copy = rbf_bloom_deserialize(out.as_ptr(), out.len());
+        assert!(!copy.is_null());
+        assert_eq!((*copy).0, (*bloom).0);
+        rbf_bloom_free(copy);
+        rbf_bloom_free(bloom);
+
+        assert!(rbf_bloom_deserialize(b"garbage".as_ptr(), 7).is_null());
+        assert!(rbf_bloom_new(0, 7, 1, ptr::null(), 0).is_null());
+        assert!(rbf_bloom_new(1001, 7, 2, ptr::null(), 0).is_null());
+        assert!(rbf_bloom_new(1001, 7, 6, [0u8; 16].as_ptr(), 16).is_null());
+        let sip = rbf_bloom_new(1001, 7, 6, [7u8; 32].as_ptr(), 32);
+        assert_eq!(
+            (*sip).0.indexing(),
+            DynIndexing::SipDouble { keys: [[7; 16]; 2] }
+        );
+        rbf_bloom_free(sip);
+    }
+}
+
+#[test]
+fn test_header_declares_every_function() {
+    let header = include_str!("../include/rust_bloomfilters.h");
+    for name in [
+        "rbf_bloom_new",
+        "rbf_bloom_free",
+        "rbf_bloom_add",
+        "rbf_bloom_has",
+        "rbf_bloom_serialize",
+        "rbf_bloom_deserialize",
+    ] {
+        assert!(header.contains(&format!("{name}(")), "{name} missing");
+    }
+}
@@ -0,0 +1,35 @@
+use crate::element::Element;
+
+// what the experiment harness needs from a filter
+pub trait Filter {
+    fn add(&mut self, element: &[u8]);
+    fn has(&self, element: &[u8]) -> bool;
+    fn count_ones(&self) -> u32;
+
+    // elements added, estimated from the set bits, for filters that know
+    // their m and k
+    fn estimate_len(&self) -> Option<f64> {
+        None
+    }
+
+    // the chance an element never added has all its bits set, from the
+    // current fill
+    fn estimated_fpr(&self) -> Option<f64> {
+        None
+    }
+
+    // adds a value in its canonical encoding, see element.rs
+    fn add_element<T: Element + ?Sized>(&mut self, value: &T)
+    where
+        Self: Sized,
+    {
+        self.add(&value.encode())
+    }
+
+    fn has_element<T: Element + ?Sized>(&self, value: &T) -> bool
+    where
+        Self: Sized,
+    {
+        self.has(&value.encode())
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    dyn_bloom::{BloomRef, DynBloom, DynIndexing},
+    folded::Folded,
+    strategy::HashStrategy,
+    Bloom,
+};
+
+// FlatBuffers encoding of proto/filter.fbs. `FilterView::new` only checks the
+// buffer's offsets and bounds, then reads fields straight out of it, so a
+// received buffer can be queried through `FilterView::as_bloom_ref` without
+// copying the bitset. Any valid FlatBuffer for the schema is accepted;
+// `encode` always writes this layout:
+//
+//   0   root offset (24), file identifier "BLMF"
+//   8   vtable: size 14, table size 24, field offsets 8, 4, 20, 21, 16
+//   24  table: vtable soffset 16, k, m, bitset offset, strategy, fold_level
+//   48  bitset: u32 length, bytes
+
+const FILE_IDENTIFIER: &[u8; 4] = b"BLMF";
+
+// field indices in the vtable, in schema order
+const FIELD_M: usize = 0;
+const FIELD_K: usize = 1;
+const FIELD_STRATEGY: usize = 2;
+const FIELD_FOLD_LEVEL: usize = 3;
+const FIELD_BITSET: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FlatError {
+    // an offset or length points outside the buffer
+    OutOfBounds,
+    Misaligned,
+    WrongIdentifier,
+    UnknownStrategy(u8),
+    // the bitset doesn't have (m >> fold_level) bits
+    BitsetLength,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilterView<'a> {
+    pub m: u64,
+    pub k: u32,
+    pub strategy: Option<HashStrategy>,
+    pub fold_level: u8,
+    pub bitset: &'a [u8],
+}
+
+fn read<const N: usize>(buf: &[u8], pos: usize) -> Result<[u8; N], FlatError> {
+    if !pos.is_multiple_of(N) {
+        return Err(FlatError::Misaligned);
+    }
+    buf.get(pos..pos + N)
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or(FlatError::OutOfBounds)
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16, FlatError> {
+    read(buf, pos).map(u16::from_le_bytes)
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<u32, FlatError> {
+    read(buf, pos).map(u32::from_le_bytes)
+}
+
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    vtable: usize,
+    vtable_size: usize,
+    size: usize,
+}
+
+impl<'a> Table<'a> {
+    fn root(buf: &'a [u8]) -> Result<Self, FlatError> {
+        let pos = read_u32(buf, 0)? as usize;
+        let soffset = i32::from_le_bytes(read(buf, pos)?) as i64;
+        let vtable = usize::try_from(pos as i64 - soffset).map_err(|_| FlatError::OutOfBounds)?;
+        let vtable_size = read_u16(buf, vtable)? as usize;
+        let size = read_u16(buf, vtable + 2)? as usize;
+        if vtable_size < 4 || vtable + vtable_size > buf.len() || pos + size > buf.len() {
+            return Err(FlatError::OutOfBounds);
+        }
+        Ok(Self {
+            buf,
+            pos,
+            vtable,
+            vtable_size,
+            size,
+        })
+    }
+
+    // absolute position of a field of the given size, None if it's absent
+    fn field(&self, index: usize, size: usize) -> Result<Option<usize>, FlatError> {
+        let entry = 4 + 2 * index;
+        if entry + 2 > self.vtable_size {
+            return Ok(None);
+        }
+        let offset = read_u16(self.buf, self.vtable + entry)? as usize;
+        if offset == 0 {
+            return Ok(None);
+        }
+        if offset + size > self.size {
+            return Err(FlatError::OutOfBounds);
+        }
+        Ok(Some(self.pos + offset))
+    }
+
+    fn scalar<const N: usize>(&self, index: usize) -> Result<[u8; N], FlatError> {
+        match self.field(index, N)? {
+            Some(pos) => read(self.buf, pos),
+            None => Ok([0; N]),
+        }
+    }
+
+    fn bytes(&self, index: usize) -> Result<&'a [u8], FlatError> {
+        let Some(pos) = self.field(index, 4)? else {
+            return Ok(&[]);
+        };
+        let vector = pos + read_u32(self.buf, pos)? as usize;
+        let len = read_u32(self.buf, vector)? as usize;
+        self.buf
+            .get(vector + 4..vector + 4 + len)
+            .ok_or(FlatError::OutOfBounds)
+    }
+}
+
+impl<'a> FilterView<'a> {
+    pub fn new(buf: &'a [u8]) -> Result<Self, FlatError> {
+        let table = Table::root(buf)?;
+        if buf.get(4..8) != Some(&FILE_IDENTIFIER[..]) {
+            return Err(FlatError::WrongIdentifier);
+        }
+        let m = u64::from_le_bytes(table.scalar(FIELD_M)?);
+        let k = u32::from_le_bytes(table.scalar(FIELD_K)?);
+        let [strategy] = table.scalar(FIELD_STRATEGY)?;
+        let [fold_level] = table.scalar(FIELD_FOLD_LEVEL)?;
+        let bitset = table.bytes(FIELD_BITSET)?;
+        let strategy = match strategy {
+            0 => None,
+            id => Some(HashStrategy::from_id(id).ok_or(FlatError::UnknownStrategy(id))?),
+        };
+        if fold_level >= 64 || (bitset.len() as u64) != (m >> fold_level).div_ceil(8) {
+            return Err(FlatError::BitsetLength);
+        }
+        Ok(Self {
+            m,
+            k,
+            strategy,
+            fold_level,
+            bitset,
+        })
+    }
+
+    // queries like the `Bloom` or `DynBloom` that was encoded
+    pub fn as_bloom_ref(&self) -> Option<BloomRef<'a>> {
+        if self.strategy != Some(HashStrategy::Xxh3Rejection) || self.fold_level != 0 {
+            return None;
+        }
+        BloomRef::new(self.bitset, usize::try_from(self.m).ok()?, self.k as usize).ok()
+    }
+}
+
+pub fn encode(m: u64, k: u32, strategy: HashStrategy, fold_level: u8, bitset: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(52 + bitset.len());
+    out.extend_from_slice(&24u32.to_le_bytes());
+    out.extend_from_slice(FILE_IDENTIFIER);
+    for entry in [14u16, 24, 8, 4, 20, 21, 16, 0] {
+        out.extend_from_slice(&entry.to_le_bytes());
+    }
+    out.extend_from_slice(&16i32.to_le_bytes());
+    out.extend_from_slice(&k.to_le_bytes());
+    out.extend_from_slice(&m.to_le_bytes());
+    out.extend_from_slice(&8u32.to_le_bytes());
+    out.extend_from_slice(&[strategy.id(), fold_level, 0, 0]);
+    out.extend_from_slice(&(bitset.len() as u32).to_le_bytes());
+    out.extend_from_slice(bitset);
+    out
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn to_flatbuffer(&self) -> Vec<u8> {
+        encode(
+            (M * 8) as u64,
+            K as u32,
+            HashStrategy::Xxh3Rejection,
+            0,
+            &self.bytes,
+        )
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    pub fn to_flatbuffer(&self) -> Vec<u8> {
+        encode(
+            ((S * 8) << F) as u64,
+            K as u32,
+            HashStrategy::Xxh3Rejection,
+            F as u8,
+            &self.bytes,
+        )
+    }
+}
+
+impl DynBloom {
+    // only the xxh3 indexed filters have a strategy id
+    pub fn to_flatbuffer(&self) -> Option<Vec<u8>> {
+        if self.indexing() != DynIndexing::Xxh3Rejection {
+            return None;
+        }
+        Some(encode(
+            self.m_bits() as u64,
+            self.k() as u32,
+            HashStrategy::Xxh3Rejection,
+            0,
+            self.bytes(),
+        ))
+    }
+}
+
+#[test]
+fn test_flatbuffer_view() {
+    let mut bloom = Bloom::<256, 30>::new();
+    for i in 0..20u32 {
+        bloom.add(&i.to_le_bytes());
+    }
+    let buf = bloom.to_flatbuffer();
+    let view = FilterView::new(&buf).unwrap();
+    assert_eq!(view.m, 2048);
+    assert_eq!(view.k, 30);
+    assert_eq!(view.bitset.as_ptr(), buf[52..].as_ptr());
+    let bloom_ref = view.as_bloom_ref().unwrap();
+    assert!((0..1000u32).all(|i| bloom_ref.has(&i.to_le_bytes()) == bloom.has(&i.to_le_bytes())));
+
+    let mut dyn_bloom = DynBloom::new(1001, 7);
+    dyn_bloom.add(b"one");
+    let buf = dyn_bloom.to_flatbuffer().unwrap();
+    assert!(FilterView::new(&buf)
+        .unwrap()
+        .as_bloom_ref()
+        .unwrap()
+        .has(b"one"));
+
+    let folded = Folded::<2, 64, 30>::new();
+    let buf = folded.to_flatbuffer();
+    let view = FilterView::new(&buf).unwrap();
+    assert_eq!((view.m, view.fold_level), (2048, 2));
+    assert_eq!(view.as_bloom_ref(), None);
+
+    // a valid buffer for a filter no query can finish on
+    let buf = encode(0, 3, HashStrategy::Xxh3Rejection, 0, &[]);
+    assert_eq!(FilterView::new(&buf).unwrap().as_bloom_ref(), None);
+    let buf = encode(8, 9, HashStrategy::Xxh3Rejection, 0, &[0]);
+    assert_eq!(FilterView::new(&buf).unwrap().as_bloom_ref(), None);
+}
+
+#[test]
+fn test_flatbuffer_defaults() {
+    // a table whose vtable only lists m, as flatc output omits default fields:
+    // k, strategy and fold_level read as 0 and the bitset as empty
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&16u32.to_le_bytes());
+    buf.extend_from_slice(b"BLMF");
+    for entry in [6u16, 16, 8, 0] {
+        buf.extend_from_slice(&entry.to_le_bytes());
+    }
+    buf.extend_from_slice(&8i32.to_le_bytes());
+    buf.extend_from_slice(&[0; 4]);
+    buf.extend_from_slice(&0u64.to_le_bytes());
+    let view = FilterView::new(&buf).unwrap();
+    assert_eq!(
+        (view.m, view.k, view.strategy, view.bitset),
+        (0, 0, None, &[][..])
+    );
+}
+
+#[test]
+fn test_flatbuffer_errors() {
+    let buf = Bloom::<2, 1>::new().to_flatbuffer();
+    assert_eq!(
+        FilterView::new(&buf[..buf.len() - 1]),
+        Err(FlatError::OutOfBounds)
+    );
+    let mut wrong = buf.clone();
+    wrong[4] = b'X';
+    assert_eq!(FilterView::new(&wrong), Err(FlatError::WrongIdentifier));
+    let mut unknown = buf.clone();
+    unknown[44] = 9;
+    assert_eq!(
+        FilterView::new(&unknown),
+        Err(FlatError::UnknownStrategy(9))
+    );
+    let mut short = buf.clone();
+    short[32] = 24;
+    assert_eq!(FilterView::new(&short), Err(FlatError::BitsetLength));
+    let mut misaligned = buf;
+    misaligned[0] = 25;
+    assert_eq!(FilterView::new(&misaligned), Err(FlatError::Misaligned));
+}
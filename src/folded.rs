@@ -1,20 +1,54 @@
-use xxhash_rust::xxh3::xxh3_64_with_seed;
-
-// M = S * F
+use crate::bloom_indices_for_element;
+
+/// A Bloom filter of (S * 8) << F bits, OR-folded F times into S bytes,
+/// with K hash functions.
+///
+/// Indices are derived like `Bloom`'s over the unfolded size, then shifted
+/// down F times, so at F = 0 a `Folded<0, S, K>` holds exactly the bits of a
+/// `Bloom<S, K>`.
 #[derive(Debug)]
 pub struct Folded<const F: usize, const S: usize, const K: usize> {
     pub bytes: [u8; S],
 }
 
 impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    // Same checks as `Bloom`'s, against the unfolded size (S * 8) << F.
+    const CHECK: () = {
+        assert!(
+            S > 0,
+            "Folded<F, S, K> needs at least one stored byte (S > 0)"
+        );
+        assert!(
+            K > 0,
+            "Folded<F, S, K> needs at least one hash function (K > 0)"
+        );
+        assert!(
+            K <= (S * 8) << F,
+            "Folded<F, S, K> can't set more indices than unfolded bits (K <= (S * 8) << F)"
+        );
+    };
+
+    /// See `Bloom::REJECTION_FREE`.
+    pub const REJECTION_FREE: bool = ((S * 8) << F).is_power_of_two();
+
     pub fn new() -> Self {
+        let () = Self::CHECK;
         Self { bytes: [0u8; S] }
     }
 
-    pub fn insert<H: AsRef<[u8]>>(&mut self, hash: &H) {
+    pub fn from_bytes(bytes: [u8; S]) -> Self {
+        let () = Self::CHECK;
+        Self { bytes }
+    }
+
+    /// true if this set a bit, like `Bloom::add`
+    pub fn insert<H: AsRef<[u8]>>(&mut self, hash: &H) -> bool {
+        let mut new = false;
         for index in Self::build_expected(hash).folded(F).indices_set {
-            self.set_bit(index)
+            new |= !self.test_bit(index);
+            self.set_bit(index);
         }
+        new
     }
 
     pub fn has<H: AsRef<[u8]>>(&self, hash: &H) -> bool {
@@ -23,19 +57,25 @@ impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
                 return false;
             }
         }
-        return true;
+        true
+    }
+
+    /// The indices `insert` sets: the unfolded indices shifted down F times,
+    /// where two landing on the same bit cancel out (XOR), in no particular
+    /// order.
+    pub fn indices_for<H: AsRef<[u8]>>(&self, hash: &H) -> Vec<usize> {
+        Self::build_expected(hash).folded(F).indices_set
     }
 
     fn build_expected<H: AsRef<[u8]>>(hash: &H) -> SparseArray {
         // sparse array
         let mut expected = SparseArray::new_with_capacity(K);
 
-        for seed in 0..K {
-            let index = xxh3_64_with_seed(hash.as_ref(), seed as u64) as usize % (S * 8 << F);
+        for index in bloom_indices_for_element(hash.as_ref(), (S * 8) << F, K) {
             expected.set_bit(index);
         }
 
-        return expected;
+        expected
     }
 
     fn set_bit(&mut self, index: usize) {
@@ -51,6 +91,27 @@ impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
     }
 }
 
+impl<const F: usize, const S: usize, const K: usize> Default for Folded<F, S, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bit j of the result is the OR of bits j << levels up to (j + 1) << levels,
+/// the same positions `Folded` maps indices to. `bytes.len()` must be
+/// divisible by 2^levels.
+pub fn or_fold(bytes: &[u8], levels: usize) -> Vec<u8> {
+    assert!(bytes.len().is_multiple_of(1 << levels));
+    let mut folded = vec![0u8; bytes.len() >> levels];
+    for index in 0..bytes.len() * 8 {
+        if bytes[index / 8] & (1 << (index % 8)) != 0 {
+            let folded_index = index >> levels;
+            folded[folded_index / 8] |= 1 << (folded_index % 8);
+        }
+    }
+    folded
+}
+
 struct SparseArray {
     indices_set: Vec<usize>,
 }
@@ -63,11 +124,7 @@ impl SparseArray {
     }
 
     fn set_bit(&mut self, index: usize) {
-        if let Some(_) = self
-            .indices_set
-            .iter()
-            .find(|index_set| **index_set == index)
-        {
+        if self.indices_set.contains(&index) {
             return;
         }
         self.indices_set.push(index)
@@ -87,11 +144,7 @@ impl SparseArray {
     }
 
     fn test_bit(&self, index: usize) -> bool {
-        return self
-            .indices_set
-            .iter()
-            .find(|index_set| **index_set == index)
-            .is_some();
+        self.indices_set.contains(&index)
     }
 
     fn folded(&self, times: usize) -> SparseArray {
@@ -110,3 +163,24 @@ fn test_folded() {
     assert!(bloom.has(b"Hello, World"));
     assert!(!bloom.has(b"Test"));
 }
+
+#[test]
+fn test_unfolded_matches_bloom() {
+    let mut folded = Folded::<0, 256, 30>::new();
+    let mut bloom = crate::Bloom::<256, 30>::new();
+    for i in 0..40u32 {
+        folded.insert(&i.to_le_bytes());
+        bloom.add(&i.to_le_bytes());
+    }
+    assert_eq!(folded.bytes, bloom.bytes);
+    for i in 0..10_000u32 {
+        assert_eq!(folded.has(&i.to_le_bytes()), bloom.has(&i.to_le_bytes()));
+    }
+
+    // folding keeps every element of the unfolded filter
+    let mut twice = Folded::<2, 64, 30>::new();
+    for i in 0..40u32 {
+        twice.insert(&i.to_le_bytes());
+    }
+    assert!((0..40u32).all(|i| twice.has(&i.to_le_bytes())));
+}
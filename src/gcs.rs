@@ -0,0 +1,251 @@
+use std::hash::Hasher;
+
+use bloomfilter::reexports::siphasher::sip::SipHasher24;
+
+use crate::{
+    bip37::{read_varint, write_varint},
+    bits::{BitReader, BitWriter},
+};
+
+// Golomb-coded sets as in BIP-158: every element is SipHash-2-4'd and mapped
+// to [0, n * m) by (hash * n * m) >> 64, the sorted values are coded as
+// Golomb-Rice deltas with parameter p. Serialized as the element count
+// (CompactSize) followed by the zero padded bit stream. A false positive
+// probability of about 1 / m.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GolombSet {
+    params: GcsParams,
+    key: [u8; 16],
+    n: u64,
+    // the coded deltas, without the count
+    bits: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GcsParams {
+    pub p: u8,
+    pub m: u64,
+}
+
+// BIP-158's basic block filter
+pub const BIP158_BASIC: GcsParams = GcsParams { p: 19, m: 784_931 };
+
+impl GcsParams {
+    // m = 1 / fpr, and the Rice parameter closest to the 2^p = m ln(2) that
+    // codes the geometric deltas in the fewest bits; BIP-158's m picks p = 19
+    pub fn for_fpr(fpr: f64) -> Self {
+        assert!(fpr > 0.0 && fpr < 1.0, "GCS parameters need 0 < fpr < 1");
+        let m = (1.0 / fpr).round() as u64;
+        let p = (m as f64 * std::f64::consts::LN_2).log2().round().max(0.0) as u8;
+        Self { p, m }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GcsError {
+    Truncated,
+    // more elements than fit the count's value range
+    TooManyElements,
+}
+
+fn hash_to_range(key: &[u8; 16], element: &[u8], range: u64) -> u64 {
+    let mut sip = SipHasher24::new_with_key(key);
+    sip.write(element);
+    ((sip.finish() as u128 * range as u128) >> 64) as u64
+}
+
+impl GolombSet {
+    pub fn build<E: AsRef<[u8]>>(
+        params: GcsParams,
+        key: [u8; 16],
+        elements: impl IntoIterator<Item = E>,
+    ) -> Self {
+        let elements: Vec<E> = elements.into_iter().collect();
+        let n = elements.len() as u64;
+        let range = n * params.m;
+        let mut values: Vec<u64> = elements
+            .iter()
+            .map(|element| hash_to_range(&key, element.as_ref(), range))
+            .collect();
+        values.sort_unstable();
+
+        let mut writer = BitWriter::new();
+        let mut last = 0;
+        for value in values {
+            writer.push_rice(value - last, params.p);
+            last = value;
+        }
+        Self {
+            params,
+            key,
+            n,
+            bits: writer.into_bytes(),
+        }
+    }
+
+    // BIP-158 basic filter of a block, keyed by the first 16 bytes of its
+    // hash in internal byte order (the reverse of how it's displayed).
+    // `elements` are the block's output and spent scripts, deduplicated.
+    pub fn bip158<E: AsRef<[u8]>>(
+        block_hash: &[u8; 32],
+        elements: impl IntoIterator<Item = E>,
+    ) -> Self {
+        Self::build(BIP158_BASIC, block_hash[..16].try_into().unwrap(), elements)
+    }
+
+    pub fn from_bytes(params: GcsParams, key: [u8; 16], data: &[u8]) -> Result<Self, GcsError> {
+        let (n, bits) = read_varint(data).map_err(|_| GcsError::Truncated)?;
+        n.checked_mul(params.m).ok_or(GcsError::TooManyElements)?;
+        let set = Self {
+            params,
+            key,
+            n,
+            bits: bits.to_vec(),
+        };
+        set.values().try_for_each(|value| value.map(|_| ()))?;
+        Ok(set)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(9 + self.bits.len());
+        write_varint(&mut out, self.n);
+        out.extend_from_slice(&self.bits);
+        out
+    }
+
+    pub fn len(&self) -> u64 {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    // the sorted hashed values
+    fn values(&self) -> impl Iterator<Item = Result<u64, GcsError>> + '_ {
+        let p = self.params.p;
+        let mut reader = BitReader::new(&self.bits);
+        let mut last = 0u64;
+        (0..self.n).map(move |_| {
+            let mut quotient = 0u64;
+            while reader.next().ok_or(GcsError::Truncated)? {
+                quotient += 1;
+            }
+            let mut delta = quotient << p;
+            for bit in (0..p).rev() {
+                delta |= (reader.next().ok_or(GcsError::Truncated)? as u64) << bit;
+            }
+            last += delta;
+            Ok(last)
+        })
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        if self.n == 0 {
+            return false;
+        }
+        let target = hash_to_range(&self.key, element, self.n * self.params.m);
+        self.values()
+            .map_while(Result::ok)
+            .take_while(|value| *value <= target)
+            .any(|value| value == target)
+    }
+
+    // like `contains` for any of `elements`, in a single pass
+    pub fn contains_any<E: AsRef<[u8]>>(&self, elements: impl IntoIterator<Item = E>) -> bool {
+        if self.n == 0 {
+            return false;
+        }
+        let range = self.n * self.params.m;
+        let mut targets: Vec<u64> = elements
+            .into_iter()
+            .map(|element| hash_to_range(&self.key, element.as_ref(), range))
+            .collect();
+        targets.sort_unstable();
+        let mut targets = targets.into_iter().peekable();
+        for value in self.values().map_while(Result::ok) {
+            while targets.next_if(|target| *target < value).is_some() {}
+            match targets.peek() {
+                Some(target) if *target == value => return true,
+                Some(_) => {}
+                None => return false,
+            }
+        }
+        false
+    }
+
+    // how many of `elements` `contains` would accept, in a single pass
+    pub fn count_contained<E: AsRef<[u8]>>(&self, elements: impl IntoIterator<Item = E>) -> usize {
+        if self.n == 0 {
+            return 0;
+        }
+        let range = self.n * self.params.m;
+        let mut targets: Vec<u64> = elements
+            .into_iter()
+            .map(|element| hash_to_range(&self.key, element.as_ref(), range))
+            .collect();
+        targets.sort_unstable();
+        let mut targets = targets.into_iter().peekable();
+        let mut count = 0;
+        for value in self.values().map_while(Result::ok) {
+            while targets.next_if(|target| *target < value).is_some() {}
+            while targets.next_if_eq(&value).is_some() {
+                count += 1;
+            }
+            if targets.peek().is_none() {
+                break;
+            }
+        }
+        count
+    }
+}
+
+#[test]
+fn test_bip158_vectors() {
+    // testnet genesis block: its only script is the coinbase output
+    let mut block_hash: [u8; 32] =
+        hex::decode("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+            .unwrap()
+            .try_into()
+            .unwrap();
+    block_hash.reverse();
+    let script = hex::decode("4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac").unwrap();
+    let filter = GolombSet::bip158(&block_hash, [&script]);
+    assert_eq!(hex::encode(filter.to_bytes()), "019dfca8");
+    assert!(filter.contains(&script));
+    assert!(!filter.contains(b"not in the block"));
+
+    let key = block_hash[..16].try_into().unwrap();
+    assert_eq!(
+        GolombSet::from_bytes(BIP158_BASIC, key, &filter.to_bytes()),
+        Ok(filter)
+    );
+    // blocks without scripts have an empty filter
+    let empty = GolombSet::bip158(&block_hash, Vec::<Vec<u8>>::new());
+    assert_eq!(empty.to_bytes(), vec![0]);
+    assert!(!empty.contains(&script));
+}
+
+#[test]
+fn test_golomb_set() {
+    assert_eq!(GcsParams::for_fpr(1.0 / 784_931.0), BIP158_BASIC);
+    assert_eq!(GcsParams::for_fpr(0.5), GcsParams { p: 0, m: 2 });
+    let params = GcsParams { p: 10, m: 1 << 10 };
+    let elements: Vec<[u8; 4]> = (0..500u32).map(|i| i.to_le_bytes()).collect();
+    let set = GolombSet::build(params, [7; 16], &elements);
+    assert!(elements.iter().all(|element| set.contains(element)));
+    let positives = (500..20_500u32)
+        .filter(|i| set.contains(&i.to_le_bytes()))
+        .count();
+    assert!(positives < 60, "{positives} false positives");
+    assert!(set.contains_any([&b"absent"[..], &elements[42]]));
+    assert!(!set.contains_any([b"absent"]));
+    let probes: Vec<[u8; 4]> = (0..20_500u32).map(|i| i.to_le_bytes()).collect();
+    assert_eq!(set.count_contained(&probes), 500 + positives);
+
+    let bytes = set.to_bytes();
+    assert_eq!(
+        GolombSet::from_bytes(params, [7; 16], &bytes[..bytes.len() - 10]),
+        Err(GcsError::Truncated)
+    );
+}
@@ -0,0 +1,167 @@
+use crate::{dyn_bloom::DynBloom, element::encode_u64, filter::Filter};
+
+// Age-partitioned Bloom filter for sliding-window dedup: N `DynBloom`
+// generations of the same size, adds going to the newest and queries
+// checking all of them. `advance_generation` clears the oldest and makes it
+// the newest, so an element is present for the rest of the generation it
+// was added in plus N - 1 full ones, then drops out exactly, with no stale
+// bits left behind as in `DecayingBloom`. Each generation needs the bits for
+// the elements of one generation at the per-generation false positive rate,
+// and the rates of the N add up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenerationalBloom {
+    generations: Vec<DynBloom>,
+    // index of the newest generation, the older ones following it
+    newest: usize,
+}
+
+impl GenerationalBloom {
+    pub fn new(generations: usize, m_bits: usize, k: usize) -> Self {
+        assert!(generations > 0 && m_bits > 0 && k > 0);
+        Self {
+            generations: vec![DynBloom::new(m_bits, k); generations],
+            newest: 0,
+        }
+    }
+
+    pub fn generations(&self) -> usize {
+        self.generations.len()
+    }
+
+    // bits per generation
+    pub fn m_bits(&self) -> usize {
+        self.generations[0].m_bits()
+    }
+
+    pub fn k(&self) -> usize {
+        self.generations[0].k()
+    }
+
+    // true if this set a bit in the newest generation, so an element still
+    // held only by older ones counts as new
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        self.generations[self.newest].add(element)
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.generations
+            .iter()
+            .any(|generation| generation.has(element))
+    }
+
+    // The dedup step: whether element looks like a duplicate, then adds it
+    // either way, so it lives for another N generations.
+    pub fn check_and_add(&mut self, element: &[u8]) -> bool {
+        let seen = self.has(element);
+        self.add(element);
+        seen
+    }
+
+    // drops everything added N generations ago
+    pub fn advance_generation(&mut self) {
+        self.newest = (self.newest + self.generations.len() - 1) % self.generations.len();
+        let generation = &mut self.generations[self.newest];
+        *generation = DynBloom::new(generation.m_bits(), generation.k());
+    }
+
+    // newest first
+    pub fn iter_generations(&self) -> impl Iterator<Item = &DynBloom> {
+        let (older, newer) = self.generations.split_at(self.newest);
+        newer.iter().chain(older)
+    }
+}
+
+impl Filter for GenerationalBloom {
+    fn add(&mut self, element: &[u8]) {
+        GenerationalBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        GenerationalBloom::has(self, element)
+    }
+
+    // set bits over all generations
+    fn count_ones(&self) -> u32 {
+        self.generations.iter().map(DynBloom::count_ones).sum()
+    }
+}
+
+// `test_decay_rates`' stream for a `GenerationalBloom`: RATE new elements
+// per tick for TICKS ticks, a generation advance every TICKS_PER_GENERATION
+// ticks, then elements within the window missing (zero by construction),
+// older ones still present (stale), and never added ones present (false
+// positives), over a few generation counts with the same total bits.
+pub fn test_generational_rates() {
+    const TICKS: u64 = 200;
+    const RATE: u64 = 50;
+    const TICKS_PER_GENERATION: u64 = 5;
+    const TOTAL_BITS: usize = 262_144;
+    const PROBES: u64 = 100_000;
+
+    println!("generations;m_bits;k;window;recent_missing;stale_present;false_positives");
+    for generations in [2, 4, 8, 16] {
+        let k = 7;
+        let mut filter = GenerationalBloom::new(generations, TOTAL_BITS / generations, k);
+        for tick in 0..TICKS {
+            if tick > 0 && tick.is_multiple_of(TICKS_PER_GENERATION) {
+                filter.advance_generation();
+            }
+            for i in 0..RATE {
+                filter.add(&encode_u64(tick * RATE + i));
+            }
+        }
+        // the ticks of the newest generation and the N - 1 before it
+        let window = (generations as u64 - 1) * TICKS_PER_GENERATION
+            + (TICKS - 1) % TICKS_PER_GENERATION
+            + 1;
+        let recent = (TICKS - window) * RATE..TICKS * RATE;
+        let recent_total = recent.end - recent.start;
+        let recent_missing = recent
+            .filter(|&element| !filter.has(&encode_u64(element)))
+            .count();
+        let stale = 0..(TICKS - window) * RATE;
+        let stale_total = stale.end;
+        let stale_present = stale
+            .filter(|&element| filter.has(&encode_u64(element)))
+            .count();
+        let false_positives = (TICKS * RATE..TICKS * RATE + PROBES)
+            .filter(|&element| filter.has(&encode_u64(element)))
+            .count();
+        println!(
+            "{generations};{};{k};{window};{recent_missing}/{recent_total};{stale_present}/{stale_total};{false_positives}/{PROBES}",
+            filter.m_bits()
+        );
+    }
+}
+
+#[test]
+fn test_generational_bloom() {
+    let mut filter = GenerationalBloom::new(3, 4096, 7);
+    assert!(!filter.check_and_add(b"one"));
+    assert!(filter.check_and_add(b"one"));
+    assert!(!filter.add(b"one"));
+    assert_eq!(filter.count_ones(), 7);
+
+    // gone after three advances, present for the two before
+    filter.advance_generation();
+    filter.add(b"two");
+    assert!(filter.has(b"one") && filter.has(b"two"));
+    // "one" only in an older generation, so new to the newest
+    assert!(filter.iter_generations().nth(1).unwrap().has(b"one"));
+    filter.advance_generation();
+    assert!(filter.has(b"one") && filter.has(b"two"));
+    filter.advance_generation();
+    assert!(!filter.has(b"one") && filter.has(b"two"));
+    assert_eq!(filter.count_ones(), 7);
+    filter.advance_generation();
+    assert!(!filter.has(b"two"));
+    assert_eq!(filter.count_ones(), 0);
+
+    // re-adding keeps an element alive
+    let mut filter = GenerationalBloom::new(2, 4096, 7);
+    filter.add(b"one");
+    for _ in 0..10 {
+        filter.advance_generation();
+        assert!(filter.check_and_add(b"one"));
+    }
+}
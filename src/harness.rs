@@ -0,0 +1,486 @@
+use std::io::Write;
+#[cfg(feature = "blake3")]
+use std::time::Instant;
+
+#[cfg(feature = "blake3")]
+use crate::{
+    builder::BloomBuilder,
+    cli,
+    compare::estimate_elements,
+    dyn_bloom::{BloomVec, DynIndexing},
+    folded,
+    gcs::{GcsParams, GolombSet},
+    indexer::{
+        Blake3Keyed, BloomIndexer, Murmur3Bip37, SipDouble, Xxh3Double, Xxh3Rejection, Xxh3Wnfs,
+    },
+    params,
+    ribbon::Ribbon8,
+    xof::{Blake3XOF, Xof},
+    xor::{Xor16, Xor8},
+};
+use crate::{
+    dyn_bloom::DynBloom, entropy::EntropySource, filter::Filter, params::FilterSpec,
+    quotient::QuotientFilter, Bloom,
+};
+
+// The experiments the binary runs by default, and the helpers the TOML
+// experiments, test vectors and Python module share with them. They print
+// progress and results to stdout.
+
+#[cfg(feature = "blake3")]
+pub fn fill_deterministic<F: Filter>(seed: &str, elements: u32, bloom: &mut F) {
+    fill_random(elements, bloom, Blake3XOF::derived(seed, b"Hello, world!"));
+}
+
+// adds `elements` 32 byte elements drawn from entropy
+pub fn fill_random<F: Filter>(elements: u32, bloom: &mut F, mut entropy: impl EntropySource) {
+    for _ in 0..elements {
+        bloom.add(&entropy.block::<32>());
+    }
+}
+
+pub fn print_test_progress(i: u64, tests: u64) {
+    if i.is_multiple_of(1000) {
+        print!("\r{:>5}/{tests}            ", i);
+        std::io::stdout().flush().unwrap();
+    }
+}
+
+// average ones of filters from new after `prefill` elements, e.g.
+// `Bloom::<256, 30>::new` against `PartitionedBloom::<256, 30>::new`, and
+// the average number of elements estimated from them where the filter can
+// tell
+pub fn test_avg_bits<F: Filter>(
+    new: impl Fn() -> F,
+    prefill: u32,
+    tests: u64,
+    mut entropy: impl EntropySource,
+) {
+    let (mut sum, mut estimates) = (0, 0.0);
+    for i in 0..tests {
+        let mut bloom = new();
+        fill_random(prefill, &mut bloom, &mut entropy);
+
+        sum += bloom.count_ones();
+        estimates += bloom.estimate_len().unwrap_or(f64::NAN);
+        print_test_progress(i, tests);
+    }
+
+    println!("\n{}", (sum as f64) / (tests as f64));
+    if !estimates.is_nan() {
+        println!("{:.2} elements", estimates / tests as f64);
+    }
+}
+
+// Two filters of n elements each, half of them shared, combined: `DynBloom`s
+// ORed bit by bit and quotient filters merged, at about the same bits. The
+// union's false positives among 1_000_000 probes, and how full the merged
+// quotient filter is, which can't take more than its slots.
+pub fn test_merge_rates(mut entropy: impl EntropySource) {
+    const PROBES: usize = 1_000_000;
+
+    println!("n;union;bloom_bits;bloom_false_positives;quotient_bits;quotient_false_positives;quotient_load");
+    for n in (4000..=28000).step_by(4000) {
+        let items: Vec<[u8; 32]> = (0..n + n / 2).map(|_| entropy.block()).collect();
+        let (a_items, b_items) = (&items[..n], &items[n / 2..]);
+
+        let quotient = |items: &[[u8; 32]]| {
+            let mut filter = QuotientFilter::new(16, 9);
+            for item in items {
+                filter.add(item);
+            }
+            filter
+        };
+        let merged = quotient(a_items).merge(&quotient(b_items)).unwrap();
+
+        let bloom = |items: &[[u8; 32]]| {
+            let mut filter = DynBloom::new(merged.size_bits(), 7);
+            for item in items {
+                filter.add(item);
+            }
+            filter
+        };
+        let union = bloom(a_items).union(&bloom(b_items)).unwrap();
+
+        let (mut bloom_false_positives, mut quotient_false_positives) = (0, 0);
+        for _ in 0..PROBES {
+            let probe = entropy.block::<32>();
+            bloom_false_positives += union.has(&probe) as u32;
+            quotient_false_positives += merged.contains(&probe) as u32;
+        }
+        println!(
+            "{n};{};{};{bloom_false_positives};{};{quotient_false_positives};{:.3}",
+            items.len(),
+            union.m_bits(),
+            merged.size_bits(),
+            merged.load_factor()
+        );
+    }
+}
+
+// Two `Bloom<1024, 7>`s of n elements each, sharing a given fraction of
+// them, against the Jaccard similarity their bits estimate, from well
+// under the 8192 bits' capacity of about 800 elements to well past it.
+pub fn test_jaccard_rates(mut entropy: impl EntropySource) {
+    println!("n;shared;jaccard;estimate");
+    for n in (200..=2000).step_by(200) {
+        for shared in [0.1, 0.5, 0.9] {
+            let common = (n as f64 * shared) as usize;
+            let items: Vec<[u8; 32]> = (0..2 * n - common).map(|_| entropy.block()).collect();
+            let (mut a, mut b) = (Bloom::<1024, 7>::new(), Bloom::<1024, 7>::new());
+            for item in &items[..n] {
+                a.add(item);
+            }
+            for item in &items[n - common..] {
+                b.add(item);
+            }
+            let jaccard = common as f64 / items.len() as f64;
+            println!("{n};{shared};{jaccard:.4};{:.4}", a.jaccard_estimate(&b));
+        }
+    }
+}
+
+pub const TESTS: usize = 100_000;
+#[cfg(feature = "blake3")]
+pub fn test_avg_saturation_bits(mut entropy: impl EntropySource) {
+    let mut histo = [0u64; 256];
+
+    const BYTES: usize = 32 * TESTS;
+
+    let mut rando = [0u8; BYTES];
+    entropy.fill(&mut rando);
+
+    let before = Instant::now();
+    for i in 0..TESTS {
+        let mut bloom: Bloom<256, 30> = Bloom::new();
+
+        bloom.add(&rando[i * 32..(i + 1) * 32]);
+        bloom.saturate();
+
+        histo[bloom.count_ones() as usize - 896] += 1;
+        print_test_progress(i as u64, TESTS as u64);
+    }
+    let after = Instant::now();
+
+    println!("\nbits;amount;estimated_elements");
+    for (i, v) in histo.iter().enumerate() {
+        let ones = i as u32 + 896;
+        println!("{ones};{v};{:.1}", estimate_elements(2048, 30, ones));
+    }
+    println!("{}", after.duration_since(before).as_millis());
+}
+
+#[cfg(feature = "blake3")]
+pub fn test_false_positive_rate<F: Filter>(mut bloom: F, prefill: u32, tests: u64) {
+    fill_deterministic("Bloom filter prefill", prefill, &mut bloom);
+
+    println!("{}", bloom.count_ones());
+    let before = Instant::now();
+
+    let mut false_positive_count = 0;
+    for i in 0..tests {
+        if bloom.has(&i.to_le_bytes()) {
+            false_positive_count += 1;
+        }
+        if i.is_multiple_of(100_000) {
+            print_test_progress(i, tests);
+        }
+    }
+
+    let after = Instant::now();
+    println!(
+        "{false_positive_count}/{tests} {}ms",
+        after.duration_since(before).as_millis()
+    );
+    // the rate measured above next to the one the fill predicts
+    if let Some(estimated) = bloom.estimated_fpr() {
+        println!(
+            "measured {:.3e}, estimated from fill {estimated:.3e}",
+            false_positive_count as f64 / tests as f64
+        );
+    }
+}
+
+// a `Bloom<256, 30>` prefilled like `test_false_positive_rate`'s, with its
+// false positives among the same probes and the time they took, indexed by
+// indexer
+#[cfg(feature = "blake3")]
+fn print_indexer_rate(indexer: &impl BloomIndexer, prefill: u32, tests: u64) {
+    let mut bloom = Bloom::<256, 30>::new();
+    let elements = Blake3XOF::derived("Bloom filter prefill", b"Hello, world!").blocks::<32>();
+    for element in elements.take(prefill as usize) {
+        bloom.add_with(indexer, &element);
+    }
+    let before = Instant::now();
+    let false_positives = (0..tests)
+        .filter(|i| bloom.has_with(indexer, &i.to_le_bytes()))
+        .count();
+    let after = Instant::now();
+    println!(
+        "{}, {}, {false_positives}/{tests} {}ms",
+        indexer.strategy().name(),
+        bloom.count_ones(),
+        after.duration_since(before).as_millis()
+    );
+}
+
+// every indexer a `Bloom` can use, on the same filter shape and elements.
+// xxh3-double next to xxh3-rejection is double hashing against hashing
+// the element again for every few indices: whether deriving all K from one
+// hash costs false positives, and how much query time it saves.
+#[cfg(feature = "blake3")]
+pub fn test_indexer_rates() {
+    let (prefill, tests) = (47, 100_000_000);
+    print_indexer_rate(&Xxh3Rejection, prefill, tests);
+    print_indexer_rate(&Blake3Keyed { key: [7; 32] }, prefill, tests);
+    print_indexer_rate(&Xxh3Wnfs, prefill, tests);
+    print_indexer_rate(&Murmur3Bip37 { tweak: 0 }, prefill, tests);
+    print_indexer_rate(
+        &SipDouble {
+            keys: [[1; 16], [2; 16]],
+        },
+        prefill,
+        tests,
+    );
+    print_indexer_rate(&Xxh3Double, prefill, tests);
+}
+
+// `fpr [--m-bits <m>] [--k <k>] [--capacity <n> [--target-fpr 1e-9]]
+// [--prefill <n>] [--tests <t>]`
+//
+// `test_false_positive_rate` on a `BloomVec` of the given size, without
+// recompiling; the defaults are the WNFS namefilter's. With --capacity, the
+// `BloomBuilder` picks m and k for that many elements at --target-fpr.
+#[cfg(feature = "blake3")]
+pub fn run_fpr(args: &[String]) -> Result<(), String> {
+    let bloom = match cli::flag(args, "--capacity") {
+        Some(_) => BloomBuilder::with_capacity(cli::parsed(args, "--capacity", 0u64)?)
+            .false_positive_rate(cli::parsed(args, "--target-fpr", 1e-9f64)?)
+            .build()
+            .map_err(|e| format!("invalid --capacity or --target-fpr: {e:?}"))?,
+        None => BloomVec::try_new(
+            cli::parsed(args, "--m-bits", 2048usize)?,
+            cli::parsed(args, "--k", 30usize)?,
+            DynIndexing::Xxh3Rejection,
+        )
+        .map_err(|e| format!("invalid --m-bits or --k: {e:?}"))?,
+    };
+    let (m_bits, k) = (bloom.m_bits(), bloom.k());
+    let prefill = cli::parsed(args, "--prefill", 47u32)?;
+    let tests = cli::parsed(args, "--tests", 1_000_000u64)?;
+    test_false_positive_rate(bloom, prefill, tests);
+    println!(
+        "theoretical for {prefill} elements {:.3e}",
+        params::theoretical_fpr(m_bits, k, prefill as u64)
+    );
+    Ok(())
+}
+
+#[cfg(feature = "blake3")]
+// false positives among `tests` probes (the little endian u64s below it) of
+// a filter prefilled with `prefill` deterministic elements
+pub fn count_false_positives<F: Filter>(bloom: &mut F, prefill: u32, tests: u64) -> u64 {
+    fill_deterministic("Bloom filter prefill", prefill, bloom);
+    (0..tests).filter(|i| bloom.has(&i.to_le_bytes())).count() as u64
+}
+
+// The filter the folded experiments run on, sized for CAPACITY elements at
+// TARGET_FPR: about 2^18 bits and 18 hash functions.
+pub const CAPACITY: u64 = 10_000;
+pub const TARGET_FPR: f64 = 4e-6;
+pub const SPEC: FilterSpec = FilterSpec::new(CAPACITY, TARGET_FPR);
+pub const M: usize = SPEC.m_bits; // original bloom filter bits
+pub const K: usize = SPEC.k; // num of hash functions
+pub const F: usize = 0; // num of folds
+pub const S: usize = (M / 8) >> F; // byte size of folded filter
+
+#[cfg(feature = "blake3")]
+// (false negatives, false positives) of a `Folded<F, S, K>` holding n
+// elements, probed with `probes` elements that aren't in it, all drawn from
+// entropy
+pub fn folded_rates(n: usize, probes: usize, mut entropy: impl EntropySource) -> (u32, u32) {
+    let mut filter: folded::Folded<F, S, K> = folded::Folded::new();
+    let items: Vec<[u8; 32]> = (0..n).map(|_| entropy.block()).collect();
+    for item in items.iter() {
+        filter.insert(item);
+    }
+
+    let mut false_negative_count = 0;
+    for item_in_filter in items.iter() {
+        if !filter.has(item_in_filter) {
+            false_negative_count += 1;
+        }
+    }
+
+    let mut false_positive_count = 0;
+    for _ in 0..probes {
+        if filter.has(&entropy.block::<32>()) {
+            false_positive_count += 1;
+        }
+    }
+
+    (false_negative_count, false_positive_count)
+}
+
+#[cfg(feature = "blake3")]
+pub fn test_folded_rates(mut entropy: impl EntropySource) {
+    let min = 4000;
+    let max = 30000;
+    let step_size = 100;
+
+    for n_fac in (min / step_size)..(max / step_size + 1) {
+        let n = step_size * n_fac;
+        let (false_negative_count, false_positive_count) = folded_rates(n, 1_000_000, &mut entropy);
+        println!("{n}, {false_negative_count}, {false_positive_count}")
+    }
+}
+
+// Transfer sizes for `test_folded_rates`' n elements: the folded filter's
+// S bytes, and a Golomb-coded set sized for the false positive rate the
+// folded filter is expected to have at n, (1 - e^(-K n / M))^K. Both
+// filters' false positives among the same 1_000_000 probes check that the
+// rates match.
+#[cfg(feature = "blake3")]
+pub fn test_gcs_sizes(mut entropy: impl EntropySource) {
+    const PROBES: usize = 1_000_000;
+
+    println!("n;fpr;folded_bytes;folded_false_positives;gcs_p;gcs_m;gcs_bytes;gcs_false_positives");
+    for n in (4000..=30000).step_by(2000) {
+        let items: Vec<[u8; 32]> = (0..n).map(|_| entropy.block()).collect();
+        let mut folded = folded::Folded::<F, S, K>::new();
+        for item in &items {
+            folded.insert(item);
+        }
+        let fpr = params::theoretical_fpr(M, K, n as u64);
+        let params = GcsParams::for_fpr(fpr);
+        let gcs = GolombSet::build(params, entropy.block(), &items);
+
+        let probes: Vec<[u8; 32]> = (0..PROBES).map(|_| entropy.block()).collect();
+        let folded_false_positives = probes.iter().filter(|probe| folded.has(probe)).count();
+        // one pass over the coded set, `contains` decodes it per probe
+        let gcs_false_positives = gcs.count_contained(&probes);
+        println!(
+            "{n};{fpr:.3e};{};{folded_false_positives};{};{};{};{gcs_false_positives}",
+            folded.bytes.len(),
+            params.p,
+            params.m,
+            gcs.to_bytes().len()
+        );
+    }
+}
+
+// `test_folded_rates`' sweep with xor filters of the same n elements: the
+// bits each needs against the folded filter's fixed S * 8, and their false
+// positives among 1_000_000 probes
+#[cfg(feature = "blake3")]
+pub fn test_xor_rates(mut entropy: impl EntropySource) {
+    const PROBES: usize = 1_000_000;
+
+    println!("n, folded_bits, folded_false_positives, xor8_bits, xor8_false_positives, xor16_bits, xor16_false_positives");
+    for n in (4000..=30000).step_by(1000) {
+        let (_, folded_false_positives) = folded_rates(n, PROBES, &mut entropy);
+        let items: Vec<[u8; 32]> = (0..n).map(|_| entropy.block()).collect();
+        let xor8 = Xor8::build(&items);
+        let xor16 = Xor16::build(&items);
+        let (mut xor8_false_positives, mut xor16_false_positives) = (0, 0);
+        for _ in 0..PROBES {
+            let probe = entropy.block::<32>();
+            xor8_false_positives += xor8.contains(&probe) as u32;
+            xor16_false_positives += xor16.contains(&probe) as u32;
+        }
+        println!(
+            "{n}, {}, {folded_false_positives}, {}, {xor8_false_positives}, {}, {xor16_false_positives}",
+            S * 8,
+            xor8.size_bits(),
+            xor16.size_bits()
+        );
+    }
+}
+
+// Build and probe times of a `Folded<F, S, K>`, a `Bloom` that may use as
+// many bits as the ribbon filter and a `Ribbon8` over the same n elements,
+// with their false positives among the same 1_000_000 probes
+#[cfg(feature = "blake3")]
+pub fn test_ribbon_rates(mut entropy: impl EntropySource) {
+    const PROBES: usize = 1_000_000;
+
+    println!("n, filter, bits, false_positives, build_ms, probe_ms");
+    for n in (4000..=30000).step_by(2000) {
+        let items: Vec<[u8; 32]> = (0..n).map(|_| entropy.block()).collect();
+        let probes: Vec<[u8; 32]> = (0..PROBES).map(|_| entropy.block()).collect();
+
+        let before = Instant::now();
+        let mut folded = folded::Folded::<F, S, K>::new();
+        for item in &items {
+            folded.insert(item);
+        }
+        let built = Instant::now();
+        let false_positives = probes.iter().filter(|probe| folded.has(probe)).count();
+        print_timings(n, "folded", S * 8, false_positives, before, built);
+
+        let before = Instant::now();
+        let ribbon = Ribbon8::build(&items);
+        let built = Instant::now();
+        let false_positives = probes
+            .iter()
+            .filter(|probe| ribbon.contains(&probe[..]))
+            .count();
+        print_timings(
+            n,
+            "ribbon8",
+            ribbon.size_bits(),
+            false_positives,
+            before,
+            built,
+        );
+
+        // the ribbon's bits, with the best k for them
+        let m_bits = ribbon.size_bits();
+        let k = ((m_bits as f64 / n as f64) * std::f64::consts::LN_2)
+            .round()
+            .max(1.0) as usize;
+        let before = Instant::now();
+        let mut bloom = BloomVec::new(m_bits, k);
+        for item in &items {
+            bloom.add(item);
+        }
+        let built = Instant::now();
+        let false_positives = probes.iter().filter(|probe| bloom.has(&probe[..])).count();
+        print_timings(n, "bloom", m_bits, false_positives, before, built);
+    }
+}
+
+#[cfg(feature = "blake3")]
+fn print_timings(
+    n: usize,
+    filter: &str,
+    bits: usize,
+    false_positives: usize,
+    before: Instant,
+    built: Instant,
+) {
+    let probed = Instant::now();
+    println!(
+        "{n}, {filter}, {bits}, {false_positives}, {}, {}",
+        built.duration_since(before).as_millis(),
+        probed.duration_since(built).as_millis()
+    );
+}
+
+#[test]
+fn test_bitavg() {
+    use crate::{entropy::ChaChaEntropy, partitioned::PartitionedBloom, Bloom};
+
+    test_avg_bits(
+        Bloom::<256, 30>::new,
+        47,
+        100_000,
+        ChaChaEntropy::from_seed(47),
+    );
+    test_avg_bits(
+        PartitionedBloom::<256, 30>::new,
+        47,
+        10_000,
+        ChaChaEntropy::from_seed(47),
+    );
+}
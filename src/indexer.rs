@@ -0,0 +1,247 @@
+use bloomfilter::reexports::siphasher::sip::SipHasher13;
+use std::hash::{Hash, Hasher};
+use xxhash_rust::xxh3;
+
+#[cfg(feature = "blake3")]
+use crate::xof::Blake3XOF;
+use crate::{
+    bip37::murmur3_32,
+    bloom_indices_from_hashes,
+    strategy::HashStrategy,
+    wnfs::WnfsIndices,
+    xof::{Xof, XXH3XOF},
+    Bloom,
+};
+
+// How a filter turns an element into its k bit indices below m_bits, one
+// implementation per `HashStrategy` that a filter can be built on.
+// `Bloom::add_with` and friends set and test bits through any of them, so
+// filters that only differ in indexing share the bit handling, and
+// experiments can swap strategies on the same filter type.
+pub trait BloomIndexer {
+    fn strategy(&self) -> HashStrategy;
+
+    // in derivation order, repeats included
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize>;
+}
+
+// `Bloom`'s own: see `bloom_indices_for_element`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Xxh3Rejection;
+
+impl BloomIndexer for Xxh3Rejection {
+    fn strategy(&self) -> HashStrategy {
+        HashStrategy::Xxh3Rejection
+    }
+
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize> {
+        bloom_indices_from_hashes(XXH3XOF::from(element).u64s(), m_bits, k)
+    }
+}
+
+// `KeyedBloom`'s: rejection sampling over a keyed blake3 stream
+#[cfg(feature = "blake3")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Blake3Keyed {
+    pub key: [u8; 32],
+}
+
+#[cfg(feature = "blake3")]
+impl BloomIndexer for Blake3Keyed {
+    fn strategy(&self) -> HashStrategy {
+        HashStrategy::Blake3Keyed
+    }
+
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize> {
+        bloom_indices_from_hashes(Blake3XOF::keyed(&self.key, element).u64s(), m_bits, k)
+    }
+}
+
+// `WnfsBloom`'s: see `WnfsIndices`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Xxh3Wnfs;
+
+impl BloomIndexer for Xxh3Wnfs {
+    fn strategy(&self) -> HashStrategy {
+        HashStrategy::Xxh3Wnfs
+    }
+
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize> {
+        WnfsIndices::new(element, m_bits).take(k)
+    }
+}
+
+// `Bip37Filter`'s, for a filter of m_bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Murmur3Bip37 {
+    pub tweak: u32,
+}
+
+impl BloomIndexer for Murmur3Bip37 {
+    fn strategy(&self) -> HashStrategy {
+        HashStrategy::Murmur3Bip37
+    }
+
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize> {
+        let tweak = self.tweak;
+        (0..k as u32).map(move |i| {
+            let seed = i.wrapping_mul(0xFBA4C795).wrapping_add(tweak);
+            murmur3_32(element, seed) as usize % m_bits
+        })
+    }
+}
+
+// largest prime below 2^64
+const SIP_DOUBLE_MODULUS: u64 = 0xFFFF_FFFF_FFFF_FFC5;
+
+// `DynIndexing::SipDouble`'s: the `bloomfilter` crate's double hashing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SipDouble {
+    pub keys: [[u8; 16]; 2],
+}
+
+impl BloomIndexer for SipDouble {
+    fn strategy(&self) -> HashStrategy {
+        HashStrategy::SipDouble
+    }
+
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize> {
+        let hash = |key: &[u8; 16]| {
+            let mut sip = SipHasher13::new_with_key(key);
+            element.hash(&mut sip);
+            sip.finish()
+        };
+        let (h0, h1) = (hash(&self.keys[0]), hash(&self.keys[1]));
+        (0..k as u64)
+            .map(move |i| match i {
+                0 => h0,
+                1 => h1,
+                _ => h0.wrapping_add(i.wrapping_mul(h1)) % SIP_DOUBLE_MODULUS,
+            })
+            .map(move |hash| (hash % m_bits as u64) as usize)
+    }
+}
+
+// Kirsch and Mitzenmacher (2006): k indices from two hashes, h1 + i * h2
+// mod m, with no asymptotic loss in false positive rate. Both halves come
+// from a single xxh3_128 of the element, where `Xxh3Rejection` hashes it
+// again for every 64 bits of indices. h2 is made odd so that for an even m
+// the step is never 0, and for a power of two m the indices don't repeat
+// before m of them. An odd m could divide h2, so it steps by
+// 1 + h2 mod (m - 1) instead; that isn't coprime to m in general, so indices
+// can still repeat before m of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Xxh3Double;
+
+impl BloomIndexer for Xxh3Double {
+    fn strategy(&self) -> HashStrategy {
+        HashStrategy::Xxh3Double
+    }
+
+    fn indices(&self, element: &[u8], m_bits: usize, k: usize) -> impl Iterator<Item = usize> {
+        let hash = xxh3::xxh3_128(element);
+        let m = m_bits as u64;
+        let (h1, h2) = (hash as u64 % m, (hash >> 64) as u64 | 1);
+        let step = if m.is_multiple_of(2) {
+            h2 % m
+        } else {
+            1 + h2 % (m - 1).max(1)
+        };
+        // stepping by addition, as h1 + i * step could overflow for large m
+        std::iter::successors(Some(h1), move |index| {
+            Some(((*index as u128 + step as u128) % m as u128) as u64)
+        })
+        .take(k)
+        .map(|index| index as usize)
+    }
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // `add` with another indexer's K indices
+    pub fn add_with(&mut self, indexer: &impl BloomIndexer, element: &[u8]) -> bool {
+        let mut new = false;
+        for index in indexer.indices(element, M * 8, K) {
+            new |= !self.test_bit(index);
+            self.set_bit(index);
+        }
+        new
+    }
+
+    pub fn has_with(&self, indexer: &impl BloomIndexer, element: &[u8]) -> bool {
+        indexer
+            .indices(element, M * 8, K)
+            .all(|index| self.test_bit(index))
+    }
+
+    pub fn has_constant_time_with(&self, indexer: &impl BloomIndexer, element: &[u8]) -> bool {
+        let mut all_set = 1u8;
+        for index in indexer.indices(element, M * 8, K) {
+            all_set &= self.bit(index);
+        }
+        all_set == 1
+    }
+
+    pub fn indices_for_with(&self, indexer: &impl BloomIndexer, element: &[u8]) -> [usize; K] {
+        let mut indices = indexer.indices(element, M * 8, K);
+        std::array::from_fn(|_| indices.next().unwrap())
+    }
+}
+
+#[test]
+fn test_indexers() {
+    use crate::{bloom_indices_for_element, dyn_bloom::DynBloom};
+
+    let element = b"indexed";
+    assert!(Xxh3Rejection
+        .indices(element, 1000, 7)
+        .eq(bloom_indices_for_element(element, 1000, 7)));
+    assert!(Xxh3Wnfs
+        .indices(element, 1000, 7)
+        .eq(WnfsIndices::new(element, 1000).take(7)));
+    let keys = [[1; 16], [2; 16]];
+    let sip = DynBloom::with_indexing(1000, 7, crate::dyn_bloom::DynIndexing::SipDouble { keys });
+    assert!(SipDouble { keys }
+        .indices(element, 1000, 7)
+        .eq(sip.indices_for(element)));
+
+    let mut bip37 = crate::bip37::Bip37Filter::new(20, 0.01, 5, 0);
+    bip37.insert(element);
+    let m_bits = bip37.bytes().len() * 8;
+    let k = bip37.hash_funcs() as usize;
+    let mut bits = vec![0u8; m_bits / 8];
+    for index in (Murmur3Bip37 { tweak: 5 }).indices(element, m_bits, k) {
+        bits[index / 8] |= 1 << (index % 8);
+    }
+    assert_eq!(bits, bip37.bytes());
+
+    // one 128-bit hash, then steps of its odd upper half
+    let hash = xxh3::xxh3_128(element);
+    let (h1, h2) = (hash as u64, (hash >> 64) as u64 | 1);
+    let expected: Vec<usize> = (0..7u64)
+        .map(|i| (h1.wrapping_add(i.wrapping_mul(h2)) % 1024) as usize)
+        .collect();
+    assert!(Xxh3Double.indices(element, 1024, 7).eq(expected));
+    let mut distinct: Vec<usize> = Xxh3Double.indices(element, 64, 64).collect();
+    distinct.sort();
+    distinct.dedup();
+    assert_eq!(distinct.len(), 64);
+    // an odd prime m, where a step of h2 mod m would be 0 for one in 7
+    for i in 0..100u32 {
+        let mut distinct: Vec<usize> = Xxh3Double.indices(&i.to_le_bytes(), 7, 7).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 7, "{i}");
+    }
+
+    // the same filter type under different indexers sets different bits
+    let mut plain = Bloom::<256, 30>::new();
+    let mut wnfs = Bloom::<256, 30>::new();
+    assert!(plain.add_with(&Xxh3Rejection, element) && wnfs.add_with(&Xxh3Wnfs, element));
+    assert!(plain.has(element) && plain.has_with(&Xxh3Rejection, element));
+    assert!(wnfs.has_with(&Xxh3Wnfs, element) && wnfs.has_constant_time_with(&Xxh3Wnfs, element));
+    assert!(plain.bytes != wnfs.bytes);
+    assert_eq!(
+        wnfs.indices_for_with(&Xxh3Wnfs, element),
+        crate::wnfs::WnfsBloom::<256, 30>::new().indices_for(element)
+    );
+}
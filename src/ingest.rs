@@ -0,0 +1,247 @@
+use std::{
+    fs,
+    io::{self, BufRead},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cli,
+    dyn_bloom::{DynBloom, DynIndexing},
+    tool,
+};
+
+// Continuous ingestion: elements stream in one per line and the filter is
+// snapshotted every so many seconds or elements, and once more when the
+// input ends. A snapshot replaces `<output>` with the filter's `to_bytes`,
+// then `<output>.stats` with one line
+//
+//   <sequence> <elements added> <ones> <m bits> <density>
+//
+// both by renaming a fully written temporary file, so readers never see a
+// partial file. Watch the stats file: once it changes, the filter next to it
+// is at least as new as the stats describe.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SnapshotPolicy {
+    pub every_elements: Option<u64>,
+    pub every: Option<Duration>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapshotStats {
+    pub sequence: u64,
+    pub added: u64,
+    pub ones: u32,
+    pub m_bits: usize,
+}
+
+impl SnapshotStats {
+    pub fn density(&self) -> f64 {
+        self.ones as f64 / self.m_bits as f64
+    }
+}
+
+pub fn write_atomically(path: &str, bytes: &[u8]) -> Result<(), String> {
+    let temporary = format!("{path}.tmp");
+    fs::write(&temporary, bytes).map_err(|e| format!("can't write {temporary}: {e}"))?;
+    fs::rename(&temporary, path).map_err(|e| format!("can't replace {path}: {e}"))
+}
+
+pub fn write_snapshot(path: &str, filter: &DynBloom, stats: &SnapshotStats) -> Result<(), String> {
+    write_atomically(path, &filter.to_bytes())?;
+    let line = format!(
+        "{} {} {} {} {:.6}\n",
+        stats.sequence,
+        stats.added,
+        stats.ones,
+        stats.m_bits,
+        stats.density()
+    );
+    write_atomically(&format!("{path}.stats"), line.as_bytes())
+}
+
+// Adds every line received until the sender hangs up, calling `snapshot`
+// as the policy asks and after the last line. Answers the elements added.
+pub fn ingest(
+    filter: &mut DynBloom,
+    lines: Receiver<Result<String, String>>,
+    hex: bool,
+    policy: SnapshotPolicy,
+    mut snapshot: impl FnMut(&DynBloom, &SnapshotStats) -> Result<(), String>,
+) -> Result<u64, String> {
+    let mut added = 0;
+    let mut since_snapshot = 0;
+    let mut sequence = 0;
+    let mut deadline = policy.every.map(|every| Instant::now() + every);
+    let mut take_snapshot = |filter: &DynBloom, added: u64| {
+        let stats = SnapshotStats {
+            sequence,
+            added,
+            ones: filter.count_ones(),
+            m_bits: filter.m_bits(),
+        };
+        sequence += 1;
+        snapshot(filter, &stats)
+    };
+    loop {
+        let received = match deadline {
+            Some(deadline) => {
+                lines.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(line) => {
+                filter.add(&tool::decode_line(&line?, added + 1, hex)?);
+                added += 1;
+                since_snapshot += 1;
+                if policy.every_elements == Some(since_snapshot) {
+                    take_snapshot(filter, added)?;
+                    since_snapshot = 0;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                take_snapshot(filter, added)?;
+                return Ok(added);
+            }
+        }
+        if let (Some(every), Some(at)) = (policy.every, deadline) {
+            if Instant::now() >= at {
+                take_snapshot(filter, added)?;
+                since_snapshot = 0;
+                deadline = Some(Instant::now() + every);
+            }
+        }
+    }
+}
+
+// stdin lines, read on their own thread so time based snapshots happen while
+// the input is idle
+fn stdin_lines() -> Receiver<Result<String, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let line = line
+                .map(|line| line.strip_suffix('\r').map(str::to_string).unwrap_or(line))
+                .map_err(|e| format!("can't read input: {e}"));
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// `ingest --m-bits <m> --k <k> --output <file> [--hex]
+//  [--every-seconds <s>] [--every-elements <n>]`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let m_bits: usize = cli::parsed(args, "--m-bits", 0)?;
+    let k: usize = cli::parsed(args, "--k", 0)?;
+    let mut filter = DynBloom::try_new(m_bits, k, DynIndexing::Xxh3Rejection)
+        .map_err(|e| format!("invalid --m-bits or --k: {e:?}"))?;
+    let output = cli::required(args, "--output")?;
+    let seconds: f64 = cli::parsed(args, "--every-seconds", 0.0)?;
+    let elements: u64 = cli::parsed(args, "--every-elements", 0)?;
+    let policy = SnapshotPolicy {
+        every_elements: (elements > 0).then_some(elements),
+        every: (seconds > 0.0).then(|| Duration::from_secs_f64(seconds)),
+    };
+    if policy == SnapshotPolicy::default() {
+        return Err("missing --every-seconds or --every-elements".to_string());
+    }
+    let added = ingest(
+        &mut filter,
+        stdin_lines(),
+        cli::switch(args, "--hex"),
+        policy,
+        |filter, stats| {
+            eprintln!(
+                "snapshot {}: {} elements, {:.4} density",
+                stats.sequence,
+                stats.added,
+                stats.density()
+            );
+            write_snapshot(output, filter, stats)
+        },
+    )?;
+    eprintln!("input ended after {added} elements");
+    Ok(())
+}
+
+#[test]
+fn test_ingest_every_elements() {
+    let (sender, receiver) = mpsc::channel();
+    for i in 0..25 {
+        sender.send(Ok(format!("element {i}"))).unwrap();
+    }
+    drop(sender);
+    let mut filter = DynBloom::new(4096, 7);
+    let mut snapshots = Vec::new();
+    let policy = SnapshotPolicy {
+        every_elements: Some(10),
+        every: None,
+    };
+    let added = ingest(&mut filter, receiver, false, policy, |filter, stats| {
+        assert_eq!(stats.ones, filter.count_ones());
+        snapshots.push((stats.sequence, stats.added));
+        Ok(())
+    });
+    assert_eq!(added, Ok(25));
+    assert_eq!(snapshots, vec![(0, 10), (1, 20), (2, 25)]);
+    assert!(filter.has(b"element 24"));
+}
+
+#[test]
+fn test_ingest_every_interval() {
+    let (sender, receiver) = mpsc::channel();
+    let feeder = thread::spawn(move || {
+        sender.send(Ok("6f6e65".to_string())).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        sender.send(Ok("74776f".to_string())).unwrap();
+    });
+    let mut filter = DynBloom::new(4096, 7);
+    let mut snapshots = Vec::new();
+    let policy = SnapshotPolicy {
+        every_elements: None,
+        every: Some(Duration::from_millis(50)),
+    };
+    ingest(&mut filter, receiver, true, policy, |_, stats| {
+        snapshots.push(stats.added);
+        Ok(())
+    })
+    .unwrap();
+    feeder.join().unwrap();
+    // idle snapshots while waiting for the second element, then the last one
+    assert!(snapshots.len() >= 3, "{snapshots:?}");
+    assert_eq!(snapshots.first(), Some(&1));
+    assert_eq!(snapshots.last(), Some(&2));
+    assert!(filter.has(b"one") && filter.has(b"two"));
+}
+
+#[test]
+fn test_write_snapshot() {
+    let path = std::env::temp_dir().join(format!("ingest-snapshot-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut filter = DynBloom::new(1000, 5);
+    filter.add(b"one");
+    let stats = SnapshotStats {
+        sequence: 3,
+        added: 1,
+        ones: filter.count_ones(),
+        m_bits: 1000,
+    };
+    write_snapshot(path, &filter, &stats).unwrap();
+    assert_eq!(
+        DynBloom::try_from_bytes(&fs::read(path).unwrap()),
+        Ok(filter)
+    );
+    assert_eq!(
+        fs::read_to_string(format!("{path}.stats")).unwrap(),
+        "3 1 5 1000 0.005000\n"
+    );
+    fs::remove_file(path).unwrap();
+    fs::remove_file(format!("{path}.stats")).unwrap();
+}
@@ -0,0 +1,148 @@
+use bloomfilter::Bloom as CratesBloom;
+
+use crate::dyn_bloom::{DynBloom, DynIndexing};
+
+// Conversions to and from the crates.io `bloomfilter` crate. Its serialized
+// form is a header (version, bitmap length in bytes, k, two 16-byte SipHash
+// keys) followed by the bitmap, with the same bit numbering as ours.
+const HEADER_SIZE: usize = 1 + 8 + 4 + 32;
+const VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InteropError {
+    // only `DynIndexing::SipDouble` filters answer queries like `bloomfilter`
+    NotSipIndexed,
+    // `bloomfilter` sizes its bitmap in whole bytes
+    NotByteAligned,
+    // `bloomfilter` rejects k = 0
+    NoHashFunctions,
+    Rejected(&'static str),
+}
+
+impl From<&CratesBloom<[u8]>> for DynBloom {
+    fn from(bloom: &CratesBloom<[u8]>) -> Self {
+        let bytes = bloom.as_slice();
+        let seed = bloom.seed();
+        let mut keys = [[0u8; 16]; 2];
+        keys[0].copy_from_slice(&seed[..16]);
+        keys[1].copy_from_slice(&seed[16..]);
+        let bits = bytes[HEADER_SIZE..].to_vec();
+        let m_bits = bits.len() * 8;
+        let k = bloom.number_of_hash_functions() as usize;
+        DynBloom::from_bytes(bits, m_bits, k, DynIndexing::SipDouble { keys })
+            .expect("whole-byte bitmap")
+    }
+}
+
+impl From<CratesBloom<[u8]>> for DynBloom {
+    fn from(bloom: CratesBloom<[u8]>) -> Self {
+        DynBloom::from(&bloom)
+    }
+}
+
+impl TryFrom<&DynBloom> for CratesBloom<[u8]> {
+    type Error = InteropError;
+
+    fn try_from(bloom: &DynBloom) -> Result<Self, Self::Error> {
+        let DynIndexing::SipDouble { keys } = bloom.indexing() else {
+            return Err(InteropError::NotSipIndexed);
+        };
+        if !bloom.m_bits().is_multiple_of(8) {
+            return Err(InteropError::NotByteAligned);
+        }
+        if bloom.k() == 0 {
+            return Err(InteropError::NoHashFunctions);
+        }
+        let k = u32::try_from(bloom.k()).map_err(|_| InteropError::Rejected("Too many keys"))?;
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + bloom.bytes().len());
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(bloom.bytes().len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&k.to_le_bytes());
+        bytes.extend_from_slice(&keys[0]);
+        bytes.extend_from_slice(&keys[1]);
+        bytes.extend_from_slice(bloom.bytes());
+        CratesBloom::from_bytes(bytes).map_err(InteropError::Rejected)
+    }
+}
+
+impl TryFrom<DynBloom> for CratesBloom<[u8]> {
+    type Error = InteropError;
+
+    fn try_from(bloom: DynBloom) -> Result<Self, Self::Error> {
+        CratesBloom::try_from(&bloom)
+    }
+}
+
+// fixed seed for the tests
+fn test_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = i as u8 * 7 + 3;
+    }
+    seed
+}
+
+#[test]
+fn test_from_crates_bloom() {
+    let mut theirs = CratesBloom::<[u8]>::new_with_seed(128, 100, &test_seed()).unwrap();
+    for i in 0..100u32 {
+        theirs.set(&i.to_le_bytes()[..]);
+    }
+    let ours = DynBloom::from(&theirs);
+    assert_eq!(ours.m_bits(), 1024);
+    assert_eq!(ours.k() as u32, theirs.number_of_hash_functions());
+    for i in 0..2000u32 {
+        let element = i.to_le_bytes();
+        assert_eq!(ours.has(&element), theirs.check(&element[..]));
+    }
+}
+
+#[test]
+fn test_to_crates_bloom() {
+    let seed = test_seed();
+    let mut keys = [[0u8; 16]; 2];
+    keys[0].copy_from_slice(&seed[..16]);
+    keys[1].copy_from_slice(&seed[16..]);
+    let mut ours = DynBloom::with_indexing(1024, 6, DynIndexing::SipDouble { keys });
+    for i in 0..100u32 {
+        ours.add(&i.to_le_bytes());
+    }
+    let theirs = CratesBloom::try_from(&ours).unwrap();
+    assert_eq!(theirs.seed(), seed);
+    for i in 0..2000u32 {
+        let element = i.to_le_bytes();
+        assert_eq!(ours.has(&element), theirs.check(&element[..]));
+    }
+    // adding on their side yields the same bits as adding on ours
+    let mut theirs = theirs;
+    ours.add(b"late");
+    theirs.set(&b"late"[..]);
+    assert_eq!(DynBloom::from(theirs), ours);
+}
+
+#[test]
+fn test_to_crates_bloom_errors() {
+    let keys = [[0u8; 16]; 2];
+    assert_eq!(
+        CratesBloom::try_from(DynBloom::new(1024, 6)).err(),
+        Some(InteropError::NotSipIndexed)
+    );
+    assert_eq!(
+        CratesBloom::try_from(DynBloom::with_indexing(
+            1001,
+            6,
+            DynIndexing::SipDouble { keys }
+        ))
+        .err(),
+        Some(InteropError::NotByteAligned)
+    );
+    assert_eq!(
+        CratesBloom::try_from(DynBloom::with_indexing(
+            1024,
+            0,
+            DynIndexing::SipDouble { keys }
+        ))
+        .err(),
+        Some(InteropError::NoHashFunctions)
+    );
+}
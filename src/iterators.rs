@@ -1,7 +1,3 @@
-use std::mem;
-
-use xxhash_rust::xxh3;
-
 macro_rules! otry {
     ($e:expr) => {
         match $e {
@@ -11,7 +7,7 @@ macro_rules! otry {
     };
 }
 
-// rejection sampling
+/// Passes on the values below max, skipping the rest
 pub struct RejectionSampling<I, O> {
     iter: I,
     max: O,
@@ -38,7 +34,7 @@ impl<I: Iterator<Item = O>, O: Ord> Iterator for RejectionSampling<I, O> {
     }
 }
 
-// skip duplicates
+/// Passes on each value the first time it comes up
 pub struct DistinctSampling<I, V> {
     iter: I,
     used_values: Vec<V>,
@@ -58,7 +54,7 @@ impl<I: Iterator<Item = V>, V: Eq + Copy> Iterator for DistinctSampling<I, V> {
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut val = otry!(self.iter.next());
-        while self.used_values.iter().find(|v| **v == val).is_some() {
+        while self.used_values.contains(&val) {
             val = otry!(self.iter.next());
         }
         self.used_values.push(val);
@@ -66,15 +62,17 @@ impl<I: Iterator<Item = V>, V: Eq + Copy> Iterator for DistinctSampling<I, V> {
     }
 }
 
-// take n bits at a time
+/// Takes n bits at a time from a stream of words, always from 64-bit words so the bits don't depend
+/// on the target's usize width
 pub struct YieldBits<I> {
     iter: I,
     bits: usize,
-    last: Option<usize>,
+    last: Option<u64>,
     bits_used: usize,
 }
 
-impl<I: Iterator<Item = usize>> YieldBits<I> {
+impl<I: Iterator<Item = u64>> YieldBits<I> {
+    #[allow(clippy::self_named_constructors)]
     pub fn yield_bits(iter: I, bits_at_a_time: usize) -> Self {
         Self {
             iter,
@@ -85,11 +83,11 @@ impl<I: Iterator<Item = usize>> YieldBits<I> {
     }
 }
 
-impl<I: Iterator<Item = usize>> Iterator for YieldBits<I> {
-    type Item = usize;
+impl<I: Iterator<Item = u64>> Iterator for YieldBits<I> {
+    type Item = u64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let last = if self.bits_used + self.bits > mem::size_of::<usize>() * 8 {
+        let last = if self.bits_used + self.bits > u64::BITS as usize {
             self.bits_used = 0;
             otry!(self.iter.next())
         } else {
@@ -106,63 +104,14 @@ impl<I: Iterator<Item = usize>> Iterator for YieldBits<I> {
     }
 }
 
-// XXH3 extendable output function
-pub struct XXH3XOF<'a> {
-    element: &'a [u8],
-    seed: u64,
-}
-
-impl<'a> From<&'a [u8]> for XXH3XOF<'a> {
-    fn from(element: &'a [u8]) -> Self {
-        Self { element, seed: 0 }
-    }
-}
-
-impl<'a> Iterator for XXH3XOF<'a> {
-    type Item = u64;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let hash = xxh3::xxh3_64_with_seed(self.element, self.seed);
-        self.seed += 1;
-        Some(hash)
-    }
-}
-
-// blake3 extendable output function that outputs u64s at a time
-pub struct Blake3XOF {
-    output_reader: blake3::OutputReader,
-}
-
-impl From<&[u8]> for Blake3XOF {
-    fn from(element: &[u8]) -> Self {
-        Self {
-            output_reader: blake3::Hasher::new().update(element).finalize_xof(),
-        }
-    }
-}
-
-impl Iterator for Blake3XOF {
-    type Item = u64;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut buf = [0u8; 8];
-        self.output_reader.fill(&mut buf);
-        let yld = u64::from_le_bytes(buf);
-        Some(yld)
-    }
-}
-
 #[test]
 fn test_yield_bits() {
-    for val in XXH3XOF::from(b"Hello, World!" as &[u8]).take(2) {
+    use crate::xof::{Xof, XXH3XOF};
+
+    for val in XXH3XOF::from(b"Hello, World!" as &[u8]).u64s().take(2) {
         println!("{:x}", val);
     }
-    for val in YieldBits::yield_bits(
-        XXH3XOF::from(b"Hello, World!" as &[u8])
-            .take(2)
-            .map(|u| u as usize),
-        8,
-    ) {
+    for val in YieldBits::yield_bits(XXH3XOF::from(b"Hello, World!" as &[u8]).u64s().take(2), 8) {
         println!("{:x}", val);
     }
 }
@@ -0,0 +1,93 @@
+use rand::RngCore;
+
+use crate::{filter::Filter, indexer::Blake3Keyed, Bloom};
+
+// Bloom filter with indices derived from blake3 in keyed mode.
+// Without the key, an observer of the bytes can't test candidate elements offline.
+#[derive(Clone)]
+pub struct KeyedBloom<const M: usize, const K: usize> {
+    indexer: Blake3Keyed,
+    bloom: Bloom<M, K>,
+}
+
+pub fn generate_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+impl<const M: usize, const K: usize> KeyedBloom<M, K> {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            indexer: Blake3Keyed { key },
+            bloom: Bloom::new(),
+        }
+    }
+
+    pub fn from_bytes(key: [u8; 32], bytes: [u8; M]) -> Self {
+        Self {
+            indexer: Blake3Keyed { key },
+            bloom: Bloom::from_bytes(bytes),
+        }
+    }
+
+    pub fn with_random_key() -> Self {
+        Self::new(generate_key())
+    }
+
+    // true if this set a bit, like `Bloom::add`
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        self.bloom.add_with(&self.indexer, element)
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.bloom.has_with(&self.indexer, element)
+    }
+
+    // see `Bloom::has_constant_time`
+    pub fn has_constant_time(&self, element: &[u8]) -> bool {
+        self.bloom.has_constant_time_with(&self.indexer, element)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bloom.count_ones()
+    }
+
+    pub fn bytes(&self) -> &[u8; M] {
+        &self.bloom.bytes
+    }
+
+    // see `Bloom::indices_for`
+    pub fn indices_for(&self, element: &[u8]) -> [usize; K] {
+        self.bloom.indices_for_with(&self.indexer, element)
+    }
+}
+
+impl<const M: usize, const K: usize> Filter for KeyedBloom<M, K> {
+    fn add(&mut self, element: &[u8]) {
+        KeyedBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        KeyedBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        KeyedBloom::count_ones(self)
+    }
+}
+
+#[test]
+fn test_keyed_bloom() {
+    let mut a = KeyedBloom::<256, 30>::new([1u8; 32]);
+    let mut b = KeyedBloom::<256, 30>::new([2u8; 32]);
+    a.add(b"Hello, World");
+    b.add(b"Hello, World");
+    assert!(a.has(b"Hello, World"));
+    assert!(b.has(b"Hello, World"));
+    assert!(!a.has(b"Test"));
+    assert!(a.has_constant_time(b"Hello, World"));
+    assert!(!a.has_constant_time(b"Test"));
+    // same element, different key: different bits
+    assert_ne!(a.bytes(), b.bytes());
+}
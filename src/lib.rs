@@ -0,0 +1,534 @@
+//! Bloom filter experiments, and the filters they run on.
+//!
+//! [`Bloom<M, K>`] is the filter everything else builds on: M bytes, K
+//! indices per element derived from an xxh3 output stream by
+//! [`bloom_indices_for_element`]. [`Folded<F, S, K>`] stores the same
+//! indices folded into fewer bytes. The other modules hold filter variants,
+//! encodings and the experiments comparing them; the binary runs those.
+
+#![allow(dead_code)]
+
+// Every filter derives its bit indices from xxh3, so that backend is the one
+// that can't be left out; blake3 and sha3 only gate the modules using them.
+#[cfg(not(feature = "xxh3"))]
+compile_error!("the filter core needs the xxh3 feature, blake3 and sha3 are optional");
+
+pub mod any_bloom;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod attack;
+#[cfg(feature = "blake3")]
+pub mod audit;
+pub mod bip37;
+pub mod bits;
+pub mod blocked;
+pub mod builder;
+pub mod cassandra;
+pub mod cli;
+#[cfg(feature = "blake3")]
+pub mod commitment;
+pub mod compare;
+#[cfg(feature = "blake3")]
+pub mod conformance;
+pub mod counted;
+pub mod counting;
+pub mod cow;
+pub mod cuckoo;
+#[cfg(feature = "dag-cbor")]
+pub mod dag_cbor;
+pub mod decay;
+#[cfg(feature = "blake3")]
+pub mod deniable;
+pub mod dyn_bloom;
+pub mod element;
+pub mod entropy;
+pub mod envelope;
+#[cfg(feature = "blake3")]
+pub mod epoch;
+#[cfg(feature = "sha3")]
+pub mod eth;
+#[cfg(feature = "blake3")]
+pub mod experiment;
+pub mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod flatbuffer;
+pub mod folded;
+pub mod gcs;
+pub mod generational;
+pub mod harness;
+pub mod indexer;
+pub mod ingest;
+pub mod interop;
+pub mod iterators;
+#[cfg(feature = "blake3")]
+pub mod keyed;
+pub mod load;
+#[cfg(feature = "blake3")]
+pub mod masked_union;
+#[cfg(feature = "blake3")]
+pub mod merkle;
+pub mod migrate;
+pub mod multibase;
+#[cfg(feature = "blake3")]
+pub mod noise;
+#[cfg(feature = "blake3")]
+pub mod oprf;
+pub mod params;
+pub mod parquet;
+pub mod partitioned;
+pub mod patch;
+pub mod prefix;
+#[cfg(feature = "blake3")]
+pub mod privacy;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "blake3")]
+pub mod psi;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quotient;
+pub mod redis;
+pub mod ribbon;
+#[cfg(feature = "blake3")]
+pub mod saturation;
+pub mod scalable;
+pub mod segmented;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod serve;
+pub mod set_ops;
+pub mod sharing;
+pub mod spectral;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod squid;
+pub mod stable;
+pub mod strategy;
+#[cfg(feature = "blake3")]
+pub mod sync;
+pub mod text;
+pub mod tool;
+#[cfg(feature = "blake3")]
+pub mod vectors;
+pub mod visualize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod willf;
+pub mod wnfs;
+pub mod xof;
+pub mod xor;
+
+pub use filter::Filter;
+pub use folded::Folded;
+pub use indexer::{BloomIndexer, Xxh3Rejection};
+pub use iterators::{DistinctSampling, RejectionSampling, YieldBits};
+use xof::{Xof, XXH3XOF};
+
+/// A Bloom filter of M bytes (m = M * 8 bits) and K hash functions.
+#[derive(Clone)]
+pub struct Bloom<const M: usize, const K: usize> {
+    pub bytes: [u8; M],
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // Evaluated once per instantiation by the constructors, so `Bloom<0, 4>`
+    // or `Bloom<1, 9>` is a build error rather than a filter that panics or
+    // loops forever in rejection sampling.
+    const CHECK: () = {
+        assert!(M > 0, "Bloom<M, K> needs at least one byte (M > 0)");
+        assert!(
+            K > 0,
+            "Bloom<M, K> needs at least one hash function (K > 0)"
+        );
+        assert!(
+            K <= M * 8,
+            "Bloom<M, K> can't set more indices than bits (K <= M * 8)"
+        );
+    };
+
+    /// Stable Rust can't emit a warning from const evaluation, so the
+    /// power-of-two note is a constant instead: when it's false, index
+    /// derivation rejects and redraws some hash outputs. Pin it with
+    /// `const _: () = assert!(Bloom::<M, K>::REJECTION_FREE);` where it matters.
+    pub const REJECTION_FREE: bool = (M * 8).is_power_of_two();
+
+    /// An empty filter. An M or K that can't make a filter fails the build.
+    pub fn new() -> Self {
+        let () = Self::CHECK;
+        Self { bytes: [0; M] }
+    }
+
+    /// A filter holding these bits, as `bytes` exposes them.
+    pub fn from_bytes(bytes: [u8; M]) -> Self {
+        let () = Self::CHECK;
+        Self { bytes }
+    }
+
+    /// true if this set a bit, i.e. the element definitely wasn't added before
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        self.add_with(&Xxh3Rejection, element)
+    }
+
+    /// False if element was definitely never added.
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.has_with(&Xxh3Rejection, element)
+    }
+
+    /// Like `has`, but probes all K positions and combines them without
+    /// branching, so timing doesn't reveal how many indices matched. Index
+    /// derivation itself still takes element-dependent time (rejection sampling).
+    pub fn has_constant_time(&self, element: &[u8]) -> bool {
+        self.has_constant_time_with(&Xxh3Rejection, element)
+    }
+
+    /// the K indices `add` sets for element, in derivation order, repeats
+    /// included
+    pub fn indices_for(&self, element: &[u8]) -> [usize; K] {
+        self.indices_for_with(&Xxh3Rejection, element)
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> u32 {
+        let mut ones = 0;
+        for n in self.bytes.iter() {
+            ones += n.count_ones();
+        }
+        ones
+    }
+
+    /// About how many distinct elements were added, from the set bits:
+    /// -m / k ln(1 - ones / m). Infinite once every bit is set.
+    pub fn estimate_len(&self) -> f64 {
+        compare::estimate_elements(M * 8, K, self.count_ones())
+    }
+
+    /// The false positive rate at the current fill, (ones / m)^K: the chance
+    /// that K independent indices all hit a set bit.
+    pub fn estimated_fpr(&self) -> f64 {
+        (self.count_ones() as f64 / (M * 8) as f64).powi(K as i32)
+    }
+
+    fn set_bit(&mut self, index: usize) {
+        let byte_index = index / 8;
+        let bit_index = index % 8;
+        self.bytes[byte_index] |= 1u8 << bit_index;
+    }
+
+    fn test_bit(&self, index: usize) -> bool {
+        let byte_index = index / 8;
+        let bit_index = index % 8;
+        (self.bytes[byte_index] & (1u8 << bit_index)) != 0
+    }
+
+    // the bit at index as 0 or 1
+    fn bit(&self, index: usize) -> u8 {
+        (self.bytes[index / 8] >> (index % 8)) & 1
+    }
+}
+
+impl<const M: usize, const K: usize> Default for Bloom<M, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const M: usize, const K: usize> Filter for Bloom<M, K> {
+    fn add(&mut self, element: &[u8]) {
+        Bloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        Bloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        Bloom::count_ones(self)
+    }
+
+    fn estimate_len(&self) -> Option<f64> {
+        Some(Bloom::estimate_len(self))
+    }
+
+    fn estimated_fpr(&self) -> Option<f64> {
+        Some(Bloom::estimated_fpr(self))
+    }
+}
+
+/// The k indices below max that `Bloom` sets for element, in derivation
+/// order. Repeats are possible.
+pub fn bloom_indices_for_element(
+    element: &[u8],
+    max: usize,
+    k: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    bloom_indices_from_hashes(XXH3XOF::from(element).u64s(), max, k)
+}
+
+/// Turns any stream of u64 hashes into k indices below max. Candidates are
+/// drawn and bounded as u64 and only then narrowed, so a 32-bit target sets
+/// the same bits as a 64-bit one.
+pub fn bloom_indices_from_hashes<I: Iterator<Item = u64>>(
+    hashes: I,
+    max: usize,
+    k: usize,
+) -> impl Iterator<Item = usize> {
+    wide_indices_from_hashes(hashes, max as u64, k).map(|index| index as usize)
+}
+
+/// `bloom_indices_for_element` for filters with more bits than usize counts
+pub fn wide_indices_for_element(
+    element: &[u8],
+    max: u64,
+    k: usize,
+) -> impl Iterator<Item = u64> + '_ {
+    wide_indices_from_hashes(XXH3XOF::from(element).u64s(), max, k)
+}
+
+/// `bloom_indices_from_hashes` without narrowing to usize
+pub fn wide_indices_from_hashes<I: Iterator<Item = u64>>(
+    hashes: I,
+    max: u64,
+    k: usize,
+) -> impl Iterator<Item = u64> {
+    let pow = bits_per_index(max);
+    RejectionSampling::accept_smaller(YieldBits::yield_bits(hashes, pow), max).take(k)
+}
+
+/// (element, m, k, indices) as a 64-bit host derives them, m staying below
+/// 2^32 so every target can check them
+pub const INDEX_VECTORS: &[(&str, usize, usize, &[usize])] = &[
+    ("one", 1000, 4, &[416, 510, 287, 986]),
+    ("ducks", 10, 3, &[1, 9, 4]),
+    ("", 3, 3, &[2, 0, 0]),
+    (
+        "Hello, world!",
+        2048,
+        30,
+        &[
+            1687, 1652, 757, 953, 220, 416, 1179, 1751, 1902, 1617, 831, 193, 1938, 945, 226, 904,
+            1178, 1607, 836, 1887, 464, 1938, 1925, 1134, 1538, 219, 1238, 1837, 1273, 804,
+        ],
+    ),
+    (
+        "wide",
+        3_000_000_000,
+        4,
+        &[2955469567, 589709622, 1812229990, 563045907],
+    ),
+];
+
+/// Nothing builds or tests 32-bit targets automatically, so the entry points
+/// run this there at startup; a mismatch means filters built on that target
+/// wouldn't interoperate with ones built elsewhere.
+pub fn check_index_vectors() -> Result<(), String> {
+    for &(element, m, k, expected) in INDEX_VECTORS {
+        let indices: Vec<usize> = bloom_indices_for_element(element.as_bytes(), m, k).collect();
+        if indices != expected {
+            return Err(format!(
+                "index derivation for {element:?} (m = {m}, k = {k}) differs on this target: {indices:?}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Bits taken from the hash stream per candidate index below max.
+pub fn bits_per_index(max: u64) -> usize {
+    let mut next_pow_of2 = if max.count_ones() == 1 {
+        max
+    } else {
+        max.next_power_of_two()
+    };
+    let mut pow = 1;
+    while next_pow_of2 != 0 {
+        next_pow_of2 >>= 1;
+        pow += 1;
+    }
+    pow
+}
+
+#[cfg(feature = "sha3")]
+#[test]
+fn test_xof() {
+    use sha3;
+    use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+    let mut hasher = sha3::Shake256::default();
+    hasher.update(b"Hello, World!");
+    let mut xof = hasher.finalize_xof();
+    let buffer = &mut [0u8; 10];
+    xof.read(buffer);
+
+    println!("{:02x?}", buffer);
+}
+
+#[test]
+fn test_xxh3_hashing_speed() {
+    use std::time::Instant;
+    use xxhash_rust::xxh3;
+
+    let before = Instant::now();
+
+    let mut hash: u64 = 1000;
+
+    for _ in 0..100_000_000 {
+        hash = xxh3::xxh3_64(&hash.to_le_bytes());
+    }
+
+    let after = Instant::now();
+    println!("{} {}", after.duration_since(before).as_millis(), hash);
+}
+
+#[test]
+fn test_vectors() {
+    let mut bloom: wnfs::WnfsBloom<125, 4> = wnfs::WnfsBloom::new();
+    bloom.add(b"one");
+    // bloom.add(b"two");
+    bloom.add(b"three");
+    assert_eq!(hex::encode(bloom.bytes()), "0000000000000000000000000000000000000000000000000000000000000000000000000000100000000000004000000000000001000000000000000000000000000400004000000000000000800000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000400");
+}
+
+#[test]
+fn test_sth() {
+    // let decoded: Vec<u8> = hex::decode("0000000000000000000000000000000000000000000000000000000000000000000000000000100000000000004000000000000001000000000000000000000000000400004000000000000000800000000000000000000000000000000000000000000000000000000000000000000020000000000000000000000400").unwrap();
+    let bloom = Bloom::<125, 4>::from_hex("0000000000000000000000000000000000000000000400000000000000000000000000000000100000000000004000000008000001000000000000000000002000000400004000000000000000800000000000000000000000000000000000000000000000000000000000002000000020000000000000000000000400").unwrap();
+    println!("{}", bloom.count_ones());
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_has_constant_time() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    harness::fill_deterministic("constant time", 40, &mut bloom);
+    for i in 0..10_000u64 {
+        let element = i.to_le_bytes();
+        assert_eq!(bloom.has_constant_time(&element), bloom.has(&element));
+    }
+}
+
+// `Bloom<0, 4>`, `Bloom<1, 0>` or `Bloom<1, 9>` fail the build in `Bloom::CHECK`
+// as soon as anything constructs one, so only the edge cases that are allowed
+// can be exercised here.
+#[test]
+fn test_parameter_checks() {
+    let mut full: Bloom<1, 8> = Bloom::new();
+    full.add(b"every bit");
+    assert!(full.has(b"every bit"));
+    assert!((1..=8).contains(&full.count_ones()));
+
+    const _: () = assert!(Bloom::<256, 30>::REJECTION_FREE);
+    const _: () = assert!(!Bloom::<125, 4>::REJECTION_FREE);
+    const _: () = assert!(folded::Folded::<2, 64, 30>::REJECTION_FREE);
+}
+
+#[test]
+fn test_add_reports_new() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    let mut dyn_bloom = dyn_bloom::DynBloom::new(256 * 8, 30);
+    let mut folded = folded::Folded::<0, 256, 30>::new();
+    for i in 0..40u64 {
+        let element = i.to_le_bytes();
+        let was_present = bloom.has(&element);
+        assert_eq!(bloom.add(&element), !was_present);
+        assert_eq!(dyn_bloom.add(&element), !was_present);
+        assert_eq!(folded.insert(&element), !was_present);
+        assert!(!bloom.add(&element));
+        assert!(!dyn_bloom.add(&element));
+        assert!(!folded.insert(&element));
+    }
+}
+
+#[test]
+fn test_index_vectors() {
+    assert_eq!(check_index_vectors(), Ok(()));
+}
+
+#[test]
+fn test_indices() {
+    let bloom = Bloom::<125, 4>::new();
+    assert_eq!(bloom.indices_for(b"one"), [416, 510, 287, 986]);
+    for s in ["two", "three"] {
+        println!("indices for '{s}': {:?}", bloom.indices_for(s.as_bytes()));
+    }
+    let small = dyn_bloom::DynBloom::new(10, 3);
+    assert_eq!(small.indices_for(b"ducks"), [1, 9, 4]);
+    for s in ["chickens", "goats"] {
+        println!("indices for '{s}': {:?}", small.indices_for(s.as_bytes()));
+    }
+
+    // every filter sets exactly the bits its indices name
+    let mut filled = Bloom::<125, 4>::new();
+    filled.add(b"one");
+    let mut expected = Bloom::<125, 4>::new();
+    for index in bloom.indices_for(b"one") {
+        expected.set_bit(index);
+    }
+    assert_eq!(filled.bytes, expected.bytes);
+    let mut unfolded = folded::Folded::<0, 125, 4>::new().indices_for(b"one");
+    unfolded.sort();
+    assert_eq!(unfolded, [287, 416, 510, 986]);
+}
+
+#[test]
+fn test_estimate_len() {
+    use partitioned::PartitionedBloom;
+
+    let mut bloom = Bloom::<1024, 7>::new();
+    let mut dyn_bloom = dyn_bloom::DynBloom::new(8192, 7);
+    let mut partitioned = PartitionedBloom::<1024, 7>::new();
+    assert_eq!(bloom.estimate_len(), 0.0);
+    for i in 0..500u32 {
+        bloom.add(&i.to_le_bytes());
+        dyn_bloom.add(&i.to_le_bytes());
+        partitioned.add(&i.to_le_bytes());
+    }
+    let estimate = bloom.estimate_len();
+    assert!((470.0..530.0).contains(&estimate), "{estimate}");
+    assert_eq!(dyn_bloom.estimate_len(), estimate);
+    let estimate = partitioned.estimate_len();
+    assert!((470.0..530.0).contains(&estimate), "{estimate}");
+    assert_eq!(Filter::estimate_len(&bloom), Some(bloom.estimate_len()));
+    assert_eq!(
+        Bloom::<1, 8>::from_bytes([0xff]).estimate_len(),
+        f64::INFINITY
+    );
+}
+
+#[test]
+fn test_estimated_fpr() {
+    use partitioned::PartitionedBloom;
+
+    let mut bloom = Bloom::<1024, 7>::new();
+    let mut partitioned = PartitionedBloom::<1024, 7>::new();
+    assert_eq!(bloom.estimated_fpr(), 0.0);
+    for i in 0..800u32 {
+        bloom.add(&i.to_le_bytes());
+        partitioned.add(&i.to_le_bytes());
+    }
+    // about 0.8% at 10 bits per element, like the closed form says
+    let theoretical = params::theoretical_fpr(8192, 7, 800);
+    for estimated in [bloom.estimated_fpr(), partitioned.estimated_fpr()] {
+        assert!((estimated / theoretical - 1.0).abs() < 0.2, "{estimated}");
+    }
+    let false_positives = (1 << 20..(1 << 20) + 100_000u32)
+        .filter(|i| bloom.has(&i.to_le_bytes()))
+        .count();
+    let measured = false_positives as f64 / 100_000.0;
+    assert!(
+        (measured / bloom.estimated_fpr() - 1.0).abs() < 0.2,
+        "{measured}"
+    );
+    assert_eq!(
+        dyn_bloom::DynBloom::from_bytes(
+            bloom.bytes.to_vec(),
+            8192,
+            7,
+            dyn_bloom::DynIndexing::Xxh3Rejection
+        )
+        .unwrap()
+        .estimated_fpr(),
+        bloom.estimated_fpr()
+    );
+}
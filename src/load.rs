@@ -0,0 +1,112 @@
+use std::{fs, io, path::Path, thread};
+
+use crate::Bloom;
+
+// Building large filters from element files on several threads. The file
+// holds one element per line as for `build` without `--hex`: raw bytes, a
+// trailing "\r" stripped. It's split at line boundaries into one chunk per
+// thread, each thread fills its own filter, and the partial filters are
+// OR-ed together, which gives the same bits as adding every line in order.
+
+// start of every chunk, the last one ending at data.len(); fewer than
+// `chunks` if there aren't enough lines
+fn chunk_starts(data: &[u8], chunks: usize) -> Vec<usize> {
+    let mut starts = vec![0];
+    for i in 1..chunks {
+        let target = (data.len() * i / chunks).max(*starts.last().unwrap());
+        // the first line starting at or after target
+        let start = if target == 0 || data[target - 1] == b'\n' {
+            target
+        } else {
+            match data[target..].iter().position(|&b| b == b'\n') {
+                Some(offset) => target + offset + 1,
+                None => data.len(),
+            }
+        };
+        if start < data.len() && start > *starts.last().unwrap() {
+            starts.push(start);
+        }
+    }
+    starts
+}
+
+// like `BufRead::lines`, minus the UTF-8 check
+fn lines(chunk: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let body = chunk.strip_suffix(b"\n").unwrap_or(chunk);
+    (!chunk.is_empty())
+        .then(|| body.split(|&b| b == b'\n'))
+        .into_iter()
+        .flatten()
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // every line of the file at path, hashed on `threads` threads
+    pub fn from_file_parallel(path: impl AsRef<Path>, threads: usize) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let starts = chunk_starts(&data, threads.max(1));
+        let partials: Vec<Box<Self>> = thread::scope(|scope| {
+            let workers: Vec<_> = starts
+                .iter()
+                .zip(starts.iter().skip(1).chain([&data.len()]))
+                .map(|(&start, &end)| {
+                    let chunk = &data[start..end];
+                    scope.spawn(move || {
+                        let mut partial = Box::new(Self::new());
+                        for line in lines(chunk) {
+                            partial.add(line);
+                        }
+                        partial
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("filter worker panicked"))
+                .collect()
+        });
+
+        let mut bloom = Self::new();
+        for partial in partials {
+            for (byte, partial) in bloom.bytes.iter_mut().zip(partial.bytes) {
+                *byte |= partial;
+            }
+        }
+        Ok(bloom)
+    }
+}
+
+#[test]
+fn test_from_file_parallel() {
+    use crate::tool::add_lines;
+
+    let mut contents = String::new();
+    for i in 0..1000 {
+        contents.push_str(&format!("element {i}\n"));
+        if i % 100 == 0 {
+            contents.push_str("\r\nwindows line\r\n\n");
+        }
+    }
+    contents.push_str("no trailing newline");
+    let path = std::env::temp_dir().join(format!("from_file_parallel_{}", std::process::id()));
+    fs::write(&path, &contents).unwrap();
+
+    let mut sequential = Bloom::<4096, 7>::new();
+    add_lines(&mut sequential, contents.as_bytes(), false).unwrap();
+    for threads in [0, 1, 3, 8, 2000] {
+        let parallel = Bloom::<4096, 7>::from_file_parallel(&path, threads).unwrap();
+        assert!(parallel.bytes == sequential.bytes, "{threads} threads");
+    }
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(chunk_starts(b"a\nbb\nccc\n", 3), [0, 5]);
+    assert_eq!(chunk_starts(b"a\nbb\nccc\n", 9), [0, 2, 5]);
+    assert_eq!(chunk_starts(b"", 4), [0]);
+    assert_eq!(
+        lines(b"a\r\n\nb").collect::<Vec<_>>(),
+        [&b"a"[..], b"", b"b"]
+    );
+    assert_eq!(lines(b"\n").collect::<Vec<_>>(), [b""]);
+    assert_eq!(lines(b"").count(), 0);
+    assert!(Bloom::<16, 2>::from_file_parallel("/nonexistent/elements", 2).is_err());
+}
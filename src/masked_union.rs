@@ -0,0 +1,155 @@
+use rand::Rng;
+
+use crate::{
+    keyed::{self, KeyedBloom},
+    xof::{Blake3XOF, Xof},
+};
+
+// Toy multi-party union of keyed filters with pairwise masks, run in-process.
+//
+// All parties share the filter key. Per bit, party i contributes a random
+// nonzero field element if the bit is set and zero otherwise, plus its
+// pairwise masks: +mask(i, j) for j > i and -mask(i, j) for j < i. Each mask
+// looks uniformly random on its own, so the aggregator learns nothing from a
+// single contribution. Summing all contributions cancels the masks, and a
+// bit's sum is nonzero exactly when some party set it (up to a 1/P chance of
+// cancellation). The random contributions hide how many parties set a bit.
+
+// 2^61 - 1
+const P: u64 = (1 << 61) - 1;
+
+fn add(a: u64, b: u64) -> u64 {
+    (a + b) % P
+}
+
+fn sub(a: u64, b: u64) -> u64 {
+    (a + P - b) % P
+}
+
+pub struct UnionParty<const M: usize, const K: usize> {
+    id: usize,
+    filter: KeyedBloom<M, K>,
+    // seeds shared with each other party, by party id
+    pair_seeds: Vec<(usize, [u8; 32])>,
+}
+
+pub struct MaskedContribution(pub Vec<u64>);
+
+impl<const M: usize, const K: usize> UnionParty<M, K> {
+    pub fn new(id: usize, key: [u8; 32], set: &[Vec<u8>]) -> Self {
+        let mut filter = KeyedBloom::new(key);
+        for element in set {
+            filter.add(element);
+        }
+        Self {
+            id,
+            filter,
+            pair_seeds: Vec::new(),
+        }
+    }
+
+    pub fn add_pair_seed(&mut self, other: usize, seed: [u8; 32]) {
+        self.pair_seeds.push((other, seed));
+    }
+
+    pub fn filter(&self) -> &KeyedBloom<M, K> {
+        &self.filter
+    }
+
+    pub fn contribution(&self) -> MaskedContribution {
+        let mut rng = rand::thread_rng();
+        let mut values: Vec<u64> = (0..M * 8)
+            .map(|index| {
+                let bit = self.filter.bytes()[index / 8] & (1u8 << (index % 8)) != 0;
+                if bit {
+                    rng.gen_range(1..P)
+                } else {
+                    0
+                }
+            })
+            .collect();
+
+        for (other, seed) in self.pair_seeds.iter() {
+            let masks = mask_stream(seed);
+            for (value, mask) in values.iter_mut().zip(masks) {
+                *value = if self.id < *other {
+                    add(*value, mask)
+                } else {
+                    sub(*value, mask)
+                };
+            }
+        }
+
+        MaskedContribution(values)
+    }
+}
+
+// field elements expanded from a pair's seed
+fn mask_stream(seed: &[u8; 32]) -> impl Iterator<Item = u64> {
+    // rejection sampling into 0..P
+    Blake3XOF::derived("rust-bloomfilters union mask v1", seed)
+        .u64s()
+        .map(|value| value & P)
+        .filter(|value| *value < P)
+}
+
+// sums all contributions and returns the union filter's bytes
+pub fn aggregate<const M: usize>(contributions: &[MaskedContribution]) -> [u8; M] {
+    let mut bytes = [0u8; M];
+    for index in 0..M * 8 {
+        let sum = contributions
+            .iter()
+            .fold(0, |sum, contribution| add(sum, contribution.0[index]));
+        if sum != 0 {
+            bytes[index / 8] |= 1u8 << (index % 8);
+        }
+    }
+    bytes
+}
+
+// sets up the parties for the given sets, with pairwise seeds distributed
+pub fn setup<const M: usize, const K: usize>(
+    key: [u8; 32],
+    sets: &[Vec<Vec<u8>>],
+) -> Vec<UnionParty<M, K>> {
+    let mut parties: Vec<UnionParty<M, K>> = sets
+        .iter()
+        .enumerate()
+        .map(|(id, set)| UnionParty::new(id, key, set))
+        .collect();
+
+    for i in 0..parties.len() {
+        for j in i + 1..parties.len() {
+            let seed = keyed::generate_key();
+            parties[i].add_pair_seed(j, seed);
+            parties[j].add_pair_seed(i, seed);
+        }
+    }
+    parties
+}
+
+#[test]
+fn test_masked_union() {
+    let key = keyed::generate_key();
+    let sets: Vec<Vec<Vec<u8>>> = (0..4u8)
+        .map(|party| (0..10u8).map(|i| vec![party, i]).collect())
+        .collect();
+    let parties = setup::<256, 30>(key, &sets);
+
+    let contributions: Vec<MaskedContribution> =
+        parties.iter().map(|party| party.contribution()).collect();
+    let union = KeyedBloom::<256, 30>::from_bytes(key, aggregate(&contributions));
+
+    let mut expected = [0u8; 256];
+    for party in parties.iter() {
+        for (byte, other) in expected.iter_mut().zip(party.filter().bytes()) {
+            *byte |= other;
+        }
+    }
+    assert_eq!(union.bytes(), &expected);
+    assert!(union.has(&[3, 9]));
+
+    // a single masked contribution doesn't look like the filter
+    let zeros = contributions[0].0.iter().filter(|v| **v == 0).count();
+    assert!(zeros < 5);
+}
@@ -0,0 +1,187 @@
+use crate::{bloom_indices_for_element, Bloom};
+
+// Merkle tree over a filter's bytes in chunks of C bytes. A prover reveals
+// only the chunks covering an element's K bit positions plus their Merkle
+// paths, and a verifier checks membership against the 32-byte root.
+//
+// Leaves are hash(leaf tag, chunk index, chunk), inner nodes hash(node tag,
+// left, right). Missing nodes of an odd level are padded with a zero hash.
+pub struct MerkleBloom<const M: usize, const K: usize, const C: usize> {
+    bloom: Bloom<M, K>,
+    // levels[0] are the leaves, the last level is the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChunkProof<const C: usize> {
+    pub chunk_index: usize,
+    pub chunk: [u8; C],
+    // sibling hashes from the leaf up
+    pub path: Vec<[u8; 32]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MembershipProof<const C: usize> {
+    pub chunks: Vec<ChunkProof<C>>,
+}
+
+fn leaf_hash(index: usize, chunk: &[u8]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new_derive_key("rust-bloomfilters merkle leaf v1");
+    hasher.update(&(index as u64).to_le_bytes());
+    hasher.update(chunk);
+    *hasher.finalize().as_bytes()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new_derive_key("rust-bloomfilters merkle node v1");
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+impl<const M: usize, const K: usize, const C: usize> MerkleBloom<M, K, C> {
+    pub fn new(bloom: Bloom<M, K>) -> Self {
+        assert!(
+            C > 0 && M.is_multiple_of(C),
+            "chunk size must divide the filter size"
+        );
+
+        let leaves: Vec<[u8; 32]> = bloom
+            .bytes
+            .chunks(C)
+            .enumerate()
+            .map(|(i, chunk)| leaf_hash(i, chunk))
+            .collect();
+
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&[0u8; 32])))
+                .collect();
+            levels.push(next);
+        }
+
+        Self { bloom, levels }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub fn bloom(&self) -> &Bloom<M, K> {
+        &self.bloom
+    }
+
+    // proof for the chunks containing the element's bit positions,
+    // whether or not the element is in the filter
+    pub fn prove(&self, element: &[u8]) -> MembershipProof<C> {
+        let mut chunk_indices: Vec<usize> = bloom_indices_for_element(element, M * 8, K)
+            .map(|index| index / 8 / C)
+            .collect();
+        chunk_indices.sort_unstable();
+        chunk_indices.dedup();
+
+        let chunks = chunk_indices
+            .into_iter()
+            .map(|chunk_index| self.prove_chunk(chunk_index))
+            .collect();
+        MembershipProof { chunks }
+    }
+
+    fn prove_chunk(&self, chunk_index: usize) -> ChunkProof<C> {
+        let mut chunk = [0u8; C];
+        chunk.copy_from_slice(&self.bloom.bytes[chunk_index * C..(chunk_index + 1) * C]);
+
+        let mut path = Vec::with_capacity(self.levels.len() - 1);
+        let mut index = chunk_index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            path.push(*level.get(index ^ 1).unwrap_or(&[0u8; 32]));
+            index /= 2;
+        }
+
+        ChunkProof {
+            chunk_index,
+            chunk,
+            path,
+        }
+    }
+}
+
+impl<const C: usize> ChunkProof<C> {
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        let mut hash = leaf_hash(self.chunk_index, &self.chunk);
+        let mut index = self.chunk_index;
+        for sibling in self.path.iter() {
+            hash = if index.is_multiple_of(2) {
+                node_hash(&hash, sibling)
+            } else {
+                node_hash(sibling, &hash)
+            };
+            index /= 2;
+        }
+        blake3::Hash::from(hash) == blake3::Hash::from(*root)
+    }
+}
+
+// Checks the proof against the root and answers the membership query for
+// a filter of M bytes with K hashes from the revealed chunks only.
+// Returns None if the proof is invalid or doesn't cover the element.
+pub fn verify_membership<const M: usize, const K: usize, const C: usize>(
+    root: &[u8; 32],
+    element: &[u8],
+    proof: &MembershipProof<C>,
+) -> Option<bool> {
+    if !proof.chunks.iter().all(|chunk| chunk.verify(root)) {
+        return None;
+    }
+
+    for index in bloom_indices_for_element(element, M * 8, K) {
+        let byte_index = index / 8;
+        let chunk = proof
+            .chunks
+            .iter()
+            .find(|chunk| chunk.chunk_index == byte_index / C)?;
+        if chunk.chunk[byte_index % C] & (1u8 << (index % 8)) == 0 {
+            return Some(false);
+        }
+    }
+    Some(true)
+}
+
+#[test]
+fn test_merkle_membership() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    bloom.add(b"Hello, World");
+    let tree = MerkleBloom::<256, 30, 16>::new(bloom);
+    let root = tree.root();
+
+    let proof = tree.prove(b"Hello, World");
+    assert_eq!(
+        verify_membership::<256, 30, 16>(&root, b"Hello, World", &proof),
+        Some(true)
+    );
+
+    let proof = tree.prove(b"Test");
+    assert_eq!(
+        verify_membership::<256, 30, 16>(&root, b"Test", &proof),
+        Some(false)
+    );
+
+    // tampered chunk
+    let mut proof = tree.prove(b"Hello, World");
+    proof.chunks[0].chunk[0] ^= 1;
+    assert_eq!(
+        verify_membership::<256, 30, 16>(&root, b"Hello, World", &proof),
+        None
+    );
+
+    // proof for a different element doesn't cover all positions
+    let proof = tree.prove(b"Test");
+    assert_ne!(
+        verify_membership::<256, 30, 16>(&root, b"Hello, World", &proof),
+        Some(true)
+    );
+}
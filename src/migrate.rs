@@ -0,0 +1,99 @@
+use crate::{compare::estimate_elements, dyn_bloom::DynBloom, filter::Filter, params, Bloom};
+
+// Moving to new filter parameters: filters can't be resized, so the new one
+// is rebuilt from the original elements. The old filter's density still says
+// roughly how many there are, which is enough to pick the new size.
+
+// Adds every element to `filter`, an empty filter with the new parameters.
+pub fn migrate<F: Filter, E: AsRef<[u8]>>(
+    elements: impl IntoIterator<Item = E>,
+    mut filter: F,
+) -> F {
+    for element in elements {
+        filter.add(element.as_ref());
+    }
+    filter
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recommendation {
+    // what the old filter's density suggests it holds
+    pub estimated_elements: f64,
+    pub m_bits: usize,
+    pub k: usize,
+}
+
+impl Recommendation {
+    pub fn empty_filter(&self) -> DynBloom {
+        DynBloom::new(self.m_bits, self.k)
+    }
+}
+
+// Parameters for `growth` times the elements an m bit, k index filter with
+// `ones` set bits seems to hold, at false positive rate `target_fpr`. None
+// for saturated filters, which say nothing about their element count.
+pub fn recommend(
+    m_bits: usize,
+    k: usize,
+    ones: u32,
+    target_fpr: f64,
+    growth: f64,
+) -> Option<Recommendation> {
+    let estimated_elements = estimate_elements(m_bits, k, ones);
+    if !estimated_elements.is_finite() {
+        return None;
+    }
+    let n = (estimated_elements * growth).ceil().max(1.0);
+    let new_m_bits = params::required_bits(n as u64, target_fpr) as usize;
+    Some(Recommendation {
+        estimated_elements,
+        m_bits: new_m_bits,
+        k: params::optimal_k(new_m_bits, n as u64),
+    })
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn recommend(&self, target_fpr: f64, growth: f64) -> Option<Recommendation> {
+        recommend(M * 8, K, self.count_ones(), target_fpr, growth)
+    }
+}
+
+impl DynBloom {
+    pub fn recommend(&self, target_fpr: f64, growth: f64) -> Option<Recommendation> {
+        recommend(
+            self.m_bits(),
+            self.k(),
+            self.count_ones(),
+            target_fpr,
+            growth,
+        )
+    }
+}
+
+#[test]
+fn test_migrate() {
+    let elements: Vec<Vec<u8>> = (0..2000u32).map(|i| i.to_le_bytes().to_vec()).collect();
+    // an overfilled Bloom<256, 30>
+    let old: Bloom<256, 30> = migrate(&elements[..100], Bloom::new());
+    assert!(old.count_ones() > 1500);
+
+    let advice = old.recommend(1e-3, 20.0).unwrap();
+    assert!(
+        (80.0..120.0).contains(&advice.estimated_elements),
+        "{advice:?}"
+    );
+    // about 2000 elements at 1e-3: 14.4 bits each and k = 10
+    assert!((25_000..35_000).contains(&advice.m_bits), "{advice:?}");
+    assert_eq!(advice.k, 10);
+
+    let new = migrate(&elements, advice.empty_filter());
+    assert!(elements.iter().all(|element| new.has(element)));
+    let false_positives = (2000..22_000u32)
+        .filter(|i| new.has(&i.to_le_bytes()))
+        .count();
+    assert!(false_positives < 60, "{false_positives}");
+
+    let mut full = DynBloom::new(64, 4);
+    full.bits_mut().fill(0xff);
+    assert_eq!(full.recommend(1e-3, 1.0), None);
+}
@@ -0,0 +1,185 @@
+use crate::folded::Folded;
+
+// Multibase strings for folded filters: 'b' (lowercase RFC 4648 base32, no
+// padding) over the multicodec prefix, then the parameters and bytes:
+//
+//   uvarint(FOLDED_XXH3_BLOOM_V2) uvarint(F) uvarint(S * 8) uvarint(K) bytes
+//
+// There's no registered multicodec for these filters yet, so the code is
+// taken from the private use range (0x300000 - 0x3fffff).
+pub const FOLDED_XXH3_BLOOM_V2: u64 = 0x30b101;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MultibaseError {
+    UnsupportedBase(char),
+    InvalidCharacter(char),
+    // a varint runs past the end or is longer than 10 bytes
+    InvalidVarint,
+    WrongCodec(u64),
+    ParameterMismatch {
+        name: &'static str,
+        expected: u64,
+        found: u64,
+    },
+    Length {
+        expected: usize,
+        found: usize,
+    },
+}
+
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+// leftover bits that don't fill a byte are dropped, as multibase decoders do
+pub fn base32_decode(text: &str) -> Result<Vec<u8>, MultibaseError> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.chars() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(MultibaseError::InvalidCharacter(c))?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn write_uvarint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_uvarint(input: &[u8]) -> Result<(u64, &[u8]), MultibaseError> {
+    let mut value = 0u64;
+    for (i, &byte) in input.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &input[i + 1..]));
+        }
+    }
+    Err(MultibaseError::InvalidVarint)
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    pub fn to_multibase(&self) -> String {
+        let mut payload = Vec::with_capacity(S + 16);
+        write_uvarint(&mut payload, FOLDED_XXH3_BLOOM_V2);
+        for parameter in [F, S * 8, K] {
+            write_uvarint(&mut payload, parameter as u64);
+        }
+        payload.extend_from_slice(&self.bytes);
+        format!("b{}", base32_encode(&payload))
+    }
+
+    pub fn from_multibase(text: &str) -> Result<Self, MultibaseError> {
+        let mut chars = text.chars();
+        match chars.next() {
+            Some('b') => {}
+            Some(base) => return Err(MultibaseError::UnsupportedBase(base)),
+            None => return Err(MultibaseError::InvalidVarint),
+        }
+        let payload = base32_decode(chars.as_str())?;
+        let (codec, mut rest) = read_uvarint(&payload)?;
+        if codec != FOLDED_XXH3_BLOOM_V2 {
+            return Err(MultibaseError::WrongCodec(codec));
+        }
+        for (name, expected) in [("f", F), ("m", S * 8), ("k", K)] {
+            let (found, tail) = read_uvarint(rest)?;
+            if found != expected as u64 {
+                return Err(MultibaseError::ParameterMismatch {
+                    name,
+                    expected: expected as u64,
+                    found,
+                });
+            }
+            rest = tail;
+        }
+        let bytes = rest.try_into().map_err(|_| MultibaseError::Length {
+            expected: S,
+            found: rest.len(),
+        })?;
+        Ok(Self::from_bytes(bytes))
+    }
+}
+
+#[test]
+fn test_base32() {
+    // RFC 4648 test vectors, lowercased and unpadded
+    for (plain, encoded) in [
+        ("", ""),
+        ("f", "my"),
+        ("fo", "mzxq"),
+        ("foo", "mzxw6"),
+        ("foob", "mzxw6yq"),
+        ("fooba", "mzxw6ytb"),
+        ("foobar", "mzxw6ytboi"),
+    ] {
+        assert_eq!(base32_encode(plain.as_bytes()), encoded);
+        assert_eq!(base32_decode(encoded).unwrap(), plain.as_bytes());
+    }
+    assert_eq!(
+        base32_decode("mZ"),
+        Err(MultibaseError::InvalidCharacter('Z'))
+    );
+}
+
+#[test]
+fn test_multibase_round_trip() {
+    let mut folded = Folded::<2, 64, 30>::new();
+    folded.insert(b"Hello, World");
+    let text = folded.to_multibase();
+    // 0x30b101 as a uvarint is 81 e2 c2 01
+    assert!(text.starts_with(&format!(
+        "b{}",
+        &base32_encode(&[0x81, 0xe2, 0xc2, 0x01])[..6]
+    )));
+    let decoded = Folded::<2, 64, 30>::from_multibase(&text).unwrap();
+    assert_eq!(decoded.bytes, folded.bytes);
+    assert!(decoded.has(b"Hello, World"));
+
+    assert_eq!(
+        Folded::<1, 64, 30>::from_multibase(&text).err(),
+        Some(MultibaseError::ParameterMismatch {
+            name: "f",
+            expected: 1,
+            found: 2
+        })
+    );
+    assert_eq!(
+        Folded::<2, 64, 30>::from_multibase(&format!("z{}", &text[1..])).err(),
+        Some(MultibaseError::UnsupportedBase('z'))
+    );
+    assert_eq!(
+        Folded::<2, 64, 30>::from_multibase(&text[..text.len() - 8]).err(),
+        Some(MultibaseError::Length {
+            expected: 64,
+            found: 59
+        })
+    );
+}
@@ -0,0 +1,116 @@
+use rand::Rng;
+
+use crate::{
+    entropy::{AsRng, EntropySource},
+    Bloom,
+};
+
+// Randomized response over the bit array: every bit is flipped independently
+// with probability p. One element touches at most K bits, so the filter is
+// epsilon-DP with respect to a single element when each bit is
+// (epsilon / K)-DP, i.e. when ln((1 - p) / p) <= epsilon / K.
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn add_noise(&mut self, flip_probability: f64) {
+        self.add_noise_with(&mut rand::thread_rng(), flip_probability)
+    }
+
+    pub fn add_noise_with<R: Rng>(&mut self, rng: &mut R, flip_probability: f64) {
+        for byte in self.bytes.iter_mut() {
+            for bit_index in 0..8 {
+                if rng.gen_bool(flip_probability) {
+                    *byte ^= 1u8 << bit_index;
+                }
+            }
+        }
+    }
+}
+
+// privacy budget spent per bit for a given flip probability
+pub fn per_bit_epsilon(flip_probability: f64) -> f64 {
+    ((1.0 - flip_probability) / flip_probability).ln()
+}
+
+// flip probability achieving a per bit budget of `epsilon_bit`
+pub fn flip_probability_for_bit(epsilon_bit: f64) -> f64 {
+    1.0 / (1.0 + epsilon_bit.exp())
+}
+
+// Smallest flip probability such that the K bits touched by an element
+// compose to (epsilon, delta)-DP. Uses whichever of basic composition
+// (K * e0 <= epsilon) and advanced composition
+// (sqrt(2K ln(1/delta)) * e0 + K * e0 * (e^e0 - 1) <= epsilon) allows the
+// larger per bit budget. With delta = 0 only basic composition applies.
+pub fn flip_probability_for(epsilon: f64, delta: f64, k: usize) -> f64 {
+    let k = k as f64;
+    let basic = epsilon / k;
+
+    let advanced = if delta > 0.0 && delta < 1.0 {
+        let composed = |e0: f64| (2.0 * k * (1.0 / delta).ln()).sqrt() * e0 + k * e0 * e0.exp_m1();
+        // composed is increasing in e0, bisect for composed(e0) = epsilon
+        let (mut low, mut high) = (0.0, epsilon);
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            if composed(mid) <= epsilon {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    } else {
+        0.0
+    };
+
+    flip_probability_for_bit(basic.max(advanced))
+}
+
+// FNR/FPR of noisy Bloom<256, 30> filters across privacy budgets
+pub fn test_noise_rates(mut entropy: impl EntropySource) {
+    const N: usize = 40;
+    const QUERIES: usize = 100_000;
+    let delta = 1e-6;
+
+    println!("epsilon;flip_probability;false_negatives;false_positives");
+    for epsilon in [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0] {
+        let flip_probability = flip_probability_for(epsilon, delta, 30);
+
+        let mut bloom: Bloom<256, 30> = Bloom::new();
+        let items: Vec<[u8; 32]> = (0..N).map(|_| entropy.block()).collect();
+        for item in items.iter() {
+            bloom.add(item);
+        }
+        bloom.add_noise_with(&mut AsRng(&mut entropy), flip_probability);
+
+        let false_negatives = items.iter().filter(|item| !bloom.has(*item)).count();
+        let false_positives = (0..QUERIES)
+            .filter(|_| bloom.has(&entropy.block::<32>()))
+            .count();
+
+        println!("{epsilon};{flip_probability};{false_negatives}/{N};{false_positives}/{QUERIES}");
+    }
+}
+
+#[test]
+fn test_add_noise() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    bloom.add(b"Hello, World");
+    let before = bloom.count_ones();
+
+    bloom.add_noise(0.0);
+    assert_eq!(bloom.count_ones(), before);
+
+    bloom.add_noise(1.0);
+    assert_eq!(bloom.count_ones(), 2048 - before);
+}
+
+#[test]
+fn test_flip_probability() {
+    // basic composition: K bits at epsilon / K each
+    let p = flip_probability_for(30.0, 0.0, 30);
+    assert!((per_bit_epsilon(p) - 1.0).abs() < 1e-9);
+
+    // allowing some delta never costs accuracy
+    assert!(flip_probability_for(30.0, 1e-6, 30) <= p);
+    // more budget, less noise
+    assert!(flip_probability_for(10.0, 1e-6, 30) > flip_probability_for(20.0, 1e-6, 30));
+}
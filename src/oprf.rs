@@ -0,0 +1,94 @@
+use crate::{saturation::Saturation, Bloom};
+
+// Elements are run through an (oblivious) PRF before the filter holder sees
+// them. The holder only ever handles `PrfOutput`s, so `BlindBloom` has no way
+// to accept raw elements.
+pub trait Oprf {
+    fn evaluate(&self, element: &[u8]) -> PrfOutput;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrfOutput(pub [u8; 32]);
+
+// Reference implementation: blake3 in keyed mode, evaluated locally.
+// Not oblivious, the evaluator sees the element. Stand-in for an interactive
+// OPRF where the key holder and the element holder are different parties.
+pub struct Blake3Prf {
+    key: [u8; 32],
+}
+
+impl Blake3Prf {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+}
+
+impl Oprf for Blake3Prf {
+    fn evaluate(&self, element: &[u8]) -> PrfOutput {
+        PrfOutput(*blake3::keyed_hash(&self.key, element).as_bytes())
+    }
+}
+
+// filter held by a party that never sees raw elements
+#[derive(Clone)]
+pub struct BlindBloom<const M: usize, const K: usize> {
+    bloom: Bloom<M, K>,
+}
+
+impl<const M: usize, const K: usize> BlindBloom<M, K> {
+    pub fn new() -> Self {
+        Self {
+            bloom: Bloom::new(),
+        }
+    }
+
+    pub fn from_bytes(bytes: [u8; M]) -> Self {
+        Self {
+            bloom: Bloom::from_bytes(bytes),
+        }
+    }
+
+    pub fn add(&mut self, output: &PrfOutput) -> bool {
+        self.bloom.add(&output.0)
+    }
+
+    pub fn has(&self, output: &PrfOutput) -> bool {
+        self.bloom.has(&output.0)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bloom.count_ones()
+    }
+
+    // indices of the PRF output, which is all the filter sees
+    pub fn indices_for(&self, output: &PrfOutput) -> [usize; K] {
+        self.bloom.indices_for(&output.0)
+    }
+
+    // saturation elements are indistinguishable from PRF outputs
+    pub fn saturate(&mut self) -> Saturation {
+        self.bloom.saturate()
+    }
+
+    pub fn bytes(&self) -> &[u8; M] {
+        &self.bloom.bytes
+    }
+}
+
+impl<const M: usize, const K: usize> Default for BlindBloom<M, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_blind_insertion() {
+    let prf = Blake3Prf::new([7u8; 32]);
+    let mut filter = BlindBloom::<256, 30>::new();
+    filter.add(&prf.evaluate(b"Hello, World"));
+
+    assert!(filter.has(&prf.evaluate(b"Hello, World")));
+    assert!(!filter.has(&prf.evaluate(b"Test")));
+    // without the PRF key the raw element doesn't test positive
+    assert!(!filter.has(&Blake3Prf::new([8u8; 32]).evaluate(b"Hello, World")));
+}
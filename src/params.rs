@@ -0,0 +1,163 @@
+use std::f64::consts::LN_2;
+
+// Filter sizing that can run at compile time, so const generic parameters
+// can be derived instead of hand-computed:
+//
+//   type Filter = bloom_params!(10_000 items, 1e-4);
+//
+// expands to the `Bloom<M, K>` with the fewest bytes holding 10_000 elements
+// at a false positive rate of 1e-4, and the k optimal for that size.
+
+// std's `f64::ln` isn't const. For positive normal x, splits off the binary
+// exponent and sums 2 atanh((y - 1) / (y + 1)) for the mantissa y in [1, 2).
+const fn ln(x: f64) -> f64 {
+    assert!(x.is_normal() && x > 0.0);
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    let t = (mantissa - 1.0) / (mantissa + 1.0);
+    let mut sum = 0.0;
+    let mut power = t;
+    let mut i = 0;
+    // t <= 1/3, so each term is at least 9 times smaller than the last
+    while i < 20 {
+        sum += power / (2 * i + 1) as f64;
+        power *= t * t;
+        i += 1;
+    }
+    exponent as f64 * LN_2 + 2.0 * sum
+}
+
+// bits needed for n elements at false positive rate fpr, -n ln(fpr) / ln(2)^2
+pub const fn required_bits(n: u64, fpr: f64) -> u64 {
+    assert!(n > 0, "sizing a filter needs at least one item");
+    assert!(fpr > 0.0 && fpr < 1.0, "sizing a filter needs 0 < fpr < 1");
+    let bits = -(n as f64) * ln(fpr) / (LN_2 * LN_2);
+    let floor = bits as u64;
+    if (floor as f64) < bits {
+        floor + 1
+    } else {
+        floor
+    }
+}
+
+// M for `Bloom<M, K>`
+pub const fn optimal_bytes(n: u64, fpr: f64) -> usize {
+    required_bits(n, fpr).div_ceil(8) as usize
+}
+
+// the k minimizing false positives for n elements in m bits, m / n ln(2)
+// rounded, at least 1
+pub const fn optimal_k(m_bits: usize, n: u64) -> usize {
+    assert!(n > 0, "sizing a filter needs at least one item");
+    let k = (m_bits as f64 / n as f64 * LN_2 + 0.5) as usize;
+    if k == 0 {
+        1
+    } else {
+        k
+    }
+}
+
+// K for `Bloom<M, K>`: `optimal_k` over the whole bytes of M
+pub const fn optimal_hashes(n: u64, fpr: f64) -> usize {
+    optimal_k(optimal_bytes(n, fpr) * 8, n)
+}
+
+// The m and k of a filter for `capacity` elements at false positive rate
+// `fpr`, the runtime counterpart of `bloom_params!`: m is `optimal_bytes`'
+// whole bytes, so a `Bloom<{ m_bits / 8 }, K>` and `DynBloom::from_spec`
+// of one spec are the same filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilterSpec {
+    pub m_bits: usize,
+    pub k: usize,
+}
+
+impl FilterSpec {
+    pub const fn new(capacity: u64, fpr: f64) -> Self {
+        Self {
+            m_bits: optimal_bytes(capacity, fpr) * 8,
+            k: optimal_hashes(capacity, fpr),
+        }
+    }
+
+    // the rate once it holds n elements
+    pub fn fpr_at(&self, n: u64) -> f64 {
+        theoretical_fpr(self.m_bits, self.k, n)
+    }
+}
+
+// The false positive rate a filter of m bits and k indices is expected to
+// have after n distinct elements, (1 - e^(-k n / m))^k. Slightly below the
+// exact rate for small m, where the bits an element sets aren't independent.
+pub fn theoretical_fpr(m_bits: usize, k: usize, n: u64) -> f64 {
+    (1.0 - (-(k as f64) * n as f64 / m_bits as f64).exp()).powi(k as i32)
+}
+
+// nothing in the binary instantiates one yet
+#[allow(unused_macros)]
+macro_rules! bloom_params {
+    ($n:literal items, $fpr:expr) => {
+        $crate::Bloom<
+            { $crate::params::optimal_bytes($n, $fpr) },
+            { $crate::params::optimal_hashes($n, $fpr) },
+        >
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use bloom_params;
+
+#[test]
+fn test_const_ln() {
+    for x in [1e-9, 1e-4, 0.01, 0.5, 1.0, 1.5, 2.0, 10.0, 12345.678] {
+        assert!((ln(x) - x.ln()).abs() < 1e-12, "ln({x})");
+    }
+}
+
+#[test]
+fn test_theoretical_fpr() {
+    assert_eq!(theoretical_fpr(2048, 30, 0), 0.0);
+    // the optimal k at 10 bits per element gives about 0.8%
+    let fpr = theoretical_fpr(10_000, 7, 1000);
+    assert!((0.0080..0.0083).contains(&fpr), "{fpr}");
+    // and sizing for a rate gets close to it back
+    let (n, target) = (10_000, 1e-4);
+    let fpr = theoretical_fpr(optimal_bytes(n, target) * 8, optimal_hashes(n, target), n);
+    assert!((0.9e-4..1.1e-4).contains(&fpr), "{fpr}");
+}
+
+#[test]
+fn test_filter_spec() {
+    assert_eq!(optimal_k(2048, 47), 30);
+    assert_eq!(optimal_k(100, 1000), 1);
+    // the folded experiments' filter, before it was derived
+    let spec = FilterSpec::new(10_000, 4e-6);
+    assert_eq!(spec.k, 18);
+    assert!((258_000..263_000).contains(&spec.m_bits), "{}", spec.m_bits);
+    assert!((3e-6..4.1e-6).contains(&spec.fpr_at(10_000)));
+
+    let mut dyn_bloom = crate::dyn_bloom::DynBloom::from_spec(spec);
+    assert_eq!((dyn_bloom.m_bits(), dyn_bloom.k()), (spec.m_bits, spec.k));
+    type Same = bloom_params!(10_000 items, 4e-6);
+    let mut bloom = Same::new();
+    dyn_bloom.add(b"one");
+    bloom.add(b"one");
+    assert_eq!(dyn_bloom.bytes(), bloom.bytes);
+}
+
+#[test]
+fn test_bloom_params() {
+    type Small = bloom_params!(10_000 items, 1e-4);
+    // 191702 bits, rounded up to 23963 bytes, then k = 13.29
+    assert_eq!(optimal_bytes(10_000, 1e-4), 23_963);
+    assert_eq!(optimal_hashes(10_000, 1e-4), 13);
+    let mut bloom = Small::new();
+    assert_eq!(bloom.bytes.len(), 23_963);
+    bloom.add(b"one");
+    assert!(bloom.has(b"one"));
+
+    // close to the hand-picked Bloom<256, 30> used for 47 elements
+    type Tiny = bloom_params!(47 items, 1e-9);
+    assert_eq!(Tiny::new().bytes.len(), 254);
+    assert_eq!(optimal_hashes(47, 1e-9), 30);
+}
@@ -0,0 +1,216 @@
+use xxhash_rust::xxh64::xxh64;
+
+use crate::filter::Filter;
+
+// Parquet split block bloom filters: a thrift compact encoded
+// BloomFilterHeader followed by the bitset. The bitset is made of 256-bit
+// blocks of eight little endian u32 words. A value's xxh64 hash (seed 0, over
+// its plain encoding) picks a block with its upper 32 bits and sets one bit
+// per word from its lower 32 bits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParquetBloom {
+    blocks: Vec<[u32; 8]>,
+}
+
+const SALT: [u32; 8] = [
+    0x47b6137b, 0x44974d91, 0x8824ad5b, 0xa2b7289d, 0x705495c7, 0x2df1424b, 0x9efc4947, 0x5c6bfb31,
+];
+
+pub const MIN_BYTES: usize = 32;
+pub const MAX_BYTES: usize = 128 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParquetError {
+    Truncated,
+    InvalidSize(usize),
+    UnsupportedHeader,
+}
+
+fn block_mask(hash: u32) -> [u32; 8] {
+    SALT.map(|salt| 1u32 << (hash.wrapping_mul(salt) >> 27))
+}
+
+impl ParquetBloom {
+    // `num_bytes` is rounded up to a power of two within the spec's bounds
+    pub fn new(num_bytes: usize) -> Self {
+        let num_bytes = num_bytes.clamp(MIN_BYTES, MAX_BYTES).next_power_of_two();
+        Self {
+            blocks: vec![[0u32; 8]; num_bytes / 32],
+        }
+    }
+
+    // size for `ndv` distinct values at false positive probability `fpp`,
+    // like parquet-mr's optimalNumOfBits
+    pub fn with_ndv_fpp(ndv: u64, fpp: f64) -> Self {
+        let bits = -8.0 * ndv as f64 / (1.0 - fpp.powf(1.0 / 8.0)).ln();
+        Self::new((bits / 8.0).ceil() as usize)
+    }
+
+    pub fn num_bytes(&self) -> usize {
+        self.blocks.len() * 32
+    }
+
+    fn block_index(&self, hash: u64) -> usize {
+        (((hash >> 32) * self.blocks.len() as u64) >> 32) as usize
+    }
+
+    pub fn insert_hash(&mut self, hash: u64) {
+        let index = self.block_index(hash);
+        let mask = block_mask(hash as u32);
+        for (word, bit) in self.blocks[index].iter_mut().zip(mask) {
+            *word |= bit;
+        }
+    }
+
+    pub fn check_hash(&self, hash: u64) -> bool {
+        let block = &self.blocks[self.block_index(hash)];
+        block
+            .iter()
+            .zip(block_mask(hash as u32))
+            .all(|(word, bit)| word & bit != 0)
+    }
+
+    // `value` is the plain encoding: raw bytes for BYTE_ARRAY, little endian for numbers
+    pub fn insert(&mut self, value: &[u8]) {
+        self.insert_hash(xxh64(value, 0))
+    }
+
+    pub fn check(&self, value: &[u8]) -> bool {
+        self.check_hash(xxh64(value, 0))
+    }
+
+    pub fn bitset(&self) -> Vec<u8> {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.iter().flat_map(|word| word.to_le_bytes()))
+            .collect()
+    }
+
+    // BloomFilterHeader { numBytes, algorithm: BLOCK, hash: XXHASH, compression: UNCOMPRESSED }
+    pub fn header(&self) -> Vec<u8> {
+        let mut out = vec![0x15];
+        let num_bytes = self.num_bytes() as i32;
+        let mut zigzag = ((num_bytes << 1) ^ (num_bytes >> 31)) as u32;
+        while zigzag >= 0x80 {
+            out.push(zigzag as u8 | 0x80);
+            zigzag >>= 7;
+        }
+        out.push(zigzag as u8);
+        // three single-field unions holding an empty struct each, then stop
+        for _ in 0..3 {
+            out.extend_from_slice(&[0x1c, 0x1c, 0x00, 0x00]);
+        }
+        out.push(0x00);
+        out
+    }
+
+    // header and bitset, as stored in the column chunk's bloom filter offset
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.header();
+        out.extend_from_slice(&self.bitset());
+        out
+    }
+
+    // parses header and bitset, returning the filter and the bytes consumed
+    pub fn from_bytes(data: &[u8]) -> Result<(Self, usize), ParquetError> {
+        if data.first() != Some(&0x15) {
+            return Err(ParquetError::UnsupportedHeader);
+        }
+        let mut position = 1;
+        let mut zigzag = 0u32;
+        let mut shift = 0;
+        loop {
+            let byte = *data.get(position).ok_or(ParquetError::Truncated)?;
+            position += 1;
+            zigzag |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(ParquetError::UnsupportedHeader);
+            }
+        }
+        let num_bytes = ((zigzag >> 1) as i32 ^ -((zigzag & 1) as i32)) as usize;
+
+        let rest = data
+            .get(position..position + 13)
+            .ok_or(ParquetError::Truncated)?;
+        if rest != [0x1c, 0x1c, 0, 0, 0x1c, 0x1c, 0, 0, 0x1c, 0x1c, 0, 0, 0] {
+            return Err(ParquetError::UnsupportedHeader);
+        }
+        position += 13;
+
+        if !(MIN_BYTES..=MAX_BYTES).contains(&num_bytes) || !num_bytes.is_power_of_two() {
+            return Err(ParquetError::InvalidSize(num_bytes));
+        }
+        let bitset = data
+            .get(position..position + num_bytes)
+            .ok_or(ParquetError::Truncated)?;
+        let blocks = bitset
+            .chunks_exact(32)
+            .map(|block| {
+                let mut words = [0u32; 8];
+                for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+                    *word = u32::from_le_bytes(bytes.try_into().unwrap());
+                }
+                words
+            })
+            .collect();
+        Ok((Self { blocks }, position + num_bytes))
+    }
+}
+
+impl Filter for ParquetBloom {
+    fn add(&mut self, element: &[u8]) {
+        self.insert(element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.check(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.blocks
+            .iter()
+            .flat_map(|block| block.iter())
+            .map(|word| word.count_ones())
+            .sum()
+    }
+}
+
+#[test]
+fn test_parquet_roundtrip() {
+    let mut filter = ParquetBloom::with_ndv_fpp(1000, 0.01);
+    assert!(filter.num_bytes().is_power_of_two());
+    for i in 0..1000i64 {
+        filter.insert(&i.to_le_bytes());
+    }
+    assert!((0..1000i64).all(|i| filter.check(&i.to_le_bytes())));
+    let false_positives = (1000..101_000i64)
+        .filter(|i| filter.check(&i.to_le_bytes()))
+        .count();
+    assert!(false_positives < 2000);
+
+    let bytes = filter.to_bytes();
+    let (parsed, consumed) = ParquetBloom::from_bytes(&bytes).unwrap();
+    assert_eq!(consumed, bytes.len());
+    assert_eq!(parsed, filter);
+}
+
+#[test]
+fn test_parquet_header() {
+    let filter = ParquetBloom::new(32);
+    assert_eq!(
+        hex::encode(filter.header()),
+        "15401c1c00001c1c00001c1c000000"
+    );
+    assert_eq!(
+        ParquetBloom::from_bytes(&filter.header()),
+        Err(ParquetError::Truncated)
+    );
+    // every insertion sets exactly one bit per word of one block
+    let mut filter = ParquetBloom::new(32);
+    filter.insert(b"hello");
+    assert_eq!(filter.count_ones(), 8);
+}
@@ -0,0 +1,144 @@
+use crate::{
+    bloom_indices_from_hashes,
+    compare::estimate_elements,
+    filter::Filter,
+    xof::{Xof, XXH3XOF},
+};
+
+// Partitioned Bloom filter: the M * 8 bits split into K slices of M * 8 / K
+// bits, and hash i of an element addressing only slice i. The K indices are
+// `Bloom`'s derivation over one slice's size, so an element always sets
+// exactly K distinct bits, one per slice, where `Bloom`'s K indices may
+// repeat. Bits past K whole slices are never used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionedBloom<const M: usize, const K: usize> {
+    pub bytes: [u8; M],
+}
+
+impl<const M: usize, const K: usize> PartitionedBloom<M, K> {
+    const CHECK: () = {
+        assert!(
+            K > 0,
+            "PartitionedBloom<M, K> needs at least one hash function (K > 0)"
+        );
+        assert!(
+            K <= M * 8,
+            "PartitionedBloom<M, K> needs a bit per slice (K <= M * 8)"
+        );
+    };
+
+    pub const SLICE_BITS: usize = M * 8 / K;
+
+    pub fn new() -> Self {
+        let () = Self::CHECK;
+        Self { bytes: [0; M] }
+    }
+
+    pub fn from_bytes(bytes: [u8; M]) -> Self {
+        let () = Self::CHECK;
+        Self { bytes }
+    }
+
+    // index i in slice i
+    pub fn indices_for(&self, element: &[u8]) -> [usize; K] {
+        let hashes = XXH3XOF::from(element).u64s();
+        let mut indices = bloom_indices_from_hashes(hashes, Self::SLICE_BITS, K);
+        std::array::from_fn(|slice| slice * Self::SLICE_BITS + indices.next().unwrap())
+    }
+
+    // true if this set a bit, like `Bloom::add`
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let mut new = false;
+        for index in self.indices_for(element) {
+            new |= self.bytes[index / 8] & (1 << (index % 8)) == 0;
+            self.bytes[index / 8] |= 1 << (index % 8);
+        }
+        new
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.indices_for(element)
+            .into_iter()
+            .all(|index| self.bytes[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bytes.iter().map(|byte| byte.count_ones()).sum()
+    }
+
+    // Each element sets one bit per slice, so a slice of s bits with x set
+    // holds about -s ln(1 - x / s) elements. Over the K slices together
+    // that's `Bloom::estimate_len`'s formula for the K * s bits in use.
+    pub fn estimate_len(&self) -> f64 {
+        estimate_elements(K * Self::SLICE_BITS, K, self.count_ones())
+    }
+
+    // the product of the slices' fill, one index landing in each
+    pub fn estimated_fpr(&self) -> f64 {
+        (0..K)
+            .map(|slice| {
+                let ones = (slice * Self::SLICE_BITS..(slice + 1) * Self::SLICE_BITS)
+                    .filter(|&index| self.bytes[index / 8] & (1 << (index % 8)) != 0)
+                    .count();
+                ones as f64 / Self::SLICE_BITS as f64
+            })
+            .product()
+    }
+}
+
+impl<const M: usize, const K: usize> Default for PartitionedBloom<M, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const M: usize, const K: usize> Filter for PartitionedBloom<M, K> {
+    fn add(&mut self, element: &[u8]) {
+        PartitionedBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        PartitionedBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        PartitionedBloom::count_ones(self)
+    }
+
+    fn estimate_len(&self) -> Option<f64> {
+        Some(PartitionedBloom::estimate_len(self))
+    }
+
+    fn estimated_fpr(&self) -> Option<f64> {
+        Some(PartitionedBloom::estimated_fpr(self))
+    }
+}
+
+#[test]
+fn test_partitioned_bloom() {
+    use crate::element::encode_u64;
+
+    // 2048 bits in 30 slices of 68, the last 8 bits unused
+    assert_eq!(PartitionedBloom::<256, 30>::SLICE_BITS, 68);
+    let mut filter = PartitionedBloom::<256, 30>::new();
+    let indices = filter.indices_for(b"one");
+    for (slice, index) in indices.into_iter().enumerate() {
+        assert_eq!(index / 68, slice);
+    }
+    assert!(filter.add(b"one") && !filter.add(b"one"));
+    assert_eq!(filter.count_ones(), 30);
+
+    for i in 0..47u64 {
+        filter.add(&encode_u64(i));
+    }
+    assert!((0..47u64).all(|i| filter.has(&encode_u64(i))));
+    assert!(filter.bytes[255] == 0);
+    // each of the 48 elements set one bit per slice, about 34 distinct ones
+    // of 68 after collisions
+    for slice in 0..30 {
+        let ones = (slice * 68..(slice + 1) * 68)
+            .filter(|&index| filter.bytes[index / 8] & (1 << (index % 8)) != 0)
+            .count();
+        assert!((25..=48).contains(&ones), "{ones}");
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{
+    bits::{BitReader, BitWriter},
+    dyn_bloom::DynBloom,
+    Bloom,
+};
+
+// Incremental updates between two versions of a filter: the positions of the
+// bits set in the new version but not in the old one, as Golomb-Rice coded
+// gaps. Bits cleared in the new version aren't represented, filters only grow.
+// Applying ORs the bits in, so it's idempotent and patches commute.
+//
+// Encoding: m (u64 little endian), number of positions (u32 little endian),
+// Rice parameter r, then for each gap g = position - previous position - 1
+// (the first position is its own gap) g >> r in unary (ones ended by a zero)
+// and the low r bits of g, most significant bit first, zero padded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Patch {
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatchError {
+    Truncated,
+    SizeMismatch { expected: u64, found: u64 },
+    OutOfRange(u64),
+    InvalidRiceParameter(u8),
+    // more positions than the filter has bits
+    TooManyPositions { count: u32, m_bits: u64 },
+}
+
+// close to optimal for geometrically distributed gaps with this mean
+fn rice_parameter(m_bits: u64, count: u64) -> u8 {
+    if count == 0 {
+        return 0;
+    }
+    let mean_gap = m_bits as f64 / count as f64;
+    (mean_gap * std::f64::consts::LN_2).log2().floor().max(0.0) as u8
+}
+
+fn diff_bits(old: &[u8], new: &[u8], m_bits: u64) -> Patch {
+    let positions: Vec<u64> = old
+        .iter()
+        .zip(new)
+        .enumerate()
+        .flat_map(|(i, (old, new))| {
+            let added = new & !old;
+            (0..8)
+                .filter(move |bit| added & (1 << bit) != 0)
+                .map(move |bit| (i * 8 + bit) as u64)
+        })
+        .collect();
+    let rice = rice_parameter(m_bits, positions.len() as u64);
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&m_bits.to_le_bytes());
+    bytes.extend_from_slice(&(positions.len() as u32).to_le_bytes());
+    bytes.push(rice);
+    let mut writer = BitWriter::after(bytes);
+    let mut next = 0;
+    for position in positions {
+        writer.push_rice(position - next, rice);
+        next = position + 1;
+    }
+    Patch {
+        bytes: writer.into_bytes(),
+    }
+}
+
+fn apply_bits(bits: &mut [u8], m_bits: u64, patch: &Patch) -> Result<(), PatchError> {
+    if patch.m_bits() != m_bits {
+        return Err(PatchError::SizeMismatch {
+            expected: m_bits,
+            found: patch.m_bits(),
+        });
+    }
+    for position in patch.positions()? {
+        bits[(position / 8) as usize] |= 1 << (position % 8);
+    }
+    Ok(())
+}
+
+impl Patch {
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, PatchError> {
+        let patch = Self { bytes };
+        patch.positions()?;
+        Ok(patch)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn m_bits(&self) -> u64 {
+        u64::from_le_bytes(self.bytes[..8].try_into().unwrap())
+    }
+
+    // the newly set bits, ascending
+    pub fn positions(&self) -> Result<Vec<u64>, PatchError> {
+        if self.bytes.len() < 13 {
+            return Err(PatchError::Truncated);
+        }
+        let m_bits = self.m_bits();
+        let count = u32::from_le_bytes(self.bytes[8..12].try_into().unwrap());
+        let rice = self.bytes[12];
+        if rice >= 64 {
+            return Err(PatchError::InvalidRiceParameter(rice));
+        }
+        if u64::from(count) > m_bits {
+            return Err(PatchError::TooManyPositions { count, m_bits });
+        }
+        let mut reader = BitReader::new(&self.bytes[13..]);
+        let mut positions = Vec::with_capacity(count.min(1 << 20) as usize);
+        let mut next = 0u64;
+        for _ in 0..count {
+            let mut quotient = 0u64;
+            while reader.next().ok_or(PatchError::Truncated)? {
+                quotient += 1;
+                // overflowing u64 is past any filter too
+                match quotient
+                    .checked_mul(1 << rice)
+                    .and_then(|high| next.checked_add(high))
+                {
+                    Some(position) if position < m_bits => {}
+                    position => return Err(PatchError::OutOfRange(position.unwrap_or(u64::MAX))),
+                }
+            }
+            let mut gap = quotient << rice;
+            for bit in (0..rice).rev() {
+                gap |= (reader.next().ok_or(PatchError::Truncated)? as u64) << bit;
+            }
+            let position = next.saturating_add(gap);
+            if position >= m_bits {
+                return Err(PatchError::OutOfRange(position));
+            }
+            positions.push(position);
+            next = position + 1;
+        }
+        Ok(positions)
+    }
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn diff(old: &Self, new: &Self) -> Patch {
+        diff_bits(&old.bytes, &new.bytes, (M * 8) as u64)
+    }
+
+    pub fn apply(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        apply_bits(&mut self.bytes, (M * 8) as u64, patch)
+    }
+}
+
+impl DynBloom {
+    // both filters need the same size, k and indexing
+    pub fn diff(old: &Self, new: &Self) -> Patch {
+        assert_eq!(old.m_bits(), new.m_bits(), "filters of different sizes");
+        diff_bits(old.bytes(), new.bytes(), old.m_bits() as u64)
+    }
+
+    pub fn apply(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        let m_bits = self.m_bits() as u64;
+        apply_bits(self.bits_mut(), m_bits, patch)
+    }
+}
+
+#[test]
+fn test_patch_round_trip() {
+    let mut old = Bloom::<32768, 18>::new();
+    for i in 0..10_000u32 {
+        old.add(&i.to_le_bytes());
+    }
+    let mut new = old.clone();
+    for i in 10_000..10_100u32 {
+        new.add(&i.to_le_bytes());
+    }
+    let patch = Bloom::diff(&old, &new);
+    // a few hundred bytes instead of 32KB
+    assert!(patch.bytes().len() < 32768 / 16, "{}", patch.bytes().len());
+    let mut synced = old.clone();
+    synced
+        .apply(&Patch::from_bytes(patch.bytes().to_vec()).unwrap())
+        .unwrap();
+    assert_eq!(synced.bytes, new.bytes);
+    // applying twice changes nothing
+    synced.apply(&patch).unwrap();
+    assert_eq!(synced.bytes, new.bytes);
+}
+
+#[test]
+fn test_patch_positions() {
+    let old = Bloom::<4, 1>::from_bytes([0b0000_0001, 0, 0, 0]);
+    let new = Bloom::<4, 1>::from_bytes([0b0000_0011, 0, 0b1000_0000, 0x80]);
+    let patch = Bloom::diff(&old, &new);
+    assert_eq!(patch.positions().unwrap(), vec![1, 23, 31]);
+    assert_eq!(
+        Bloom::diff(&new, &new).positions().unwrap(),
+        Vec::<u64>::new()
+    );
+
+    let mut dyn_old = DynBloom::new(1001, 7);
+    dyn_old.add(b"one");
+    let mut dyn_new = dyn_old.clone();
+    dyn_new.add(b"two");
+    let mut synced = dyn_old.clone();
+    synced.apply(&DynBloom::diff(&dyn_old, &dyn_new)).unwrap();
+    assert_eq!(synced, dyn_new);
+}
+
+#[test]
+fn test_patch_errors() {
+    let patch = Bloom::diff(&Bloom::<4, 1>::new(), &Bloom::<4, 1>::from_bytes([0xff; 4]));
+    assert_eq!(
+        Bloom::<8, 1>::new().apply(&patch),
+        Err(PatchError::SizeMismatch {
+            expected: 64,
+            found: 32
+        })
+    );
+    let bytes = patch.bytes();
+    assert_eq!(
+        Patch::from_bytes(bytes[..bytes.len() - 1].to_vec()),
+        Err(PatchError::Truncated)
+    );
+    // 40 ones: a gap longer than the filter
+    let mut long = bytes[..13].to_vec();
+    long[8..12].copy_from_slice(&1u32.to_le_bytes());
+    long.extend_from_slice(&[0xff; 5]);
+    long.push(0);
+    assert!(matches!(
+        Patch::from_bytes(long),
+        Err(PatchError::OutOfRange(_))
+    ));
+
+    let mut crowded = bytes[..13].to_vec();
+    crowded[8..12].copy_from_slice(&33u32.to_le_bytes());
+    assert_eq!(
+        Patch::from_bytes(crowded),
+        Err(PatchError::TooManyPositions {
+            count: 33,
+            m_bits: 32
+        })
+    );
+    // r = 63 and quotient 2: a gap of 2^64, which used to wrap to 0
+    let mut wrapping = u64::MAX.to_le_bytes().to_vec();
+    wrapping.extend_from_slice(&1u32.to_le_bytes());
+    wrapping.push(63);
+    wrapping.extend_from_slice(&[0b1100_0000, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        Patch::from_bytes(wrapping),
+        Err(PatchError::OutOfRange(u64::MAX))
+    );
+}
@@ -0,0 +1,141 @@
+use crate::{element::encode_pair, filter::Filter, Bloom};
+
+// A filter that also answers "does any key starting with this prefix
+// exist", like RocksDB's prefix bloom: adding a key adds its truncations to
+// each configured length too. Only those lengths can be queried, and keys
+// shorter than a length contribute no prefix of it.
+//
+// Prefixes go in as the pair ("prefix", prefix) in its canonical encoding,
+// so a prefix query for "user/" isn't answered by a whole key "user/".
+pub struct PrefixBloom<F> {
+    filter: F,
+    prefix_lengths: Vec<usize>,
+}
+
+fn prefix_entry(prefix: &[u8]) -> Vec<u8> {
+    encode_pair(b"prefix", prefix)
+}
+
+impl<F: Filter> PrefixBloom<F> {
+    pub fn new(filter: F, prefix_lengths: &[usize]) -> Self {
+        let mut prefix_lengths = prefix_lengths.to_vec();
+        prefix_lengths.sort_unstable();
+        prefix_lengths.dedup();
+        Self {
+            filter,
+            prefix_lengths,
+        }
+    }
+
+    pub fn prefix_lengths(&self) -> &[usize] {
+        &self.prefix_lengths
+    }
+
+    // None when prefix's length isn't one the filter was built with
+    pub fn has_prefix(&self, prefix: &[u8]) -> Option<bool> {
+        self.prefix_lengths
+            .contains(&prefix.len())
+            .then(|| self.filter.has(&prefix_entry(prefix)))
+    }
+
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    pub fn into_inner(self) -> F {
+        self.filter
+    }
+}
+
+impl<F: Filter> Filter for PrefixBloom<F> {
+    fn add(&mut self, key: &[u8]) {
+        self.filter.add(key);
+        for &length in self.prefix_lengths.iter().take_while(|&&l| l <= key.len()) {
+            self.filter.add(&prefix_entry(&key[..length]));
+        }
+    }
+
+    // whole keys only, see `has_prefix`
+    fn has(&self, key: &[u8]) -> bool {
+        self.filter.has(key)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.filter.count_ones()
+    }
+}
+
+// "tenant/user/item" keys, so the prefix lengths 5 and 10 cut at tenants
+// and users
+fn prefix_key(tenant: u32, user: u32, item: u32) -> String {
+    format!("{tenant:04}/{user:04}/{item}")
+}
+
+// Per prefix length: distinct prefixes stored, the false positive rate
+// expected from all entries sharing one Bloom<16384, 7>, and how many of
+// 10_000 absent prefixes of that length (0 being whole keys) report present.
+pub fn test_prefix_rates() {
+    const TENANTS: u32 = 50;
+    const USERS: u32 = 20;
+    const ITEMS: u32 = 5;
+    const PROBES: u32 = 10_000;
+
+    let mut prefix_bloom = PrefixBloom::new(Bloom::<16384, 7>::new(), &[5, 10]);
+    for tenant in 0..TENANTS {
+        for user in 0..USERS {
+            for item in 0..ITEMS {
+                prefix_bloom.add(prefix_key(tenant, user, item).as_bytes());
+            }
+        }
+    }
+    let keys = TENANTS * USERS * ITEMS;
+    let entries = keys + TENANTS + TENANTS * USERS;
+    let m_bits = 16384.0 * 8.0;
+    let expected = (1.0 - (-7.0 * entries as f64 / m_bits).exp()).powi(7);
+
+    println!("prefix_length;distinct;entries;expected_fpr;false_positives");
+    let whole_keys = (0..PROBES)
+        .filter(|i| prefix_bloom.has(prefix_key(i % TENANTS, i / TENANTS, ITEMS).as_bytes()))
+        .count();
+    println!("0;{keys};{entries};{expected};{whole_keys}/{PROBES}");
+    let tenants = (TENANTS..TENANTS + PROBES)
+        .filter(|tenant| prefix_bloom.has_prefix(format!("{tenant:04}/").as_bytes()) == Some(true))
+        .count();
+    println!("5;{TENANTS};{entries};{expected};{tenants}/{PROBES}");
+    let users = (0..PROBES)
+        .filter(|i| {
+            let prefix = format!("{:04}/{:04}/", i % TENANTS, USERS + i / TENANTS);
+            prefix_bloom.has_prefix(prefix.as_bytes()) == Some(true)
+        })
+        .count();
+    println!(
+        "10;{};{entries};{expected};{users}/{PROBES}",
+        TENANTS * USERS
+    );
+}
+
+#[test]
+fn test_prefix_bloom() {
+    let mut prefix_bloom = PrefixBloom::new(Bloom::<256, 30>::new(), &[8, 5, 8]);
+    assert_eq!(prefix_bloom.prefix_lengths(), [5, 8]);
+    prefix_bloom.add(b"user/42/photo1");
+    prefix_bloom.add(b"tag/");
+
+    assert!(prefix_bloom.has(b"user/42/photo1"));
+    assert_eq!(prefix_bloom.has_prefix(b"user/"), Some(true));
+    assert_eq!(prefix_bloom.has_prefix(b"user/42/"), Some(true));
+    assert_eq!(prefix_bloom.has_prefix(b"user/43/"), Some(false));
+    assert_eq!(prefix_bloom.has_prefix(b"user"), None);
+    // prefixes and whole keys don't answer for each other
+    assert!(!prefix_bloom.has(b"user/"));
+    assert!(prefix_bloom.has(b"tag/"));
+    assert_eq!(prefix_bloom.has_prefix(b"tag/"), None);
+
+    // "tag/" is too short for either length, so it adds only itself
+    let mut plain = Bloom::<256, 30>::new();
+    plain.add(b"user/42/photo1");
+    plain.add(&prefix_entry(b"user/"));
+    plain.add(&prefix_entry(b"user/42/"));
+    plain.add(b"tag/");
+    assert_eq!(prefix_bloom.into_inner().bytes, plain.bytes);
+}
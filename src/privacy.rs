@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+};
+
+use crate::{cli, entropy::EntropySource, xof::Blake3XOF, Bloom};
+
+// How many elements of a candidate universe test positive against a filter,
+// i.e. what an attacker learns by dictionary-probing it. Measured both for
+// the filter as given and for a saturated copy.
+#[derive(Debug, PartialEq)]
+pub struct EnumerationReport {
+    pub universe: u64,
+    pub positives_before: u64,
+    pub positives_after: u64,
+    pub ones_before: u32,
+    pub ones_after: u32,
+}
+
+pub fn enumeration_report<const M: usize, const K: usize, E: AsRef<[u8]>>(
+    bloom: &Bloom<M, K>,
+    universe: impl Iterator<Item = E>,
+) -> EnumerationReport {
+    let mut saturated = bloom.clone();
+    saturated.saturate();
+
+    let mut report = EnumerationReport {
+        universe: 0,
+        positives_before: 0,
+        positives_after: 0,
+        ones_before: bloom.count_ones(),
+        ones_after: saturated.count_ones(),
+    };
+    for element in universe {
+        report.universe += 1;
+        if bloom.has(element.as_ref()) {
+            report.positives_before += 1;
+        }
+        if saturated.has(element.as_ref()) {
+            report.positives_after += 1;
+        }
+    }
+    report
+}
+
+// Expected number of elements of a universe of `universe` elements that test
+// positive against a filter of `m_bits` bits, `ones` of them set, and K hash
+// indices: the anonymity set a positive answer hides in. Each index hits a set
+// bit with probability ones / m_bits.
+pub fn expected_anonymity_set(m_bits: usize, k: usize, ones: u32, universe: u64) -> f64 {
+    let density = ones as f64 / m_bits as f64;
+    universe as f64 * density.powi(k as i32)
+}
+
+// candidate universe of n 32-byte elements drawn from entropy
+pub fn generated_universe(
+    mut entropy: impl EntropySource,
+    n: usize,
+) -> impl Iterator<Item = [u8; 32]> {
+    (0..n).map(move |_| entropy.block())
+}
+
+// `privacy --filter <hex file> (--universe <file> | --generate <n> [--seed <s>])`
+//
+// The filter file holds the hex encoded bytes of a Bloom<256, 30>, the shape
+// `saturate()` is designed for. Universe files have one element per line.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let hex_string = fs::read_to_string(cli::required(args, "--filter")?)
+        .map_err(|e| format!("can't read filter: {e}"))?;
+    let bytes = hex::decode(hex_string.trim()).map_err(|e| format!("invalid filter hex: {e}"))?;
+    let bytes: [u8; 256] = bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("expected 256 filter bytes, got {}", b.len()))?;
+    let bloom = Bloom::<256, 30>::from_bytes(bytes);
+
+    let report = match cli::flag(args, "--universe") {
+        Some(path) => {
+            let file = fs::File::open(path).map_err(|e| format!("can't read universe: {e}"))?;
+            let lines = BufReader::new(file).lines().map_while(Result::ok);
+            enumeration_report(&bloom, lines)
+        }
+        None => {
+            let n = cli::parsed(args, "--generate", 1_000_000)?;
+            let seed = cli::flag(args, "--seed").unwrap_or("privacy universe");
+            enumeration_report(&bloom, generated_universe(Blake3XOF::new(seed), n))
+        }
+    };
+
+    let expected = |ones| expected_anonymity_set(2048, 30, ones, report.universe);
+    println!("filter;universe;ones;positives;expected_anonymity_set");
+    println!(
+        "given;{};{};{};{}",
+        report.universe,
+        report.ones_before,
+        report.positives_before,
+        expected(report.ones_before)
+    );
+    println!(
+        "saturated;{};{};{};{}",
+        report.universe,
+        report.ones_after,
+        report.positives_after,
+        expected(report.ones_after)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_enumeration_report() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    let members: Vec<[u8; 32]> = generated_universe(Blake3XOF::new("members"), 20).collect();
+    for member in members.iter() {
+        bloom.add(member);
+    }
+
+    let universe = members
+        .iter()
+        .copied()
+        .chain(generated_universe(Blake3XOF::new("others"), 10_000));
+    let report = enumeration_report(&bloom, universe);
+
+    assert_eq!(report.universe, 10_020);
+    // no false negatives, and saturation only ever adds positives
+    assert!(report.positives_before >= 20);
+    assert!(report.positives_after >= report.positives_before);
+    assert!(report.ones_after > report.ones_before);
+}
+
+#[test]
+fn test_expected_anonymity_set() {
+    assert_eq!(expected_anonymity_set(2048, 30, 0, 1_000_000), 0.0);
+    assert_eq!(
+        expected_anonymity_set(2048, 30, 2048, 1_000_000),
+        1_000_000.0
+    );
+    let half = expected_anonymity_set(2048, 30, 1024, 1 << 40);
+    assert!((half - 1024.0).abs() < 1e-6);
+}
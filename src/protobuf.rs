@@ -0,0 +1,224 @@
+use prost::Message;
+
+use crate::{
+    dyn_bloom::{DynBloom, DynBloomError, DynIndexing},
+    folded::Folded,
+    strategy::HashStrategy,
+    Bloom,
+};
+
+// prost messages for proto/filter.proto, written by hand so the build doesn't
+// need protoc. Keep both in sync.
+
+pub const PROTO_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum HashStrategyProto {
+    Unspecified = 0,
+    Xxh3Rejection = 1,
+    Xxh3Seeded = 2,
+    Blake3Keyed = 3,
+    Xxh3Wnfs = 4,
+    Murmur3Bip37 = 5,
+    SipDouble = 6,
+    Xxh3Double = 7,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FilterProto {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint64, tag = "2")]
+    pub m: u64,
+    #[prost(uint32, tag = "3")]
+    pub k: u32,
+    #[prost(enumeration = "HashStrategyProto", tag = "4")]
+    pub strategy: i32,
+    #[prost(uint32, tag = "5")]
+    pub fold_level: u32,
+    #[prost(bytes = "vec", tag = "6")]
+    pub bitset: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProtoError {
+    Decode(prost::DecodeError),
+    UnsupportedVersion(u32),
+    // the message describes a different filter than the one requested
+    Mismatch(&'static str),
+    // m and k no DynBloom can have
+    InvalidParameters(DynBloomError),
+}
+
+impl From<HashStrategy> for HashStrategyProto {
+    fn from(strategy: HashStrategy) -> Self {
+        match strategy {
+            HashStrategy::Xxh3Rejection => HashStrategyProto::Xxh3Rejection,
+            HashStrategy::Xxh3Seeded => HashStrategyProto::Xxh3Seeded,
+            HashStrategy::Blake3Keyed => HashStrategyProto::Blake3Keyed,
+            HashStrategy::Xxh3Wnfs => HashStrategyProto::Xxh3Wnfs,
+            HashStrategy::Murmur3Bip37 => HashStrategyProto::Murmur3Bip37,
+            HashStrategy::SipDouble => HashStrategyProto::SipDouble,
+            HashStrategy::Xxh3Double => HashStrategyProto::Xxh3Double,
+        }
+    }
+}
+
+impl FilterProto {
+    fn new(m: usize, k: usize, strategy: HashStrategy, fold_level: usize, bitset: &[u8]) -> Self {
+        Self {
+            version: PROTO_VERSION,
+            m: m as u64,
+            k: k as u32,
+            strategy: HashStrategyProto::from(strategy) as i32,
+            fold_level: fold_level as u32,
+            bitset: bitset.to_vec(),
+        }
+    }
+
+    fn decode_checked(data: &[u8]) -> Result<Self, ProtoError> {
+        let message = FilterProto::decode(data).map_err(ProtoError::Decode)?;
+        if message.version != PROTO_VERSION {
+            return Err(ProtoError::UnsupportedVersion(message.version));
+        }
+        Ok(message)
+    }
+
+    fn expect(
+        &self,
+        m: usize,
+        k: usize,
+        strategy: HashStrategy,
+        fold_level: usize,
+    ) -> Result<(), ProtoError> {
+        if self.strategy != HashStrategyProto::from(strategy) as i32 {
+            return Err(ProtoError::Mismatch("strategy"));
+        }
+        if self.m != m as u64 {
+            return Err(ProtoError::Mismatch("m"));
+        }
+        if self.k as usize != k {
+            return Err(ProtoError::Mismatch("k"));
+        }
+        if self.fold_level as usize != fold_level {
+            return Err(ProtoError::Mismatch("fold_level"));
+        }
+        if self.bitset.len() != (m >> fold_level).div_ceil(8) {
+            return Err(ProtoError::Mismatch("bitset"));
+        }
+        Ok(())
+    }
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        FilterProto::new(M * 8, K, HashStrategy::Xxh3Rejection, 0, &self.bytes).encode_to_vec()
+    }
+
+    pub fn from_protobuf(data: &[u8]) -> Result<Self, ProtoError> {
+        let message = FilterProto::decode_checked(data)?;
+        message.expect(M * 8, K, HashStrategy::Xxh3Rejection, 0)?;
+        Ok(Self::from_bytes(message.bitset.try_into().unwrap()))
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        FilterProto::new((S * 8) << F, K, HashStrategy::Xxh3Rejection, F, &self.bytes)
+            .encode_to_vec()
+    }
+
+    pub fn from_protobuf(data: &[u8]) -> Result<Self, ProtoError> {
+        let message = FilterProto::decode_checked(data)?;
+        message.expect((S * 8) << F, K, HashStrategy::Xxh3Rejection, F)?;
+        Ok(Self::from_bytes(message.bitset.try_into().unwrap()))
+    }
+}
+
+impl DynBloom {
+    // only the xxh3 indexed filters have a strategy id
+    pub fn to_protobuf(&self) -> Option<Vec<u8>> {
+        if self.indexing() != DynIndexing::Xxh3Rejection {
+            return None;
+        }
+        let message = FilterProto::new(
+            self.m_bits(),
+            self.k(),
+            HashStrategy::Xxh3Rejection,
+            0,
+            self.bytes(),
+        );
+        Some(message.encode_to_vec())
+    }
+
+    pub fn from_protobuf(data: &[u8]) -> Result<Self, ProtoError> {
+        let message = FilterProto::decode_checked(data)?;
+        let (m_bits, k) = (message.m as usize, message.k as usize);
+        message.expect(m_bits, k, HashStrategy::Xxh3Rejection, 0)?;
+        // `expect` checked the bitset's length
+        DynBloom::from_bytes(message.bitset, m_bits, k, DynIndexing::Xxh3Rejection).map_err(|e| {
+            match e {
+                DynBloomError::Padding => ProtoError::Mismatch("bitset"),
+                e => ProtoError::InvalidParameters(e),
+            }
+        })
+    }
+}
+
+#[test]
+fn test_protobuf_encoding() {
+    let bloom = Bloom::<2, 1>::from_bytes([0x12, 0x34]);
+    // version 1, m 16, k 1, strategy 1, fold_level 0 omitted, bitset
+    assert_eq!(
+        bloom.to_protobuf(),
+        vec![0x08, 0x01, 0x10, 0x10, 0x18, 0x01, 0x20, 0x01, 0x32, 0x02, 0x12, 0x34]
+    );
+}
+
+#[test]
+fn test_protobuf_round_trip() {
+    let mut bloom = Bloom::<256, 30>::new();
+    let mut folded = Folded::<2, 64, 30>::new();
+    let mut dyn_bloom = DynBloom::new(1001, 7);
+    for i in 0..10u32 {
+        bloom.add(&i.to_le_bytes());
+        folded.insert(&i.to_le_bytes());
+        dyn_bloom.add(&i.to_le_bytes());
+    }
+    let encoded = bloom.to_protobuf();
+    assert_eq!(
+        Bloom::<256, 30>::from_protobuf(&encoded).unwrap().bytes,
+        bloom.bytes
+    );
+    let encoded_folded = folded.to_protobuf();
+    assert_eq!(
+        Folded::<2, 64, 30>::from_protobuf(&encoded_folded)
+            .unwrap()
+            .bytes,
+        folded.bytes
+    );
+    let encoded_dyn = dyn_bloom.to_protobuf().unwrap();
+    assert_eq!(DynBloom::from_protobuf(&encoded_dyn).unwrap(), dyn_bloom);
+
+    assert_eq!(
+        Bloom::<256, 16>::from_protobuf(&encoded).err(),
+        Some(ProtoError::Mismatch("k"))
+    );
+    assert_eq!(
+        Bloom::<256, 30>::from_protobuf(&encoded_folded).err(),
+        Some(ProtoError::Mismatch("fold_level"))
+    );
+    let mut future = FilterProto::decode(&encoded[..]).unwrap();
+    future.version = 2;
+    assert_eq!(
+        Bloom::<256, 30>::from_protobuf(&future.encode_to_vec()).err(),
+        Some(ProtoError::UnsupportedVersion(2))
+    );
+
+    let empty = FilterProto::new(0, 3, HashStrategy::Xxh3Rejection, 0, &[]);
+    assert_eq!(
+        DynBloom::from_protobuf(&empty.encode_to_vec()).err(),
+        Some(ProtoError::InvalidParameters(DynBloomError::NoBits))
+    );
+}
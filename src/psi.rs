@@ -0,0 +1,204 @@
+use std::collections::HashSet;
+
+use crate::{
+    entropy::EntropySource,
+    keyed::{self, KeyedBloom},
+};
+
+// Toy two-party private set intersection over keyed filters, run in-process.
+//
+// 1. Both parties send a random key share, the joint key is derived from both.
+// 2. Alice sends a KeyedBloom over her set. Bob keeps his elements that test
+//    positive as the approximate intersection.
+// 3. Bob sends blinded tags (keyed hashes) of his candidates. Alice confirms
+//    the tags matching her own set and sends them back, which removes Bob's
+//    false positives and tells Alice the intersection.
+//
+// Observers of the transcript only see key-dependent bytes. The parties
+// themselves learn more than the intersection: Bob learns the false positives
+// of step 2, Alice learns tags of Bob's non-intersecting candidates.
+
+pub struct Party {
+    set: Vec<[u8; 32]>,
+    key_share: [u8; 32],
+    joint_key: Option<[u8; 32]>,
+}
+
+// messages exchanged between the parties, in protocol order
+pub struct KeyShare(pub [u8; 32]);
+pub struct FilterMessage<const M: usize>(pub [u8; M]);
+pub struct BlindedTags(pub Vec<[u8; 32]>);
+
+impl Party {
+    pub fn new(set: Vec<[u8; 32]>) -> Self {
+        Self {
+            set,
+            key_share: keyed::generate_key(),
+            joint_key: None,
+        }
+    }
+
+    pub fn key_share(&self) -> KeyShare {
+        KeyShare(self.key_share)
+    }
+
+    // both parties must agree on whose share goes first
+    pub fn receive_key_share(&mut self, other: &KeyShare, we_go_first: bool) {
+        let mut material = Vec::with_capacity(64);
+        if we_go_first {
+            material.extend_from_slice(&self.key_share);
+            material.extend_from_slice(&other.0);
+        } else {
+            material.extend_from_slice(&other.0);
+            material.extend_from_slice(&self.key_share);
+        }
+        self.joint_key = Some(blake3::derive_key("psi joint key", &material));
+    }
+
+    pub fn filter<const M: usize, const K: usize>(&self) -> FilterMessage<M> {
+        let mut filter = KeyedBloom::<M, K>::new(self.joint_key());
+        for element in self.set.iter() {
+            filter.add(element);
+        }
+        FilterMessage(*filter.bytes())
+    }
+
+    pub fn candidates<const M: usize, const K: usize>(
+        &self,
+        message: &FilterMessage<M>,
+    ) -> Vec<[u8; 32]> {
+        let filter = KeyedBloom::<M, K>::from_bytes(self.joint_key(), message.0);
+        self.set
+            .iter()
+            .filter(|element| filter.has(*element))
+            .copied()
+            .collect()
+    }
+
+    pub fn blind(&self, elements: &[[u8; 32]]) -> BlindedTags {
+        BlindedTags(elements.iter().map(|e| self.tag(e)).collect())
+    }
+
+    // the subset of the received tags that belong to our own set
+    pub fn confirm(&self, tags: &BlindedTags) -> BlindedTags {
+        let own: HashSet<[u8; 32]> = self.set.iter().map(|e| self.tag(e)).collect();
+        BlindedTags(
+            tags.0
+                .iter()
+                .filter(|t| own.contains(*t))
+                .copied()
+                .collect(),
+        )
+    }
+
+    pub fn unblind(&self, candidates: &[[u8; 32]], confirmed: &BlindedTags) -> Vec<[u8; 32]> {
+        let confirmed: HashSet<&[u8; 32]> = confirmed.0.iter().collect();
+        candidates
+            .iter()
+            .filter(|c| confirmed.contains(&self.tag(c)))
+            .copied()
+            .collect()
+    }
+
+    fn tag(&self, element: &[u8; 32]) -> [u8; 32] {
+        *blake3::keyed_hash(&self.joint_key(), element).as_bytes()
+    }
+
+    fn joint_key(&self) -> [u8; 32] {
+        self.joint_key.expect("key shares must be exchanged first")
+    }
+}
+
+#[derive(Debug)]
+pub struct PsiOutcome {
+    pub true_intersection: usize,
+    // Bob's elements testing positive against Alice's filter
+    pub candidates: usize,
+    // candidates outside the intersection, i.e. what Bob learns wrongly
+    // and what Alice learns tags of beyond the intersection
+    pub leaked_candidates: usize,
+    // final intersection after confirmation
+    pub intersection: usize,
+}
+
+pub fn run_psi<const M: usize, const K: usize>(
+    alice_set: Vec<[u8; 32]>,
+    bob_set: Vec<[u8; 32]>,
+) -> PsiOutcome {
+    let true_intersection = {
+        let alice: HashSet<&[u8; 32]> = alice_set.iter().collect();
+        bob_set.iter().filter(|e| alice.contains(e)).count()
+    };
+
+    let mut alice = Party::new(alice_set);
+    let mut bob = Party::new(bob_set);
+
+    let (alice_share, bob_share) = (alice.key_share(), bob.key_share());
+    alice.receive_key_share(&bob_share, true);
+    bob.receive_key_share(&alice_share, false);
+
+    let filter = alice.filter::<M, K>();
+    let candidates = bob.candidates::<M, K>(&filter);
+    let confirmed = alice.confirm(&bob.blind(&candidates));
+    let intersection = bob.unblind(&candidates, &confirmed);
+
+    PsiOutcome {
+        true_intersection,
+        candidates: candidates.len(),
+        leaked_candidates: candidates.len() - intersection.len(),
+        intersection: intersection.len(),
+    }
+}
+
+// two sets of `size` elements drawn from entropy, `overlap` of them shared
+fn psi_sets(
+    size: usize,
+    overlap: usize,
+    mut entropy: impl EntropySource,
+) -> (Vec<[u8; 32]>, Vec<[u8; 32]>) {
+    let mut draw = |n| (0..n).map(|_| entropy.block()).collect::<Vec<[u8; 32]>>();
+    let shared = draw(overlap);
+    let alice = [shared.clone(), draw(size - overlap)].concat();
+    let bob = [shared, draw(size - overlap)].concat();
+    (alice, bob)
+}
+
+fn print_psi<const M: usize, const K: usize>(
+    size: usize,
+    overlap: usize,
+    entropy: impl EntropySource,
+) {
+    let (alice, bob) = psi_sets(size, overlap, entropy);
+    let outcome = run_psi::<M, K>(alice, bob);
+    println!(
+        "{};{K};{size};{};{};{};{}",
+        M * 8,
+        outcome.true_intersection,
+        outcome.candidates,
+        outcome.leaked_candidates,
+        outcome.intersection
+    );
+}
+
+// leakage and accuracy of the filter round for different filter sizes
+pub fn test_psi_rates(mut entropy: impl EntropySource) {
+    println!("m;k;set_size;true_intersection;candidates;leaked;intersection");
+    for overlap in [10, 100, 500] {
+        print_psi::<128, 7>(1000, overlap, &mut entropy);
+        print_psi::<256, 7>(1000, overlap, &mut entropy);
+        print_psi::<1024, 7>(1000, overlap, &mut entropy);
+        print_psi::<2048, 10>(1000, overlap, &mut entropy);
+    }
+}
+
+#[test]
+fn test_psi() {
+    let (alice, bob) = psi_sets(200, 50, crate::xof::Blake3XOF::new(b"psi sets"));
+    let outcome = run_psi::<1024, 7>(alice, bob);
+    assert_eq!(outcome.true_intersection, 50);
+    assert_eq!(outcome.intersection, 50);
+    assert_eq!(
+        outcome.candidates,
+        outcome.intersection + outcome.leaked_candidates
+    );
+}
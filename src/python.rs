@@ -0,0 +1,116 @@
+use pyo3::{exceptions::PyValueError, prelude::*, types::PyBytes};
+
+use crate::{
+    dyn_bloom::{DynBloom, DynIndexing},
+    harness::{count_false_positives, folded_rates},
+    Bloom,
+};
+
+// Python module for driving experiments from notebooks. Filters are
+// `DynBloom`s; the drivers answer plain tuples instead of printing CSV.
+
+#[pyclass(name = "Bloom")]
+pub struct PyBloom {
+    inner: DynBloom,
+}
+
+#[pymethods]
+impl PyBloom {
+    #[new]
+    fn new(m_bits: usize, k: usize) -> PyResult<Self> {
+        Ok(Self {
+            inner: DynBloom::try_new(m_bits, k, DynIndexing::Xxh3Rejection)
+                .map_err(|e| PyValueError::new_err(format!("{e:?}")))?,
+        })
+    }
+
+    // true if the element definitely wasn't in the filter before
+    fn add(&mut self, element: &[u8]) -> bool {
+        self.inner.add(element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.inner.has(element)
+    }
+
+    fn __contains__(&self, element: &[u8]) -> bool {
+        self.inner.has(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.inner.count_ones()
+    }
+
+    #[getter]
+    fn m_bits(&self) -> usize {
+        self.inner.m_bits()
+    }
+
+    #[getter]
+    fn k(&self) -> usize {
+        self.inner.k()
+    }
+
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.to_bytes())
+    }
+
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        DynBloom::try_from_bytes(data)
+            .map(|inner| Self { inner })
+            .map_err(|e| PyValueError::new_err(format!("invalid filter: {e:?}")))
+    }
+}
+
+// [(n, false negatives, false positives)] like `test_folded_rates` prints,
+// reproducible when given a seed
+#[pyfunction]
+#[pyo3(signature = (ns, probes = 1_000_000, seed = None))]
+fn folded_sweep(
+    py: Python<'_>,
+    ns: Vec<usize>,
+    probes: usize,
+    seed: Option<String>,
+) -> Vec<(usize, u32, u32)> {
+    py.detach(|| {
+        let mut entropy = crate::entropy::from_seed(seed.as_deref());
+        ns.into_iter()
+            .map(|n| {
+                let (false_negatives, false_positives) = folded_rates(n, probes, &mut entropy);
+                (n, false_negatives, false_positives)
+            })
+            .collect()
+    })
+}
+
+// false positives among `tests` probes of a `Bloom` shaped filter prefilled
+// like `test_false_positive_rate` does
+#[pyfunction]
+fn false_positives(py: Python<'_>, m_bits: usize, k: usize, prefill: u32, tests: u64) -> u64 {
+    py.detach(|| count_false_positives(&mut DynBloom::new(m_bits, k), prefill, tests))
+}
+
+// average ones of `Bloom<256, 30>` filters after `prefill` random elements,
+// like `test_avg_bits` prints
+#[pyfunction]
+fn average_ones(py: Python<'_>, prefill: u32, tests: u64) -> f64 {
+    py.detach(|| {
+        let mut sum = 0u64;
+        for _ in 0..tests {
+            let mut bloom: Bloom<256, 30> = Bloom::new();
+            crate::harness::fill_random(prefill, &mut bloom, crate::entropy::ThreadEntropy);
+            sum += bloom.count_ones() as u64;
+        }
+        sum as f64 / tests as f64
+    })
+}
+
+#[pymodule]
+fn rust_bloomfilters(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyBloom>()?;
+    module.add_function(wrap_pyfunction!(folded_sweep, module)?)?;
+    module.add_function(wrap_pyfunction!(false_positives, module)?)?;
+    module.add_function(wrap_pyfunction!(average_ones, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,356 @@
+use crate::{
+    filter::Filter,
+    xof::{Xof, XXH3XOF},
+};
+
+// Quotient filter (Bender et al., 2012): each element's q + r bit
+// fingerprint, from the first u64 of its `XXH3XOF` stream, is stored as its
+// r bit remainder in a table of 2^q slots, at or after the slot its q bit
+// quotient names. Remainders sharing a quotient form a sorted run, runs
+// pushed right by earlier ones form a cluster, and three bits per slot
+// (occupied, continuation, shifted) recover each remainder's quotient. The
+// table holds fingerprints exactly, so unlike a Bloom filter two filters
+// merge into one that can't tell a repeat, and its fingerprints can be
+// listed back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuotientFilter {
+    quotient_bits: u32,
+    remainder_bits: u32,
+    slots: Vec<Slot>,
+    // distinct fingerprints
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Slot {
+    remainder: u64,
+    // some fingerprint has this slot as its quotient
+    occupied: bool,
+    // the remainder continues the run of the slot before
+    continuation: bool,
+    // the remainder isn't in its quotient's slot
+    shifted: bool,
+}
+
+impl Slot {
+    fn is_empty(&self) -> bool {
+        !self.occupied && !self.continuation && !self.shifted
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QuotientError {
+    // only one slot is left, which lookups need empty to find cluster ends;
+    // the element wasn't added
+    Full,
+    // merged filters need the same quotient and remainder bits
+    Mismatch,
+}
+
+impl QuotientFilter {
+    // 2^quotient_bits slots of remainder_bits, together at most 64 bits
+    pub fn new(quotient_bits: u32, remainder_bits: u32) -> Self {
+        assert!(
+            (1..=32).contains(&quotient_bits),
+            "quotients are 1 to 32 bits"
+        );
+        assert!(
+            remainder_bits >= 1 && quotient_bits + remainder_bits <= 64,
+            "remainders are at least 1 bit, fingerprints at most 64"
+        );
+        Self {
+            quotient_bits,
+            remainder_bits,
+            slots: vec![Slot::default(); 1 << quotient_bits],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn quotient_bits(&self) -> u32 {
+        self.quotient_bits
+    }
+
+    pub fn remainder_bits(&self) -> u32 {
+        self.remainder_bits
+    }
+
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.slots.len() as f64
+    }
+
+    // remainders and the three metadata bits, to compare with a Bloom
+    // filter's m
+    pub fn size_bits(&self) -> usize {
+        self.slots.len() * (self.remainder_bits as usize + 3)
+    }
+
+    pub fn fingerprint(&self, element: &[u8]) -> u64 {
+        let bits = self.quotient_bits + self.remainder_bits;
+        XXH3XOF::from(element).next_u64() & (u64::MAX >> (64 - bits))
+    }
+
+    fn split(&self, fingerprint: u64) -> (usize, u64) {
+        let quotient = (fingerprint >> self.remainder_bits) as usize;
+        let remainder = fingerprint & ((1 << self.remainder_bits) - 1);
+        (quotient, remainder)
+    }
+
+    fn next(&self, slot: usize) -> usize {
+        (slot + 1) & (self.slots.len() - 1)
+    }
+
+    fn prev(&self, slot: usize) -> usize {
+        slot.wrapping_sub(1) & (self.slots.len() - 1)
+    }
+
+    // Where the run of quotient starts, or would start. Walks back to the
+    // start of quotient's cluster, then forward run by run, one per
+    // occupied slot, until the run belonging to quotient.
+    fn run_start(&self, quotient: usize) -> usize {
+        let mut occupied = quotient;
+        while self.slots[occupied].shifted {
+            occupied = self.prev(occupied);
+        }
+        let mut run = occupied;
+        while occupied != quotient {
+            run = self.next(run);
+            while self.slots[run].continuation {
+                run = self.next(run);
+            }
+            occupied = self.next(occupied);
+            while !self.slots[occupied].occupied {
+                occupied = self.next(occupied);
+            }
+        }
+        run
+    }
+
+    pub fn contains_fingerprint(&self, fingerprint: u64) -> bool {
+        let (quotient, remainder) = self.split(fingerprint);
+        if !self.slots[quotient].occupied {
+            return false;
+        }
+        let mut slot = self.run_start(quotient);
+        loop {
+            match self.slots[slot].remainder {
+                stored if stored == remainder => return true,
+                // runs are sorted
+                stored if stored > remainder => return false,
+                _ => {}
+            }
+            slot = self.next(slot);
+            if !self.slots[slot].continuation {
+                return false;
+            }
+        }
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        self.contains_fingerprint(self.fingerprint(element))
+    }
+
+    // false if the fingerprint was already there
+    pub fn insert_fingerprint(&mut self, fingerprint: u64) -> Result<bool, QuotientError> {
+        if self.contains_fingerprint(fingerprint) {
+            return Ok(false);
+        }
+        if self.len + 1 >= self.slots.len() {
+            return Err(QuotientError::Full);
+        }
+        let (quotient, remainder) = self.split(fingerprint);
+        self.len += 1;
+        if self.slots[quotient].is_empty() {
+            self.slots[quotient] = Slot {
+                remainder,
+                occupied: true,
+                ..Slot::default()
+            };
+            return Ok(true);
+        }
+
+        let run_existed = self.slots[quotient].occupied;
+        self.slots[quotient].occupied = true;
+        let start = self.run_start(quotient);
+        let mut position = start;
+        if run_existed {
+            // after the remainders below this one, or after the run
+            while self.slots[position].remainder < remainder {
+                position = self.next(position);
+                if !self.slots[position].continuation {
+                    break;
+                }
+            }
+        }
+
+        // Shifts everything from position to the next empty slot one slot
+        // right. Occupied bits stay, they belong to the slot.
+        let mut carried = (remainder, run_existed && position != start);
+        let mut shifted = position != quotient;
+        let mut slot = position;
+        loop {
+            let was_empty = self.slots[slot].is_empty();
+            let current = &mut self.slots[slot];
+            let displaced = (current.remainder, current.continuation);
+            (current.remainder, current.continuation) = carried;
+            current.shifted = shifted;
+            if was_empty {
+                break;
+            }
+            carried = displaced;
+            shifted = true;
+            slot = self.next(slot);
+        }
+        // a new head pushes the old one into its run
+        if run_existed && position == start {
+            let next = self.next(start);
+            self.slots[next].continuation = true;
+        }
+        Ok(true)
+    }
+
+    // false if element's fingerprint was already there, a repeat or a
+    // false positive
+    pub fn insert(&mut self, element: &[u8]) -> Result<bool, QuotientError> {
+        self.insert_fingerprint(self.fingerprint(element))
+    }
+
+    // every fingerprint once, in ascending order
+    pub fn fingerprints(&self) -> Vec<u64> {
+        let mut fingerprints = Vec::with_capacity(self.len);
+        // starting after an empty slot, no run is already under way
+        let Some(empty) = self.slots.iter().position(Slot::is_empty) else {
+            return fingerprints;
+        };
+        let mut quotients = std::collections::VecDeque::new();
+        let mut quotient = 0;
+        let mut slot = empty;
+        for _ in 0..self.slots.len() {
+            slot = self.next(slot);
+            let current = self.slots[slot];
+            if current.occupied {
+                quotients.push_back(slot);
+            }
+            if current.is_empty() {
+                continue;
+            }
+            if !current.continuation {
+                quotient = quotients.pop_front().unwrap();
+            }
+            fingerprints.push((quotient as u64) << self.remainder_bits | current.remainder);
+        }
+        fingerprints.sort_unstable();
+        fingerprints
+    }
+
+    // A filter of both filters' fingerprints, each once, as if built from
+    // all their elements. Full if together they don't fit.
+    pub fn merge(&self, other: &Self) -> Result<Self, QuotientError> {
+        if (self.quotient_bits, self.remainder_bits) != (other.quotient_bits, other.remainder_bits)
+        {
+            return Err(QuotientError::Mismatch);
+        }
+        let mut merged = Self::new(self.quotient_bits, self.remainder_bits);
+        for fingerprint in self.fingerprints().into_iter().chain(other.fingerprints()) {
+            merged.insert_fingerprint(fingerprint)?;
+        }
+        Ok(merged)
+    }
+}
+
+// Panics once 2^q - 1 slots are taken, since a quotient filter can't grow in
+// place; pick quotient_bits for the elements the harness will add.
+impl Filter for QuotientFilter {
+    fn add(&mut self, element: &[u8]) {
+        self.insert(element).expect("quotient filter is full");
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains(element)
+    }
+
+    // occupied slots
+    fn count_ones(&self) -> u32 {
+        self.len as u32
+    }
+}
+
+#[test]
+fn test_quotient_filter() {
+    use crate::element::encode_u64;
+
+    let mut filter = QuotientFilter::new(12, 10);
+    assert_eq!(filter.size_bits(), 4096 * 13);
+    let mut inserted = 0;
+    for i in 0..3500u64 {
+        inserted += filter.insert(&encode_u64(i)).unwrap() as usize;
+    }
+    // the few missing are fingerprint collisions
+    assert!(inserted > 3490 && inserted == filter.len(), "{inserted}");
+    assert!((0..3500u64).all(|i| filter.contains(&encode_u64(i))));
+    assert!(!filter.insert(&encode_u64(7)).unwrap());
+
+    // about 2^-10 at 85% load, in runs of up to a few slots
+    let false_positives = (1 << 32..(1 << 32) + 100_000u64)
+        .filter(|&i| filter.contains(&encode_u64(i)))
+        .count();
+    assert!((40..150).contains(&false_positives), "{false_positives}");
+
+    // the table lists back exactly the distinct fingerprints
+    let mut expected: Vec<u64> = (0..3500u64)
+        .map(|i| filter.fingerprint(&encode_u64(i)))
+        .collect();
+    expected.sort_unstable();
+    expected.dedup();
+    assert_eq!(filter.fingerprints(), expected);
+
+    // merging overlapping halves gives the filter of their union
+    let (mut a, mut b) = (QuotientFilter::new(12, 10), QuotientFilter::new(12, 10));
+    for i in 0..2000u64 {
+        a.insert(&encode_u64(i)).unwrap();
+        b.insert(&encode_u64(i + 1500)).unwrap();
+    }
+    let merged = a.merge(&b).unwrap();
+    assert_eq!(merged.fingerprints(), filter.fingerprints());
+    assert_eq!(merged.len(), filter.len());
+    assert_eq!(
+        a.merge(&QuotientFilter::new(12, 8)),
+        Err(QuotientError::Mismatch)
+    );
+
+    // crowded tables of tiny remainders, checked against the exact set
+    for quotient_bits in [3, 5, 7] {
+        let mut filter = QuotientFilter::new(quotient_bits, 3);
+        let mut set = std::collections::BTreeSet::new();
+        for i in 0..1000u64 {
+            let fingerprint = filter.fingerprint(&encode_u64(i));
+            match filter.insert_fingerprint(fingerprint) {
+                Ok(new) => assert_eq!(new, set.insert(fingerprint)),
+                Err(QuotientError::Full) => break,
+                Err(error) => panic!("{error:?}"),
+            }
+            assert!((0..1 << (quotient_bits + 3))
+                .all(|f| filter.contains_fingerprint(f) == set.contains(&f)));
+        }
+        assert_eq!(filter.fingerprints(), set.into_iter().collect::<Vec<_>>());
+    }
+
+    // the last slot stays empty, which wraps runs around the end
+    let mut tiny = QuotientFilter::new(2, 4);
+    let mut i = 0;
+    while tiny.len() < 3 {
+        tiny.insert(&encode_u64(i)).unwrap();
+        i += 1;
+    }
+    let outside = (i..).find(|&j| !tiny.contains(&encode_u64(j))).unwrap();
+    assert_eq!(tiny.insert(&encode_u64(outside)), Err(QuotientError::Full));
+    assert!((0..i).all(|i| tiny.contains(&encode_u64(i))));
+    assert_eq!(tiny.fingerprints().len(), 3);
+}
@@ -0,0 +1,513 @@
+use crate::filter::Filter;
+
+// RedisBloom scalable bloom filters (BF.* commands) and their BF.SCANDUMP /
+// BF.LOADCHUNK chunk format.
+//
+// The first chunk (iterator 1) is the packed little endian chain header:
+//   u64 size, u32 nfilters, u32 options, u32 growth,
+//   per link: u64 bytes, u64 bits, u64 size, f64 error, f64 bpe,
+//             u32 hashes, u64 entries, u8 n2
+// followed by chunks of the links' bit arrays, concatenated in link order.
+// Each data chunk is returned with iterator 1 + (offset of its end), and
+// BF.LOADCHUNK places it at iterator - 1 - length.
+//
+// Indices are (a + i * b) % bits with a = MurmurHash64A(element, 0xc6a4a7935bd1e995)
+// and b = MurmurHash64A(element, a), or masked to n2 bits for power of two
+// filters. Bits are numbered least significant first.
+
+pub const BLOOM_OPT_NOROUND: u32 = 1;
+pub const BLOOM_OPT_FORCE64: u32 = 4;
+pub const BLOOM_OPT_NO_SCALING: u32 = 8;
+
+const ERROR_TIGHTENING_RATIO: f64 = 0.5;
+const HEADER_BYTES: usize = 8 + 4 + 4 + 4;
+const LINK_BYTES: usize = 8 + 8 + 8 + 8 + 8 + 4 + 8 + 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedisLink {
+    pub bytes: Vec<u8>,
+    pub bits: u64,
+    // items added to this link
+    pub size: u64,
+    pub error: f64,
+    pub bpe: f64,
+    pub hashes: u32,
+    // capacity
+    pub entries: u64,
+    pub n2: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedisBloomChain {
+    pub links: Vec<RedisLink>,
+    pub size: u64,
+    pub options: u32,
+    pub growth: u32,
+}
+
+// BF.ADD's "ERR non scaling filter is full": BLOOM_OPT_NO_SCALING and the
+// one link at capacity
+#[derive(Debug, PartialEq, Eq)]
+pub enum RedisAddError {
+    Full,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RedisDumpError {
+    MissingHeader,
+    Truncated,
+    ChunkOutOfRange { iter: u64 },
+    NoLinks,
+    // a header field of the link'th link no filter can have: no bits, more
+    // bits than bytes, a power of two mask wider than 63 bits or than the
+    // bits, or no hashes
+    InvalidLink { link: usize, field: &'static str },
+    // more link bytes than the data chunks hold, refused before allocating
+    LinksTooLarge { bytes: u64, available: u64 },
+}
+
+pub fn murmur64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (data.len() as u64).wrapping_mul(M);
+    let mut blocks = data.chunks_exact(8);
+    for block in &mut blocks {
+        let mut k = u64::from_le_bytes(block.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        for (i, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+// C's logb for positive normal x, the unbiased binary exponent
+fn logb(x: f64) -> i32 {
+    ((x.to_bits() >> 52) & 0x7ff) as i32 - 1023
+}
+
+fn redis_hashes(element: &[u8]) -> (u64, u64) {
+    let a = murmur64a(element, 0xc6a4a7935bd1e995);
+    let b = murmur64a(element, a);
+    (a, b)
+}
+
+impl RedisLink {
+    // like RedisBloom's bloom_init
+    pub fn new(entries: u64, error: f64, options: u32) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bpe = -(error.ln() / (ln2 * ln2));
+        let (mut bits, n2) = if options & BLOOM_OPT_NOROUND != 0 {
+            ((bpe * entries as f64) as u64, 0)
+        } else {
+            // logb + 1, so exactly 2^n bits round up to 2^(n + 1)
+            let n2 = (logb(bpe * entries as f64) + 1) as u8;
+            (1u64 << n2, n2)
+        };
+        let bytes = bits.div_ceil(8);
+        bits = bytes * 8;
+        Self {
+            bytes: vec![0u8; bytes as usize],
+            bits,
+            size: 0,
+            error,
+            bpe,
+            hashes: (ln2 * bpe).ceil() as u32,
+            entries,
+            n2,
+        }
+    }
+
+    fn index(&self, (a, b): (u64, u64), i: u64) -> usize {
+        let x = a.wrapping_add(i.wrapping_mul(b));
+        if self.n2 > 0 {
+            (x & ((1u64 << self.n2) - 1)) as usize
+        } else {
+            (x % self.bits) as usize
+        }
+    }
+
+    fn contains(&self, hashes: (u64, u64)) -> bool {
+        (0..self.hashes as u64).all(|i| {
+            let x = self.index(hashes, i);
+            self.bytes[x >> 3] & (1 << (x % 8)) != 0
+        })
+    }
+
+    // true if any bit was newly set
+    fn insert(&mut self, hashes: (u64, u64)) -> bool {
+        let mut new = false;
+        for i in 0..self.hashes as u64 {
+            let x = self.index(hashes, i);
+            new |= self.bytes[x >> 3] & (1 << (x % 8)) == 0;
+            self.bytes[x >> 3] |= 1 << (x % 8);
+        }
+        new
+    }
+}
+
+impl RedisBloomChain {
+    // BF.RESERVE key error capacity, with RedisBloom's default options and growth
+    pub fn new(capacity: u64, error: f64) -> Self {
+        Self::with_options(capacity, error, BLOOM_OPT_NOROUND | BLOOM_OPT_FORCE64, 2)
+    }
+
+    pub fn with_options(capacity: u64, error: f64, options: u32, growth: u32) -> Self {
+        Self {
+            links: vec![RedisLink::new(capacity, error, options)],
+            size: 0,
+            options,
+            growth,
+        }
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        let hashes = redis_hashes(element);
+        self.links.iter().rev().any(|link| link.contains(hashes))
+    }
+
+    // BF.ADD, scaling into a new, tighter link once the last one is full.
+    // Returns false if the element was (probably) present already.
+    pub fn insert(&mut self, element: &[u8]) -> Result<bool, RedisAddError> {
+        let hashes = redis_hashes(element);
+        if self.links.iter().any(|link| link.contains(hashes)) {
+            return Ok(false);
+        }
+
+        let last = self.links.last().unwrap();
+        if last.size >= last.entries {
+            if self.options & BLOOM_OPT_NO_SCALING != 0 {
+                return Err(RedisAddError::Full);
+            }
+            let link = RedisLink::new(
+                last.entries * self.growth as u64,
+                last.error * ERROR_TIGHTENING_RATIO,
+                self.options,
+            );
+            self.links.push(link);
+        }
+
+        let last = self.links.last_mut().unwrap();
+        last.insert(hashes);
+        last.size += 1;
+        self.size += 1;
+        Ok(true)
+    }
+
+    pub fn header(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_BYTES + LINK_BYTES * self.links.len());
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out.extend_from_slice(&(self.links.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.options.to_le_bytes());
+        out.extend_from_slice(&self.growth.to_le_bytes());
+        for link in self.links.iter() {
+            out.extend_from_slice(&(link.bytes.len() as u64).to_le_bytes());
+            out.extend_from_slice(&link.bits.to_le_bytes());
+            out.extend_from_slice(&link.size.to_le_bytes());
+            out.extend_from_slice(&link.error.to_le_bytes());
+            out.extend_from_slice(&link.bpe.to_le_bytes());
+            out.extend_from_slice(&link.hashes.to_le_bytes());
+            out.extend_from_slice(&link.entries.to_le_bytes());
+            out.push(link.n2);
+        }
+        out
+    }
+
+    // all (iterator, data) pairs BF.SCANDUMP returns, header first
+    pub fn scandump(&self, max_chunk: usize) -> Vec<(u64, Vec<u8>)> {
+        let mut chunks = vec![(1, self.header())];
+        let mut offset = 0u64;
+        for link in self.links.iter() {
+            for chunk in link.bytes.chunks(max_chunk) {
+                offset += chunk.len() as u64;
+                chunks.push((offset + 1, chunk.to_vec()));
+            }
+        }
+        chunks
+    }
+
+    // rebuilds a chain from BF.SCANDUMP output, as BF.LOADCHUNK would
+    pub fn loadchunks(chunks: &[(u64, Vec<u8>)]) -> Result<Self, RedisDumpError> {
+        let (_, header) = chunks
+            .iter()
+            .find(|(iter, _)| *iter == 1)
+            .ok_or(RedisDumpError::MissingHeader)?;
+        let available = chunks
+            .iter()
+            .filter(|(iter, _)| *iter != 1)
+            .map(|(_, data)| data.len() as u64)
+            .sum();
+        let mut chain = Self::from_header(header, available)?;
+
+        for (iter, data) in chunks.iter().filter(|(iter, _)| *iter != 1) {
+            let end = iter
+                .checked_sub(1)
+                .ok_or(RedisDumpError::ChunkOutOfRange { iter: *iter })?;
+            let mut offset = end
+                .checked_sub(data.len() as u64)
+                .ok_or(RedisDumpError::ChunkOutOfRange { iter: *iter })?;
+            // chunks never span links
+            let link = chain
+                .links
+                .iter_mut()
+                .find(|link| {
+                    let len = link.bytes.len() as u64;
+                    if offset < len {
+                        true
+                    } else {
+                        offset -= len;
+                        false
+                    }
+                })
+                .ok_or(RedisDumpError::ChunkOutOfRange { iter: *iter })?;
+            let start = offset as usize;
+            link.bytes
+                .get_mut(start..start + data.len())
+                .ok_or(RedisDumpError::ChunkOutOfRange { iter: *iter })?
+                .copy_from_slice(data);
+        }
+        Ok(chain)
+    }
+
+    // the header's links, each checked so that queries stay within its
+    // bytes, with at most `available` bytes between them
+    fn from_header(header: &[u8], available: u64) -> Result<Self, RedisDumpError> {
+        let mut reader = Reader(header);
+        let size = reader.u64()?;
+        let nfilters = reader.u32()?;
+        let options = reader.u32()?;
+        let growth = reader.u32()?;
+        if nfilters == 0 {
+            return Err(RedisDumpError::NoLinks);
+        }
+        let mut total = 0u64;
+        let mut links = Vec::new();
+        for link in 0..nfilters as usize {
+            let invalid = |field| RedisDumpError::InvalidLink { link, field };
+            let bytes = reader.u64()?;
+            let bits = reader.u64()?;
+            let size = reader.u64()?;
+            let error = f64::from_bits(reader.u64()?);
+            let bpe = f64::from_bits(reader.u64()?);
+            let hashes = reader.u32()?;
+            let entries = reader.u64()?;
+            let n2 = reader.u8()?;
+            if bits == 0 {
+                return Err(invalid("bits"));
+            }
+            if bytes.checked_mul(8).is_none_or(|capacity| bits > capacity) {
+                return Err(invalid("bytes"));
+            }
+            if n2 >= 64 || (n2 > 0 && 1u64 << n2 > bits) {
+                return Err(invalid("n2"));
+            }
+            if hashes == 0 {
+                return Err(invalid("hashes"));
+            }
+            total = total.saturating_add(bytes);
+            if total > available {
+                return Err(RedisDumpError::LinksTooLarge {
+                    bytes: total,
+                    available,
+                });
+            }
+            links.push(RedisLink {
+                bytes: vec![0u8; bytes as usize],
+                bits,
+                size,
+                error,
+                bpe,
+                hashes,
+                entries,
+                n2,
+            });
+        }
+        Ok(Self {
+            links,
+            size,
+            options,
+            growth,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], RedisDumpError> {
+        if self.0.len() < N {
+            return Err(RedisDumpError::Truncated);
+        }
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, RedisDumpError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, RedisDumpError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, RedisDumpError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+}
+
+impl Filter for RedisBloomChain {
+    fn add(&mut self, element: &[u8]) {
+        self.insert(element).expect("non scaling filter is full");
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains(element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.links
+            .iter()
+            .flat_map(|link| link.bytes.iter())
+            .map(|b| b.count_ones())
+            .sum()
+    }
+}
+
+#[test]
+fn test_murmur64a() {
+    // Appleby's reference MurmurHash64A from SMHasher, which RedisBloom's
+    // contrib/MurmurHash2.c carries, at seed 0 and the seed of an element's
+    // first hash
+    let fox = b"The quick brown fox jumps over the lazy dog";
+    for (data, seed, hash) in [
+        (&b""[..], 0, 0),
+        (b"", 0xc6a4a7935bd1e995, 0x1ab11ea5a7b2c56e),
+        (b"a", 0, 0x071717d2d36b6b11),
+        (b"a", 0xc6a4a7935bd1e995, 0x4292cee227b9150a),
+        (b"hello", 0, 0x1e68d17c457bf117),
+        (b"hello", 0xc6a4a7935bd1e995, 0x5ba5b8a59803e699),
+        (b"hello world", 0, 0xd3ba2368a832afce),
+        (b"hello world", 0xc6a4a7935bd1e995, 0xbae8fb35317acde1),
+        (fox, 0, 0x5589ca33042a861b),
+        (fox, 0xc6a4a7935bd1e995, 0xc7a616a28f4a74d6),
+    ] {
+        assert_eq!(murmur64a(data, seed), hash, "{data:?} {seed:x}");
+    }
+}
+
+#[test]
+fn test_redis_link_rounding() {
+    // 9.59 bits per element for 100 is 958.5 bits, rounded up to 2^10
+    let link = RedisLink::new(100, 0.01, 0);
+    assert_eq!((link.n2, link.bits), (10, 1024));
+    // exactly 8 bits per element for 128 is exactly 2^10 bits, which
+    // RedisBloom's logb + 1 still doubles
+    let link = RedisLink::new(128, 0.021415847120683718, 0);
+    assert_eq!(link.bpe, 8.0);
+    assert_eq!((link.n2, link.bits), (11, 2048));
+    let link = RedisLink::new(128, 0.021415847120683718, BLOOM_OPT_NOROUND);
+    assert_eq!((link.n2, link.bits), (0, 1024));
+}
+
+#[test]
+fn test_redis_no_scaling() {
+    let mut chain = RedisBloomChain::with_options(10, 0.01, BLOOM_OPT_NO_SCALING, 2);
+    let mut added = 0;
+    let mut i = 0u32;
+    while added < 10 {
+        if chain.insert(&i.to_le_bytes()) == Ok(true) {
+            added += 1;
+        }
+        i += 1;
+    }
+    // present elements still answer, new ones don't fit
+    assert_eq!(chain.insert(&0u32.to_le_bytes()), Ok(false));
+    assert_eq!(chain.insert(b"one more"), Err(RedisAddError::Full));
+    assert_eq!((chain.links.len(), chain.size), (1, 10));
+}
+
+#[test]
+fn test_redis_scandump_roundtrip() {
+    let mut chain = RedisBloomChain::new(100, 0.01);
+    let inserted = (0..500u32)
+        .filter(|i| chain.insert(&i.to_le_bytes()).unwrap())
+        .count();
+    // the rest were false positives
+    assert!(inserted > 490);
+    assert_eq!(chain.size, inserted as u64);
+    assert!(chain.links.len() > 1, "chain scaled");
+    assert_eq!(chain.insert(&7u32.to_le_bytes()), Ok(false));
+
+    let chunks = chain.scandump(64);
+    assert_eq!(chunks[0].0, 1);
+    let loaded = RedisBloomChain::loadchunks(&chunks).unwrap();
+    assert_eq!(loaded, chain);
+    assert!((0..500u32).all(|i| loaded.contains(&i.to_le_bytes())));
+
+    assert_eq!(
+        RedisBloomChain::loadchunks(&chunks[1..]),
+        Err(RedisDumpError::MissingHeader)
+    );
+    assert_eq!(
+        RedisBloomChain::loadchunks(&[(1, chunks[0].1[..10].to_vec())]),
+        Err(RedisDumpError::Truncated)
+    );
+}
+
+#[test]
+fn test_redis_hostile_dumps() {
+    let mut chain = RedisBloomChain::new(100, 0.01);
+    chain.insert(b"one").unwrap();
+    let chunks = chain.scandump(64);
+    // the first link's fields start after the chain's, in header order
+    let with_field = |offset: usize, value: &[u8]| {
+        let mut chunks = chunks.clone();
+        chunks[0].1[HEADER_BYTES + offset..HEADER_BYTES + offset + value.len()]
+            .copy_from_slice(value);
+        RedisBloomChain::loadchunks(&chunks)
+    };
+    let invalid = |field| Err(RedisDumpError::InvalidLink { link: 0, field });
+    let bytes = chain.links[0].bytes.len() as u64;
+
+    assert_eq!(with_field(8, &0u64.to_le_bytes()), invalid("bits"));
+    assert_eq!(
+        with_field(8, &(bytes * 8 + 1).to_le_bytes()),
+        invalid("bytes")
+    );
+    assert_eq!(with_field(LINK_BYTES - 1, &[64]), invalid("n2"));
+    // a 2^10 bit mask over fewer bits
+    assert_eq!(with_field(LINK_BYTES - 1, &[10]), invalid("n2"));
+    assert_eq!(with_field(40, &0u32.to_le_bytes()), invalid("hashes"));
+    assert_eq!(
+        with_field(0, &(1u64 << 46).to_le_bytes()),
+        Err(RedisDumpError::LinksTooLarge {
+            bytes: 1 << 46,
+            available: bytes
+        })
+    );
+    let mut no_links = chunks.clone();
+    no_links[0].1[8..12].copy_from_slice(&0u32.to_le_bytes());
+    assert_eq!(
+        RedisBloomChain::loadchunks(&no_links),
+        Err(RedisDumpError::NoLinks)
+    );
+    let mut iter_zero = chunks.clone();
+    iter_zero[1].0 = 0;
+    assert_eq!(
+        RedisBloomChain::loadchunks(&iter_zero),
+        Err(RedisDumpError::ChunkOutOfRange { iter: 0 })
+    );
+}
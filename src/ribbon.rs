@@ -0,0 +1,152 @@
+use xxhash_rust::xxh3::xxh3_128_with_seed;
+
+use crate::xor::Fingerprint;
+
+// Standard ribbon filters (Dillinger and Walzer, 2021): static filters that
+// solve for a fingerprint per slot such that each element's fingerprint is
+// the XOR of the slots its 64-bit coefficient row selects, starting at a
+// hashed slot. Rows are added by banded Gaussian elimination, each one
+// XOR-ed with the row already pivoted on its first set bit until it finds a
+// free pivot; back substitution then fills the slots from the end. About
+// 1.08 * 8 bits per element for `Ribbon8`, plus the 64 slots the last band
+// reaches past.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RibbonFilter<F> {
+    seed: u64,
+    // distinct elements it was built from
+    len: usize,
+    solution: Vec<F>,
+}
+
+pub type Ribbon8 = RibbonFilter<u8>;
+pub type Ribbon16 = RibbonFilter<u16>;
+
+// bits per coefficient row
+const WIDTH: usize = 64;
+
+// slots per element besides the trailing `WIDTH`
+const OVERHEAD: f64 = 1.08;
+
+const MAX_SEEDS: u64 = 100;
+
+// (first slot, coefficients, fingerprint) of an element's row; bit j of
+// the coefficients selects slot start + j, and bit 0 is always set
+fn row<F: Fingerprint>(element: &[u8], seed: u64, starts: usize) -> (usize, u64, F) {
+    let hash = xxh3_128_with_seed(element, seed);
+    let (low, high) = (hash as u64, (hash >> 64) as u64);
+    let start = ((low as u32 as u64 * starts as u64) >> 32) as usize;
+    (start, high | 1, F::from_hash(low >> 32))
+}
+
+impl<F: Fingerprint> RibbonFilter<F> {
+    pub fn build<E: AsRef<[u8]>>(elements: impl IntoIterator<Item = E>) -> Self {
+        let elements: Vec<E> = elements.into_iter().collect();
+        let slots = (OVERHEAD * elements.len() as f64).ceil() as usize + WIDTH;
+        'seeds: for seed in 0..MAX_SEEDS {
+            let mut coefficients = vec![0u64; slots];
+            let mut results = vec![F::default(); slots];
+            let mut len = 0;
+            for element in &elements {
+                let (mut start, mut coefficient, mut result) =
+                    row::<F>(element.as_ref(), seed, slots - WIDTH + 1);
+                loop {
+                    if coefficients[start] == 0 {
+                        coefficients[start] = coefficient;
+                        results[start] = result;
+                        len += 1;
+                        break;
+                    }
+                    coefficient ^= coefficients[start];
+                    result = result ^ results[start];
+                    if coefficient == 0 {
+                        // a repeated element agrees with itself, anything
+                        // else makes the system unsolvable for this seed
+                        if result == F::default() {
+                            break;
+                        }
+                        continue 'seeds;
+                    }
+                    let shift = coefficient.trailing_zeros();
+                    start += shift as usize;
+                    coefficient >>= shift;
+                }
+            }
+
+            // slots without a pivot are free, zero works
+            let mut solution = vec![F::default(); slots];
+            for i in (0..slots).rev() {
+                let mut value = results[i];
+                let mut rest = coefficients[i] >> 1;
+                while rest != 0 {
+                    let j = rest.trailing_zeros() as usize + 1;
+                    value = value ^ solution[i + j];
+                    rest &= rest - 1;
+                }
+                solution[i] = value;
+            }
+            return Self {
+                seed,
+                len,
+                solution,
+            };
+        }
+        panic!("ribbon filter construction failed for {MAX_SEEDS} seeds");
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        let (start, mut coefficient, fingerprint) =
+            row::<F>(element, self.seed, self.solution.len() - WIDTH + 1);
+        let mut value = F::default();
+        while coefficient != 0 {
+            value = value ^ self.solution[start + coefficient.trailing_zeros() as usize];
+            coefficient &= coefficient - 1;
+        }
+        value == fingerprint
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn size_bits(&self) -> usize {
+        self.solution.len() * F::BITS as usize
+    }
+
+    pub fn bits_per_element(&self) -> f64 {
+        self.size_bits() as f64 / self.len as f64
+    }
+}
+
+#[test]
+fn test_ribbon_filters() {
+    use crate::element::encode_u64;
+
+    let elements: Vec<[u8; 8]> = (0..10_000u64).map(encode_u64).collect();
+    let ribbon8 = Ribbon8::build(&elements);
+    let ribbon16 = Ribbon16::build(&elements);
+    assert_eq!(ribbon8.len(), 10_000);
+    assert!(elements
+        .iter()
+        .all(|e| ribbon8.contains(e) && ribbon16.contains(e)));
+    assert!(ribbon8.bits_per_element() < 9.0);
+
+    // 2^-8 and 2^-16 of 100_000 probes are about 391 and 1.5
+    let probes = (10_000..110_000u64).map(encode_u64);
+    let (fp8, fp16) = probes.fold((0, 0), |(fp8, fp16), probe| {
+        (
+            fp8 + ribbon8.contains(&probe) as u32,
+            fp16 + ribbon16.contains(&probe) as u32,
+        )
+    });
+    assert!((250..550).contains(&fp8), "{fp8}");
+    assert!(fp16 < 10, "{fp16}");
+
+    let twice = Ribbon8::build(elements[..5].iter().chain(&elements[..5]));
+    assert_eq!(twice.len(), 5);
+    assert!(elements[..5].iter().all(|e| twice.contains(e)));
+    assert!(Ribbon8::build(Vec::<&[u8]>::new()).is_empty());
+}
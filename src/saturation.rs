@@ -0,0 +1,241 @@
+use crate::{
+    bloom_indices_from_hashes,
+    cow::CowFilter,
+    filter::Filter,
+    iterators::DistinctSampling,
+    xof::{Blake3XOF, Xof},
+    Bloom,
+};
+
+// The accumulator's threshold for 2048-bit filters, kept so saturated filters
+// stay identical to the JS and Go implementations. Other shapes derive their
+// threshold from a target density instead.
+const ACCUMULATOR_THRESHOLD: u32 = 1019;
+
+// what a call to `saturate` did
+#[derive(Debug, PartialEq, Eq)]
+pub enum Saturation {
+    // nothing could be added without exceeding the threshold
+    AlreadySaturated,
+    // stopped because the next element would have exceeded the threshold,
+    // for exact saturation `added` counts bits instead of elements
+    Saturated { added: usize },
+    // stopped after `attempts` elements without reaching the threshold,
+    // e.g. because the threshold is at or beyond the filter's size
+    GaveUp { added: usize, attempts: usize },
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // Upper bound on elements tried. Reaching any reachable threshold takes
+    // far fewer, even for K = 1 at high densities.
+    const MAX_SATURATION_ATTEMPTS: usize = 16 * M * 8 + 1024;
+
+    pub fn saturate(&mut self) -> Saturation {
+        if M * 8 == 2048 {
+            self.saturate_to_threshold(ACCUMULATOR_THRESHOLD)
+        } else {
+            self.saturate_to(0.5)
+        }
+    }
+
+    // saturate so that the expected density afterwards is `target_ratio`
+    pub fn saturate_to(&mut self, target_ratio: f64) -> Saturation {
+        self.saturate_to_threshold(saturation_threshold(M * 8, K, target_ratio))
+    }
+
+    // keep adding pseudo-random elements until the next one would set more than `threshold` bits
+    pub fn saturate_to_threshold(&mut self, threshold: u32) -> Saturation {
+        if self.count_ones() >= threshold {
+            return Saturation::AlreadySaturated;
+        }
+
+        let mut xof = Blake3XOF::derived("nyberg accumulator saturation", &self.bytes);
+        let mut buffer = [0u8; 32];
+
+        let mut current = CowFilter::new(self.clone());
+        let mut added = 0;
+        for _ in 0..Self::MAX_SATURATION_ATTEMPTS {
+            xof.fill(&mut buffer);
+            // only copies the bytes if the element sets new bits
+            let mut branch = current.clone();
+            Filter::add(&mut branch, &buffer);
+            if Filter::count_ones(&branch) > threshold {
+                *self = current.into_inner();
+                return match added {
+                    0 => Saturation::AlreadySaturated,
+                    added => Saturation::Saturated { added },
+                };
+            }
+            current = branch;
+            added += 1;
+        }
+
+        *self = current.into_inner();
+        Saturation::GaveUp {
+            added,
+            attempts: Self::MAX_SATURATION_ATTEMPTS,
+        }
+    }
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // Sets bits one at a time from a distinct index stream seeded by the
+    // current bytes until exactly `target_ones` are set. Unlike element-wise
+    // saturation, the resulting density is fixed and the result is canonical
+    // for a given input filter.
+    pub fn saturate_exact(&mut self, target_ones: u32) -> Saturation {
+        let mut ones = self.count_ones();
+        if ones >= target_ones {
+            return Saturation::AlreadySaturated;
+        }
+        if target_ones as usize > M * 8 {
+            return Saturation::GaveUp {
+                added: 0,
+                attempts: 0,
+            };
+        }
+
+        let xof = Blake3XOF::derived("nyberg accumulator exact saturation", &self.bytes);
+        let indices =
+            DistinctSampling::distinct(bloom_indices_from_hashes(xof.u64s(), M * 8, usize::MAX));
+        let mut added = 0;
+        for index in indices {
+            if !self.test_bit(index) {
+                self.set_bit(index);
+                ones += 1;
+                added += 1;
+                if ones == target_ones {
+                    break;
+                }
+            }
+        }
+        Saturation::Saturated { added }
+    }
+
+    pub fn saturate_exact_to(&mut self, target_ratio: f64) -> Saturation {
+        self.saturate_exact((target_ratio * (M * 8) as f64).round() as u32)
+    }
+}
+
+// Saturation stops below the threshold, by a gap depending on how many bits
+// the rejected element would have set. Near density d, an element sets about
+// J ~ Binomial(k, 1 - d) new bits (ignoring collisions between its own
+// indices). Elements setting no new bits don't end the loop, so the gap is the
+// residual of a renewal process with jumps J | J >= 1:
+// P(gap = g) = P(J > g) / E[J], for g in 0..k.
+pub fn saturation_gap_distribution(k: usize, density: f64) -> Vec<f64> {
+    let p = 1.0 - density;
+    if p <= 0.0 {
+        // limit for a full filter: the rare new bit comes alone
+        let mut distribution = vec![0.0; k];
+        distribution[0] = 1.0;
+        return distribution;
+    }
+    let mut jump = vec![0.0; k + 1];
+    for (j, probability) in jump.iter_mut().enumerate() {
+        *probability = binomial(k, j) * p.powi(j as i32) * density.powi((k - j) as i32);
+    }
+    // condition on at least one new bit
+    let nonzero = 1.0 - jump[0];
+    jump[0] = 0.0;
+    jump.iter_mut()
+        .for_each(|probability| *probability /= nonzero);
+
+    let mean_jump: f64 = jump.iter().enumerate().map(|(j, p)| j as f64 * p).sum();
+    (0..k)
+        .map(|gap| jump[gap + 1..].iter().sum::<f64>() / mean_jump)
+        .collect()
+}
+
+pub fn expected_saturation_gap(k: usize, density: f64) -> f64 {
+    saturation_gap_distribution(k, density)
+        .iter()
+        .enumerate()
+        .map(|(gap, p)| gap as f64 * p)
+        .sum()
+}
+
+// bit threshold at which the expected ones-count after saturation is target_ratio * m_bits
+pub fn saturation_threshold(m_bits: usize, k: usize, target_ratio: f64) -> u32 {
+    let target = target_ratio * m_bits as f64;
+    (target + expected_saturation_gap(k, target_ratio)).round() as u32
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+#[test]
+fn test_saturation_threshold() {
+    let distribution = saturation_gap_distribution(30, 0.5);
+    assert_eq!(distribution.len(), 30);
+    assert!((distribution.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+    let threshold = saturation_threshold(2048, 30, 0.5);
+    assert!(threshold > 1024 && threshold < 1024 + 30);
+    assert_eq!(saturation_threshold(2048, 1, 0.5), 1024);
+}
+
+#[test]
+fn test_saturate_to() {
+    let runs = 200;
+    let mut sum = 0;
+    for i in 0..runs {
+        let mut bloom: Bloom<512, 20> = Bloom::new();
+        bloom.add(&(i as u64).to_le_bytes());
+        bloom.saturate_to(0.5);
+        sum += bloom.count_ones();
+    }
+    let mean = sum as f64 / runs as f64;
+    assert!(
+        (mean - 2048.0).abs() < 3.0,
+        "mean ones after saturation {mean}"
+    );
+}
+
+#[test]
+fn test_saturation_outcomes() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    assert!(matches!(bloom.saturate(), Saturation::Saturated { added } if added > 0));
+    assert_eq!(bloom.saturate(), Saturation::AlreadySaturated);
+
+    let mut full: Bloom<16, 3> = Bloom::from_bytes([0xff; 16]);
+    assert_eq!(full.saturate_to(0.5), Saturation::AlreadySaturated);
+
+    // thresholds the filter can never exceed
+    let mut tiny: Bloom<1, 1> = Bloom::new();
+    assert!(matches!(
+        tiny.saturate_to_threshold(8),
+        Saturation::GaveUp { added, .. } if added > 0
+    ));
+    assert_eq!(tiny.count_ones(), 8);
+
+    let mut small: Bloom<4, 2> = Bloom::new();
+    assert!(matches!(small.saturate_to(1.0), Saturation::GaveUp { .. }));
+}
+
+#[test]
+fn test_saturate_exact() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    bloom.add(b"Hello, World");
+    let mut copy = bloom.clone();
+
+    assert!(matches!(
+        bloom.saturate_exact_to(0.5),
+        Saturation::Saturated { .. }
+    ));
+    assert_eq!(bloom.count_ones(), 1024);
+    assert!(bloom.has(b"Hello, World"));
+    assert_eq!(bloom.saturate_exact(1024), Saturation::AlreadySaturated);
+
+    copy.saturate_exact(1024);
+    assert_eq!(copy.bytes, bloom.bytes);
+
+    let mut small: Bloom<4, 2> = Bloom::new();
+    assert!(matches!(
+        small.saturate_exact(33),
+        Saturation::GaveUp { .. }
+    ));
+    small.saturate_exact(32);
+    assert_eq!(small.count_ones(), 32);
+}
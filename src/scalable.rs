@@ -0,0 +1,208 @@
+use crate::{dyn_bloom::DynBloom, entropy::EntropySource, filter::Filter, params::FilterSpec};
+
+// Scalable Bloom filter (Almeida et al., 2007): a chain of `DynBloom`
+// stages, each sized by `params` for its own capacity and false positive
+// rate. Once the newest stage holds its capacity, the next one is `growth`
+// times larger at `tightening` times its rate. Starting from fpr * (1 -
+// tightening), the stage rates sum to at most fpr however many stages
+// follow, so the filter keeps its bound past the capacity it started with,
+// at the cost of probing every stage per query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalableBloom {
+    stages: Vec<Stage>,
+    growth: u64,
+    tightening: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Stage {
+    filter: DynBloom,
+    capacity: u64,
+    fpr: f64,
+    len: u64,
+}
+
+pub const GROWTH: u64 = 2;
+pub const TIGHTENING: f64 = 0.9;
+
+impl Stage {
+    fn new(capacity: u64, fpr: f64) -> Self {
+        Self {
+            filter: DynBloom::from_spec(FilterSpec::new(capacity, fpr)),
+            capacity,
+            fpr,
+            len: 0,
+        }
+    }
+}
+
+impl ScalableBloom {
+    // first stage for initial_capacity elements, all of them together within
+    // fpr
+    pub fn new(initial_capacity: u64, fpr: f64) -> Self {
+        Self::with_ratios(initial_capacity, fpr, GROWTH, TIGHTENING)
+    }
+
+    pub fn with_ratios(initial_capacity: u64, fpr: f64, growth: u64, tightening: f64) -> Self {
+        assert!(growth >= 1, "stages can't shrink");
+        assert!(
+            tightening > 0.0 && tightening < 1.0,
+            "stage rates need 0 < tightening < 1"
+        );
+        Self {
+            stages: vec![Stage::new(initial_capacity, fpr * (1.0 - tightening))],
+            growth,
+            tightening,
+        }
+    }
+
+    // Adds element to the newest stage, opening a new one if that's full.
+    // An element some stage already has isn't added again, so repeats don't
+    // use up capacity; false if it was there.
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        if self.has(element) {
+            return false;
+        }
+        let last = self.stages.last().unwrap();
+        if last.len >= last.capacity {
+            let next = Stage::new(last.capacity * self.growth, last.fpr * self.tightening);
+            self.stages.push(next);
+        }
+        let last = self.stages.last_mut().unwrap();
+        last.filter.add(element);
+        last.len += 1;
+        true
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.stages.iter().any(|stage| stage.filter.has(element))
+    }
+
+    // elements added, repeats counted once
+    pub fn len(&self) -> u64 {
+        self.stages.iter().map(|stage| stage.len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // elements the current stages hold before another one opens
+    pub fn capacity(&self) -> u64 {
+        self.stages.iter().map(|stage| stage.capacity).sum()
+    }
+
+    pub fn stages(&self) -> impl Iterator<Item = &DynBloom> {
+        self.stages.iter().map(|stage| &stage.filter)
+    }
+
+    pub fn size_bits(&self) -> usize {
+        self.stages().map(DynBloom::m_bits).sum()
+    }
+
+    // Bound on the false positive rate once every stage is full, 1 minus the
+    // chance that none of them matches. Below the fpr it was built for.
+    pub fn fpr_bound(&self) -> f64 {
+        1.0 - self
+            .stages
+            .iter()
+            .map(|stage| 1.0 - stage.fpr)
+            .product::<f64>()
+    }
+}
+
+impl Filter for ScalableBloom {
+    fn add(&mut self, element: &[u8]) {
+        ScalableBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        ScalableBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.stages().map(DynBloom::count_ones).sum()
+    }
+}
+
+// False positives among `PROBES` fresh elements as a scalable filter grows
+// to 64 times the capacity it started with, next to a `DynBloom` sized for
+// that starting capacity only. Prints a row every `STEP` inserts, to plot
+// false positive rates against inserts for both.
+pub fn test_scalable_rates(mut entropy: impl EntropySource) {
+    const CAPACITY: u64 = 1000;
+    const FPR: f64 = 0.01;
+    const STEP: u64 = 1000;
+    const PROBES: usize = 100_000;
+
+    let mut scalable = ScalableBloom::new(CAPACITY, FPR);
+    let mut fixed = DynBloom::from_spec(FilterSpec::new(CAPACITY, FPR));
+    println!("inserts;stages;scalable_bits;scalable_fpr;fixed_bits;fixed_fpr");
+    for inserts in (STEP..=64 * CAPACITY).step_by(STEP as usize) {
+        for _ in 0..STEP {
+            let element = entropy.block::<32>();
+            scalable.add(&element);
+            fixed.add(&element);
+        }
+        let (mut scalable_false_positives, mut fixed_false_positives) = (0, 0);
+        for _ in 0..PROBES {
+            let probe = entropy.block::<32>();
+            scalable_false_positives += scalable.has(&probe) as u32;
+            fixed_false_positives += fixed.has(&probe) as u32;
+        }
+        println!(
+            "{inserts};{};{};{};{};{}",
+            scalable.stages().count(),
+            scalable.size_bits(),
+            scalable_false_positives as f64 / PROBES as f64,
+            fixed.m_bits(),
+            fixed_false_positives as f64 / PROBES as f64,
+        );
+    }
+}
+
+#[test]
+fn test_scalable_bloom() {
+    use crate::element::encode_u64;
+
+    let mut filter = ScalableBloom::new(1000, 0.01);
+    assert!(filter.is_empty());
+    assert_eq!(filter.stages().count(), 1);
+    for i in 0..1000u64 {
+        assert!(filter.add(&encode_u64(i)));
+    }
+    assert_eq!(filter.stages().count(), 1);
+    assert!(!filter.add(&encode_u64(7)));
+
+    // 1000 + 2000 + 4000 + 8000 hold 15_000, the fifth stage opens after.
+    // A few adds are false positives of earlier stages and don't count.
+    let mut next = 1000u64;
+    while filter.len() < 15_000 {
+        filter.add(&encode_u64(next));
+        next += 1;
+    }
+    assert!(next < 15_050, "{next}");
+    assert_eq!(filter.stages().count(), 4);
+    assert_eq!(filter.capacity(), 15_000);
+    while !filter.add(&encode_u64(next)) {
+        next += 1;
+    }
+    assert_eq!(filter.stages().count(), 5);
+    assert!((0..=next).all(|i| filter.has(&encode_u64(i))));
+
+    // 0.001 + 0.0009 + ... + 0.00065 with all stages full, and the actual
+    // rate near it with the last stage barely started
+    assert!(filter.fpr_bound() < 0.01);
+    let false_positives = (1 << 32..(1 << 32) + 100_000u64)
+        .filter(|&i| filter.has(&encode_u64(i)))
+        .count();
+    assert!(false_positives < 600, "{false_positives}");
+
+    // growth 1 and tightening 0.5 still keep below the bound
+    let mut flat = ScalableBloom::with_ratios(100, 0.01, 1, 0.5);
+    for i in 0..1000u64 {
+        flat.add(&encode_u64(i));
+    }
+    assert_eq!(flat.capacity(), 1000);
+    assert!(flat.fpr_bound() < 0.01);
+}
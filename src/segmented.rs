@@ -0,0 +1,246 @@
+use std::io::{self, Write};
+
+use crate::{
+    envelope::{Envelope, EnvelopeError, FilterKind},
+    filter::Filter,
+    strategy::HashStrategy,
+    wide_indices_for_element,
+};
+
+// A filter of m bits kept in segments of 2^shift bits each instead of one
+// allocation, for sizes no single array holds. Index i lives in segment
+// i >> shift at bit i mod 2^shift, so the segments laid end to end are
+// exactly `DynBloom::new(m_bits, k)`'s bits: same indices, same envelope,
+// and either can read what the other wrote. Only the last segment may be
+// shorter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegmentedBloom {
+    segments: Vec<Vec<u8>>,
+    m_bits: u64,
+    k: usize,
+    shift: u32,
+}
+
+// xxh3 rejection sampling's limit, as for `DynBloom`
+const MAX_BITS: u64 = 1 << 61;
+
+// segment lengths in bytes for m bits in segments of 2^shift bits
+fn segment_lengths(m_bits: u64, shift: u32) -> impl Iterator<Item = usize> {
+    let segment_bits = 1u64 << shift;
+    (0..m_bits.div_ceil(segment_bits)).map(move |segment| {
+        let bits = (m_bits - segment * segment_bits).min(segment_bits);
+        bits.div_ceil(8) as usize
+    })
+}
+
+impl SegmentedBloom {
+    // Segments hold whole bytes, so shift is at least 3; 30 gives 128 MiB
+    // segments.
+    pub fn new(m_bits: u64, k: usize, shift: u32) -> Self {
+        assert!(m_bits > 0, "SegmentedBloom needs at least one bit");
+        assert!(k > 0, "SegmentedBloom needs at least one hash function");
+        assert!(
+            k as u64 <= m_bits,
+            "SegmentedBloom can't set more indices than bits"
+        );
+        assert!(m_bits <= MAX_BITS, "SegmentedBloom holds at most 2^61 bits");
+        assert!((3..64).contains(&shift), "segments are 2^3 to 2^63 bits");
+        Self {
+            segments: segment_lengths(m_bits, shift)
+                .map(|len| vec![0; len])
+                .collect(),
+            m_bits,
+            k,
+            shift,
+        }
+    }
+
+    pub fn m_bits(&self) -> u64 {
+        self.m_bits
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn segments(&self) -> &[Vec<u8>] {
+        &self.segments
+    }
+
+    // like `DynBloom::indices_for`, as u64 since m may exceed usize
+    pub fn indices_for(&self, element: &[u8]) -> Vec<u64> {
+        wide_indices_for_element(element, self.m_bits, self.k).collect()
+    }
+
+    // (segment, byte in it, bit in that byte)
+    fn locate(&self, index: u64) -> (usize, usize, u32) {
+        let offset = index & ((1 << self.shift) - 1);
+        (
+            (index >> self.shift) as usize,
+            (offset / 8) as usize,
+            (offset % 8) as u32,
+        )
+    }
+
+    // true if this set a bit, like `Bloom::add`
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let mut new = false;
+        for index in wide_indices_for_element(element, self.m_bits, self.k) {
+            let (segment, byte, bit) = self.locate(index);
+            let byte = &mut self.segments[segment][byte];
+            new |= *byte & (1 << bit) == 0;
+            *byte |= 1 << bit;
+        }
+        new
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        wide_indices_for_element(element, self.m_bits, self.k).all(|index| {
+            let (segment, byte, bit) = self.locate(index);
+            self.segments[segment][byte] & (1 << bit) != 0
+        })
+    }
+
+    // u64, as m may exceed u32::MAX
+    pub fn count_ones(&self) -> u64 {
+        self.segments
+            .iter()
+            .flatten()
+            .map(|byte| byte.count_ones() as u64)
+            .sum()
+    }
+
+    // the `DynBloom` envelope, written a segment at a time
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let header = Envelope {
+            kind: FilterKind::Dyn,
+            m_bits: self.m_bits,
+            k: self.k as u32,
+            fold_level: 0,
+            strategy: HashStrategy::Xxh3Rejection,
+            seed: &[],
+            payload: &[],
+        }
+        .encode();
+        out.write_all(&header)?;
+        for segment in &self.segments {
+            out.write_all(segment)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)
+            .expect("writing to a Vec doesn't fail");
+        out
+    }
+
+    // an xxh3 rejection indexed `DynBloom` envelope, split into segments of
+    // 2^shift bits
+    pub fn try_from_bytes(data: &[u8], shift: u32) -> Result<Self, EnvelopeError> {
+        let envelope = Envelope::decode(data)?;
+        if envelope.kind != FilterKind::Dyn {
+            return Err(EnvelopeError::Mismatch("kind"));
+        }
+        if envelope.strategy != HashStrategy::Xxh3Rejection || !envelope.seed.is_empty() {
+            return Err(EnvelopeError::Mismatch("strategy"));
+        }
+        if envelope.fold_level != 0 {
+            return Err(EnvelopeError::ParameterMismatch {
+                parameter: "fold level",
+                expected: 0,
+                found: envelope.fold_level as u64,
+            });
+        }
+        let m_bits = envelope.m_bits;
+        if m_bits == 0 || envelope.k == 0 || envelope.k as u64 > m_bits || m_bits > MAX_BITS {
+            return Err(EnvelopeError::Mismatch("m"));
+        }
+        if !(3..64).contains(&shift) {
+            return Err(EnvelopeError::Mismatch("segment size"));
+        }
+        if m_bits % 8 != 0 && envelope.payload.last().unwrap() >> (m_bits % 8) != 0 {
+            return Err(EnvelopeError::Mismatch("padding"));
+        }
+        let mut rest = envelope.payload;
+        let segments = segment_lengths(m_bits, shift)
+            .map(|len| {
+                let (segment, tail) = rest.split_at(len);
+                rest = tail;
+                segment.to_vec()
+            })
+            .collect();
+        Ok(Self {
+            segments,
+            m_bits,
+            k: envelope.k as usize,
+            shift,
+        })
+    }
+}
+
+impl Filter for SegmentedBloom {
+    fn add(&mut self, element: &[u8]) {
+        SegmentedBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        SegmentedBloom::has(self, element)
+    }
+
+    // saturates past u32::MAX set bits
+    fn count_ones(&self) -> u32 {
+        SegmentedBloom::count_ones(self).min(u32::MAX as u64) as u32
+    }
+}
+
+#[test]
+fn test_segmented_matches_flat() {
+    use crate::dyn_bloom::DynBloom;
+
+    // 1001 bits in 64-bit segments: 15 full ones and 41 bits left over
+    let mut segmented = SegmentedBloom::new(1001, 7, 6);
+    let mut flat = DynBloom::new(1001, 7);
+    assert_eq!(segmented.segments().len(), 16);
+    assert_eq!(segmented.segments()[15].len(), 6);
+    for i in 0..100u32 {
+        assert_eq!(segmented.add(&i.to_le_bytes()), flat.add(&i.to_le_bytes()));
+    }
+    assert_eq!(segmented.segments().concat(), flat.bytes());
+    assert_eq!(segmented.count_ones(), flat.count_ones() as u64);
+    assert!((0..200u32).all(|i| segmented.has(&i.to_le_bytes()) == flat.has(&i.to_le_bytes())));
+
+    assert_eq!(segmented.to_bytes(), flat.to_bytes());
+    let resegmented = SegmentedBloom::try_from_bytes(&flat.to_bytes(), 9).unwrap();
+    assert_eq!(resegmented.segments().len(), 2);
+    assert_eq!(resegmented.segments().concat(), flat.bytes());
+    assert_eq!(DynBloom::try_from_bytes(&resegmented.to_bytes()), Ok(flat));
+
+    let keyed = DynBloom::with_indexing(
+        64,
+        2,
+        crate::dyn_bloom::DynIndexing::SipDouble { keys: [[0; 16]; 2] },
+    );
+    assert_eq!(
+        SegmentedBloom::try_from_bytes(&keyed.to_bytes(), 6),
+        Err(EnvelopeError::Mismatch("strategy"))
+    );
+}
+
+#[test]
+fn test_segmented_beyond_u32_bits() {
+    // 2^33 bits in 2^30 bit segments, each allocation 128 MiB
+    let m_bits = 1u64 << 33;
+    let indices: Vec<u64> = wide_indices_for_element(b"far", m_bits, 4).collect();
+    assert!(indices.iter().any(|&index| index > u32::MAX as u64));
+    let mut segmented = SegmentedBloom::new(m_bits, 4, 30);
+    assert_eq!(segmented.segments().len(), 8);
+    assert!(segmented.add(b"far"));
+    assert!(segmented.has(b"far") && !segmented.has(b"near"));
+    for index in indices {
+        let (segment, byte, bit) = segmented.locate(index);
+        assert_eq!(segment as u64, index >> 30);
+        assert!(segmented.segments()[segment][byte] & (1 << bit) != 0);
+    }
+}
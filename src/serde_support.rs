@@ -0,0 +1,253 @@
+use std::fmt;
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{Bloom, Folded};
+
+// Serde for the const-generic filters: a struct of the parameters and the
+// bits, `Bloom { m_bits, k, bytes }` and `Folded { fold, m_bits, k, bytes }`
+// with m_bits the unfolded size. Deserializing checks every parameter
+// against the type it's read into, so a filter can't come back as one with
+// another size or K and silently answer wrong.
+
+impl<const M: usize, const K: usize> Serialize for Bloom<M, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Bloom", 3)?;
+        state.serialize_field("m_bits", &(M * 8))?;
+        state.serialize_field("k", &K)?;
+        state.serialize_field("bytes", &Bytes(&self.bytes))?;
+        state.end()
+    }
+}
+
+impl<'de, const M: usize, const K: usize> Deserialize<'de> for Bloom<M, K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = deserializer.deserialize_struct(
+            "Bloom",
+            BLOOM_FIELDS,
+            FieldsVisitor { fold: false },
+        )?;
+        let bytes = fields.check(0, M * 8, K)?;
+        Ok(Bloom::from_bytes(bytes))
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> Serialize for Folded<F, S, K> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut state = serializer.serialize_struct("Folded", 4)?;
+        state.serialize_field("fold", &F)?;
+        state.serialize_field("m_bits", &((S * 8) << F))?;
+        state.serialize_field("k", &K)?;
+        state.serialize_field("bytes", &Bytes(&self.bytes))?;
+        state.end()
+    }
+}
+
+impl<'de, const F: usize, const S: usize, const K: usize> Deserialize<'de> for Folded<F, S, K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = deserializer.deserialize_struct(
+            "Folded",
+            FOLDED_FIELDS,
+            FieldsVisitor { fold: true },
+        )?;
+        let bytes = fields.check(F, (S * 8) << F, K)?;
+        Ok(Folded::from_bytes(bytes))
+    }
+}
+
+// serialize_bytes, so binary formats store them as one blob
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+// what a self-describing format like JSON gives back for `Bytes`: a blob,
+// or a sequence of numbers
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+struct ByteBufVisitor;
+
+impl<'de> Visitor<'de> for ByteBufVisitor {
+    type Value = ByteBuf;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("filter bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<ByteBuf, E> {
+        Ok(ByteBuf(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<ByteBuf, E> {
+        Ok(ByteBuf(bytes))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ByteBuf, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(ByteBuf(bytes))
+    }
+}
+
+const BLOOM_FIELDS: &[&str] = &["m_bits", "k", "bytes"];
+const FOLDED_FIELDS: &[&str] = &["fold", "m_bits", "k", "bytes"];
+
+// the fields as read, before they're checked against the target type
+struct Fields {
+    fold: usize,
+    m_bits: usize,
+    k: usize,
+    bytes: Vec<u8>,
+}
+
+impl Fields {
+    fn check<E: de::Error, const N: usize>(
+        self,
+        fold: usize,
+        m_bits: usize,
+        k: usize,
+    ) -> Result<[u8; N], E> {
+        if self.fold != fold {
+            return Err(E::custom(format!(
+                "filter is folded {} times, expected {fold}",
+                self.fold
+            )));
+        }
+        if self.m_bits != m_bits {
+            return Err(E::custom(format!(
+                "filter has {} bits, expected {m_bits}",
+                self.m_bits
+            )));
+        }
+        if self.k != k {
+            return Err(E::custom(format!(
+                "filter has k = {}, expected {k}",
+                self.k
+            )));
+        }
+        let len = self.bytes.len();
+        self.bytes
+            .try_into()
+            .map_err(|_| E::invalid_length(len, &format!("{N} bytes").as_str()))
+    }
+}
+
+// `Bloom` has no fold field, which reads as 0
+struct FieldsVisitor {
+    fold: bool,
+}
+
+impl FieldsVisitor {
+    fn names(&self) -> &'static [&'static str] {
+        if self.fold {
+            FOLDED_FIELDS
+        } else {
+            BLOOM_FIELDS
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for FieldsVisitor {
+    type Value = Fields;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a filter with fields {:?}", self.names())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Fields, A::Error> {
+        let mut next = |index: usize| {
+            seq.next_element::<usize>()?
+                .ok_or_else(|| de::Error::invalid_length(index, &self))
+        };
+        let fold = if self.fold { next(0)? } else { 0 };
+        let m_bits = next(self.fold as usize)?;
+        let k = next(self.fold as usize + 1)?;
+        let ByteBuf(bytes) = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(self.fold as usize + 2, &self))?;
+        Ok(Fields {
+            fold,
+            m_bits,
+            k,
+            bytes,
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Fields, A::Error> {
+        let (mut fold, mut m_bits, mut k, mut bytes) = (None, None, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "fold" if self.fold => fold = Some(map.next_value()?),
+                "m_bits" => m_bits = Some(map.next_value()?),
+                "k" => k = Some(map.next_value()?),
+                "bytes" => bytes = Some(map.next_value::<ByteBuf>()?.0),
+                other => return Err(de::Error::unknown_field(other, self.names())),
+            }
+        }
+        if self.fold && fold.is_none() {
+            return Err(de::Error::missing_field("fold"));
+        }
+        Ok(Fields {
+            fold: fold.unwrap_or(0),
+            m_bits: m_bits.ok_or_else(|| de::Error::missing_field("m_bits"))?,
+            k: k.ok_or_else(|| de::Error::missing_field("k"))?,
+            bytes: bytes.ok_or_else(|| de::Error::missing_field("bytes"))?,
+        })
+    }
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let mut bloom = Bloom::<256, 30>::new();
+    let mut folded = Folded::<2, 64, 30>::new();
+    for i in 0..40u64 {
+        bloom.add(&i.to_le_bytes());
+        folded.insert(&i.to_le_bytes());
+    }
+
+    let json = serde_json::to_string(&bloom).unwrap();
+    assert!(json.starts_with(r#"{"m_bits":2048,"k":30,"bytes":["#));
+    let back: Bloom<256, 30> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.bytes, bloom.bytes);
+
+    let json = serde_json::to_string(&folded).unwrap();
+    assert!(json.starts_with(r#"{"fold":2,"m_bits":2048,"k":30,"bytes":["#));
+    let back: Folded<2, 64, 30> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.bytes, folded.bytes);
+
+    // the same bits under other parameters are refused
+    let json = serde_json::to_string(&bloom).unwrap();
+    let error = serde_json::from_str::<Bloom<256, 29>>(&json).err().unwrap();
+    assert!(error.to_string().contains("k = 30, expected 29"), "{error}");
+    let error = serde_json::from_str::<Folded<0, 256, 30>>(&json).unwrap_err();
+    assert!(
+        error.to_string().contains("missing field `fold`"),
+        "{error}"
+    );
+    let json = serde_json::to_string(&folded).unwrap();
+    let error = serde_json::from_str::<Folded<1, 64, 30>>(&json).unwrap_err();
+    assert!(error.to_string().contains("folded 2 times"), "{error}");
+    let error = serde_json::from_str::<Bloom<64, 30>>(&json).err().unwrap();
+    assert!(
+        error.to_string().contains("unknown field `fold`"),
+        "{error}"
+    );
+    let short = r#"{"m_bits":16,"k":2,"bytes":[1]}"#;
+    let error = serde_json::from_str::<Bloom<2, 2>>(short).err().unwrap();
+    assert!(error.to_string().contains("expected 2 bytes"), "{error}");
+}
@@ -0,0 +1,325 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    cli,
+    dyn_bloom::{DynBloom, DynBloomError, DynIndexing},
+    envelope::{Envelope, EnvelopeError, FilterKind},
+    strategy::HashStrategy,
+};
+
+// A tiny HTTP/1.1 service for querying filters without linking the crate:
+//
+//   POST /filters                        body: a `to_bytes` envelope of a
+//                                        `DynBloom` or `Bloom`, answers the id
+//   GET  /filters/{id}/contains?hex=..   answers `true` or `false`
+//
+// One connection at a time, one request per connection, text/plain bodies.
+
+const MAX_BODY: usize = 1 << 29;
+
+#[derive(Default)]
+pub struct FilterStore {
+    filters: Vec<DynBloom>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Error",
+    }
+}
+
+// `Bloom` envelopes query like a `DynBloom` with the same m and k
+pub fn queryable(data: &[u8]) -> Result<DynBloom, String> {
+    let envelope = Envelope::decode(data).map_err(|e| format!("invalid filter: {e:?}"))?;
+    match envelope.kind {
+        FilterKind::Dyn => {
+            DynBloom::try_from_bytes(data).map_err(|e| format!("invalid filter: {e:?}"))
+        }
+        FilterKind::Bloom
+            if envelope.strategy == HashStrategy::Xxh3Rejection && envelope.fold_level == 0 =>
+        {
+            let m_bits = usize::try_from(envelope.m_bits)
+                .map_err(|_| format!("invalid filter: {:?}", EnvelopeError::Mismatch("m")))?;
+            DynBloom::from_bytes(
+                envelope.payload.to_vec(),
+                m_bits,
+                envelope.k as usize,
+                DynIndexing::Xxh3Rejection,
+            )
+            .map_err(|e| match e {
+                DynBloomError::Padding => "invalid filter padding".to_string(),
+                e => format!("invalid filter: {:?}", EnvelopeError::InvalidParameters(e)),
+            })
+        }
+        kind => Err(format!("can't query {kind:?} filters")),
+    }
+}
+
+impl FilterStore {
+    pub fn handle(&mut self, method: &str, target: &str, body: &[u8]) -> Response {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("POST", ["filters"]) => match queryable(body) {
+                Ok(filter) => {
+                    self.filters.push(filter);
+                    Response::new(201, format!("{}\n", self.filters.len() - 1))
+                }
+                Err(message) => Response::new(400, message + "\n"),
+            },
+            ("GET", ["filters", id, "contains"]) => {
+                let Some(filter) = id.parse::<usize>().ok().and_then(|id| self.filters.get(id))
+                else {
+                    return Response::new(404, "no such filter\n");
+                };
+                let hex = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("hex="))
+                    .unwrap_or_default();
+                match hex::decode(hex) {
+                    Ok(element) => Response::new(200, format!("{}\n", filter.has(&element))),
+                    Err(e) => Response::new(400, format!("invalid hex: {e}\n")),
+                }
+            }
+            (_, ["filters"]) | (_, ["filters", _, "contains"]) => {
+                Response::new(405, "method not allowed\n")
+            }
+            _ => Response::new(404, "not found\n"),
+        }
+    }
+
+    pub fn respond(&mut self, stream: &mut TcpStream) -> Result<(), String> {
+        let response = match read_request(stream) {
+            Ok((method, target, body)) => self.handle(&method, &target, &body),
+            Err(response) => response,
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            response.status,
+            reason(response.status),
+            response.body.len(),
+            response.body
+        )
+        .map_err(|e| format!("can't respond: {e}"))
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>), Response> {
+    let bad_request = |message: &str| Response::new(400, format!("{message}\n"));
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|_| bad_request("unreadable request"))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("malformed request line"));
+    };
+    let (method, target) = (method.to_string(), target.to_string());
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|_| bad_request("unreadable header"))?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| bad_request("invalid content-length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(Response::new(413, "filter too large\n"));
+    }
+    let mut body = vec![0u8; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|_| bad_request("truncated body"))?;
+    Ok((method, target, body))
+}
+
+// `serve [--listen <addr>]`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let address = cli::flag(args, "--listen").unwrap_or("127.0.0.1:8080");
+    let listener = TcpListener::bind(address).map_err(|e| format!("can't listen: {e}"))?;
+    eprintln!("serving filters on http://{address}");
+    let mut store = FilterStore::default();
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        if let Err(message) = store.respond(&mut stream) {
+            eprintln!("{message}");
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_handle() {
+    let mut store = FilterStore::default();
+    let mut bloom = crate::Bloom::<256, 30>::new();
+    bloom.add(b"one");
+    let mut dyn_bloom = DynBloom::new(1001, 7);
+    dyn_bloom.add(b"two");
+
+    assert_eq!(
+        store.handle("POST", "/filters", &bloom.to_bytes()),
+        Response::new(201, "0\n")
+    );
+    assert_eq!(
+        store.handle("POST", "/filters", &dyn_bloom.to_bytes()),
+        Response::new(201, "1\n")
+    );
+    let contains = |store: &mut FilterStore, id: usize, element: &[u8]| {
+        store.handle(
+            "GET",
+            &format!("/filters/{id}/contains?hex={}", hex::encode(element)),
+            &[],
+        )
+    };
+    assert_eq!(
+        contains(&mut store, 0, b"one"),
+        Response::new(200, "true\n")
+    );
+    assert_eq!(
+        contains(&mut store, 0, b"two"),
+        Response::new(200, "false\n")
+    );
+    assert_eq!(
+        contains(&mut store, 1, b"two"),
+        Response::new(200, "true\n")
+    );
+    assert_eq!(contains(&mut store, 2, b"two").status, 404);
+    assert_eq!(
+        store
+            .handle("GET", "/filters/0/contains?hex=zz", &[])
+            .status,
+        400
+    );
+    assert_eq!(store.handle("POST", "/filters", b"garbage").status, 400);
+    assert_eq!(
+        store
+            .handle(
+                "POST",
+                "/filters",
+                &crate::folded::Folded::<1, 64, 30>::new().to_bytes()
+            )
+            .status,
+        400
+    );
+    assert_eq!(store.handle("GET", "/filters", &[]).status, 405);
+    assert_eq!(store.handle("GET", "/elsewhere", &[]).status, 404);
+}
+
+#[test]
+fn test_handle_invalid_parameters() {
+    // zero bits would never answer a query, so neither kind is stored
+    let mut store = FilterStore::default();
+    for kind in [FilterKind::Bloom, FilterKind::Dyn] {
+        let empty = Envelope {
+            kind,
+            m_bits: 0,
+            k: 1,
+            fold_level: 0,
+            strategy: HashStrategy::Xxh3Rejection,
+            seed: &[],
+            payload: &[],
+        }
+        .encode();
+        assert_eq!(
+            store.handle("POST", "/filters", &empty),
+            Response::new(400, "invalid filter: InvalidParameters(NoBits)\n")
+        );
+    }
+    let no_hashes = Envelope {
+        kind: FilterKind::Bloom,
+        m_bits: 16,
+        k: 0,
+        fold_level: 0,
+        strategy: HashStrategy::Xxh3Rejection,
+        seed: &[],
+        payload: &[0; 2],
+    }
+    .encode();
+    assert_eq!(store.handle("POST", "/filters", &no_hashes).status, 400);
+    assert_eq!(
+        store
+            .handle("GET", "/filters/0/contains?hex=00", &[])
+            .status,
+        404
+    );
+}
+
+#[test]
+fn test_serve_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let mut store = FilterStore::default();
+        for stream in listener.incoming().take(2) {
+            store.respond(&mut stream.unwrap()).unwrap();
+        }
+    });
+    let request = |head: String, body: &[u8]| {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(head.as_bytes()).unwrap();
+        stream.write_all(body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let mut bloom = DynBloom::new(2048, 7);
+    bloom.add(b"one");
+    let body = bloom.to_bytes();
+    let response = request(
+        format!(
+            "POST /filters HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        ),
+        &body,
+    );
+    assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+    assert!(response.ends_with("\r\n\r\n0\n"));
+    let response = request(
+        format!(
+            "GET /filters/0/contains?hex={} HTTP/1.1\r\n\r\n",
+            hex::encode("one")
+        ),
+        &[],
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("true\n"));
+    server.join().unwrap();
+}
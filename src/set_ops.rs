@@ -0,0 +1,275 @@
+use crate::{compare::estimate_elements, dyn_bloom::DynBloom, Bloom};
+
+// Set operations on filters of identical parameters, bit by bit, e.g. to
+// merge the filters several writers kept for one logical set. As in
+// compare.rs, `Bloom`'s parameters are part of its type and `DynBloom`s
+// refuse unless m, k and indexing match.
+
+fn union_with(a: &mut [u8], b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b) {
+        *a |= b;
+    }
+}
+
+fn intersect_with(a: &mut [u8], b: &[u8]) {
+    for (a, b) in a.iter_mut().zip(b) {
+        *a &= b;
+    }
+}
+
+fn is_subset_of(a: &[u8], b: &[u8]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a & !b == 0)
+}
+
+// |A|, |B| and |A ∪ B|, each estimated from its set bits; the ones of the
+// union are those of both minus the overlap.
+fn estimate_sizes(m_bits: usize, k: usize, a: &[u8], b: &[u8]) -> (f64, f64, f64) {
+    let ones = |bytes: &[u8]| bytes.iter().map(|byte| byte.count_ones()).sum::<u32>();
+    let (ones_a, ones_b) = (ones(a), ones(b));
+    let overlap: u32 = a.iter().zip(b).map(|(a, b)| (a & b).count_ones()).sum();
+    (
+        estimate_elements(m_bits, k, ones_a),
+        estimate_elements(m_bits, k, ones_b),
+        estimate_elements(m_bits, k, ones_a + ones_b - overlap),
+    )
+}
+
+// |A ∩ B| by inclusion-exclusion, |A| + |B| - |A ∪ B|
+fn estimate_intersection(m_bits: usize, k: usize, a: &[u8], b: &[u8]) -> f64 {
+    let (a, b, union) = estimate_sizes(m_bits, k, a, b);
+    (a + b - union).max(0.0)
+}
+
+// |A ∩ B| / |A ∪ B|, 1 for two empty filters. NaN once the union is
+// saturated and its size can't be estimated.
+fn jaccard_estimate(m_bits: usize, k: usize, a: &[u8], b: &[u8]) -> f64 {
+    let (a, b, union) = estimate_sizes(m_bits, k, a, b);
+    if union == 0.0 {
+        return 1.0;
+    }
+    ((a + b - union) / union).clamp(0.0, 1.0)
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    // The filter of both sets: has every element either one has, and is
+    // exactly the filter adding all of them would give.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        union.union_with(other);
+        union
+    }
+
+    pub fn union_with(&mut self, other: &Self) {
+        union_with(&mut self.bytes, &other.bytes);
+    }
+
+    // The bits set in both. It has every element both have, but isn't the
+    // filter of the intersection: an element of only one set can still find
+    // all its bits set by the other's elements, so it has more false
+    // positives than adding the shared elements alone would give.
+    pub fn intersect(&self, other: &Self) -> Self {
+        let mut intersection = self.clone();
+        intersection.intersect_with(other);
+        intersection
+    }
+
+    pub fn intersect_with(&mut self, other: &Self) {
+        intersect_with(&mut self.bytes, &other.bytes);
+    }
+
+    // about how many elements were added to both
+    pub fn estimate_intersection_size(&self, other: &Self) -> f64 {
+        estimate_intersection(M * 8, K, &self.bytes, &other.bytes)
+    }
+
+    // Every bit set here is set in other, so other has every element added
+    // here. One-sided like `has`: false means some element here definitely
+    // isn't in other, true only that all of them may be.
+    pub fn is_subset_of(&self, other: &Self) -> bool {
+        is_subset_of(&self.bytes, &other.bytes)
+    }
+
+    // about the share of all elements added to either filter that were
+    // added to both
+    pub fn jaccard_estimate(&self, other: &Self) -> f64 {
+        jaccard_estimate(M * 8, K, &self.bytes, &other.bytes)
+    }
+}
+
+impl DynBloom {
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        let mut union = self.clone();
+        union.union_with(other).then_some(union)
+    }
+
+    // false, leaving self as it was, if the parameters differ
+    pub fn union_with(&mut self, other: &Self) -> bool {
+        if !self.same_parameters(other) {
+            return false;
+        }
+        union_with(self.bits_mut(), other.bytes());
+        true
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let mut intersection = self.clone();
+        intersection.intersect_with(other).then_some(intersection)
+    }
+
+    pub fn intersect_with(&mut self, other: &Self) -> bool {
+        if !self.same_parameters(other) {
+            return false;
+        }
+        intersect_with(self.bits_mut(), other.bytes());
+        true
+    }
+
+    pub fn estimate_intersection_size(&self, other: &Self) -> Option<f64> {
+        self.same_parameters(other)
+            .then(|| estimate_intersection(self.m_bits(), self.k(), self.bytes(), other.bytes()))
+    }
+
+    pub fn is_subset_of(&self, other: &Self) -> Option<bool> {
+        self.same_parameters(other)
+            .then(|| is_subset_of(self.bytes(), other.bytes()))
+    }
+
+    pub fn jaccard_estimate(&self, other: &Self) -> Option<f64> {
+        self.same_parameters(other)
+            .then(|| jaccard_estimate(self.m_bits(), self.k(), self.bytes(), other.bytes()))
+    }
+}
+
+#[test]
+fn test_union() {
+    let mut a = Bloom::<256, 30>::new();
+    let mut b = Bloom::<256, 30>::new();
+    let mut both = Bloom::<256, 30>::new();
+    let mut dyn_a = DynBloom::new(1001, 7);
+    let mut dyn_b = DynBloom::new(1001, 7);
+    let mut dyn_both = DynBloom::new(1001, 7);
+    for i in 0..40u64 {
+        let element = i.to_le_bytes();
+        if i % 3 != 0 || i % 2 == 1 {
+            a.add(&element);
+            dyn_a.add(&element);
+        }
+        if i % 2 == 0 {
+            b.add(&element);
+            dyn_b.add(&element);
+        }
+        both.add(&element);
+        dyn_both.add(&element);
+    }
+    let union = a.union(&b);
+    assert_eq!(union.bytes, both.bytes);
+    assert_eq!(b.union(&a).bytes, both.bytes);
+    assert!((0..40u64).all(|i| union.has(&i.to_le_bytes())));
+    a.union_with(&b);
+    assert_eq!(a.bytes, both.bytes);
+
+    assert_eq!(dyn_a.union(&dyn_b), Some(dyn_both.clone()));
+    assert!(dyn_a.union_with(&dyn_b));
+    assert_eq!(dyn_a, dyn_both);
+    assert_eq!(dyn_a.union(&DynBloom::new(1001, 6)), None);
+    assert!(!dyn_a.union_with(&DynBloom::new(1000, 7)));
+    assert_eq!(dyn_a, dyn_both);
+}
+
+#[test]
+fn test_intersection() {
+    // 300 shared elements, 100 only in a, 50 only in b
+    let mut a = Bloom::<1024, 7>::new();
+    let mut b = Bloom::<1024, 7>::new();
+    let mut dyn_a = DynBloom::new(8192, 7);
+    let mut dyn_b = DynBloom::new(8192, 7);
+    for i in 0..400u32 {
+        a.add(&i.to_le_bytes());
+        dyn_a.add(&i.to_le_bytes());
+    }
+    for i in 100..450u32 {
+        b.add(&i.to_le_bytes());
+        dyn_b.add(&i.to_le_bytes());
+    }
+    let intersection = a.intersect(&b);
+    assert!((100..400u32).all(|i| intersection.has(&i.to_le_bytes())));
+    assert_eq!(intersection.count_ones(), a.overlap_bits(&b));
+    assert_eq!(b.intersect(&a).bytes, intersection.bytes);
+    let mut in_place = a.clone();
+    in_place.intersect_with(&b);
+    assert_eq!(in_place.bytes, intersection.bytes);
+
+    let estimate = a.estimate_intersection_size(&b);
+    assert!((280.0..320.0).contains(&estimate), "{estimate}");
+    let estimate = a.estimate_intersection_size(&a);
+    assert!((380.0..420.0).contains(&estimate), "{estimate}");
+    assert_eq!(a.estimate_intersection_size(&Bloom::new()), 0.0);
+
+    assert_eq!(dyn_a.intersect(&dyn_b).unwrap().bytes(), intersection.bytes);
+    assert_eq!(
+        dyn_a.estimate_intersection_size(&dyn_b),
+        Some(a.estimate_intersection_size(&b))
+    );
+    assert_eq!(dyn_a.intersect(&DynBloom::new(8192, 6)), None);
+    assert!(!dyn_a.intersect_with(&DynBloom::new(8191, 7)));
+    assert_eq!(
+        dyn_a.estimate_intersection_size(&DynBloom::new(8191, 7)),
+        None
+    );
+}
+
+#[test]
+fn test_is_subset_of() {
+    let mut small = Bloom::<256, 30>::new();
+    let mut large = Bloom::<256, 30>::new();
+    let mut dyn_small = DynBloom::new(1001, 7);
+    let mut dyn_large = DynBloom::new(1001, 7);
+    for i in 0..40u64 {
+        if i < 10 {
+            small.add(&i.to_le_bytes());
+            dyn_small.add(&i.to_le_bytes());
+        }
+        large.add(&i.to_le_bytes());
+        dyn_large.add(&i.to_le_bytes());
+    }
+    assert!(small.is_subset_of(&large) && !large.is_subset_of(&small));
+    assert!(small.is_subset_of(&small));
+    assert!(Bloom::new().is_subset_of(&small));
+    small.add(b"elsewhere");
+    assert!(!small.is_subset_of(&large));
+    assert!(small.is_subset_of(&small.union(&large)));
+    assert!(small.intersect(&large).is_subset_of(&large));
+
+    assert_eq!(dyn_small.is_subset_of(&dyn_large), Some(true));
+    assert_eq!(dyn_large.is_subset_of(&dyn_small), Some(false));
+    assert_eq!(dyn_small.is_subset_of(&DynBloom::new(1001, 6)), None);
+}
+
+#[test]
+fn test_jaccard_estimate() {
+    // 300 shared elements of 450, J = 2/3
+    let mut a = Bloom::<1024, 7>::new();
+    let mut b = Bloom::<1024, 7>::new();
+    for i in 0..400u32 {
+        a.add(&i.to_le_bytes());
+    }
+    for i in 100..450u32 {
+        b.add(&i.to_le_bytes());
+    }
+    let estimate = a.jaccard_estimate(&b);
+    assert!((0.62..0.71).contains(&estimate), "{estimate}");
+    assert_eq!(b.jaccard_estimate(&a), estimate);
+    assert_eq!(a.jaccard_estimate(&a), 1.0);
+    assert_eq!(Bloom::<1024, 7>::new().jaccard_estimate(&Bloom::new()), 1.0);
+
+    let mut c = Bloom::<1024, 7>::new();
+    for i in 1000..1400u32 {
+        c.add(&i.to_le_bytes());
+    }
+    assert!(a.jaccard_estimate(&c) < 0.02);
+
+    let mut dyn_a = DynBloom::new(8192, 7);
+    dyn_a.add(b"one");
+    assert_eq!(dyn_a.jaccard_estimate(&dyn_a), Some(1.0));
+    assert_eq!(dyn_a.jaccard_estimate(&DynBloom::new(8192, 6)), None);
+}
@@ -0,0 +1,159 @@
+use rand::RngCore;
+
+use crate::Bloom;
+
+// Shamir secret sharing of a filter's bytes over GF(256), each byte shared
+// independently. Any `threshold` of the `n` shares reconstruct the filter,
+// fewer reveal nothing about it.
+
+#[derive(Clone, Debug)]
+pub struct Share<const M: usize> {
+    // evaluation point, 1..=n
+    pub x: u8,
+    pub threshold: u8,
+    pub bytes: [u8; M],
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShareError {
+    NotEnoughShares { have: usize, need: usize },
+    InconsistentThreshold,
+    DuplicateShare { x: u8 },
+}
+
+// multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 = a^-1 for a != 0
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    for _ in 0..254 {
+        result = gf_mul(result, a);
+    }
+    result
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn split(&self, n: usize, threshold: usize) -> Vec<Share<M>> {
+        assert!(
+            threshold >= 1 && threshold <= n && n <= 255,
+            "need 1 <= threshold <= n <= 255"
+        );
+
+        // coefficients[c][i] is the c-th coefficient of byte i's polynomial,
+        // with the secret byte as the constant term
+        let mut coefficients = vec![[0u8; M]; threshold];
+        coefficients[0] = self.bytes;
+        for coefficient in coefficients.iter_mut().skip(1) {
+            rand::thread_rng().fill_bytes(coefficient);
+        }
+
+        (1..=n as u8)
+            .map(|x| {
+                let mut bytes = [0u8; M];
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    // horner
+                    *byte = coefficients
+                        .iter()
+                        .rev()
+                        .fold(0, |acc, coefficient| gf_mul(acc, x) ^ coefficient[i]);
+                }
+                Share {
+                    x,
+                    threshold: threshold as u8,
+                    bytes,
+                }
+            })
+            .collect()
+    }
+
+    pub fn reconstruct(shares: &[Share<M>]) -> Result<Self, ShareError> {
+        let need = shares.first().map(|s| s.threshold as usize).unwrap_or(1);
+        if shares.iter().any(|s| s.threshold as usize != need) {
+            return Err(ShareError::InconsistentThreshold);
+        }
+        if shares.len() < need {
+            return Err(ShareError::NotEnoughShares {
+                have: shares.len(),
+                need,
+            });
+        }
+        let shares = &shares[..need];
+        for (i, share) in shares.iter().enumerate() {
+            if shares[..i].iter().any(|other| other.x == share.x) {
+                return Err(ShareError::DuplicateShare { x: share.x });
+            }
+        }
+
+        // lagrange interpolation at 0, in characteristic 2 subtraction is xor
+        let mut bytes = [0u8; M];
+        for (i, share) in shares.iter().enumerate() {
+            let mut basis = 1;
+            for (j, other) in shares.iter().enumerate() {
+                if i != j {
+                    basis = gf_mul(basis, gf_mul(other.x, gf_inv(other.x ^ share.x)));
+                }
+            }
+            for (byte, share_byte) in bytes.iter_mut().zip(share.bytes.iter()) {
+                *byte ^= gf_mul(basis, *share_byte);
+            }
+        }
+        Ok(Self::from_bytes(bytes))
+    }
+}
+
+#[test]
+fn test_gf() {
+    for a in 1..=255u8 {
+        assert_eq!(gf_mul(a, gf_inv(a)), 1);
+    }
+    assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+}
+
+#[test]
+fn test_split_reconstruct() {
+    let mut bloom: Bloom<256, 30> = Bloom::new();
+    for i in 0..20u8 {
+        bloom.add(&[i]);
+    }
+
+    for (n, threshold) in [(1, 1), (2, 2), (3, 2), (5, 3), (10, 10), (255, 7)] {
+        let shares = bloom.split(n, threshold);
+        assert_eq!(shares.len(), n);
+
+        let first = Bloom::<256, 30>::reconstruct(&shares[..threshold]).unwrap();
+        assert_eq!(first.bytes, bloom.bytes, "n={n} threshold={threshold}");
+        let last = Bloom::<256, 30>::reconstruct(&shares[n - threshold..]).unwrap();
+        assert_eq!(last.bytes, bloom.bytes, "n={n} threshold={threshold}");
+
+        if threshold > 1 {
+            assert_eq!(
+                Bloom::<256, 30>::reconstruct(&shares[..threshold - 1]).err(),
+                Some(ShareError::NotEnoughShares {
+                    have: threshold - 1,
+                    need: threshold
+                })
+            );
+        }
+    }
+
+    let shares = bloom.split(3, 2);
+    assert_eq!(
+        Bloom::<256, 30>::reconstruct(&[shares[1].clone(), shares[1].clone()]).err(),
+        Some(ShareError::DuplicateShare { x: 2 })
+    );
+}
@@ -0,0 +1,123 @@
+use crate::{bloom_indices_for_element, filter::Filter};
+
+// Spectral Bloom filter (Cohen and Matias, 2003): m 32-bit counters at
+// `Bloom`'s indices, each add incrementing the element's k of them, so
+// besides membership it estimates how often an element was added. The
+// estimate is the smallest of its counters (minimum selection), which other
+// elements can only have raised: never below the true count, and exact
+// unless all k counters are shared with something else.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpectralBloom {
+    counters: Vec<u32>,
+    k: usize,
+}
+
+impl SpectralBloom {
+    pub fn new(m: usize, k: usize) -> Self {
+        assert!(m > 0 && k > 0 && k <= m);
+        Self {
+            counters: vec![0; m],
+            k,
+        }
+    }
+
+    pub fn m(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    // in derivation order, repeats included
+    pub fn indices_for(&self, element: &[u8]) -> Vec<usize> {
+        bloom_indices_for_element(element, self.counters.len(), self.k).collect()
+    }
+
+    pub fn add(&mut self, element: &[u8]) {
+        self.add_count(element, 1);
+    }
+
+    // count occurrences at once; counters saturate at u32::MAX
+    pub fn add_count(&mut self, element: &[u8], count: u32) {
+        for index in bloom_indices_for_element(element, self.counters.len(), self.k) {
+            self.counters[index] = self.counters[index].saturating_add(count);
+        }
+    }
+
+    // at least the number of times element was added, 0 only if never
+    pub fn estimate_count(&self, element: &[u8]) -> u64 {
+        bloom_indices_for_element(element, self.counters.len(), self.k)
+            .map(|index| self.counters[index])
+            .min()
+            .unwrap() as u64
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.estimate_count(element) != 0
+    }
+
+    // adds, counting each element as often as it was added
+    pub fn total(&self) -> u64 {
+        self.counters
+            .iter()
+            .map(|&counter| counter as u64)
+            .sum::<u64>()
+            / self.k as u64
+    }
+}
+
+impl Filter for SpectralBloom {
+    fn add(&mut self, element: &[u8]) {
+        SpectralBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        SpectralBloom::has(self, element)
+    }
+
+    // nonzero counters
+    fn count_ones(&self) -> u32 {
+        self.counters
+            .iter()
+            .filter(|&&counter| counter != 0)
+            .count() as u32
+    }
+}
+
+#[test]
+fn test_spectral_bloom() {
+    use crate::element::encode_u64;
+
+    // element i added i % 10 times
+    let mut filter = SpectralBloom::new(20_000, 5);
+    for i in 0..2000u64 {
+        filter.add_count(&encode_u64(i), (i % 10) as u32);
+    }
+    assert_eq!(filter.total(), (0..2000u64).map(|i| i % 10).sum::<u64>());
+    let mut exact = 0;
+    for i in 0..2000u64 {
+        let estimate = filter.estimate_count(&encode_u64(i));
+        assert!(estimate >= i % 10);
+        exact += (estimate == i % 10) as u32;
+    }
+    // 1800 elements over 20_000 counters rarely cover all 5 of another's
+    assert!(exact > 1950, "{exact}");
+
+    // elements added 0 times are mostly absent
+    assert!(!filter.has(&encode_u64(10)) || !filter.has(&encode_u64(20)));
+    filter.add(&encode_u64(10));
+    filter.add(&encode_u64(10));
+    assert!(filter.estimate_count(&encode_u64(10)) >= 2);
+    assert_eq!(filter.indices_for(b"one").len(), 5);
+
+    // membership answers like a Bloom filter of the same indices
+    let mut bloom = crate::dyn_bloom::DynBloom::new(20_000, 5);
+    let mut spectral = SpectralBloom::new(20_000, 5);
+    for i in 0..500u64 {
+        bloom.add(&encode_u64(i));
+        spectral.add(&encode_u64(i));
+    }
+    assert_eq!(spectral.count_ones(), bloom.count_ones());
+    assert!((0..10_000u64).all(|i| spectral.has(&encode_u64(i)) == bloom.has(&encode_u64(i))));
+}
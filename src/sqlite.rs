@@ -0,0 +1,87 @@
+use rusqlite::{types::ValueRef, Connection};
+
+use crate::{dyn_bloom::DynBloom, element::encode_i64, filter::Filter, Bloom};
+
+// Filters over the first column of a query's rows, streamed without
+// collecting them. Blobs are inserted as is, text as its UTF-8 bytes and
+// integers as `encode_i64` bytes; NULLs are skipped and REAL
+// values are an error, they have no canonical bytes.
+
+// answers the number of elements inserted
+pub fn insert_sqlite<F: Filter>(
+    filter: &mut F,
+    conn: &Connection,
+    sql: &str,
+) -> rusqlite::Result<usize> {
+    let mut statement = conn.prepare(sql)?;
+    let mut rows = statement.query([])?;
+    let mut inserted = 0;
+    while let Some(row) = rows.next()? {
+        match row.get_ref(0)? {
+            ValueRef::Null => continue,
+            ValueRef::Blob(bytes) | ValueRef::Text(bytes) => filter.add(bytes),
+            ValueRef::Integer(value) => filter.add(&encode_i64(value)),
+            value @ ValueRef::Real(_) => {
+                let name = row.as_ref().column_name(0)?.to_string();
+                return Err(rusqlite::Error::InvalidColumnType(
+                    0,
+                    name,
+                    value.data_type(),
+                ));
+            }
+        }
+        inserted += 1;
+    }
+    Ok(inserted)
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn from_sqlite(conn: &Connection, sql: &str) -> rusqlite::Result<Self> {
+        let mut bloom = Self::new();
+        insert_sqlite(&mut bloom, conn, sql)?;
+        Ok(bloom)
+    }
+}
+
+impl DynBloom {
+    pub fn from_sqlite(
+        conn: &Connection,
+        sql: &str,
+        m_bits: usize,
+        k: usize,
+    ) -> rusqlite::Result<Self> {
+        let mut bloom = Self::new(m_bits, k);
+        insert_sqlite(&mut bloom, conn, sql)?;
+        Ok(bloom)
+    }
+}
+
+#[test]
+fn test_from_sqlite() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE keys (key);
+         INSERT INTO keys VALUES ('one'), (x'74776f'), (3), (NULL);",
+    )
+    .unwrap();
+    let bloom: Bloom<256, 30> = Bloom::from_sqlite(&conn, "SELECT key FROM keys").unwrap();
+    let mut expected: Bloom<256, 30> = Bloom::new();
+    for element in [&b"one"[..], b"two", &3i64.to_le_bytes()] {
+        expected.add(element);
+    }
+    assert_eq!(bloom.bytes, expected.bytes);
+
+    let dyn_bloom = DynBloom::from_sqlite(&conn, "SELECT key FROM keys", 256 * 8, 30).unwrap();
+    assert_eq!(dyn_bloom.bytes(), &expected.bytes[..]);
+
+    let mut count = DynBloom::new(1001, 7);
+    assert_eq!(
+        insert_sqlite(&mut count, &conn, "SELECT key FROM keys WHERE key IS NOT 3"),
+        Ok(2)
+    );
+    assert!(matches!(
+        insert_sqlite(&mut count, &conn, "SELECT 1.5 AS ratio"),
+        Err(rusqlite::Error::InvalidColumnType(0, _, _))
+    ));
+    assert!(Bloom::<256, 30>::from_sqlite(&conn, "SELECT nope FROM keys").is_err());
+}
@@ -0,0 +1,324 @@
+use crate::filter::Filter;
+
+// Squid cache digests, the body of a peer's `/squid-internal-periodic/
+// store_digest` object: a 128 byte StoreDigestCBlock, all integers big
+// endian,
+//
+//   0   version current (u16, 5), version required (u16, 3)
+//   4   capacity (u32)
+//   8   count (u32)
+//   12  del count (u32)
+//   16  mask size in bytes (u32)
+//   20  bits per entry (u8)
+//   21  hash function count (u8, 4)
+//   22  reserved, zero
+//
+// followed by the mask. Entries are MD5 store keys; the four indices are the
+// key's big endian u32 words modulo the mask's bit count, each setting bit
+// i % 8 of byte i / 8.
+
+const CURRENT_VERSION: u16 = 5;
+const REQUIRED_VERSION: u16 = 3;
+const CBLOCK_LEN: usize = 128;
+const HASH_FUNC_COUNT: u8 = 4;
+// indices are u32 key words modulo the mask's bit count, itself a u32
+const MAX_MASK_BYTES: usize = u32::MAX as usize / 8;
+
+// Squid's `Http::METHOD_GET`, the method byte of most public store keys
+pub const METHOD_GET: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SquidDigest {
+    capacity: u32,
+    count: u32,
+    del_count: u32,
+    bits_per_entry: u8,
+    mask: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SquidError {
+    Truncated,
+    UnsupportedVersion(u16),
+    HashFuncCount(u8),
+    // the mask size doesn't follow from capacity and bits per entry
+    MaskSize { expected: usize, found: usize },
+    // capacity or bits per entry is zero
+    EmptyMask,
+    // a mask of more than u32::MAX bits, in bytes
+    MaskTooLarge(usize),
+    TrailingBytes(usize),
+}
+
+// RFC 1321
+pub fn md5(data: &[u8]) -> [u8; 16] {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    let t: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in message.chunks_exact(64) {
+        let words: Vec<u32> = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(t[i])
+                .wrapping_add(words[g])
+                .rotate_left(S[i]);
+            (a, b, c, d) = (d, b.wrapping_add(rotated), b, c);
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 16];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+// `storeKeyPublic` for the default key scope: MD5 of the method byte and the URL
+pub fn store_key_public(method: u8, url: &str) -> [u8; 16] {
+    let mut input = Vec::with_capacity(1 + url.len());
+    input.push(method);
+    input.extend_from_slice(url.as_bytes());
+    md5(&input)
+}
+
+// `CacheDigest::CalcMaskSize`
+fn mask_size(capacity: u32, bits_per_entry: u8) -> usize {
+    (capacity as usize * bits_per_entry as usize).div_ceil(8)
+}
+
+impl SquidDigest {
+    pub fn new(capacity: u32, bits_per_entry: u8) -> Self {
+        assert!(
+            (1..=MAX_MASK_BYTES).contains(&mask_size(capacity, bits_per_entry)),
+            "masks are 1 to u32::MAX bits"
+        );
+        Self {
+            capacity,
+            count: 0,
+            del_count: 0,
+            bits_per_entry,
+            mask: vec![0; mask_size(capacity, bits_per_entry)],
+        }
+    }
+
+    pub fn indices_for(&self, key: &[u8; 16]) -> [usize; 4] {
+        let bits = (self.mask.len() * 8) as u32;
+        let word = |i: usize| u32::from_be_bytes(key[i * 4..i * 4 + 4].try_into().unwrap());
+        [0, 1, 2, 3].map(|i| (word(i) % bits) as usize)
+    }
+
+    pub fn add_key(&mut self, key: &[u8; 16]) {
+        for index in self.indices_for(key) {
+            self.mask[index / 8] |= 1 << (index % 8);
+        }
+        self.count += 1;
+    }
+
+    pub fn contains_key(&self, key: &[u8; 16]) -> bool {
+        self.indices_for(key)
+            .iter()
+            .all(|index| self.mask[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    pub fn add_url(&mut self, method: u8, url: &str) {
+        self.add_key(&store_key_public(method, url))
+    }
+
+    pub fn contains_url(&self, method: u8, url: &str) -> bool {
+        self.contains_key(&store_key_public(method, url))
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    // entries added, as counted by the digest's owner
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mask(&self) -> &[u8] {
+        &self.mask
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, SquidError> {
+        if data.len() < CBLOCK_LEN {
+            return Err(SquidError::Truncated);
+        }
+        let u16_at = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        let u32_at = |i: usize| u32::from_be_bytes(data[i..i + 4].try_into().unwrap());
+        let required = u16_at(2);
+        if required > CURRENT_VERSION {
+            return Err(SquidError::UnsupportedVersion(required));
+        }
+        if data[21] != HASH_FUNC_COUNT {
+            return Err(SquidError::HashFuncCount(data[21]));
+        }
+        let (capacity, bits_per_entry) = (u32_at(4), data[20]);
+        let expected = mask_size(capacity, bits_per_entry);
+        if expected == 0 {
+            return Err(SquidError::EmptyMask);
+        }
+        if expected > MAX_MASK_BYTES {
+            return Err(SquidError::MaskTooLarge(expected));
+        }
+        let found = u32_at(16) as usize;
+        if found != expected {
+            return Err(SquidError::MaskSize { expected, found });
+        }
+        let mask = &data[CBLOCK_LEN..];
+        if mask.len() < expected {
+            return Err(SquidError::Truncated);
+        }
+        if mask.len() > expected {
+            return Err(SquidError::TrailingBytes(mask.len() - expected));
+        }
+        Ok(Self {
+            capacity,
+            count: u32_at(8),
+            del_count: u32_at(12),
+            bits_per_entry,
+            mask: mask.to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(CBLOCK_LEN + self.mask.len());
+        out.extend_from_slice(&CURRENT_VERSION.to_be_bytes());
+        out.extend_from_slice(&REQUIRED_VERSION.to_be_bytes());
+        out.extend_from_slice(&self.capacity.to_be_bytes());
+        out.extend_from_slice(&self.count.to_be_bytes());
+        out.extend_from_slice(&self.del_count.to_be_bytes());
+        out.extend_from_slice(&(self.mask.len() as u32).to_be_bytes());
+        out.push(self.bits_per_entry);
+        out.push(HASH_FUNC_COUNT);
+        out.resize(CBLOCK_LEN, 0);
+        out.extend_from_slice(&self.mask);
+        out
+    }
+}
+
+// elements are hashed into store keys, so `add(&[METHOD_GET, url..])` is
+// `add_url(METHOD_GET, url)`
+impl Filter for SquidDigest {
+    fn add(&mut self, element: &[u8]) {
+        self.add_key(&md5(element))
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        self.contains_key(&md5(element))
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.mask.iter().map(|b| b.count_ones()).sum()
+    }
+}
+
+#[test]
+fn test_md5() {
+    assert_eq!(hex::encode(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(hex::encode(md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(
+        hex::encode(md5(
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+        )),
+        "57edf4a22be3c955ac49da2e2107b67a"
+    );
+}
+
+#[test]
+fn test_squid_digest() {
+    let mut digest = SquidDigest::new(100, 5);
+    assert_eq!(digest.mask().len(), 63);
+    for i in 0..20 {
+        digest.add_url(METHOD_GET, &format!("http://example.com/{i}"));
+    }
+    assert!((0..20).all(|i| digest.contains_url(METHOD_GET, &format!("http://example.com/{i}"))));
+    assert!(digest.has(b"\x01http://example.com/3"));
+
+    // key words 0x01020304, 0x05060708, ... modulo 504 bits
+    let mut single = SquidDigest::new(100, 5);
+    let key: [u8; 16] = core::array::from_fn(|i| i as u8 + 1);
+    single.add_key(&key);
+    let expected = [0x01020304u32, 0x05060708, 0x090a0b0c, 0x0d0e0f10].map(|w| w % 504);
+    for index in expected {
+        assert_ne!(single.mask()[index as usize / 8] & (1 << (index % 8)), 0);
+    }
+    assert_eq!(single.count_ones(), 4);
+
+    let data = digest.to_bytes();
+    assert_eq!(data.len(), 128 + 63);
+    assert_eq!(&data[..4], &[0, 5, 0, 3]);
+    assert_eq!(&data[16..22], &[0, 0, 0, 63, 5, 4]);
+    assert_eq!(SquidDigest::from_bytes(&data), Ok(digest.clone()));
+    assert_eq!(
+        SquidDigest::from_bytes(&data[..150]),
+        Err(SquidError::Truncated)
+    );
+    let mut wrong_size = data.clone();
+    wrong_size[19] = 64;
+    assert_eq!(
+        SquidDigest::from_bytes(&wrong_size),
+        Err(SquidError::MaskSize {
+            expected: 63,
+            found: 64
+        })
+    );
+    let mut future = data;
+    future[3] = 6;
+    assert_eq!(
+        SquidDigest::from_bytes(&future),
+        Err(SquidError::UnsupportedVersion(6))
+    );
+
+    // control blocks without a mask, as SquidDigest::new(0, 5) used to write
+    let header = |capacity: u32, bits_per_entry: u8| {
+        let mut data = SquidDigest::new(1, 8).to_bytes();
+        data.truncate(CBLOCK_LEN);
+        data[4..8].copy_from_slice(&capacity.to_be_bytes());
+        let size = mask_size(capacity, bits_per_entry) as u32;
+        data[16..20].copy_from_slice(&size.to_be_bytes());
+        data[20] = bits_per_entry;
+        data
+    };
+    assert_eq!(
+        SquidDigest::from_bytes(&header(0, 5)),
+        Err(SquidError::EmptyMask)
+    );
+    assert_eq!(
+        SquidDigest::from_bytes(&header(100, 0)),
+        Err(SquidError::EmptyMask)
+    );
+    assert_eq!(
+        SquidDigest::from_bytes(&header(u32::MAX, 8)),
+        Err(SquidError::MaskTooLarge(u32::MAX as usize))
+    );
+}
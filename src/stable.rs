@@ -0,0 +1,253 @@
+use crate::{
+    bloom_indices_for_element, cli,
+    element::encode_u64,
+    entropy::{self, EntropySource, ThreadEntropy},
+    filter::Filter,
+};
+
+// Stable Bloom filter (Deng and Rafiei, 2006), for deduplicating unbounded
+// streams: cells of 1 to 8 bits instead of bits, and every add first
+// decrements `decrements` consecutive cells from a random one, then sets the
+// element's k cells (`Bloom`'s indices) to the maximum. Old elements decay
+// out instead of filling the filter, so the fraction of zero cells settles
+// at a stable point and the false positive rate with it, paid for with
+// false negatives for elements not seen in a while.
+pub struct StableBloom<E = ThreadEntropy> {
+    cells: Vec<u8>,
+    max: u8,
+    k: usize,
+    decrements: usize,
+    entropy: E,
+}
+
+impl StableBloom {
+    pub fn new(cells: usize, cell_bits: u32, k: usize, decrements: usize) -> Self {
+        Self::with_entropy(cells, cell_bits, k, decrements, ThreadEntropy)
+    }
+}
+
+impl<E: EntropySource> StableBloom<E> {
+    // decrement positions drawn from entropy, e.g. seeded for reproducible
+    // streams
+    pub fn with_entropy(
+        cells: usize,
+        cell_bits: u32,
+        k: usize,
+        decrements: usize,
+        entropy: E,
+    ) -> Self {
+        assert!(
+            (1..=8).contains(&cell_bits),
+            "stable bloom cells are 1 to 8 bits"
+        );
+        assert!(cells > 0 && k > 0 && k <= cells);
+        Self {
+            cells: vec![0; cells],
+            max: (u16::pow(2, cell_bits) - 1) as u8,
+            k,
+            decrements: decrements.min(cells),
+            entropy,
+        }
+    }
+
+    pub fn cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn decrements(&self) -> usize {
+        self.decrements
+    }
+
+    pub fn add(&mut self, element: &[u8]) {
+        let len = self.cells.len();
+        let start = (u64::from_le_bytes(self.entropy.block()) % len as u64) as usize;
+        for offset in 0..self.decrements {
+            let cell = &mut self.cells[(start + offset) % len];
+            *cell = cell.saturating_sub(1);
+        }
+        for index in bloom_indices_for_element(element, self.cells.len(), self.k) {
+            self.cells[index] = self.max;
+        }
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        bloom_indices_for_element(element, self.cells.len(), self.k)
+            .all(|index| self.cells[index] != 0)
+    }
+
+    // The dedup step: whether element looks like a duplicate, then adds it
+    // either way, so it counts as recent again.
+    pub fn check_and_add(&mut self, element: &[u8]) -> bool {
+        let seen = self.has(element);
+        self.add(element);
+        seen
+    }
+
+    pub fn zero_fraction(&self) -> f64 {
+        self.cells.iter().filter(|&&cell| cell == 0).count() as f64 / self.cells.len() as f64
+    }
+
+    // The fraction of zero cells the filter settles at, (1 / (1 + 1 /
+    // (decrements (1/k - 1/cells))))^max, and the false positive rate
+    // (1 - zeros)^k that comes with it.
+    pub fn stable_zeros(&self) -> f64 {
+        stable_zeros(self.cells.len(), self.k, self.max, self.decrements as f64)
+    }
+
+    pub fn stable_fpr(&self) -> f64 {
+        (1.0 - self.stable_zeros()).powi(self.k as i32)
+    }
+}
+
+fn stable_zeros(cells: usize, k: usize, max: u8, decrements: f64) -> f64 {
+    let rate = decrements * (1.0 / k as f64 - 1.0 / cells as f64);
+    (1.0 / (1.0 + 1.0 / rate)).powi(max as i32)
+}
+
+// Decrements per add for a stable false positive rate of fpr, the paper's
+// 1 / ((1 / (1 - fpr^(1/k)))^(1/max) - 1) / (1/k - 1/cells), at least 1.
+pub fn decrements_for(cells: usize, k: usize, cell_bits: u32, fpr: f64) -> usize {
+    assert!(fpr > 0.0 && fpr < 1.0, "a stable fpr needs 0 < fpr < 1");
+    let max = (2u32.pow(cell_bits) - 1) as f64;
+    let zeros = 1.0 - fpr.powf(1.0 / k as f64);
+    let decrements =
+        1.0 / ((1.0 / zeros).powf(1.0 / max) - 1.0) / (1.0 / k as f64 - 1.0 / cells as f64);
+    (decrements.round() as usize).max(1)
+}
+
+impl<E: EntropySource> Filter for StableBloom<E> {
+    fn add(&mut self, element: &[u8]) {
+        StableBloom::add(self, element);
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        StableBloom::has(self, element)
+    }
+
+    // nonzero cells
+    fn count_ones(&self) -> u32 {
+        self.cells.iter().filter(|&&cell| cell != 0).count() as u32
+    }
+}
+
+// `stable [--cells 1048576] [--cell-bits 3] [--k 4] [--fpr 0.01]
+// [--stream 10000000] [--duplicates 0.5] [--recent 100000]
+// [--report 1000000] [--seed <seed>]`
+//
+// Dedups a stream where each element repeats one of the `recent` latest
+// distinct elements with probability `duplicates`, and is new otherwise.
+// Every `report` elements it prints the share of new elements reported as
+// duplicates (false positives) and of duplicates let through (false
+// negatives) since the last report, with the zero cells against the stable
+// point.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let cells = cli::parsed(args, "--cells", 1usize << 20)?;
+    let cell_bits = cli::parsed(args, "--cell-bits", 3u32)?;
+    let k = cli::parsed(args, "--k", 4usize)?;
+    let fpr = cli::parsed(args, "--fpr", 0.01f64)?;
+    let stream = cli::parsed(args, "--stream", 10_000_000u64)?;
+    let duplicates = cli::parsed(args, "--duplicates", 0.5f64)?;
+    let recent = cli::parsed(args, "--recent", 100_000u64)?;
+    let report = cli::parsed(args, "--report", 1_000_000u64)?;
+    if !(1..=8).contains(&cell_bits) {
+        return Err(format!("--cell-bits must be 1 to 8, not {cell_bits}"));
+    }
+    if k == 0 || k > cells {
+        return Err(format!("--k must be 1 to --cells, not {k}"));
+    }
+    if !(fpr > 0.0 && fpr < 1.0) {
+        return Err(format!("--fpr must be between 0 and 1, not {fpr}"));
+    }
+    if !(0.0..=1.0).contains(&duplicates) {
+        return Err(format!("--duplicates must be 0 to 1, not {duplicates}"));
+    }
+    if recent == 0 || report == 0 {
+        return Err("--recent and --report must be positive".to_string());
+    }
+
+    let entropy = entropy::from_seed(cli::flag(args, "--seed"));
+    let decrements = decrements_for(cells, k, cell_bits, fpr);
+    let mut filter = StableBloom::with_entropy(cells, cell_bits, k, decrements, entropy);
+    println!(
+        "# cells {cells}, cell bits {cell_bits}, k {k}, decrements {decrements}, stable fpr {:.5}",
+        filter.stable_fpr()
+    );
+    println!("elements;new;false_positives;duplicates;false_negatives;zero_fraction;stable_zeros");
+    // elements are numbered by first appearance
+    let mut distinct = 0u64;
+    let (mut new, mut false_positives, mut repeats, mut false_negatives) = (0, 0, 0, 0);
+    for i in 1..=stream {
+        let draw = u64::from_le_bytes(filter.entropy.block());
+        let repeat = distinct > 0 && (draw as f64) < duplicates * u64::MAX as f64;
+        let element = if repeat {
+            let back = u64::from_le_bytes(filter.entropy.block()) % recent.min(distinct);
+            distinct - 1 - back
+        } else {
+            distinct += 1;
+            distinct - 1
+        };
+        let seen = filter.check_and_add(&encode_u64(element));
+        if repeat {
+            repeats += 1;
+            false_negatives += !seen as u64;
+        } else {
+            new += 1;
+            false_positives += seen as u64;
+        }
+        if i.is_multiple_of(report) {
+            println!(
+                "{i};{new};{false_positives};{repeats};{false_negatives};{:.4};{:.4}",
+                filter.zero_fraction(),
+                filter.stable_zeros()
+            );
+            (new, false_positives, repeats, false_negatives) = (0, 0, 0, 0);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_stable_bloom() {
+    use crate::entropy::ChaChaEntropy;
+
+    let cells = 10_000;
+    let decrements = decrements_for(cells, 4, 3, 0.01);
+    let mut filter =
+        StableBloom::with_entropy(cells, 3, 4, decrements, ChaChaEntropy::from_seed(1));
+    assert!(
+        (0.0095..0.0105).contains(&filter.stable_fpr()),
+        "{}",
+        filter.stable_fpr()
+    );
+    assert!(!filter.check_and_add(b"one"));
+    assert!(filter.check_and_add(b"one"));
+
+    // long past its capacity the zeros settle at the stable point, about
+    // which the false positive rate is what it was sized for
+    for i in 0..200_000u64 {
+        filter.add(&encode_u64(i));
+    }
+    let zeros = filter.zero_fraction();
+    assert!((zeros - filter.stable_zeros()).abs() < 0.02, "{zeros}");
+    let false_positives = (1 << 32..(1 << 32) + 100_000u64)
+        .filter(|&i| filter.has(&encode_u64(i)))
+        .count();
+    assert!((500..1600).contains(&false_positives), "{false_positives}");
+    // the latest elements are still there, the first ones decayed
+    assert!((199_900..200_000u64).all(|i| filter.has(&encode_u64(i))));
+    let early = (0..1000u64).filter(|&i| filter.has(&encode_u64(i))).count();
+    assert!(early < 30, "{early}");
+
+    // one bit cells and no decrements are a plain Bloom filter
+    let mut plain = StableBloom::with_entropy(2048, 1, 30, 0, ChaChaEntropy::from_seed(1));
+    let mut bloom = crate::Bloom::<256, 30>::new();
+    for i in 0..47u64 {
+        plain.add(&encode_u64(i));
+        bloom.add(&encode_u64(i));
+    }
+    assert_eq!(plain.count_ones(), bloom.count_ones());
+}
@@ -0,0 +1,81 @@
+// The ways filters in this crate derive their bit indices from an element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashStrategy {
+    // `Bloom` and `Folded`: one XXH3 stream (seeds 0, 1, ...) cut into
+    // fixed-width chunks, rejecting chunks that are out of range
+    Xxh3Rejection,
+    // one seeded XXH3 hash per index, reduced modulo m, without duplicates
+    Xxh3Seeded,
+    // `KeyedBloom`: like `Xxh3Rejection`, but over a keyed blake3 stream
+    Blake3Keyed,
+    // `WnfsBloom`: one seeded XXH3 hash per candidate index, reduced modulo
+    // the next power of two and rejected if out of range
+    Xxh3Wnfs,
+    // `Bip37Filter`: MurmurHash3 x86_32 with seed i * 0xFBA4C795 + tweak, modulo m
+    Murmur3Bip37,
+    // `DynIndexing::SipDouble`: the `bloomfilter` crate's double hashing over
+    // two SipHash-1-3 hashes, modulo m
+    SipDouble,
+    // Kirsch-Mitzenmacher double hashing: the two halves h1, h2 of one
+    // 128-bit XXH3 hash, index i is (h1 + i * (h2 | 1)) mod m
+    Xxh3Double,
+}
+
+pub const ALL_STRATEGIES: [HashStrategy; 7] = [
+    HashStrategy::Xxh3Rejection,
+    HashStrategy::Xxh3Seeded,
+    HashStrategy::Blake3Keyed,
+    HashStrategy::Xxh3Wnfs,
+    HashStrategy::Murmur3Bip37,
+    HashStrategy::SipDouble,
+    HashStrategy::Xxh3Double,
+];
+
+impl HashStrategy {
+    // wire ids, shared by proto/filter.proto and proto/filter.fbs; 0 is unspecified
+    pub fn id(self) -> u8 {
+        match self {
+            HashStrategy::Xxh3Rejection => 1,
+            HashStrategy::Xxh3Seeded => 2,
+            HashStrategy::Blake3Keyed => 3,
+            HashStrategy::Xxh3Wnfs => 4,
+            HashStrategy::Murmur3Bip37 => 5,
+            HashStrategy::SipDouble => 6,
+            HashStrategy::Xxh3Double => 7,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        ALL_STRATEGIES
+            .into_iter()
+            .find(|strategy| strategy.id() == id)
+    }
+
+    // the names used by vectors/<strategy>/ and experiment files
+    pub fn name(self) -> &'static str {
+        match self {
+            HashStrategy::Xxh3Rejection => "xxh3-rejection",
+            HashStrategy::Xxh3Seeded => "xxh3-seeded",
+            HashStrategy::Blake3Keyed => "blake3-keyed",
+            HashStrategy::Xxh3Wnfs => "xxh3-wnfs",
+            HashStrategy::Murmur3Bip37 => "murmur3-bip37",
+            HashStrategy::SipDouble => "sip-double",
+            HashStrategy::Xxh3Double => "xxh3-double",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_STRATEGIES
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+    }
+}
+
+#[test]
+fn test_strategy_ids() {
+    for strategy in ALL_STRATEGIES {
+        assert_eq!(HashStrategy::from_id(strategy.id()), Some(strategy));
+        assert_eq!(HashStrategy::from_name(strategy.name()), Some(strategy));
+    }
+    assert_eq!(HashStrategy::from_id(0), None);
+}
@@ -0,0 +1,260 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    cli,
+    dyn_bloom::{DynBloom, DynIndexing},
+    patch::Patch,
+    privacy::generated_universe,
+    xof::Blake3XOF,
+};
+
+// Two processes reconciling their filters over TCP:
+//
+//   client -> server  the client's filter (`DynBloom::to_bytes` envelope)
+//   server -> client  a `Patch` with the bits only the server has
+//
+// The server merges the client's bits into its own filter, the client applies
+// the patch, and both end up with the union. Frames are a u32 little endian
+// length followed by the payload.
+
+// refuse frames beyond this, a 2^32 bit filter plus its header
+const MAX_FRAME: u32 = (1 << 29) + 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncReport {
+    pub local_ones: u32,
+    pub remote_ones: u32,
+    pub merged_ones: u32,
+    // bits only this side had before merging
+    pub only_local: u32,
+    pub only_remote: u32,
+    pub sent_bytes: usize,
+    pub received_bytes: usize,
+}
+
+fn write_frame(stream: &mut impl Write, payload: &[u8]) -> Result<(), String> {
+    let len = u32::try_from(payload.len()).map_err(|_| "frame too large".to_string())?;
+    stream
+        .write_all(&len.to_le_bytes())
+        .and_then(|_| stream.write_all(payload))
+        .map_err(|e| format!("can't send: {e}"))
+}
+
+fn read_frame(stream: &mut impl Read) -> Result<Vec<u8>, String> {
+    let mut len = [0u8; 4];
+    stream
+        .read_exact(&mut len)
+        .map_err(|e| format!("can't receive: {e}"))?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_FRAME {
+        return Err(format!("frame of {len} bytes is too large"));
+    }
+    let mut payload = vec![0u8; len as usize];
+    stream
+        .read_exact(&mut payload)
+        .map_err(|e| format!("can't receive: {e}"))?;
+    Ok(payload)
+}
+
+fn only_in(ours: &DynBloom, theirs: &DynBloom) -> u32 {
+    ours.bytes()
+        .iter()
+        .zip(theirs.bytes())
+        .map(|(ours, theirs)| (ours & !theirs).count_ones())
+        .sum()
+}
+
+// serves a single client, merging its filter into `filter`
+pub fn serve(stream: &mut TcpStream, filter: &mut DynBloom) -> Result<SyncReport, String> {
+    let received = read_frame(stream)?;
+    let theirs =
+        DynBloom::try_from_bytes(&received).map_err(|e| format!("invalid filter: {e:?}"))?;
+    if (theirs.m_bits(), theirs.k(), theirs.indexing())
+        != (filter.m_bits(), filter.k(), filter.indexing())
+    {
+        return Err("client filter has different parameters".to_string());
+    }
+    let patch = DynBloom::diff(&theirs, filter);
+    write_frame(stream, patch.bytes())?;
+
+    let report = SyncReport {
+        local_ones: filter.count_ones(),
+        remote_ones: theirs.count_ones(),
+        merged_ones: 0,
+        only_local: only_in(filter, &theirs),
+        only_remote: only_in(&theirs, filter),
+        sent_bytes: patch.bytes().len(),
+        received_bytes: received.len(),
+    };
+    filter
+        .apply(&DynBloom::diff(filter, &theirs))
+        .map_err(|e| format!("can't merge: {e:?}"))?;
+    Ok(SyncReport {
+        merged_ones: filter.count_ones(),
+        ..report
+    })
+}
+
+pub fn sync(stream: &mut TcpStream, filter: &mut DynBloom) -> Result<SyncReport, String> {
+    let sent = filter.to_bytes();
+    write_frame(stream, &sent)?;
+    let received = read_frame(stream)?;
+    let patch = Patch::from_bytes(received).map_err(|e| format!("invalid patch: {e:?}"))?;
+    let before = filter.clone();
+    filter
+        .apply(&patch)
+        .map_err(|e| format!("can't merge: {e:?}"))?;
+    let only_remote = filter.count_ones() - before.count_ones();
+    Ok(SyncReport {
+        local_ones: before.count_ones(),
+        remote_ones: 0,
+        merged_ones: filter.count_ones(),
+        only_local: 0,
+        only_remote,
+        sent_bytes: sent.len(),
+        received_bytes: patch.bytes().len(),
+    })
+}
+
+// the client can't tell the server's popcount or its own exclusive bits from
+// the patch alone, those columns are only meaningful on the server
+fn print_report(role: &str, filter: &DynBloom, report: &SyncReport) {
+    println!("role;m_bits;k;local_ones;remote_ones;merged_ones;only_local;only_remote;sent_bytes;received_bytes");
+    println!(
+        "{role};{};{};{};{};{};{};{};{};{}",
+        filter.m_bits(),
+        filter.k(),
+        report.local_ones,
+        report.remote_ones,
+        report.merged_ones,
+        report.only_local,
+        report.only_remote,
+        report.sent_bytes,
+        report.received_bytes
+    );
+}
+
+// the local filter from `--elements <file>` (one per line) or
+// `--generate <n> [--seed <s>]`, sized by `--m-bits` and `--k`. Peers left
+// at the default seed generate the same elements.
+fn local_filter(args: &[String]) -> Result<DynBloom, String> {
+    let m_bits = cli::parsed(args, "--m-bits", 262_144)?;
+    let k = cli::parsed(args, "--k", 18)?;
+    let mut filter = DynBloom::try_new(m_bits, k, DynIndexing::Xxh3Rejection)
+        .map_err(|e| format!("invalid --m-bits or --k: {e:?}"))?;
+    match cli::flag(args, "--elements") {
+        Some(path) => {
+            let file = fs::File::open(path).map_err(|e| format!("can't read elements: {e}"))?;
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                filter.add(line.as_bytes());
+            }
+        }
+        None => {
+            let n = cli::parsed(args, "--generate", 10_000)?;
+            let seed = cli::flag(args, "--seed").unwrap_or("sync universe");
+            for element in generated_universe(Blake3XOF::new(seed), n) {
+                filter.add(&element);
+            }
+        }
+    }
+    Ok(filter)
+}
+
+// `sync-server --listen <addr> [--clients <n>] <filter flags>`
+pub fn run_server(args: &[String]) -> Result<(), String> {
+    let mut filter = local_filter(args)?;
+    let listener = TcpListener::bind(cli::required(args, "--listen")?)
+        .map_err(|e| format!("can't listen: {e}"))?;
+    let clients = cli::parsed(args, "--clients", 1)?;
+    for stream in listener.incoming().take(clients) {
+        let mut stream = stream.map_err(|e| format!("can't accept: {e}"))?;
+        let report = serve(&mut stream, &mut filter)?;
+        print_report("server", &filter, &report);
+    }
+    Ok(())
+}
+
+// `sync-client --connect <addr> <filter flags>`
+pub fn run_client(args: &[String]) -> Result<(), String> {
+    let mut filter = local_filter(args)?;
+    let mut stream = TcpStream::connect(cli::required(args, "--connect")?)
+        .map_err(|e| format!("can't connect: {e}"))?;
+    let report = sync(&mut stream, &mut filter)?;
+    print_report("client", &filter, &report);
+    Ok(())
+}
+
+#[test]
+fn test_sync() {
+    let mut server_filter = DynBloom::new(8192, 7);
+    let mut client_filter = DynBloom::new(8192, 7);
+    for element in generated_universe(Blake3XOF::new("shared"), 100) {
+        server_filter.add(&element);
+        client_filter.add(&element);
+    }
+    for element in generated_universe(Blake3XOF::new("server"), 50) {
+        server_filter.add(&element);
+    }
+    for element in generated_universe(Blake3XOF::new("client"), 20) {
+        client_filter.add(&element);
+    }
+    let mut expected = server_filter.clone();
+    expected
+        .apply(&DynBloom::diff(&server_filter, &client_filter))
+        .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let report = serve(&mut stream, &mut server_filter).unwrap();
+        (server_filter, report)
+    });
+    let mut stream = TcpStream::connect(address).unwrap();
+    let client_report = sync(&mut stream, &mut client_filter).unwrap();
+    let (server_filter, server_report) = server.join().unwrap();
+
+    assert_eq!(server_filter, expected);
+    assert_eq!(client_filter, expected);
+    assert_eq!(server_report.merged_ones, expected.count_ones());
+    assert_eq!(client_report.only_remote, server_report.only_local);
+    assert_eq!(
+        server_report.local_ones + server_report.only_remote,
+        server_report.merged_ones
+    );
+    // the patch is much smaller than the filter
+    assert!(server_report.sent_bytes < client_report.sent_bytes / 2);
+}
+
+#[test]
+fn test_sync_rejects_different_parameters() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        serve(&mut stream, &mut DynBloom::new(8192, 7))
+    });
+    let mut stream = TcpStream::connect(address).unwrap();
+    write_frame(&mut stream, &DynBloom::new(8192, 8).to_bytes()).unwrap();
+    assert!(server.join().unwrap().is_err());
+}
+
+#[test]
+fn test_local_filter_parameters() {
+    let args = |flags: &[&str]| flags.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        local_filter(&args(&["--m-bits", "0", "--generate", "10"])).err(),
+        Some("invalid --m-bits or --k: NoBits".to_string())
+    );
+    assert_eq!(
+        local_filter(&args(&["--m-bits", "8", "--k", "9"])).err(),
+        Some("invalid --m-bits or --k: TooManyHashFunctions { k: 9, m_bits: 8 }".to_string())
+    );
+    let filter =
+        local_filter(&args(&["--m-bits", "8192", "--k", "7", "--generate", "10"])).unwrap();
+    assert_eq!((filter.m_bits(), filter.k()), (8192, 7));
+}
@@ -0,0 +1,157 @@
+use crate::Bloom;
+
+// A `Bloom`'s bits as text, for pasting into tests and logs: lowercase hex,
+// or standard padded base64 (RFC 4648). Unlike `to_bytes` there's no
+// header, so M and K come from the type the text is read into, and only
+// the length can be checked against it.
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, PartialEq)]
+pub enum TextError {
+    Hex(hex::FromHexError),
+    InvalidBase64Character(char),
+    // a base64 length that isn't a multiple of 4, or padding in the middle
+    InvalidBase64Padding,
+    Length { expected: usize, found: usize },
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (i, &byte)| {
+            buffer | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(buffer >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, TextError> {
+    if !text.len().is_multiple_of(4) {
+        return Err(TextError::InvalidBase64Padding);
+    }
+    let data = text.trim_end_matches('=');
+    if text.len() - data.len() > 2 {
+        return Err(TextError::InvalidBase64Padding);
+    }
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in data.chars() {
+        if c == '=' {
+            return Err(TextError::InvalidBase64Padding);
+        }
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or(TextError::InvalidBase64Character(c))?;
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.bytes)
+    }
+
+    // upper or lower case, exactly 2 * M digits
+    pub fn from_hex(text: &str) -> Result<Self, TextError> {
+        let bytes = hex::decode(text).map_err(TextError::Hex)?;
+        Self::from_text_bytes(bytes)
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64_encode(&self.bytes)
+    }
+
+    pub fn from_base64(text: &str) -> Result<Self, TextError> {
+        Self::from_text_bytes(base64_decode(text)?)
+    }
+
+    fn from_text_bytes(bytes: Vec<u8>) -> Result<Self, TextError> {
+        let found = bytes.len();
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| TextError::Length { expected: M, found })?;
+        Ok(Self::from_bytes(bytes))
+    }
+}
+
+#[test]
+fn test_base64() {
+    // RFC 4648 test vectors
+    for (plain, encoded) in [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ] {
+        assert_eq!(base64_encode(plain.as_bytes()), encoded);
+        assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+    }
+    assert_eq!(
+        base64_decode("Zm9v-A=="),
+        Err(TextError::InvalidBase64Character('-'))
+    );
+    assert_eq!(base64_decode("Zg="), Err(TextError::InvalidBase64Padding));
+    assert_eq!(base64_decode("Z=9v"), Err(TextError::InvalidBase64Padding));
+}
+
+#[test]
+fn test_text_round_trip() {
+    let mut bloom = Bloom::<256, 30>::new();
+    for i in 0..40u64 {
+        bloom.add(&i.to_le_bytes());
+    }
+    let hex = bloom.to_hex();
+    assert_eq!(hex.len(), 512);
+    assert_eq!(Bloom::<256, 30>::from_hex(&hex).unwrap().bytes, bloom.bytes);
+    assert_eq!(
+        Bloom::<256, 30>::from_hex(&hex.to_uppercase())
+            .unwrap()
+            .bytes,
+        bloom.bytes
+    );
+    let base64 = bloom.to_base64();
+    assert_eq!(base64.len(), 344);
+    assert_eq!(
+        Bloom::<256, 30>::from_base64(&base64).unwrap().bytes,
+        bloom.bytes
+    );
+
+    assert_eq!(
+        Bloom::<128, 30>::from_hex(&hex).err(),
+        Some(TextError::Length {
+            expected: 128,
+            found: 256
+        })
+    );
+    assert_eq!(
+        Bloom::<256, 30>::from_hex(&hex[1..]).err(),
+        Some(TextError::Hex(hex::FromHexError::OddLength))
+    );
+    assert_eq!(
+        Bloom::<255, 30>::from_base64(&base64).err(),
+        Some(TextError::Length {
+            expected: 255,
+            found: 256
+        })
+    );
+}
@@ -0,0 +1,195 @@
+use std::{
+    borrow::Cow,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+};
+
+use crate::{
+    cli,
+    counted::{Capacity, Counted},
+    dyn_bloom::{DynBloom, DynIndexing},
+    filter::Filter,
+    serve,
+};
+
+// Standalone filter tooling over `DynBloom::to_bytes` files.
+
+// the element of a line, hex decoded with `hex`; numbers count from 1
+pub fn decode_line(line: &str, number: u64, hex: bool) -> Result<Cow<'_, [u8]>, String> {
+    if hex {
+        hex::decode(line)
+            .map(Cow::Owned)
+            .map_err(|e| format!("line {number}: invalid hex: {e}"))
+    } else {
+        Ok(Cow::Borrowed(line.as_bytes()))
+    }
+}
+
+// one element per line, trailing "\r" stripped
+fn for_each_element<R: BufRead>(
+    input: R,
+    hex: bool,
+    mut f: impl FnMut(&str, &[u8]) -> Result<(), String>,
+) -> Result<u64, String> {
+    let mut count = 0;
+    for line in input.lines() {
+        let line = line.map_err(|e| format!("can't read input: {e}"))?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        f(line, &decode_line(line, count + 1, hex)?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub fn add_lines<F: Filter, R: BufRead>(
+    filter: &mut F,
+    input: R,
+    hex: bool,
+) -> Result<u64, String> {
+    for_each_element(input, hex, |_, element| {
+        filter.add(element);
+        Ok(())
+    })
+}
+
+// writes "true" or "false", a tab and the line for every input line, returns
+// (positives, lines)
+pub fn query_lines<R: BufRead, W: Write>(
+    filter: &DynBloom,
+    input: R,
+    output: &mut W,
+    hex: bool,
+) -> Result<(u64, u64), String> {
+    let mut positives = 0;
+    let lines = for_each_element(input, hex, |line, element| {
+        let has = filter.has(element);
+        positives += has as u64;
+        writeln!(output, "{has}\t{line}").map_err(|e| format!("can't write output: {e}"))
+    })?;
+    Ok((positives, lines))
+}
+
+// `build --m-bits <m> --k <k> [--hex] [--input <file>]... [--output <file>]
+//  [--fpr <p>]`
+//
+// Reads stdin unless input files are given, writes the filter to stdout
+// unless an output file is given. With --fpr, warns once the input outgrows
+// the filter's capacity at that false positive rate.
+pub fn run_build(args: &[String]) -> Result<(), String> {
+    let m_bits: usize = cli::required(args, "--m-bits")?
+        .parse()
+        .map_err(|_| "invalid value for --m-bits".to_string())?;
+    let k: usize = cli::required(args, "--k")?
+        .parse()
+        .map_err(|_| "invalid value for --k".to_string())?;
+    let filter = DynBloom::try_new(m_bits, k, DynIndexing::Xxh3Rejection)
+        .map_err(|e| format!("invalid --m-bits or --k: {e:?}"))?;
+    let mut counted = Counted::new(filter, m_bits);
+    if let Some(fpr) = cli::flag(args, "--fpr") {
+        let fpr: f64 = fpr.parse().map_err(|_| "invalid value for --fpr")?;
+        if !(fpr > 0.0 && fpr < 1.0) {
+            return Err("--fpr must be between 0 and 1".to_string());
+        }
+        counted = counted.with_capacity(Capacity::for_fpr(m_bits, k, fpr));
+    }
+    let hex = cli::switch(args, "--hex");
+    let inputs = cli::flags(args, "--input");
+    if inputs.is_empty() {
+        add_lines(&mut counted, io::stdin().lock(), hex)?;
+    }
+    for path in inputs {
+        let file = fs::File::open(path).map_err(|e| format!("can't read {path}: {e}"))?;
+        add_lines(&mut counted, BufReader::new(file), hex)?;
+    }
+    let added = counted.inserted();
+    let filter = counted.into_inner();
+
+    let bytes = filter.to_bytes();
+    match cli::flag(args, "--output") {
+        Some(path) => fs::write(path, &bytes).map_err(|e| format!("can't write {path}: {e}"))?,
+        None => io::stdout()
+            .lock()
+            .write_all(&bytes)
+            .map_err(|e| format!("can't write filter: {e}"))?,
+    }
+    eprintln!(
+        "added {added} elements, {} of {m_bits} bits set",
+        filter.count_ones()
+    );
+    Ok(())
+}
+
+// `query <filter file> [--hex] [--input <file>]...`
+//
+// Accepts `DynBloom` and unfolded `Bloom` files, reads stdin unless input
+// files are given.
+pub fn run_query(args: &[String]) -> Result<(), String> {
+    let path = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .ok_or("missing filter file")?;
+    let data = fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let filter = serve::queryable(&data)?;
+    let hex = cli::switch(args, "--hex");
+    let inputs = cli::flags(args, "--input");
+    let mut output = io::BufWriter::new(io::stdout().lock());
+    let (mut positives, mut lines) = (0, 0);
+    let mut tally = |(p, l): (u64, u64)| {
+        positives += p;
+        lines += l;
+    };
+    if inputs.is_empty() {
+        tally(query_lines(&filter, io::stdin().lock(), &mut output, hex)?);
+    }
+    for path in inputs {
+        let file = fs::File::open(path).map_err(|e| format!("can't read {path}: {e}"))?;
+        tally(query_lines(
+            &filter,
+            BufReader::new(file),
+            &mut output,
+            hex,
+        )?);
+    }
+    output
+        .flush()
+        .map_err(|e| format!("can't write output: {e}"))?;
+    eprintln!("{positives} of {lines} lines may be in the filter");
+    Ok(())
+}
+
+#[test]
+fn test_add_lines() {
+    let mut raw = DynBloom::new(4096, 7);
+    assert_eq!(add_lines(&mut raw, &b"one\r\ntwo\nthree"[..], false), Ok(3));
+    assert!(raw.has(b"one") && raw.has(b"two") && raw.has(b"three"));
+
+    let mut decoded = DynBloom::new(4096, 7);
+    assert_eq!(
+        add_lines(&mut decoded, &b"6f6e65\n74776f\n"[..], true),
+        Ok(2)
+    );
+    assert!(decoded.has(b"one") && decoded.has(b"two"));
+    assert_eq!(
+        add_lines(&mut decoded, &b"6f6e65\nxyzw\n"[..], true),
+        Err("line 2: invalid hex: Invalid character 'x' at position 0".to_string())
+    );
+}
+
+#[test]
+fn test_query_lines() {
+    let mut filter = DynBloom::new(4096, 7);
+    add_lines(&mut filter, &b"one\ntwo\n"[..], false).unwrap();
+    let mut output = Vec::new();
+    assert_eq!(
+        query_lines(&filter, &b"one\nthree\r\ntwo"[..], &mut output, false),
+        Ok((2, 3))
+    );
+    assert_eq!(output, b"true\tone\nfalse\tthree\ntrue\ttwo\n");
+
+    let mut output = Vec::new();
+    assert_eq!(
+        query_lines(&filter, &b"6f6e65\n"[..], &mut output, true),
+        Ok((1, 1))
+    );
+    assert_eq!(output, b"true\t6f6e65\n");
+}
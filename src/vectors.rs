@@ -0,0 +1,147 @@
+use crate::{dyn_bloom::DynBloom, harness::fill_deterministic, Bloom};
+
+// Saturation test vectors shared with the JS and Go accumulator implementations.
+//
+// vectors/saturation.txt has one vector per line:
+// `<M bytes> <K> <input hex> <saturated hex>`
+
+const SATURATION_VECTORS: &str = include_str!("../vectors/saturation.txt");
+
+// `DynBloom` vectors shared by the native build and the wasm bindings.
+//
+// vectors/dyn_bloom.txt has one vector per line:
+// `<m bits> <k> <n> <to_bytes hex>`, the filter holding the UTF-8 strings
+// "element 0" up to "element <n - 1>"
+
+const DYN_VECTORS: &str = include_str!("../vectors/dyn_bloom.txt");
+
+const DYN_SHAPES: [(usize, usize); 3] = [(1001, 7), (2048, 3), (256 * 8, 30)];
+const DYN_ELEMENTS: [usize; 3] = [0, 1, 20];
+
+pub struct DynVector {
+    pub m_bits: usize,
+    pub k: usize,
+    pub n: usize,
+    pub filter_hex: String,
+}
+
+impl DynVector {
+    pub fn elements(&self) -> impl Iterator<Item = Vec<u8>> {
+        (0..self.n).map(|i| format!("element {i}").into_bytes())
+    }
+}
+
+pub fn dyn_vectors() -> Vec<DynVector> {
+    DYN_VECTORS
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|line| {
+            let fields: Vec<&str> = line.split(' ').collect();
+            DynVector {
+                m_bits: fields[0].parse().unwrap(),
+                k: fields[1].parse().unwrap(),
+                n: fields[2].parse().unwrap(),
+                filter_hex: fields[3].to_string(),
+            }
+        })
+        .collect()
+}
+
+// `dyn-vectors`, regenerates vectors/dyn_bloom.txt
+pub fn print_dyn_vectors() {
+    for (m_bits, k) in DYN_SHAPES {
+        for n in DYN_ELEMENTS {
+            let mut vector = DynVector {
+                m_bits,
+                k,
+                n,
+                filter_hex: String::new(),
+            };
+            let mut bloom = DynBloom::new(m_bits, k);
+            for element in vector.elements() {
+                bloom.add(&element);
+            }
+            vector.filter_hex = hex::encode(bloom.to_bytes());
+            println!("{m_bits} {k} {n} {}", vector.filter_hex);
+        }
+    }
+}
+
+// prefill sizes for each shape: empty filter, a single element and a few more
+const PREFILLS: [u32; 3] = [0, 1, 20];
+
+fn saturation_vectors_for<const M: usize, const K: usize>() -> Vec<(Bloom<M, K>, Bloom<M, K>)> {
+    PREFILLS
+        .iter()
+        .map(|prefill| {
+            let mut input: Bloom<M, K> = Bloom::new();
+            fill_deterministic("saturation vectors", *prefill, &mut input);
+            let mut saturated = input.clone();
+            saturated.saturate();
+            (input, saturated)
+        })
+        .collect()
+}
+
+fn print_saturation_vectors_for<const M: usize, const K: usize>() {
+    for (input, saturated) in saturation_vectors_for::<M, K>() {
+        println!(
+            "{M} {K} {} {}",
+            hex::encode(input.bytes),
+            hex::encode(saturated.bytes)
+        );
+    }
+}
+
+// `saturation-vectors`, regenerates vectors/saturation.txt
+pub fn print_saturation_vectors() {
+    print_saturation_vectors_for::<256, 30>();
+    print_saturation_vectors_for::<256, 16>();
+    print_saturation_vectors_for::<256, 4>();
+    print_saturation_vectors_for::<512, 30>();
+}
+
+fn check_saturation_vector<const M: usize, const K: usize>(input: &str, expected: &str) {
+    let bytes: [u8; M] = hex::decode(input).unwrap().try_into().unwrap();
+    let mut bloom: Bloom<M, K> = Bloom::from_bytes(bytes);
+    bloom.saturate();
+    assert_eq!(hex::encode(bloom.bytes), expected, "saturating M={M} K={K}");
+}
+
+#[test]
+fn test_saturation_vectors() {
+    let mut checked = 0;
+    for line in SATURATION_VECTORS.lines().filter(|l| !l.is_empty()) {
+        let fields: Vec<&str> = line.split(' ').collect();
+        let (input, expected) = (fields[2], fields[3]);
+        match (fields[0], fields[1]) {
+            ("256", "30") => check_saturation_vector::<256, 30>(input, expected),
+            ("256", "16") => check_saturation_vector::<256, 16>(input, expected),
+            ("256", "4") => check_saturation_vector::<256, 4>(input, expected),
+            ("512", "30") => check_saturation_vector::<512, 30>(input, expected),
+            shape => panic!("no vectors for shape {shape:?}"),
+        }
+        checked += 1;
+    }
+    assert_eq!(checked, 4 * PREFILLS.len());
+}
+
+#[test]
+fn test_dyn_vectors() {
+    let vectors = dyn_vectors();
+    for vector in &vectors {
+        let mut bloom = DynBloom::new(vector.m_bits, vector.k);
+        for element in vector.elements() {
+            bloom.add(&element);
+        }
+        assert_eq!(
+            hex::encode(bloom.to_bytes()),
+            vector.filter_hex,
+            "m={} k={} n={}",
+            vector.m_bits,
+            vector.k,
+            vector.n
+        );
+    }
+    assert_eq!(vectors.len(), DYN_SHAPES.len() * DYN_ELEMENTS.len());
+}
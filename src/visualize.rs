@@ -0,0 +1,300 @@
+use std::{fmt, fs};
+
+use crate::{cli, dyn_bloom::DynBloom, folded::Folded, serve, Bloom};
+
+// Filter bit arrays as images, bit i at row i / width, column i % width.
+// A single filter renders set bits black; several filters of the same size
+// render as a heatmap, darker where more of them set the bit. Pixels past
+// the last bit are white.
+//
+// Formats by output extension: .pbm (binary bitmap, single filters only),
+// .pgm and .png (8-bit grayscale, written uncompressed). Without an output
+// each filter is printed as text by `render_ascii`.
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    // row major, 0 is black
+    pub pixels: Vec<u8>,
+}
+
+// about square unless a width is given
+pub fn dimensions(m_bits: usize, width: Option<usize>) -> (usize, usize) {
+    let width = width
+        .unwrap_or_else(|| (m_bits as f64).sqrt().ceil() as usize)
+        .max(1);
+    (width, m_bits.div_ceil(width))
+}
+
+fn bit(bits: &[u8], index: usize) -> bool {
+    bits[index / 8] & (1 << (index % 8)) != 0
+}
+
+pub fn heatmap(filters: &[DynBloom], width: Option<usize>) -> Result<GrayImage, String> {
+    let m_bits = filters.first().ok_or("no filters")?.m_bits();
+    if filters.iter().any(|filter| filter.m_bits() != m_bits) {
+        return Err("filters of different sizes".to_string());
+    }
+    let (width, height) = dimensions(m_bits, width);
+    let mut pixels = vec![255u8; width * height];
+    for (index, pixel) in pixels.iter_mut().enumerate().take(m_bits) {
+        let set = filters
+            .iter()
+            .filter(|filter| bit(filter.bytes(), index))
+            .count();
+        *pixel = 255 - (set * 255 / filters.len()) as u8;
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// P4, rows padded to whole bytes, most significant bit first, 1 is black
+pub fn to_pbm(filter: &DynBloom, width: Option<usize>) -> Vec<u8> {
+    let m_bits = filter.m_bits();
+    let (width, height) = dimensions(m_bits, width);
+    let mut out = format!("P4\n{width} {height}\n").into_bytes();
+    for row in 0..height {
+        let mut packed = vec![0u8; width.div_ceil(8)];
+        for column in 0..width {
+            let index = row * width + column;
+            if index < m_bits && bit(filter.bytes(), index) {
+                packed[column / 8] |= 0x80 >> (column % 8);
+            }
+        }
+        out.extend_from_slice(&packed);
+    }
+    out
+}
+
+pub fn to_pgm(image: &GrayImage) -> Vec<u8> {
+    let mut out = format!("P5\n{} {}\n255\n", image.width, image.height).into_bytes();
+    out.extend_from_slice(&image.pixels);
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// grayscale PNG whose zlib stream only has stored deflate blocks
+pub fn to_png(image: &GrayImage) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((image.width + 1) * image.height);
+    for row in image.pixels.chunks(image.width) {
+        raw.push(0); // no filter
+        raw.extend_from_slice(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    while let Some(block) = blocks.next() {
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_chunk(&mut out, b"IHDR", &header);
+    push_chunk(&mut out, b"IDAT", &zlib);
+    push_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+// one character per byte, darker the more of its bits are set
+const SHADES: [char; 9] = [' ', '.', ':', '-', '=', '+', '*', '#', '@'];
+const ASCII_WIDTH: usize = 64;
+
+// A summary line (m, k, ones, fill) followed by rows of `ASCII_WIDTH` bytes,
+// each `|` delimited row covering 8 * `ASCII_WIDTH` bits.
+pub fn render_ascii(bits: &[u8], m_bits: usize, k: usize) -> String {
+    let ones: u32 = bits.iter().map(|byte| byte.count_ones()).sum();
+    let fill = 100.0 * ones as f64 / m_bits.max(1) as f64;
+    let mut text = format!("m={m_bits} k={k} ones={ones} fill={fill:.2}%\n");
+    for row in bits.chunks(ASCII_WIDTH) {
+        text.push('|');
+        text.extend(row.iter().map(|byte| SHADES[byte.count_ones() as usize]));
+        text.push_str("|\n");
+    }
+    text
+}
+
+impl<const M: usize, const K: usize> Bloom<M, K> {
+    pub fn render_ascii(&self) -> String {
+        render_ascii(&self.bytes, M * 8, K)
+    }
+}
+
+impl<const M: usize, const K: usize> fmt::Display for Bloom<M, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render_ascii())
+    }
+}
+
+impl DynBloom {
+    pub fn render_ascii(&self) -> String {
+        render_ascii(self.bytes(), self.m_bits(), self.k())
+    }
+}
+
+impl fmt::Display for DynBloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render_ascii())
+    }
+}
+
+// the stored (folded) bits, m is S * 8
+impl<const F: usize, const S: usize, const K: usize> Folded<F, S, K> {
+    pub fn render_ascii(&self) -> String {
+        render_ascii(&self.bytes, S * 8, K)
+    }
+}
+
+impl<const F: usize, const S: usize, const K: usize> fmt::Display for Folded<F, S, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render_ascii())
+    }
+}
+
+// `visualize <filter file>... [--output <image>] [--width <w>]`
+pub fn run(args: &[String]) -> Result<(), String> {
+    let width = cli::flag(args, "--width")
+        .map(|width| width.parse().map_err(|_| "invalid value for --width"))
+        .transpose()?;
+    // file arguments are the ones no flag precedes
+    let paths: Vec<&String> = args
+        .iter()
+        .enumerate()
+        .filter(|(i, arg)| !arg.starts_with("--") && (*i == 0 || !args[i - 1].starts_with("--")))
+        .map(|(_, arg)| arg)
+        .collect();
+    let filters = paths
+        .iter()
+        .map(|path| {
+            let data = fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?;
+            serve::queryable(&data).map_err(|e| format!("{path}: {e}"))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let Some(output) = cli::flag(args, "--output") else {
+        for (path, filter) in paths.iter().zip(&filters) {
+            print!("{path}: {filter}");
+        }
+        return Ok(());
+    };
+    let image = match output.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("pbm") if filters.len() == 1 => to_pbm(&filters[0], width),
+        Some("pbm") => return Err("heatmaps need a .pgm or .png output".to_string()),
+        Some("pgm") => to_pgm(&heatmap(&filters, width)?),
+        Some("png") => to_png(&heatmap(&filters, width)?),
+        _ => return Err(format!("unknown image format: {output}")),
+    };
+    fs::write(output, image).map_err(|e| format!("can't write {output}: {e}"))
+}
+
+#[test]
+fn test_dimensions() {
+    assert_eq!(dimensions(2048, None), (46, 45));
+    assert_eq!(dimensions(1001, Some(100)), (100, 11));
+    assert_eq!(dimensions(64, Some(8)), (8, 8));
+}
+
+#[test]
+fn test_to_pbm() {
+    let filter = DynBloom::from_bytes(
+        vec![0b0000_0001, 0b1000_0000, 0b1],
+        17,
+        1,
+        crate::dyn_bloom::DynIndexing::Xxh3Rejection,
+    )
+    .unwrap();
+    // bits 0, 15 and 16 on a 10 wide image
+    assert_eq!(
+        to_pbm(&filter, Some(10)),
+        b"P4\n10 2\n\x80\x00\x06\x00".to_vec()
+    );
+}
+
+#[test]
+fn test_heatmap() {
+    let mut one = DynBloom::new(16, 1);
+    one.bits_mut()[0] = 0b11;
+    let mut two = DynBloom::new(16, 1);
+    two.bits_mut()[0] = 0b01;
+    let image = heatmap(&[one.clone(), two], Some(4)).unwrap();
+    assert_eq!((image.width, image.height), (4, 4));
+    assert_eq!(&image.pixels[..3], &[0, 128, 255]);
+    assert!(heatmap(&[one, DynBloom::new(8, 1)], None).is_err());
+    assert!(to_pgm(&image).starts_with(b"P5\n4 4\n255\n"));
+}
+
+#[test]
+fn test_to_png() {
+    assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    let image = GrayImage {
+        width: 2,
+        height: 1,
+        pixels: vec![0, 255],
+    };
+    let png = to_png(&image);
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x02\0\0\0\x01\x08\0"));
+    assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+    // one final stored block holding the filter byte and both pixels
+    let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+    assert_eq!(
+        &png[idat..idat + 10],
+        b"\x78\x01\x01\x03\x00\xfc\xff\x00\x00\xff"
+    );
+}
+
+#[test]
+fn test_render_ascii() {
+    let mut filter = DynBloom::new(17, 1);
+    filter.bits_mut().copy_from_slice(&[0xff, 0b0001_0001, 0b1]);
+    assert_eq!(filter.to_string(), "m=17 k=1 ones=11 fill=64.71%\n|@:.|\n");
+
+    let mut bloom = Bloom::<256, 30>::new();
+    bloom.add(b"one");
+    let text = bloom.render_ascii();
+    let mut lines = text.lines();
+    assert!(lines.next().unwrap().starts_with("m=2048 k=30 ones="));
+    assert_eq!(lines.clone().count(), 4);
+    assert!(lines.all(|row| row.chars().count() == ASCII_WIDTH + 2));
+}
@@ -0,0 +1,64 @@
+use wasm_bindgen::prelude::*;
+
+use crate::dyn_bloom::{DynBloom, DynIndexing};
+
+// wasm-bindgen exports for building and querying filters in the browser.
+// Serialized filters are `DynBloom::to_bytes` envelopes, so they move freely
+// between the web client and native code.
+
+#[wasm_bindgen]
+pub struct WasmBloom {
+    inner: DynBloom,
+}
+
+#[wasm_bindgen]
+impl WasmBloom {
+    #[wasm_bindgen(constructor)]
+    pub fn new(m_bits: usize, k: usize) -> Result<WasmBloom, String> {
+        #[cfg(target_pointer_width = "32")]
+        crate::check_index_vectors()?;
+        Ok(Self {
+            inner: DynBloom::try_new(m_bits, k, DynIndexing::Xxh3Rejection)
+                .map_err(|e| format!("{e:?}"))?,
+        })
+    }
+
+    // true if the element definitely wasn't in the filter before
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        self.inner.add(element)
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.inner.has(element)
+    }
+
+    #[wasm_bindgen(js_name = countOnes)]
+    pub fn count_ones(&self) -> u32 {
+        self.inner.count_ones()
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<WasmBloom, String> {
+        DynBloom::try_from_bytes(bytes)
+            .map(|inner| Self { inner })
+            .map_err(|e| format!("invalid filter: {e:?}"))
+    }
+}
+
+#[test]
+fn test_wasm_bloom_vectors() {
+    for vector in crate::vectors::dyn_vectors() {
+        let mut bloom = WasmBloom::new(vector.m_bits, vector.k).unwrap();
+        for element in vector.elements() {
+            bloom.add(&element);
+        }
+        assert_eq!(hex::encode(bloom.serialize()), vector.filter_hex);
+        let decoded = WasmBloom::deserialize(&bloom.serialize()).unwrap();
+        assert!(vector.elements().all(|element| decoded.has(&element)));
+    }
+    assert!(WasmBloom::deserialize(b"garbage").is_err());
+    assert!(WasmBloom::new(0, 7).is_err());
+}
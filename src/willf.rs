@@ -0,0 +1,200 @@
+use crate::filter::Filter;
+
+// Bloom filters compatible with the Go willf/bloom package. `WriteTo` emits
+// m, k and the bit set length (all u64 big endian), then the bit set's u64
+// words, each big endian. Bit i lives in word i / 64 at position i % 64.
+//
+// Index i is (a + i * b) % m, where a and b are the lower and upper 32-bit
+// halves of the 64-bit FNV-1 hash of the element, widened to Go's 64-bit
+// `uint` before the sum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoBloom {
+    m: u64,
+    k: u64,
+    words: Vec<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GoBloomError {
+    Truncated,
+    TrailingBytes(usize),
+    // the bit set's length has to equal m
+    LengthMismatch { m: u64, length: u64 },
+    ZeroSize,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// Go's hash/fnv New64: multiply, then xor
+pub fn fnv1_64(data: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for &byte in data {
+        hash = hash.wrapping_mul(FNV_PRIME);
+        hash ^= byte as u64;
+    }
+    hash
+}
+
+fn words_needed(bits: u64) -> usize {
+    bits.div_ceil(64) as usize
+}
+
+impl GoBloom {
+    pub fn new(m: u64, k: u64) -> Self {
+        Self {
+            m,
+            k,
+            words: vec![0; words_needed(m)],
+        }
+    }
+
+    pub fn read_from(data: &[u8]) -> Result<Self, GoBloomError> {
+        if data.len() < 24 {
+            return Err(GoBloomError::Truncated);
+        }
+        let field = |i: usize| u64::from_be_bytes(data[i * 8..i * 8 + 8].try_into().unwrap());
+        let (m, k, length) = (field(0), field(1), field(2));
+        if m == 0 {
+            return Err(GoBloomError::ZeroSize);
+        }
+        if length != m {
+            return Err(GoBloomError::LengthMismatch { m, length });
+        }
+        let body = &data[24..];
+        let words = words_needed(length);
+        if (body.len() as u64) < words as u64 * 8 {
+            return Err(GoBloomError::Truncated);
+        }
+        if body.len() > words * 8 {
+            return Err(GoBloomError::TrailingBytes(body.len() - words * 8));
+        }
+        let words = body
+            .chunks_exact(8)
+            .map(|word| u64::from_be_bytes(word.try_into().unwrap()))
+            .collect();
+        Ok(Self { m, k, words })
+    }
+
+    pub fn write_to(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(24 + self.words.len() * 8);
+        out.extend_from_slice(&self.m.to_be_bytes());
+        out.extend_from_slice(&self.k.to_be_bytes());
+        out.extend_from_slice(&self.m.to_be_bytes());
+        for word in &self.words {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+
+    pub fn indices_for(&self, data: &[u8]) -> impl Iterator<Item = u64> {
+        let hash = fnv1_64(data);
+        let a = hash as u32 as u64;
+        let b = hash >> 32;
+        let m = self.m;
+        (0..self.k).map(move |i| a.wrapping_add(b.wrapping_mul(i)) % m)
+    }
+
+    pub fn add(&mut self, data: &[u8]) {
+        for index in self.indices_for(data).collect::<Vec<_>>() {
+            self.words[(index / 64) as usize] |= 1 << (index % 64);
+        }
+    }
+
+    pub fn test(&self, data: &[u8]) -> bool {
+        self.indices_for(data)
+            .all(|index| self.words[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    pub fn m(&self) -> u64 {
+        self.m
+    }
+
+    pub fn k(&self) -> u64 {
+        self.k
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+}
+
+impl Filter for GoBloom {
+    fn add(&mut self, element: &[u8]) {
+        GoBloom::add(self, element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        GoBloom::test(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        GoBloom::count_ones(self)
+    }
+}
+
+#[test]
+fn test_fnv1_64() {
+    // values from Go's hash/fnv tests
+    assert_eq!(fnv1_64(b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1_64(b"a"), 0xaf63bd4c8601b7be);
+    assert_eq!(fnv1_64(b"ab"), 0x08326707b4eb37b8);
+    assert_eq!(fnv1_64(b"abc"), 0xd8dcca186bafadcb);
+}
+
+#[test]
+fn test_go_bloom_layout() {
+    // the bits willf/bloom's New(100, 3).Add([]byte("a")) sets; a + b
+    // overflows 32 bits, which Go's 64-bit uint doesn't wrap
+    let mut bloom = GoBloom::new(100, 3);
+    bloom.add(b"a");
+    assert_eq!(bloom.indices_for(b"a").collect::<Vec<_>>(), [18, 42, 66]);
+    let bytes = bloom.write_to();
+    assert_eq!(bytes.len(), 24 + 2 * 8);
+    assert_eq!(
+        &bytes[..24],
+        &[
+            [0, 0, 0, 0, 0, 0, 0, 100],
+            [0, 0, 0, 0, 0, 0, 0, 3],
+            [0, 0, 0, 0, 0, 0, 0, 100]
+        ]
+        .concat()[..]
+    );
+    let word0 = (1u64 << 18) | (1 << 42);
+    let word1 = 1u64 << (66 - 64);
+    assert_eq!(&bytes[24..32], &word0.to_be_bytes());
+    assert_eq!(&bytes[32..], &word1.to_be_bytes());
+    assert!(bloom.test(b"a"));
+}
+
+#[test]
+fn test_go_bloom_round_trip() {
+    let mut bloom = GoBloom::new(1000, 7);
+    for i in 0..100u32 {
+        bloom.add(format!("{i}").as_bytes());
+    }
+    let decoded = GoBloom::read_from(&bloom.write_to()).unwrap();
+    assert_eq!(decoded, bloom);
+    assert!((0..100u32).all(|i| decoded.test(format!("{i}").as_bytes())));
+
+    let bytes = bloom.write_to();
+    assert_eq!(
+        GoBloom::read_from(&bytes[..bytes.len() - 1]),
+        Err(GoBloomError::Truncated)
+    );
+    let mut long = bytes.clone();
+    long.push(0);
+    assert_eq!(
+        GoBloom::read_from(&long),
+        Err(GoBloomError::TrailingBytes(1))
+    );
+    let mut mismatched = bytes;
+    mismatched[23] = 99;
+    assert_eq!(
+        GoBloom::read_from(&mismatched),
+        Err(GoBloomError::LengthMismatch {
+            m: 1000,
+            length: 0x363
+        })
+    );
+}
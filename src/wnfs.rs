@@ -0,0 +1,137 @@
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+use crate::{filter::Filter, indexer::Xxh3Wnfs, Bloom};
+
+// Filters bit-identical to the WNFS private forest bloom filters: index i is
+// the first xxh3_64_with_seed(element, seed) % m.next_power_of_two() below m,
+// trying seeds 0, 1, 2, ... across all indices. Bits are numbered least
+// significant first within each byte, as in `Bloom`. Duplicate indices are
+// not skipped.
+#[derive(Clone)]
+pub struct WnfsBloom<const M: usize, const K: usize> {
+    bloom: Bloom<M, K>,
+}
+
+// the private forest namefilter shape
+pub type WnfsNamefilter = WnfsBloom<256, 30>;
+
+pub struct WnfsIndices<'a> {
+    element: &'a [u8],
+    m_bits: usize,
+    seed: u64,
+}
+
+impl<'a> WnfsIndices<'a> {
+    pub fn new(element: &'a [u8], m_bits: usize) -> Self {
+        Self {
+            element,
+            m_bits,
+            seed: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for WnfsIndices<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m_bits_po2 = self.m_bits.next_power_of_two() as u64;
+        loop {
+            let hash = xxh3_64_with_seed(self.element, self.seed);
+            self.seed += 1;
+            let value = (hash % m_bits_po2) as usize;
+            if value < self.m_bits {
+                return Some(value);
+            }
+        }
+    }
+}
+
+impl<const M: usize, const K: usize> WnfsBloom<M, K> {
+    pub fn new() -> Self {
+        Self {
+            bloom: Bloom::new(),
+        }
+    }
+
+    pub fn from_bytes(bytes: [u8; M]) -> Self {
+        Self {
+            bloom: Bloom::from_bytes(bytes),
+        }
+    }
+
+    pub fn add(&mut self, element: &[u8]) {
+        self.bloom.add_with(&Xxh3Wnfs, element);
+    }
+
+    pub fn has(&self, element: &[u8]) -> bool {
+        self.bloom.has_with(&Xxh3Wnfs, element)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.bloom.count_ones()
+    }
+
+    // see `Bloom::indices_for`
+    pub fn indices_for(&self, element: &[u8]) -> [usize; K] {
+        self.bloom.indices_for_with(&Xxh3Wnfs, element)
+    }
+
+    pub fn bytes(&self) -> &[u8; M] {
+        &self.bloom.bytes
+    }
+}
+
+impl<const M: usize, const K: usize> Default for WnfsBloom<M, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const M: usize, const K: usize> Filter for WnfsBloom<M, K> {
+    fn add(&mut self, element: &[u8]) {
+        WnfsBloom::add(self, element)
+    }
+
+    fn has(&self, element: &[u8]) -> bool {
+        WnfsBloom::has(self, element)
+    }
+
+    fn count_ones(&self) -> u32 {
+        WnfsBloom::count_ones(self)
+    }
+}
+
+#[test]
+fn test_wnfs_indices() {
+    // from wnfs-namefilter 0.1.23's `BloomFilter::<256, 30>::hash_indices`
+    let indices: Vec<usize> = WnfsIndices::new(b"one", 2048).take(30).collect();
+    assert_eq!(
+        indices,
+        [
+            416, 986, 623, 1554, 83, 1446, 880, 307, 1201, 1872, 1126, 94, 490, 1128, 83, 70, 1286,
+            1524, 1349, 1707, 913, 1334, 1101, 172, 1418, 1258, 545, 392, 1529, 294
+        ]
+    );
+
+    // and its bytes after adding "first", "second" and "third"
+    let mut namefilter = WnfsNamefilter::new();
+    for item in ["first", "second", "third"] {
+        namefilter.add(item.as_bytes());
+    }
+    assert_eq!(
+        hex::encode(namefilter.bytes()),
+        concat!(
+            "0000004000002506002008000020100400000040084000280000800000000000",
+            "4040400008000402000008000000001000004000000000000000210004000000",
+            "0000000000000008000008181000800000000040000000900000020000000010",
+            "8000000001000000000000000008018048400000800000000000a00001000008",
+            "0000000000200000000042001000000000000000000000050000800000000000",
+            "00001000000000080000a0410000000000000000010000a80000000000000001",
+            "0000000000000000002000000000000000100100002040000000020100000010",
+            "0480000000000080000000048000010000000000000000000002000000208000",
+        )
+    );
+    assert!(namefilter.has(b"second"));
+    assert!(!namefilter.has(b"secnd"));
+}
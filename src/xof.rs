@@ -0,0 +1,216 @@
+use xxhash_rust::xxh3;
+
+// Extendable output functions: an endless byte stream determined by their
+// input. Everything that needs pseudo-random bytes for index derivation,
+// deterministic elements or saturation reads them through `Xof`, either
+// directly, as little endian u64s or as fixed-size blocks; all three views
+// walk the same stream.
+pub trait Xof {
+    fn fill(&mut self, buffer: &mut [u8]);
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn u64s(self) -> U64s<Self>
+    where
+        Self: Sized,
+    {
+        U64s(self)
+    }
+
+    fn blocks<const N: usize>(self) -> Blocks<Self, N>
+    where
+        Self: Sized,
+    {
+        Blocks(self)
+    }
+}
+
+pub struct U64s<X>(X);
+
+impl<X: Xof> Iterator for U64s<X> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        Some(self.0.next_u64())
+    }
+}
+
+pub struct Blocks<X, const N: usize>(X);
+
+impl<X: Xof, const N: usize> Iterator for Blocks<X, N> {
+    type Item = [u8; N];
+
+    fn next(&mut self) -> Option<[u8; N]> {
+        let mut block = [0u8; N];
+        self.0.fill(&mut block);
+        Some(block)
+    }
+}
+
+// A stream produced in chunks of B bytes, served from a buffer so callers
+// reading a few bytes at a time don't pay for a chunk each.
+struct Buffered<const B: usize> {
+    buffer: [u8; B],
+    position: usize,
+}
+
+impl<const B: usize> Buffered<B> {
+    fn new() -> Self {
+        Self {
+            buffer: [0; B],
+            position: B,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position == B
+    }
+
+    fn fill(&mut self, mut out: &mut [u8], mut refill: impl FnMut(&mut [u8; B])) {
+        while !out.is_empty() {
+            if self.position == B {
+                refill(&mut self.buffer);
+                self.position = 0;
+            }
+            let n = out.len().min(B - self.position);
+            out[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+            self.position += n;
+            out = &mut out[n..];
+        }
+    }
+}
+
+// XXH3 of the element with seeds 0, 1, 2, ..., each hash 8 little endian bytes
+pub struct XXH3XOF<'a> {
+    element: &'a [u8],
+    seed: u64,
+    buffered: Buffered<8>,
+}
+
+impl<'a> From<&'a [u8]> for XXH3XOF<'a> {
+    fn from(element: &'a [u8]) -> Self {
+        Self {
+            element,
+            seed: 0,
+            buffered: Buffered::new(),
+        }
+    }
+}
+
+impl Xof for XXH3XOF<'_> {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        let (element, seed) = (self.element, &mut self.seed);
+        self.buffered.fill(buffer, |chunk| {
+            *chunk = xxh3::xxh3_64_with_seed(element, *seed).to_le_bytes();
+            *seed += 1;
+        });
+    }
+
+    // the index streams' hot path: whole hashes skip the buffer
+    fn next_u64(&mut self) -> u64 {
+        if !self.buffered.is_empty() {
+            let mut bytes = [0u8; 8];
+            self.fill(&mut bytes);
+            return u64::from_le_bytes(bytes);
+        }
+        let hash = xxh3::xxh3_64_with_seed(self.element, self.seed);
+        self.seed += 1;
+        hash
+    }
+}
+
+// blake3's output stream, in its plain, derive_key or keyed mode
+#[cfg(feature = "blake3")]
+pub struct Blake3XOF {
+    output_reader: blake3::OutputReader,
+    buffered: Buffered<64>,
+}
+
+#[cfg(feature = "blake3")]
+impl Blake3XOF {
+    pub fn new(data: impl AsRef<[u8]>) -> Self {
+        Self::from_hasher(blake3::Hasher::new().update(data.as_ref()))
+    }
+
+    // derive_key mode, for domain separated streams
+    pub fn derived(context: &str, element: &[u8]) -> Self {
+        Self::from_hasher(blake3::Hasher::new_derive_key(context).update(element))
+    }
+
+    // keyed mode, for index streams that depend on a secret
+    pub fn keyed(key: &[u8; 32], element: &[u8]) -> Self {
+        Self::from_hasher(blake3::Hasher::new_keyed(key).update(element))
+    }
+
+    fn from_hasher(hasher: &blake3::Hasher) -> Self {
+        Self {
+            output_reader: hasher.finalize_xof(),
+            buffered: Buffered::new(),
+        }
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl Xof for Blake3XOF {
+    fn fill(&mut self, buffer: &mut [u8]) {
+        let output_reader = &mut self.output_reader;
+        self.buffered
+            .fill(buffer, |chunk| output_reader.fill(chunk.as_mut_slice()));
+    }
+}
+
+#[test]
+fn test_xxh3_xof() {
+    let element = b"Hello, World!" as &[u8];
+    let u64s: Vec<u64> = XXH3XOF::from(element).u64s().take(3).collect();
+    let expected: Vec<u64> = (0..3)
+        .map(|seed| xxh3::xxh3_64_with_seed(element, seed))
+        .collect();
+    assert_eq!(u64s, expected);
+
+    // reads of any size walk the same stream
+    let mut bytes = [0u8; 24];
+    let mut xof = XXH3XOF::from(element);
+    xof.fill(&mut bytes[..3]);
+    xof.fill(&mut bytes[3..20]);
+    xof.fill(&mut bytes[20..]);
+    let blocks: Vec<[u8; 12]> = XXH3XOF::from(element).blocks().take(2).collect();
+    assert_eq!(bytes[..], blocks.concat()[..]);
+    assert_eq!(bytes[16..24], expected[2].to_le_bytes());
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_blake3_xof() {
+    let mut expected = [0u8; 100];
+    blake3::Hasher::new_derive_key("context")
+        .update(b"element")
+        .finalize_xof()
+        .fill(&mut expected);
+
+    let mut xof = Blake3XOF::derived("context", b"element");
+    let mut bytes = [0u8; 100];
+    xof.fill(&mut bytes[..5]);
+    xof.fill(&mut bytes[5..70]);
+    xof.fill(&mut bytes[70..]);
+    assert_eq!(bytes, expected);
+
+    let blocks: Vec<[u8; 32]> = Blake3XOF::new(b"data").blocks().take(3).collect();
+    let mut plain = [0u8; 96];
+    blake3::Hasher::new()
+        .update(b"data")
+        .finalize_xof()
+        .fill(&mut plain);
+    assert_eq!(blocks.concat(), plain);
+    let first = Blake3XOF::keyed(&[7; 32], b"x").next_u64();
+    let mut keyed = [0u8; 8];
+    blake3::Hasher::new_keyed(&[7; 32])
+        .update(b"x")
+        .finalize_xof()
+        .fill(&mut keyed);
+    assert_eq!(first, u64::from_le_bytes(keyed));
+}
@@ -0,0 +1,187 @@
+use std::ops::BitXor;
+
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+// Xor filters (Graf and Lemire, 2020): static filters built once from a set
+// of elements. Each element's xxh3 hash picks a slot in each third of an
+// array of about 1.23 n fingerprints, and the three XOR to the element's
+// fingerprint. Building peels elements off slots only they use until none
+// are left, retrying with the next seed when that gets stuck, then fills the
+// slots in reverse. About 1.23 * 8 bits per element for `Xor8` at an FPR of
+// 2^-8, and 1.23 * 16 for `Xor16` at 2^-16.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XorFilter<F> {
+    seed: u64,
+    // elements it was built from, duplicates counted once
+    len: usize,
+    block_length: usize,
+    fingerprints: Vec<F>,
+}
+
+pub type Xor8 = XorFilter<u8>;
+pub type Xor16 = XorFilter<u16>;
+
+pub trait Fingerprint: Copy + Default + Eq + BitXor<Output = Self> {
+    const BITS: u32;
+
+    // the low bits of a mixed hash
+    fn from_hash(hash: u64) -> Self;
+}
+
+impl Fingerprint for u8 {
+    const BITS: u32 = 8;
+
+    fn from_hash(hash: u64) -> Self {
+        hash as u8
+    }
+}
+
+impl Fingerprint for u16 {
+    const BITS: u32 = 16;
+
+    fn from_hash(hash: u64) -> Self {
+        hash as u16
+    }
+}
+
+// seeds tried before giving up, each failing with a tiny probability
+const MAX_SEEDS: u64 = 1000;
+
+// the slot of hash in [0, block_length), by multiply-shift instead of modulo
+fn reduce(hash: u32, block_length: usize) -> usize {
+    ((hash as u64 * block_length as u64) >> 32) as usize
+}
+
+impl<F: Fingerprint> XorFilter<F> {
+    pub fn build<E: AsRef<[u8]>>(elements: impl IntoIterator<Item = E>) -> Self {
+        let elements: Vec<E> = elements.into_iter().collect();
+        let capacity = 32 + (1.23 * elements.len() as f64).ceil() as usize;
+        let block_length = capacity / 3;
+        for seed in 0..MAX_SEEDS {
+            let mut hashes: Vec<u64> = elements
+                .iter()
+                .map(|element| xxh3_64_with_seed(element.as_ref(), seed))
+                .collect();
+            // two equal hashes never peel
+            hashes.sort_unstable();
+            hashes.dedup();
+            let mut filter = Self {
+                seed,
+                len: hashes.len(),
+                block_length,
+                fingerprints: vec![F::default(); 3 * block_length],
+            };
+            if let Some(order) = filter.peel(&hashes) {
+                for (slot, hash) in order.into_iter().rev() {
+                    let [h0, h1, h2] = filter.slots(hash);
+                    filter.fingerprints[slot] = Self::fingerprint(hash)
+                        ^ filter.fingerprints[h0]
+                        ^ filter.fingerprints[h1]
+                        ^ filter.fingerprints[h2];
+                }
+                return filter;
+            }
+        }
+        panic!("xor filter construction failed for {MAX_SEEDS} seeds");
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        let hash = xxh3_64_with_seed(element, self.seed);
+        let [h0, h1, h2] = self.slots(hash);
+        Self::fingerprint(hash)
+            == self.fingerprints[h0] ^ self.fingerprints[h1] ^ self.fingerprints[h2]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn size_bits(&self) -> usize {
+        self.fingerprints.len() * F::BITS as usize
+    }
+
+    pub fn bits_per_element(&self) -> f64 {
+        self.size_bits() as f64 / self.len as f64
+    }
+
+    fn fingerprint(hash: u64) -> F {
+        F::from_hash(hash ^ (hash >> 32))
+    }
+
+    // one slot in each third of the array
+    fn slots(&self, hash: u64) -> [usize; 3] {
+        std::array::from_fn(|i| {
+            let part = hash.rotate_left(21 * i as u32) as u32;
+            reduce(part, self.block_length) + i * self.block_length
+        })
+    }
+
+    // (slot, hash) in the order they were peeled, None if some elements
+    // stay stuck on shared slots
+    fn peel(&self, hashes: &[u64]) -> Option<Vec<(usize, u64)>> {
+        let slots = self.fingerprints.len();
+        let mut counts = vec![0u32; slots];
+        // XOR of the hashes using each slot, the last one's hash once the
+        // count is down to 1
+        let mut xors = vec![0u64; slots];
+        for &hash in hashes {
+            for slot in self.slots(hash) {
+                counts[slot] += 1;
+                xors[slot] ^= hash;
+            }
+        }
+        let mut queue: Vec<usize> = (0..slots).filter(|&slot| counts[slot] == 1).collect();
+        let mut order = Vec::with_capacity(hashes.len());
+        while let Some(slot) = queue.pop() {
+            if counts[slot] != 1 {
+                continue;
+            }
+            let hash = xors[slot];
+            order.push((slot, hash));
+            for other in self.slots(hash) {
+                counts[other] -= 1;
+                xors[other] ^= hash;
+                if counts[other] == 1 {
+                    queue.push(other);
+                }
+            }
+        }
+        (order.len() == hashes.len()).then_some(order)
+    }
+}
+
+#[test]
+fn test_xor_filters() {
+    use crate::element::encode_u64;
+
+    let elements: Vec<[u8; 8]> = (0..10_000u64).map(encode_u64).collect();
+    let xor8 = Xor8::build(&elements);
+    let xor16 = Xor16::build(&elements);
+    assert_eq!(xor8.len(), 10_000);
+    assert!(elements
+        .iter()
+        .all(|e| xor8.contains(e) && xor16.contains(e)));
+    assert!(xor8.bits_per_element() < 10.0);
+    assert!(xor16.bits_per_element() < 20.0);
+
+    // 2^-8 and 2^-16 of 100_000 probes are about 391 and 1.5
+    let probes = (10_000..110_000u64).map(encode_u64);
+    let (fp8, fp16) = probes.fold((0, 0), |(fp8, fp16), probe| {
+        (
+            fp8 + xor8.contains(&probe) as u32,
+            fp16 + xor16.contains(&probe) as u32,
+        )
+    });
+    assert!((250..550).contains(&fp8), "{fp8}");
+    assert!(fp16 < 10, "{fp16}");
+
+    // duplicates count once, and tiny or empty sets still build
+    let twice = Xor8::build(elements[..5].iter().chain(&elements[..5]));
+    assert_eq!(twice.len(), 5);
+    assert!(elements[..5].iter().all(|e| twice.contains(e)));
+    assert!(Xor8::build(Vec::<&[u8]>::new()).is_empty());
+}